@@ -0,0 +1,157 @@
+use rpc_client::RestApiClient;
+use types::*;
+use util::*;
+
+use chrono::prelude::*;
+use stq_router::{Builder as RouterBuilder, Router};
+use stq_static_resources::Currency;
+use stq_types::*;
+
+#[derive(Clone, Debug)]
+pub enum Route {
+    Invoices,
+    InvoiceByOrder { order_id: OrderId },
+    InvoiceRefund { invoice_id: InvoiceId },
+    PayoutsSearch,
+}
+
+impl RouteBuilder for Route {
+    fn route(&self) -> String {
+        use self::Route::*;
+
+        match self {
+            Invoices => "invoices".to_string(),
+            InvoiceByOrder { order_id } => format!("invoices/by-order-id/{}", order_id),
+            InvoiceRefund { invoice_id } => format!("invoices/by-id/{}/refund", invoice_id),
+            PayoutsSearch => "payouts/search".to_string(),
+        }
+    }
+}
+
+impl Route {
+    pub fn from_path(s: &str) -> Option<Self> {
+        lazy_static! {
+            static ref ROUTER: Router<Route> = RouterBuilder::default()
+                .with_route(r"^/invoices$", |_| Some(Route::Invoices))
+                .with_route(r"^/invoices/by-order-id/([a-zA-Z0-9-]+)$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok())
+                    .map(|order_id| Route::InvoiceByOrder { order_id }))
+                .with_route(r"^/invoices/by-id/([a-zA-Z0-9-]+)/refund$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok())
+                    .map(|invoice_id| Route::InvoiceRefund { invoice_id }))
+                .with_route(r"^/payouts/search$", |_| Some(Route::PayoutsSearch))
+                .build();
+        }
+
+        ROUTER.test(s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OrderPrice {
+    pub order_id: OrderId,
+    pub store_id: StoreId,
+    pub price: ProductPrice,
+    pub currency: Currency,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateInvoice {
+    pub saga_id: SagaId,
+    pub customer_id: UserId,
+    pub orders: Vec<OrderPrice>,
+    pub currency: Currency,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Invoice {
+    pub id: InvoiceId,
+    pub customer_id: UserId,
+    pub order_ids: Vec<OrderId>,
+    pub amount: ProductPrice,
+    pub currency: Currency,
+    pub paid: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RefundPayload {
+    /// Amount to refund, whole invoice is refunded if not set
+    pub amount: Option<ProductPrice>,
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PayoutSearchTerms {
+    pub store_id: Option<StoreId>,
+    pub order_id: Option<OrderId>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Payout {
+    pub id: PayoutId,
+    pub store_id: StoreId,
+    pub order_ids: Vec<OrderId>,
+    pub amount: ProductPrice,
+    pub currency: Currency,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+pub trait BillingClient {
+    fn create_invoice(&self, payload: CreateInvoice) -> ApiFuture<Invoice>;
+    fn get_invoice_by_order(&self, order_id: OrderId) -> ApiFuture<Option<Invoice>>;
+    fn refund_invoice(
+        &self,
+        invoice_id: InvoiceId,
+        amount: Option<ProductPrice>,
+        reason: Option<String>,
+    ) -> ApiFuture<Invoice>;
+    /// Search using the terms provided.
+    fn search_payouts(&self, terms: PayoutSearchTerms) -> ApiFuture<Vec<Payout>>;
+}
+
+impl BillingClient for RestApiClient {
+    fn create_invoice(&self, payload: CreateInvoice) -> ApiFuture<Invoice> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::Invoices))
+                .body(JsonPayload(payload)),
+        )
+    }
+
+    fn get_invoice_by_order(&self, order_id: OrderId) -> ApiFuture<Option<Invoice>> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::InvoiceByOrder { order_id })),
+        )
+    }
+
+    fn refund_invoice(
+        &self,
+        invoice_id: InvoiceId,
+        amount: Option<ProductPrice>,
+        reason: Option<String>,
+    ) -> ApiFuture<Invoice> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::InvoiceRefund { invoice_id }))
+                .body(JsonPayload(RefundPayload { amount, reason })),
+        )
+    }
+
+    fn search_payouts(&self, terms: PayoutSearchTerms) -> ApiFuture<Vec<Payout>> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::PayoutsSearch))
+                .body(JsonPayload(terms)),
+        )
+    }
+}
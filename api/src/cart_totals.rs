@@ -0,0 +1,525 @@
+//! Totals of a cart being converted to orders, so that orders and billing services price carts the same way
+use errors::Error;
+use orders::{DeliveryInfo, ProductInfo};
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+use stq_static_resources::Currency;
+use stq_types::*;
+
+/// Amounts of selected cart items, in the currency of their seller prices
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Totals {
+    /// Price of the items before discounts
+    pub subtotal: Money,
+    pub product_discount: Money,
+    pub coupon_discount: Money,
+    pub delivery_price: Money,
+    /// Cashback paid to the buyer, not subtracted from `total`
+    pub cashback: Money,
+    /// Amount to be paid: subtotal less discounts plus delivery
+    pub total: Money,
+}
+
+impl Totals {
+    pub fn zero(currency: Currency) -> Self {
+        Self {
+            subtotal: Money::zero(currency),
+            product_discount: Money::zero(currency),
+            coupon_discount: Money::zero(currency),
+            delivery_price: Money::zero(currency),
+            cashback: Money::zero(currency),
+            total: Money::zero(currency),
+        }
+    }
+
+    fn add(&mut self, other: &Totals) -> Result<(), MoneyError> {
+        self.subtotal = self.subtotal.checked_add(other.subtotal)?;
+        self.product_discount = self.product_discount.checked_add(other.product_discount)?;
+        self.coupon_discount = self.coupon_discount.checked_add(other.coupon_discount)?;
+        self.delivery_price = self.delivery_price.checked_add(other.delivery_price)?;
+        self.cashback = self.cashback.checked_add(other.cashback)?;
+        self.total = self.total.checked_add(other.total)?;
+        Ok(())
+    }
+}
+
+/// Totals by currency, as items of a cart may be priced in different currencies
+pub type CurrencyTotals = HashMap<Currency, Totals>;
+
+/// Coupon of a cart item which is not applied, e.g. as it has expired
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RejectedCoupon {
+    pub product_id: ProductId,
+    pub coupon_id: CouponId,
+    pub error: CouponError,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CartTotals {
+    pub stores: HashMap<StoreId, CurrencyTotals>,
+    pub grand: CurrencyTotals,
+    /// Coupons of the selected items rejected by their rules, ordered by product
+    pub rejected_coupons: Vec<RejectedCoupon>,
+}
+
+fn money_error(e: MoneyError) -> Error {
+    Error::Unknown(format!("Cart totals cannot be calculated: {}", e))
+}
+
+/// Converts amount in major units to `Money`, rounding it to minor units of `currency`
+fn money(amount: f64, currency: Currency) -> Result<Money, Error> {
+    Money::from_major(amount, currency)
+        .ok_or_else(|| Error::Parse(format!("Invalid amount: {} {}", amount, currency)))
+}
+
+/// Part of `amount` given as a fraction, e.g. 0.25 of a 25% discount, rounded half away from zero
+fn fraction_of(amount: Money, fraction: f64) -> Result<Money, Error> {
+    ExchangeRate::from_f64(fraction)
+        .ok_or_else(|| Error::Parse(format!("Invalid fraction: {}", fraction)))?
+        .convert(amount, amount.currency, RoundingMode::HalfAwayFromZero)
+        .map_err(money_error)
+}
+
+impl CartTotals {
+    /// Totals of the selected items of the cart, with the arguments keyed the same way as in `ConvertCartPayload`.
+    ///
+    /// Amounts are converted to minor units of the seller price currency first and then summed exactly,
+    /// discounts and cashback are rounded half away from zero.
+    /// For each item the product discount is taken off its price, then the coupon is applied to the rest as of `now`.
+    /// Coupons rejected by their rules give no discount and are listed in `rejected_coupons`.
+    /// Cashback is paid from the discounted price, delivery price is in the currency of the seller price.
+    /// Fails if a selected item has no seller price, if a coupon amount is in another currency or on overflow.
+    pub fn calculate(
+        cart: &Cart,
+        seller_prices: &HashMap<ProductId, ProductSellerPrice>,
+        coupons: &HashMap<CouponId, CouponPolicy>,
+        delivery_info: &HashMap<ProductId, DeliveryInfo>,
+        product_info: &HashMap<ProductId, ProductInfo>,
+        now: SystemTime,
+    ) -> Result<Self, Error> {
+        let mut totals = CartTotals::default();
+        for item in cart.iter().filter(|item| item.selected) {
+            let seller_price = seller_prices.get(&item.product_id).ok_or_else(|| {
+                Error::Unknown(format!("No seller price for product {}", item.product_id))
+            })?;
+            let currency = seller_price.currency;
+            let discount = seller_price.discount.unwrap_or_default();
+
+            let price = money(seller_price.price.0, currency)?;
+            let subtotal = price
+                .checked_mul(i64::from(item.quantity.0))
+                .map_err(money_error)?;
+            let product_discount = fraction_of(subtotal, discount)?;
+            let coupon = item.coupon_id.and_then(|coupon_id| coupons.get(&coupon_id));
+            let coupon_discount = match coupon {
+                Some(coupon) => {
+                    let price = price
+                        .checked_sub(fraction_of(price, discount)?)
+                        .map_err(money_error)?;
+                    match coupon.apply(item, price, subtotal, now) {
+                        Ok(discount) => discount.amount,
+                        Err(CouponError::Money(e)) => return Err(money_error(e)),
+                        Err(error) => {
+                            totals.rejected_coupons.push(RejectedCoupon {
+                                product_id: item.product_id,
+                                coupon_id: coupon.id,
+                                error,
+                            });
+                            Money::zero(currency)
+                        }
+                    }
+                }
+                None => Money::zero(currency),
+            };
+            let discounted = subtotal
+                .checked_sub(product_discount)
+                .and_then(|amount| amount.checked_sub(coupon_discount))
+                .map_err(money_error)?;
+            let cashback = product_info
+                .get(&item.product_id)
+                .and_then(|info| info.cashback);
+            let cashback = match cashback {
+                Some(cashback) => fraction_of(discounted, cashback.0)?,
+                None => Money::zero(currency),
+            };
+            let delivery_price = match delivery_info.get(&item.product_id) {
+                Some(info) => money(info.price, currency)?,
+                None => Money::zero(currency),
+            };
+
+            let item_totals = Totals {
+                subtotal,
+                product_discount,
+                coupon_discount,
+                delivery_price,
+                cashback,
+                total: discounted
+                    .checked_add(delivery_price)
+                    .map_err(money_error)?,
+            };
+            totals
+                .stores
+                .entry(item.store_id)
+                .or_insert_with(HashMap::new)
+                .entry(currency)
+                .or_insert_with(|| Totals::zero(currency))
+                .add(&item_totals)
+                .map_err(money_error)?;
+            totals
+                .grand
+                .entry(currency)
+                .or_insert_with(|| Totals::zero(currency))
+                .add(&item_totals)
+                .map_err(money_error)?;
+        }
+        totals
+            .rejected_coupons
+            .sort_by_key(|rejected| rejected.product_id);
+        Ok(totals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stq_static_resources::CurrencyType;
+
+    fn item(product_id: i32, store_id: i32, quantity: i32) -> CartItem {
+        CartItem {
+            id: CartItemId::default(),
+            customer: CartCustomer::User(UserId(1)),
+            product_id: ProductId(product_id),
+            quantity: Quantity(quantity),
+            selected: true,
+            comment: String::new(),
+            store_id: StoreId(store_id),
+            pre_order: false,
+            pre_order_days: 0,
+            coupon_id: None,
+            delivery_method_id: None,
+            currency_type: CurrencyType::Crypto,
+            user_country_code: None,
+        }
+    }
+
+    fn seller_price(price: f64, currency: Currency, discount: Option<f64>) -> ProductSellerPrice {
+        ProductSellerPrice {
+            price: ProductPrice(price),
+            currency,
+            discount,
+        }
+    }
+
+    fn delivery(price: f64) -> DeliveryInfo {
+        DeliveryInfo {
+            company_package_id: CompanyPackageId(1),
+            shipping_id: ShippingId(1),
+            name: "DHL".to_string(),
+            logo: String::new(),
+            price,
+        }
+    }
+
+    fn cashback(percent: f64) -> ProductInfo {
+        ProductInfo {
+            base_product_id: BaseProductId(1),
+            cashback: Some(CashbackPercent(percent)),
+            pre_order: false,
+            pre_order_days: 0,
+        }
+    }
+
+    fn stq(amount: i64) -> Money {
+        Money::new(amount * 100_000_000, Currency::STQ)
+    }
+
+    #[test]
+    fn test_product_discount_and_coupon() {
+        let cart = vec![CartItem {
+            coupon_id: Some(CouponId(7)),
+            ..item(1, 10, 2)
+        }]
+        .into_iter()
+        .collect::<Cart>();
+        let seller_prices = vec![(ProductId(1), seller_price(100.0, Currency::STQ, Some(0.25)))]
+            .into_iter()
+            .collect();
+        let coupons = vec![(CouponId(7), CouponPolicy::percent(CouponId(7), 50))]
+            .into_iter()
+            .collect();
+        let delivery_info = vec![(ProductId(1), delivery(10.0))].into_iter().collect();
+
+        let totals = CartTotals::calculate(
+            &cart,
+            &seller_prices,
+            &coupons,
+            &delivery_info,
+            &HashMap::new(),
+            SystemTime::now(),
+        )
+        .unwrap();
+
+        let expected = Totals {
+            subtotal: stq(200),
+            product_discount: stq(50),
+            coupon_discount: stq(75),
+            delivery_price: stq(10),
+            cashback: stq(0),
+            total: stq(85),
+        };
+        assert_eq!(totals.grand[&Currency::STQ], expected);
+        assert_eq!(totals.stores[&StoreId(10)][&Currency::STQ], expected);
+    }
+
+    #[test]
+    fn test_rejected_coupons() {
+        let now = SystemTime::now();
+        let cart = vec![
+            CartItem {
+                coupon_id: Some(CouponId(7)),
+                ..item(1, 10, 1)
+            },
+            CartItem {
+                coupon_id: Some(CouponId(8)),
+                ..item(2, 10, 1)
+            },
+        ]
+        .into_iter()
+        .collect::<Cart>();
+        let seller_prices = vec![
+            (ProductId(1), seller_price(100.0, Currency::STQ, None)),
+            (ProductId(2), seller_price(100.0, Currency::STQ, None)),
+        ]
+        .into_iter()
+        .collect();
+        let coupons = vec![
+            (
+                CouponId(7),
+                CouponPolicy {
+                    expires_at: Some(now),
+                    ..CouponPolicy::percent(CouponId(7), 50)
+                },
+            ),
+            (
+                CouponId(8),
+                CouponPolicy {
+                    store_id: Some(StoreId(20)),
+                    ..CouponPolicy::percent(CouponId(8), 50)
+                },
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let totals = CartTotals::calculate(
+            &cart,
+            &seller_prices,
+            &coupons,
+            &HashMap::new(),
+            &HashMap::new(),
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(totals.grand[&Currency::STQ].coupon_discount, stq(0));
+        assert_eq!(totals.grand[&Currency::STQ].total, stq(200));
+        assert_eq!(
+            totals.rejected_coupons,
+            vec![
+                RejectedCoupon {
+                    product_id: ProductId(1),
+                    coupon_id: CouponId(7),
+                    error: CouponError::Expired,
+                },
+                RejectedCoupon {
+                    product_id: ProductId(2),
+                    coupon_id: CouponId(8),
+                    error: CouponError::StoreNotApplicable(StoreId(10)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cashback() {
+        let cart = vec![item(1, 10, 4)].into_iter().collect::<Cart>();
+        let seller_prices = vec![(ProductId(1), seller_price(50.0, Currency::STQ, Some(0.5)))]
+            .into_iter()
+            .collect();
+        let product_info = vec![(ProductId(1), cashback(0.25))].into_iter().collect();
+
+        let totals = CartTotals::calculate(
+            &cart,
+            &seller_prices,
+            &HashMap::new(),
+            &HashMap::new(),
+            &product_info,
+            SystemTime::now(),
+        )
+        .unwrap();
+
+        let grand = totals.grand[&Currency::STQ];
+        assert_eq!(grand.cashback, stq(25));
+        // Cashback is paid to the buyer, not taken off the total
+        assert_eq!(grand.total, stq(100));
+    }
+
+    #[test]
+    fn test_mixed_currencies_and_stores() {
+        let cart = vec![item(1, 10, 1), item(2, 10, 1), item(3, 20, 2)]
+            .into_iter()
+            .collect::<Cart>();
+        let seller_prices = vec![
+            (ProductId(1), seller_price(10.0, Currency::STQ, None)),
+            (ProductId(2), seller_price(2.0, Currency::ETH, None)),
+            (ProductId(3), seller_price(5.0, Currency::STQ, None)),
+        ]
+        .into_iter()
+        .collect();
+        let delivery_info = vec![(ProductId(3), delivery(1.0))].into_iter().collect();
+
+        let totals = CartTotals::calculate(
+            &cart,
+            &seller_prices,
+            &HashMap::new(),
+            &delivery_info,
+            &HashMap::new(),
+            SystemTime::now(),
+        )
+        .unwrap();
+
+        assert_eq!(totals.stores.len(), 2);
+        assert_eq!(totals.stores[&StoreId(10)].len(), 2);
+        assert_eq!(totals.stores[&StoreId(10)][&Currency::STQ].total, stq(10));
+        assert_eq!(
+            totals.stores[&StoreId(10)][&Currency::ETH].total,
+            Money::new(200_000_000, Currency::ETH)
+        );
+        assert_eq!(totals.stores[&StoreId(20)].len(), 1);
+        assert_eq!(totals.stores[&StoreId(20)][&Currency::STQ].total, stq(11));
+
+        assert_eq!(totals.grand.len(), 2);
+        assert_eq!(totals.grand[&Currency::STQ].subtotal, stq(20));
+        assert_eq!(totals.grand[&Currency::STQ].delivery_price, stq(1));
+        assert_eq!(totals.grand[&Currency::STQ].total, stq(21));
+        assert_eq!(
+            totals.grand[&Currency::ETH].total,
+            Money::new(200_000_000, Currency::ETH)
+        );
+    }
+
+    #[test]
+    fn test_unselected_items_are_skipped() {
+        let cart = vec![
+            item(1, 10, 1),
+            CartItem {
+                selected: false,
+                ..item(2, 20, 1)
+            },
+        ]
+        .into_iter()
+        .collect::<Cart>();
+        // Unselected item has no seller price, which is fine as it is not priced
+        let seller_prices = vec![(ProductId(1), seller_price(10.0, Currency::STQ, None))]
+            .into_iter()
+            .collect();
+
+        let totals = CartTotals::calculate(
+            &cart,
+            &seller_prices,
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            SystemTime::now(),
+        )
+        .unwrap();
+
+        assert_eq!(totals.stores.keys().collect::<Vec<_>>(), vec![&StoreId(10)]);
+        assert_eq!(totals.grand[&Currency::STQ].total, stq(10));
+    }
+
+    #[test]
+    fn test_amounts_are_exact() {
+        let cart = vec![item(1, 10, 1), item(2, 10, 1), item(3, 20, 1)]
+            .into_iter()
+            .collect::<Cart>();
+        let seller_prices = vec![
+            (ProductId(1), seller_price(0.1, Currency::USD, None)),
+            (ProductId(2), seller_price(0.2, Currency::USD, None)),
+            (ProductId(3), seller_price(0.99, Currency::USD, Some(0.5))),
+        ]
+        .into_iter()
+        .collect();
+
+        let totals = CartTotals::calculate(
+            &cart,
+            &seller_prices,
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            SystemTime::now(),
+        )
+        .unwrap();
+
+        let usd = |amount_minor| Money::new(amount_minor, Currency::USD);
+        // 0.1 + 0.2 is 0.30000000000000004 in f64
+        assert_eq!(totals.stores[&StoreId(10)][&Currency::USD].total, usd(30));
+        // Half of 0.99 is rounded half away from zero
+        assert_eq!(
+            totals.stores[&StoreId(20)][&Currency::USD].product_discount,
+            usd(50)
+        );
+        assert_eq!(totals.stores[&StoreId(20)][&Currency::USD].total, usd(49));
+        assert_eq!(totals.grand[&Currency::USD].total, usd(79));
+    }
+
+    #[test]
+    fn test_coupon_in_another_currency() {
+        let cart = vec![CartItem {
+            coupon_id: Some(CouponId(7)),
+            ..item(1, 10, 1)
+        }]
+        .into_iter()
+        .collect::<Cart>();
+        let seller_prices = vec![(ProductId(1), seller_price(10.0, Currency::USD, None))]
+            .into_iter()
+            .collect();
+        let coupons = vec![(
+            CouponId(7),
+            CouponPolicy {
+                value: CouponValue::Fixed(Money::new(100, Currency::EUR)),
+                ..CouponPolicy::percent(CouponId(7), 0)
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let res = CartTotals::calculate(
+            &cart,
+            &seller_prices,
+            &coupons,
+            &HashMap::new(),
+            &HashMap::new(),
+            SystemTime::now(),
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_missing_seller_price() {
+        let cart = vec![item(1, 10, 1)].into_iter().collect::<Cart>();
+
+        let res = CartTotals::calculate(
+            &cart,
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            SystemTime::now(),
+        );
+
+        assert!(res.is_err());
+    }
+}
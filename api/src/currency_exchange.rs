@@ -0,0 +1,271 @@
+//! Conversion of prices between currencies.
+//!
+//! Every service converting e.g. seller prices to the customer currency should go through
+//! `CurrencyConverter`, so that rates are taken from the same source and rounded the same way.
+
+use rpc_client::RestApiClient;
+use types::*;
+use util::*;
+
+use errors::Error;
+use futures::future;
+use futures::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use stq_router::{Builder as RouterBuilder, Router};
+use stq_static_resources::Currency;
+use stq_types::{ExchangeRate, Money, RoundingMode};
+
+#[derive(Clone, Debug)]
+pub enum Route {
+    CurrencyExchange,
+}
+
+impl RouteBuilder for Route {
+    fn route(&self) -> String {
+        use self::Route::*;
+
+        match self {
+            CurrencyExchange => "currency_exchange".to_string(),
+        }
+    }
+}
+
+impl Route {
+    pub fn from_path(s: &str) -> Option<Self> {
+        lazy_static! {
+            static ref ROUTER: Router<Route> = RouterBuilder::default()
+                .with_route(r"^/currency_exchange$", |_| Some(Route::CurrencyExchange))
+                .build();
+        }
+
+        ROUTER.test(s)
+    }
+}
+
+/// Rates by source currency, then by target currency:
+/// amount of the target currency for one unit of the source one
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeRates(pub HashMap<Currency, HashMap<Currency, ExchangeRate>>);
+
+impl ExchangeRates {
+    /// Rate from `from` to `to`, falling back to the inverse of the reverse rate
+    pub fn rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        rounding: RoundingMode,
+    ) -> Option<ExchangeRate> {
+        let lookup = |from: Currency, to: Currency| {
+            self.0.get(&from).and_then(|rates| rates.get(&to)).cloned()
+        };
+
+        lookup(from, to).or_else(|| lookup(to, from).and_then(|rate| rate.inverse(rounding)))
+    }
+
+    /// Converts `money` to `currency`, amounts already in `currency` are returned as is
+    pub fn convert(
+        &self,
+        money: Money,
+        currency: Currency,
+        rounding: RoundingMode,
+    ) -> Result<Money, Error> {
+        if money.currency == currency {
+            return Ok(money);
+        }
+
+        let rate = self
+            .rate(money.currency, currency, rounding)
+            .ok_or_else(|| {
+                Error::Unknown(format!(
+                    "No exchange rate from {} to {}",
+                    money.currency, currency
+                ))
+            })?;
+        rate.convert(money, currency, rounding).map_err(|e| {
+            Error::Unknown(format!(
+                "Failed to convert {} to {}: {}",
+                money, currency, e
+            ))
+        })
+    }
+}
+
+pub trait CurrencyExchangeClient {
+    /// Latest exchange rates
+    fn get_exchange_rates(&self) -> ApiFuture<ExchangeRates>;
+}
+
+impl CurrencyExchangeClient for RestApiClient {
+    fn get_exchange_rates(&self) -> ApiFuture<ExchangeRates> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::CurrencyExchange)),
+        )
+    }
+}
+
+pub trait CurrencyConverter {
+    fn convert(&self, money: Money, currency: Currency) -> ApiFuture<Money>;
+}
+
+struct CachedRates {
+    rates: Arc<ExchangeRates>,
+    fetched_at: Instant,
+}
+
+/// Converter keeping rates received from the exchange client for `ttl`.
+/// Clones share the cache.
+pub struct CachedCurrencyConverter<C> {
+    client: Arc<C>,
+    ttl: Duration,
+    rounding: RoundingMode,
+    cached: Arc<Mutex<Option<CachedRates>>>,
+}
+
+// Not derived, as that would require the client to be `Clone`
+impl<C> Clone for CachedCurrencyConverter<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            ttl: self.ttl,
+            rounding: self.rounding,
+            cached: self.cached.clone(),
+        }
+    }
+}
+
+impl<C> CachedCurrencyConverter<C>
+where
+    C: CurrencyExchangeClient,
+{
+    pub fn new(client: C, ttl: Duration) -> Self {
+        Self {
+            client: Arc::new(client),
+            ttl,
+            rounding: RoundingMode::default(),
+            cached: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn with_rounding(mut self, rounding: RoundingMode) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Drops cached rates, so that the next conversion fetches them again
+    pub fn invalidate(&self) {
+        *self.cached.lock().unwrap() = None;
+    }
+
+    /// Cached rates if they are not older than `ttl`, fresh ones otherwise
+    pub fn rates(&self) -> ApiFuture<Arc<ExchangeRates>> {
+        if let Some(ref cached) = *self.cached.lock().unwrap() {
+            if cached.fetched_at.elapsed() < self.ttl {
+                return Box::new(future::ok(cached.rates.clone()));
+            }
+        }
+
+        let cache = self.cached.clone();
+        Box::new(self.client.get_exchange_rates().map(move |rates| {
+            let rates = Arc::new(rates);
+            *cache.lock().unwrap() = Some(CachedRates {
+                rates: rates.clone(),
+                fetched_at: Instant::now(),
+            });
+            rates
+        }))
+    }
+}
+
+impl<C> CurrencyConverter for CachedCurrencyConverter<C>
+where
+    C: CurrencyExchangeClient,
+{
+    fn convert(&self, money: Money, currency: Currency) -> ApiFuture<Money> {
+        if money.currency == currency {
+            return Box::new(future::ok(money));
+        }
+
+        let rounding = self.rounding;
+        Box::new(
+            self.rates()
+                .and_then(move |rates| rates.convert(money, currency, rounding)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Client counting requests of exchange rates, 1 USD is 0.5 EUR
+    struct CountingClient(Arc<AtomicUsize>);
+
+    impl CurrencyExchangeClient for CountingClient {
+        fn get_exchange_rates(&self) -> ApiFuture<ExchangeRates> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let mut usd = HashMap::new();
+            usd.insert(Currency::EUR, ExchangeRate::from(0.5));
+            let mut rates = HashMap::new();
+            rates.insert(Currency::USD, usd);
+            Box::new(future::ok(ExchangeRates(rates)))
+        }
+    }
+
+    fn converter(ttl: Duration) -> (CachedCurrencyConverter<CountingClient>, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        (
+            CachedCurrencyConverter::new(CountingClient(requests.clone()), ttl),
+            requests,
+        )
+    }
+
+    #[test]
+    fn test_rates_are_cached_for_ttl() {
+        let (converter, requests) = converter(Duration::from_secs(60));
+        let cloned = converter.clone();
+
+        assert_eq!(
+            converter
+                .convert(Money::new(1000, Currency::USD), Currency::EUR)
+                .wait()
+                .unwrap(),
+            Money::new(500, Currency::EUR)
+        );
+        assert_eq!(
+            cloned
+                .convert(Money::new(500, Currency::EUR), Currency::USD)
+                .wait()
+                .unwrap(),
+            Money::new(1000, Currency::USD)
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        converter.invalidate();
+        cloned.rates().wait().unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_expired_rates_are_fetched_again() {
+        let (converter, requests) = converter(Duration::from_secs(0));
+        converter.rates().wait().unwrap();
+        converter.rates().wait().unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_same_currency_is_not_converted() {
+        let (converter, requests) = converter(Duration::from_secs(60));
+        let money = Money::new(1000, Currency::USD);
+        assert_eq!(
+            converter.convert(money, Currency::USD).wait().unwrap(),
+            money
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,242 @@
+use rpc_client::RestApiClient;
+use types::*;
+use util::*;
+
+use futures::Future;
+use stq_router::{Builder as RouterBuilder, Router};
+use stq_static_resources::Currency;
+use stq_types::*;
+
+#[derive(Clone, Debug)]
+pub enum Route {
+    AvailablePackages {
+        from: Alpha3,
+        to: Alpha3,
+    },
+    CompanyPackage {
+        company_package_id: CompanyPackageId,
+    },
+    ShippingRates,
+    Pickups,
+    Pickup {
+        pickup_id: PickupId,
+    },
+    Shipping {
+        shipping_id: ShippingId,
+    },
+}
+
+impl RouteBuilder for Route {
+    fn route(&self) -> String {
+        use self::Route::*;
+
+        match self {
+            AvailablePackages { from, to } => format!("available_packages/from/{}/to/{}", from, to),
+            CompanyPackage { company_package_id } => {
+                format!("companies_packages/{}", company_package_id)
+            }
+            ShippingRates => "shipping_rates/calculate".to_string(),
+            Pickups => "pickups".to_string(),
+            Pickup { pickup_id } => format!("pickups/{}", pickup_id),
+            Shipping { shipping_id } => format!("shipping/{}", shipping_id),
+        }
+    }
+}
+
+impl Route {
+    pub fn from_path(s: &str) -> Option<Self> {
+        lazy_static! {
+            static ref ROUTER: Router<Route> = RouterBuilder::default()
+                .with_route(
+                    r"^/available_packages/from/([A-Z]{3})/to/([A-Z]{3})$",
+                    |params| {
+                        let mut params = params.into_iter();
+                        let from = Alpha3::parse(&params.next()?).ok()?;
+                        let to = Alpha3::parse(&params.next()?).ok()?;
+                        Some(Route::AvailablePackages { from, to })
+                    }
+                )
+                .with_route(r"^/companies_packages/(\d+)$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok())
+                    .map(|company_package_id| Route::CompanyPackage {
+                        company_package_id
+                    }))
+                .with_route(r"^/shipping_rates/calculate$", |_| Some(
+                    Route::ShippingRates
+                ))
+                .with_route(r"^/pickups$", |_| Some(Route::Pickups))
+                .with_route(r"^/pickups/(\d+)$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok())
+                    .map(|pickup_id| Route::Pickup { pickup_id }))
+                .with_route(r"^/shipping/(\d+)$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok())
+                    .map(|shipping_id| Route::Shipping { shipping_id }))
+                .build();
+        }
+
+        ROUTER.test(s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AvailablePackage {
+    pub id: CompanyPackageId,
+    pub company_id: CompanyId,
+    pub package_id: PackageId,
+    pub name: String,
+    pub logo: String,
+    pub deliveries_to: Vec<Alpha3>,
+    pub local_available: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShippingRatesPayload {
+    pub company_package_id: CompanyPackageId,
+    pub from: Alpha3,
+    pub to: Alpha3,
+    /// Volume in cubic centimeters
+    pub volume: u32,
+    /// Weight in grams
+    pub weight: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShippingRate {
+    pub company_package_id: CompanyPackageId,
+    pub to: Alpha3,
+    pub price: ProductPrice,
+    pub currency: Currency,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NewPickup {
+    pub base_product_id: BaseProductId,
+    pub store_id: StoreId,
+    pub pickup: bool,
+    pub price: Option<ProductPrice>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pickup {
+    pub id: PickupId,
+    pub base_product_id: BaseProductId,
+    pub store_id: StoreId,
+    pub pickup: bool,
+    pub price: Option<ProductPrice>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Shipping {
+    pub id: ShippingId,
+    pub base_product_id: BaseProductId,
+    pub store_id: StoreId,
+    pub company_package_id: CompanyPackageId,
+    pub price: Option<ProductPrice>,
+    pub deliveries_to: Vec<Alpha3>,
+}
+
+/// Delivery method resolved from `DeliveryMethodId`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryMethod {
+    Package(AvailablePackage),
+    Pickup(Pickup),
+    ShippingPackage(Shipping),
+}
+
+pub trait DeliveryClient {
+    /// Packages able to deliver from one country to another
+    fn get_available_packages(&self, from: Alpha3, to: Alpha3) -> ApiFuture<Vec<AvailablePackage>>;
+    fn get_company_package(
+        &self,
+        company_package_id: CompanyPackageId,
+    ) -> ApiFuture<Option<AvailablePackage>>;
+    fn calculate_shipping_rates(
+        &self,
+        payload: ShippingRatesPayload,
+    ) -> ApiFuture<Vec<ShippingRate>>;
+    fn create_pickup(&self, pickup: NewPickup) -> ApiFuture<Pickup>;
+    fn get_pickup(&self, pickup_id: PickupId) -> ApiFuture<Option<Pickup>>;
+    fn get_shipping(&self, shipping_id: ShippingId) -> ApiFuture<Option<Shipping>>;
+    fn get_delivery_method(
+        &self,
+        delivery_method_id: DeliveryMethodId,
+    ) -> ApiFuture<Option<DeliveryMethod>>;
+}
+
+impl DeliveryClient for RestApiClient {
+    fn get_available_packages(&self, from: Alpha3, to: Alpha3) -> ApiFuture<Vec<AvailablePackage>> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::AvailablePackages { from, to })),
+        )
+    }
+
+    fn get_company_package(
+        &self,
+        company_package_id: CompanyPackageId,
+    ) -> ApiFuture<Option<AvailablePackage>> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::CompanyPackage { company_package_id })),
+        )
+    }
+
+    fn calculate_shipping_rates(
+        &self,
+        payload: ShippingRatesPayload,
+    ) -> ApiFuture<Vec<ShippingRate>> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::ShippingRates))
+                .body(JsonPayload(payload)),
+        )
+    }
+
+    fn create_pickup(&self, pickup: NewPickup) -> ApiFuture<Pickup> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::Pickups))
+                .body(JsonPayload(pickup)),
+        )
+    }
+
+    fn get_pickup(&self, pickup_id: PickupId) -> ApiFuture<Option<Pickup>> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::Pickup { pickup_id })),
+        )
+    }
+
+    fn get_shipping(&self, shipping_id: ShippingId) -> ApiFuture<Option<Shipping>> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::Shipping { shipping_id })),
+        )
+    }
+
+    fn get_delivery_method(
+        &self,
+        delivery_method_id: DeliveryMethodId,
+    ) -> ApiFuture<Option<DeliveryMethod>> {
+        match delivery_method_id {
+            DeliveryMethodId::Package { id } => Box::new(
+                self.get_company_package(id)
+                    .map(|v| v.map(DeliveryMethod::Package)),
+            ),
+            DeliveryMethodId::Pickup { id } => {
+                Box::new(self.get_pickup(id).map(|v| v.map(DeliveryMethod::Pickup)))
+            }
+            DeliveryMethodId::ShippingPackage { id } => Box::new(
+                self.get_shipping(id)
+                    .map(|v| v.map(DeliveryMethod::ShippingPackage)),
+            ),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use hyper;
-use serde_json::Value;
+use serde_json::{self, Value};
+use std::collections::HashMap;
 use std::fmt;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -9,11 +10,42 @@ pub struct ErrorMessage {
     pub payload: Option<Value>,
 }
 
+impl ErrorMessage {
+    /// Decodes validation errors from payload, if payload has the format produced by `validator` crate
+    pub fn validation_errors(&self) -> Option<ValidationErrors> {
+        self.payload
+            .clone()
+            .and_then(|payload| serde_json::from_value::<ValidationErrors>(payload).ok())
+            .and_then(|errors| {
+                if errors.is_empty() {
+                    None
+                } else {
+                    Some(errors)
+                }
+            })
+    }
+}
+
+/// Single failed validation of a field
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub code: String,
+    pub message: Option<String>,
+    #[serde(default)]
+    pub params: HashMap<String, Value>,
+}
+
+/// Failed validations by field name
+pub type ValidationErrors = HashMap<String, Vec<FieldError>>;
+
 #[derive(Debug, Clone, Fail)]
 pub enum Error {
     Api(hyper::StatusCode, Option<ErrorMessage>),
+    Validation(hyper::StatusCode, ErrorMessage, ValidationErrors),
     Network(String),
     Parse(String),
+    Timeout,
+    DuplicateRequest(Option<ErrorMessage>),
     Unknown(String),
 }
 
@@ -26,9 +58,46 @@ impl fmt::Display for Error {
                 status, error_message.code, error_message.description, error_message.payload
             ),
             Error::Api(status, None) => write!(f, "API client 100: Api error: status: {}", status),
+            Error::Validation(ref status, ref error_message, ref errors) => write!(
+                f,
+                "API client 100: Validation error: status: {}, code: {}, description: {}, errors: {:?}",
+                status, error_message.code, error_message.description, errors
+            ),
             Error::Network(ref err) => write!(f, "API client 200: Network error: {}", err),
             Error::Parse(ref err) => write!(f, "API client 300: Parse error: {}", err),
             Error::Unknown(ref err) => write!(f, "API client 400: Unknown error: {}", err),
+            Error::Timeout => write!(f, "API client 500: Request deadline exceeded"),
+            Error::DuplicateRequest(Some(ref error_message)) => write!(
+                f,
+                "API client 600: Duplicate request: code: {}, description: {}",
+                error_message.code, error_message.description
+            ),
+            Error::DuplicateRequest(None) => write!(f, "API client 600: Duplicate request"),
+        }
+    }
+}
+
+impl Error {
+    /// Builds error from the response of failed request, recognizing validation errors
+    pub fn from_response(status: hyper::StatusCode, error_message: ErrorMessage) -> Self {
+        match error_message.validation_errors() {
+            Some(errors) => Error::Validation(status, error_message, errors),
+            None => Error::Api(status, Some(error_message)),
+        }
+    }
+
+    /// Conflict responses to requests with `Idempotency-Key` mean the operation was already performed
+    pub(crate) fn into_duplicate_request(self) -> Self {
+        match self {
+            Error::Api(status, error_message) if status == hyper::StatusCode::CONFLICT => {
+                Error::DuplicateRequest(error_message)
+            }
+            Error::Validation(status, error_message, _)
+                if status == hyper::StatusCode::CONFLICT =>
+            {
+                Error::DuplicateRequest(Some(error_message))
+            }
+            other => other,
         }
     }
 }
@@ -1,32 +1,45 @@
 extern crate chrono;
 #[macro_use]
 extern crate failure;
+extern crate flate2;
 extern crate futures;
 extern crate geo;
 extern crate hyper;
 #[macro_use]
 extern crate lazy_static;
-extern crate regex;
 extern crate reqwest;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
+extern crate stq_cache;
 extern crate stq_roles;
 extern crate stq_router;
 extern crate stq_static_resources;
 extern crate stq_types;
 extern crate tokio_core;
+extern crate tokio_timer;
+extern crate url;
 extern crate validator;
 #[macro_use]
 extern crate validator_derive;
 extern crate uuid;
 
+pub mod billing;
+pub mod cart_totals;
+pub mod currency_exchange;
+pub mod delivery;
 pub mod errors;
+pub mod notifications;
 pub mod orders;
 pub mod pages;
+pub mod response_cache;
+pub mod retry;
 pub mod roles;
 pub mod rpc_client;
+pub mod saga;
+pub mod stores;
 pub mod types;
+pub mod users;
 pub mod util;
 pub mod warehouses;
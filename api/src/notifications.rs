@@ -0,0 +1,240 @@
+use errors::Error;
+use rpc_client::RestApiClient;
+use types::*;
+use util::*;
+
+use futures::future;
+use stq_router::{Builder as RouterBuilder, Router};
+use stq_static_resources::{
+    DisputeOpenedForStore, DisputeOpenedForUser, DisputeResolvedForStore, DisputeResolvedForUser,
+    Email, Language, OrderCreateForStore, OrderCreateForUser, OrderUpdateStateForStore,
+    OrderUpdateStateForUser, PayoutSentForStore, PreOrderReminderForUser, RefundIssuedForUser,
+    SimpleMail, TemplateRenderer,
+};
+
+#[derive(Clone, Debug)]
+pub enum Route {
+    SimpleMail,
+    OrderUpdateStateForUser,
+    OrderUpdateStateForStore,
+    OrderCreateForUser,
+    OrderCreateForStore,
+    DisputeOpenedForUser,
+    DisputeOpenedForStore,
+    DisputeResolvedForUser,
+    DisputeResolvedForStore,
+    RefundIssuedForUser,
+    PayoutSentForStore,
+    PreOrderReminderForUser,
+}
+
+impl RouteBuilder for Route {
+    fn route(&self) -> String {
+        use self::Route::*;
+
+        match self {
+            SimpleMail => "simple-mail".to_string(),
+            OrderUpdateStateForUser => "users/order-update-state".to_string(),
+            OrderUpdateStateForStore => "stores/order-update-state".to_string(),
+            OrderCreateForUser => "users/order-create".to_string(),
+            OrderCreateForStore => "stores/order-create".to_string(),
+            DisputeOpenedForUser => "users/dispute-opened".to_string(),
+            DisputeOpenedForStore => "stores/dispute-opened".to_string(),
+            DisputeResolvedForUser => "users/dispute-resolved".to_string(),
+            DisputeResolvedForStore => "stores/dispute-resolved".to_string(),
+            RefundIssuedForUser => "users/refund-issued".to_string(),
+            PayoutSentForStore => "stores/payout-sent".to_string(),
+            PreOrderReminderForUser => "users/pre-order-reminder".to_string(),
+        }
+    }
+}
+
+impl Route {
+    pub fn from_path(s: &str) -> Option<Self> {
+        lazy_static! {
+            static ref ROUTER: Router<Route> = RouterBuilder::default()
+                .with_route(r"^/simple-mail$", |_| Some(Route::SimpleMail))
+                .with_route(r"^/users/order-update-state$", |_| Some(
+                    Route::OrderUpdateStateForUser
+                ))
+                .with_route(r"^/stores/order-update-state$", |_| Some(
+                    Route::OrderUpdateStateForStore
+                ))
+                .with_route(r"^/users/order-create$", |_| Some(
+                    Route::OrderCreateForUser
+                ))
+                .with_route(r"^/stores/order-create$", |_| Some(
+                    Route::OrderCreateForStore
+                ))
+                .with_route(r"^/users/dispute-opened$", |_| Some(
+                    Route::DisputeOpenedForUser
+                ))
+                .with_route(r"^/stores/dispute-opened$", |_| Some(
+                    Route::DisputeOpenedForStore
+                ))
+                .with_route(r"^/users/dispute-resolved$", |_| Some(
+                    Route::DisputeResolvedForUser
+                ))
+                .with_route(r"^/stores/dispute-resolved$", |_| Some(
+                    Route::DisputeResolvedForStore
+                ))
+                .with_route(r"^/users/refund-issued$", |_| Some(
+                    Route::RefundIssuedForUser
+                ))
+                .with_route(r"^/stores/payout-sent$", |_| Some(
+                    Route::PayoutSentForStore
+                ))
+                .with_route(r"^/users/pre-order-reminder$", |_| Some(
+                    Route::PreOrderReminderForUser
+                ))
+                .build();
+        }
+
+        ROUTER.test(s)
+    }
+}
+
+pub trait NotificationsClient {
+    fn send_email(&self, mail: SimpleMail) -> ApiFuture<()>;
+
+    /// Renders the email locally and sends it as a simple mail
+    fn send_template_email<E>(&self, email: E) -> ApiFuture<()>
+    where
+        E: Email,
+        Self: Sized,
+    {
+        match email.try_into_send_mail() {
+            Ok(mail) => self.send_email(mail),
+            Err(e) => Box::new(future::err(Error::Unknown(e.to_string()))),
+        }
+    }
+
+    /// Renders the email locally with `renderer` in `lang`, e.g. with templates loaded
+    /// from a directory, and sends it as a simple mail
+    fn send_template_email_with<E, R>(
+        &self,
+        email: E,
+        renderer: &R,
+        lang: Language,
+    ) -> ApiFuture<()>
+    where
+        E: Email,
+        R: TemplateRenderer,
+        Self: Sized,
+    {
+        match email.into_send_mail_with(renderer, lang) {
+            Ok(mail) => self.send_email(mail),
+            Err(e) => Box::new(future::err(Error::Unknown(e.to_string()))),
+        }
+    }
+
+    fn send_order_update_state_for_user(&self, email: OrderUpdateStateForUser) -> ApiFuture<()>;
+    fn send_order_update_state_for_store(&self, email: OrderUpdateStateForStore) -> ApiFuture<()>;
+    fn send_order_create_for_user(&self, email: OrderCreateForUser) -> ApiFuture<()>;
+    fn send_order_create_for_store(&self, email: OrderCreateForStore) -> ApiFuture<()>;
+    fn send_dispute_opened_for_user(&self, email: DisputeOpenedForUser) -> ApiFuture<()>;
+    fn send_dispute_opened_for_store(&self, email: DisputeOpenedForStore) -> ApiFuture<()>;
+    fn send_dispute_resolved_for_user(&self, email: DisputeResolvedForUser) -> ApiFuture<()>;
+    fn send_dispute_resolved_for_store(&self, email: DisputeResolvedForStore) -> ApiFuture<()>;
+    fn send_refund_issued_for_user(&self, email: RefundIssuedForUser) -> ApiFuture<()>;
+    fn send_payout_sent_for_store(&self, email: PayoutSentForStore) -> ApiFuture<()>;
+    fn send_pre_order_reminder_for_user(&self, email: PreOrderReminderForUser) -> ApiFuture<()>;
+}
+
+impl NotificationsClient for RestApiClient {
+    fn send_email(&self, mail: SimpleMail) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::SimpleMail))
+                .body(JsonPayload(mail)),
+        )
+    }
+
+    fn send_order_update_state_for_user(&self, email: OrderUpdateStateForUser) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::OrderUpdateStateForUser))
+                .body(JsonPayload(email)),
+        )
+    }
+
+    fn send_order_update_state_for_store(&self, email: OrderUpdateStateForStore) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::OrderUpdateStateForStore))
+                .body(JsonPayload(email)),
+        )
+    }
+
+    fn send_order_create_for_user(&self, email: OrderCreateForUser) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::OrderCreateForUser))
+                .body(JsonPayload(email)),
+        )
+    }
+
+    fn send_order_create_for_store(&self, email: OrderCreateForStore) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::OrderCreateForStore))
+                .body(JsonPayload(email)),
+        )
+    }
+
+    fn send_dispute_opened_for_user(&self, email: DisputeOpenedForUser) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::DisputeOpenedForUser))
+                .body(JsonPayload(email)),
+        )
+    }
+
+    fn send_dispute_opened_for_store(&self, email: DisputeOpenedForStore) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::DisputeOpenedForStore))
+                .body(JsonPayload(email)),
+        )
+    }
+
+    fn send_dispute_resolved_for_user(&self, email: DisputeResolvedForUser) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::DisputeResolvedForUser))
+                .body(JsonPayload(email)),
+        )
+    }
+
+    fn send_dispute_resolved_for_store(&self, email: DisputeResolvedForStore) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::DisputeResolvedForStore))
+                .body(JsonPayload(email)),
+        )
+    }
+
+    fn send_refund_issued_for_user(&self, email: RefundIssuedForUser) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::RefundIssuedForUser))
+                .body(JsonPayload(email)),
+        )
+    }
+
+    fn send_payout_sent_for_store(&self, email: PayoutSentForStore) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::PayoutSentForStore))
+                .body(JsonPayload(email)),
+        )
+    }
+
+    fn send_pre_order_reminder_for_user(&self, email: PreOrderReminderForUser) -> ApiFuture<()> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::PreOrderReminderForUser))
+                .body(JsonPayload(email)),
+        )
+    }
+}
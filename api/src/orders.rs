@@ -1,17 +1,22 @@
+use errors::Error;
 use rpc_client::RestApiClient;
 use types::*;
 use util::*;
 
 use chrono::prelude::*;
-use regex::Regex;
+use futures::{future, stream, Future, Stream};
+use reqwest::Method;
+use url::form_urlencoded;
 use uuid::Uuid;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use stq_roles;
 use stq_router::{Builder as RouterBuilder, Router};
 use stq_static_resources::{CommitterRole, Currency, CurrencyType, OrderState};
 use stq_types::*;
-use validator::{Validate, ValidationError};
+use validator::Validate;
+
+pub use stq_types::{validate_email, validate_phone};
 
 #[derive(Clone, Debug)]
 pub enum Route {
@@ -21,6 +26,9 @@ pub enum Route {
     CartProducts {
         customer: CartCustomer,
     },
+    CartProductsBulk {
+        customer: CartCustomer,
+    },
     CartIncrementProduct {
         customer: CartCustomer,
         product_id: ProductId,
@@ -68,6 +76,7 @@ pub enum Route {
     OrderFromBuyNow,
     OrderFromCartRevert,
     OrderSearch,
+    OrderSearchPaged,
     Orders,
     OrdersByUser {
         user: UserId,
@@ -75,16 +84,26 @@ pub enum Route {
     OrdersByStore {
         store_id: StoreId,
     },
+    OrdersByUserPaged {
+        user: UserId,
+    },
+    OrdersByStorePaged {
+        store_id: StoreId,
+    },
     Order {
         order_id: OrderIdentifier,
     },
     OrderDiff {
         order_id: OrderIdentifier,
     },
+    OrderDiffSearch {
+        order_id: OrderIdentifier,
+    },
     OrderStatus {
         order_id: OrderIdentifier,
     },
     OrdersAllowedStatuses,
+    OrdersStatuses,
     Roles(stq_roles::routing::Route),
 }
 
@@ -119,6 +138,9 @@ impl RouteBuilder for Route {
         match self {
             Cart { customer } => format!("cart/{}", cart_customer_route(customer)),
             CartProducts { customer } => format!("cart/{}/products", cart_customer_route(customer)),
+            CartProductsBulk { customer } => {
+                format!("cart/{}/products/bulk", cart_customer_route(customer))
+            }
             CartIncrementProduct {
                 customer,
                 product_id,
@@ -203,15 +225,22 @@ impl RouteBuilder for Route {
             OrderFromBuyNow => "orders/create_buy_now".to_string(),
             OrderFromCartRevert => "orders/create_from_cart/revert".to_string(),
             OrderSearch => "orders/search".to_string(),
+            OrderSearchPaged => "orders/search/paged".to_string(),
             Orders => "orders".to_string(),
             OrdersByUser { user } => format!("orders/by-user/{}", user),
             OrdersByStore { store_id } => format!("orders/by-store/{}", store_id),
+            OrdersByUserPaged { user } => format!("orders/by-user/{}/paged", user),
+            OrdersByStorePaged { store_id } => format!("orders/by-store/{}/paged", store_id),
             Order { order_id } => format!("orders/{}", order_identifier_route(order_id)),
             OrderDiff { order_id } => format!("order_diffs/{}", order_identifier_route(order_id)),
+            OrderDiffSearch { order_id } => {
+                format!("order_diffs/{}/search", order_identifier_route(order_id))
+            }
             OrderStatus { order_id } => {
                 format!("orders/{}/status", order_identifier_route(order_id))
             }
             OrdersAllowedStatuses => "orders/allowed_statuses".to_string(),
+            OrdersStatuses => "orders/statuses".to_string(),
             Roles(route) => route.route(),
         }
     }
@@ -553,6 +582,21 @@ impl Route {
                             })
                             .map(|customer| Route::CartProducts { customer })
                     })
+                    .with_route(r"^/cart/by-user/(\d+)/products/bulk$", |params| params
+                        .get(0)
+                        .and_then(|string_id| string_id.parse().ok().map(CartCustomer::User))
+                        .map(|customer| Route::CartProductsBulk { customer }))
+                    .with_route(
+                        r"^/cart/by-session/([a-zA-Z0-9-]+)/products/bulk$",
+                        |params| {
+                            params
+                                .get(0)
+                                .and_then(|string_id| {
+                                    string_id.parse().ok().map(CartCustomer::Anonymous)
+                                })
+                                .map(|customer| Route::CartProductsBulk { customer })
+                        }
+                    )
                     .with_route(r"^/cart/by-user/(\d+)/clear$", |params| params
                         .get(0)
                         .and_then(|string_id| string_id.parse().ok().map(CartCustomer::User))
@@ -572,11 +616,21 @@ impl Route {
                     .with_route(r"^/orders/create_from_cart/revert$", |_| Some(
                         Route::OrderFromCartRevert
                     ))
+                    .with_route(r"^/orders/search/paged$", |_| Some(Route::OrderSearchPaged))
                     .with_route(r"^/orders/search", |_| Some(Route::OrderSearch))
+                    .with_route(r"^/orders/statuses$", |_| Some(Route::OrdersStatuses))
                     .with_route(r"^/orders/by-store/(\d+)$", |params| params
                         .get(0)
                         .and_then(|string_id| string_id.parse().ok())
                         .map(|store_id| Route::OrdersByStore { store_id }))
+                    .with_route(r"^/orders/by-store/(\d+)/paged$", |params| params
+                        .get(0)
+                        .and_then(|string_id| string_id.parse().ok())
+                        .map(|store_id| Route::OrdersByStorePaged { store_id }))
+                    .with_route(r"^/orders/by-user/(\d+)/paged$", |params| params
+                        .get(0)
+                        .and_then(|string_id| string_id.parse().ok())
+                        .map(|user| Route::OrdersByUserPaged { user }))
                     .with_route(r"^/orders/by-id/([a-zA-Z0-9-]+)$", |params| params
                         .get(0)
                         .and_then(|string_id| string_id.parse().ok().map(OrderIdentifier::Id))
@@ -601,6 +655,16 @@ impl Route {
                         .get(0)
                         .and_then(|string_id| string_id.parse().ok().map(OrderIdentifier::Slug))
                         .map(|order_id| Route::OrderDiff { order_id }))
+                    .with_route(r"^/order_diffs/by-id/([a-zA-Z0-9-]+)/search$", |params| {
+                        params
+                            .get(0)
+                            .and_then(|string_id| string_id.parse().ok().map(OrderIdentifier::Id))
+                            .map(|order_id| Route::OrderDiffSearch { order_id })
+                    })
+                    .with_route(r"^/order_diffs/by-slug/(\d+)/search$", |params| params
+                        .get(0)
+                        .and_then(|string_id| string_id.parse().ok().map(OrderIdentifier::Slug))
+                        .map(|order_id| Route::OrderDiffSearch { order_id }))
                     .build();
         }
 
@@ -627,6 +691,22 @@ pub struct CartProductIncrementPayload {
     pub user_country_code: Option<UserCountryCodeUpdater>,
 }
 
+/// Item added to cart by `CartClient::add_items`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NewCartItem {
+    pub product_id: ProductId,
+    pub quantity: Quantity,
+    pub store_id: StoreId,
+    pub pre_order: bool,
+    pub pre_order_days: i32,
+    pub currency_type: CurrencyType,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CartAddItemsPayload {
+    pub items: Vec<NewCartItem>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum UserCountryCodeUpdater {
     Reset,
@@ -677,6 +757,8 @@ pub trait CartClient {
         currency_type: CurrencyType,
         user_country_code: Option<UserCountryCodeUpdater>,
     ) -> ApiFuture<Cart>;
+    /// Add several items at once, quantities of items already in cart are increased
+    fn add_items(&self, customer: CartCustomer, items: Vec<NewCartItem>) -> ApiFuture<Cart>;
     /// Set item to desired quantity in user's cart
     fn set_quantity(
         &self,
@@ -786,6 +868,14 @@ impl CartClient for RestApiClient {
         )
     }
 
+    fn add_items(&self, customer: CartCustomer, items: Vec<NewCartItem>) -> ApiFuture<Cart> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::CartProductsBulk { customer }))
+                .body(JsonPayload(&CartAddItemsPayload { items })),
+        )
+    }
+
     fn set_quantity(
         &self,
         customer: CartCustomer,
@@ -982,8 +1072,8 @@ pub struct Order {
     pub quantity: Quantity,
     pub address: AddressFull,
     pub receiver_name: String,
-    pub receiver_phone: String,
-    pub receiver_email: String,
+    pub receiver_phone: PhoneNumber,
+    pub receiver_email: EmailAddress,
     pub state: OrderState,
     pub payment_status: bool,
     pub delivery_company: Option<String>,
@@ -1004,22 +1094,6 @@ pub struct Order {
     pub currency_type: CurrencyType,
 }
 
-pub fn validate_phone(phone: &str) -> Result<(), ValidationError> {
-    lazy_static! {
-        static ref PHONE_VALIDATION_RE: Regex = Regex::new(r"^\+?\d{7}\d*$").unwrap();
-    }
-
-    if PHONE_VALIDATION_RE.is_match(phone) {
-        Ok(())
-    } else {
-        Err(ValidationError {
-            code: "phone".into(),
-            message: Some("Incorrect phone format".into()),
-            params: HashMap::new(),
-        })
-    }
-}
-
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Validate)]
 pub struct DeliveryInfo {
     pub company_package_id: CompanyPackageId,
@@ -1042,9 +1116,8 @@ pub struct ConvertCartPayload {
     pub conversion_id: Option<ConversionId>,
     pub user_id: UserId,
     pub receiver_name: String,
-    #[validate(custom = "validate_phone")]
-    pub receiver_phone: String,
-    pub receiver_email: String,
+    pub receiver_phone: PhoneNumber,
+    pub receiver_email: EmailAddress,
     #[serde(flatten)]
     pub address: AddressFull,
     pub seller_prices: HashMap<ProductId, ProductSellerPrice>,
@@ -1062,12 +1135,11 @@ pub struct BuyNow {
     pub store_id: StoreId,
     pub address: AddressFull,
     pub receiver_name: String,
-    pub receiver_email: String,
+    pub receiver_email: EmailAddress,
     pub price: ProductSellerPrice,
     pub quantity: Quantity,
     pub currency: Currency,
-    #[validate(custom = "validate_phone")]
-    pub receiver_phone: String,
+    pub receiver_phone: PhoneNumber,
     pub pre_order: bool,
     pub pre_order_days: i32,
     pub coupon: Option<CouponInfo>,
@@ -1082,6 +1154,13 @@ pub struct CouponInfo {
     pub percent: i32,
 }
 
+/// Coupon info carries no rules, so the coupon is applicable to any item
+impl From<CouponInfo> for CouponPolicy {
+    fn from(v: CouponInfo) -> Self {
+        CouponPolicy::percent(v.id, v.percent)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct BuyNowPayload {
     pub conversion_id: Option<ConversionId>,
@@ -1094,6 +1173,9 @@ pub struct ConvertCartRevertPayload {
     pub conversion_id: ConversionId,
 }
 
+/// Number of orders fetched per request by `OrderClient::export_orders`
+pub const ORDERS_EXPORT_CHUNK_SIZE: i64 = 500;
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct OrderSearchTerms {
     pub slug: Option<OrderSlug>,
@@ -1106,6 +1188,21 @@ pub struct OrderSearchTerms {
     pub store: Option<StoreId>,
     pub state: Option<OrderState>,
     pub currency_type: Option<CurrencyType>,
+    // Newer terms are omitted when unset, so that the payload stays accepted by older orders service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_amount_from: Option<ProductPrice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_amount_to: Option<ProductPrice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product: Option<ProductId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delivery_company: Option<String>,
+    /// Country of the delivery address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// Free text matched against receiver's name, phone and email
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receiver: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -1119,7 +1216,66 @@ pub struct OrderDiff {
     pub committer_role: CommitterRole,
 }
 
+/// Filters of the order history, the orders service responds with the latest `limit` matching diffs.
+/// Sent as the query of `Route::OrderDiffSearch`, which services parse with `from_query`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiffSearchTerms {
+    pub committer_role: Option<CommitterRole>,
+    pub state: Option<OrderState>,
+    pub committed_from: Option<DateTime<Utc>>,
+    pub committed_to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+impl DiffSearchTerms {
+    /// Query parameters of the terms set, times are unix timestamps in seconds
+    pub fn to_query(&self) -> String {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        if let Some(committer_role) = self.committer_role {
+            query.append_pair("committer_role", &committer_role.to_string());
+        }
+        if let Some(state) = self.state {
+            query.append_pair("state", &state.to_string());
+        }
+        if let Some(ref committed_from) = self.committed_from {
+            query.append_pair("committed_from", &committed_from.timestamp().to_string());
+        }
+        if let Some(ref committed_to) = self.committed_to {
+            query.append_pair("committed_to", &committed_to.timestamp().to_string());
+        }
+        if let Some(limit) = self.limit {
+            query.append_pair("limit", &limit.to_string());
+        }
+        query.finish()
+    }
+
+    /// Parses query parameters produced by `to_query`, unknown parameters are ignored
+    pub fn from_query(query: &str) -> Option<Self> {
+        let timestamp = |value: &str| {
+            value
+                .parse()
+                .ok()
+                .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+        };
+        let mut terms = DiffSearchTerms::default();
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "committer_role" => terms.committer_role = Some(value.parse().ok()?),
+                "state" => terms.state = Some(value.parse().ok()?),
+                "committed_from" => terms.committed_from = Some(timestamp(&value)?),
+                "committed_to" => terms.committed_to = Some(timestamp(&value)?),
+                "limit" => terms.limit = Some(value.parse().ok()?),
+                _ => {}
+            }
+        }
+        Some(terms)
+    }
+}
+
 pub trait OrderClient {
+    /// `conversion_id` identifies the operation, it is sent as `Idempotency-Key`
+    /// so that repeated conversions are dropped. Receiver contacts are normalized,
+    /// invalid ones fail with `Error::Parse` before the request is sent
     fn convert_cart(
         &self,
         conversion_id: Option<ConversionId>,
@@ -1135,6 +1291,7 @@ pub trait OrderClient {
         uuid: Uuid,
         currency_type: Option<CurrencyType>,
     ) -> ApiFuture<Vec<Order>>;
+    /// `conversion_id` is sent as `Idempotency-Key`, same as in `convert_cart`
     fn create_buy_now(
         &self,
         payload: BuyNow,
@@ -1143,9 +1300,60 @@ pub trait OrderClient {
     fn revert_cart_conversion(&self, conversion_id: ConversionId) -> ApiFuture<()>;
     fn get_order(&self, id: OrderIdentifier) -> ApiFuture<Option<Order>>;
     fn get_order_diff(&self, id: OrderIdentifier) -> ApiFuture<Vec<OrderDiff>>;
+    /// History of the order, only the diffs matching the terms.
+    /// Fails by default, so that other implementations of the trait keep compiling.
+    fn search_order_diffs(
+        &self,
+        _id: OrderIdentifier,
+        _terms: DiffSearchTerms,
+    ) -> ApiFuture<Vec<OrderDiff>> {
+        Box::new(future::err(Error::Unknown(
+            "search_order_diffs is not supported by this client".to_string(),
+        )))
+    }
+    /// States orders can be moved to from each state, as enforced by the orders service.
+    /// Fails by default, so that other implementations of the trait keep compiling.
+    fn get_allowed_statuses(&self) -> ApiFuture<HashMap<OrderState, HashSet<OrderState>>> {
+        Box::new(future::err(Error::Unknown(
+            "get_allowed_statuses is not supported by this client".to_string(),
+        )))
+    }
     fn get_orders_for_user(&self, user_id: UserId) -> ApiFuture<Vec<Order>>;
     fn get_orders_for_store(&self, store_id: StoreId) -> ApiFuture<Vec<Order>>;
+    /// Orders of the user, sorted by creation time.
+    /// Fails by default, so that other implementations of the trait keep compiling.
+    fn get_orders_for_user_paged(
+        &self,
+        _user_id: UserId,
+        _page: PageParams,
+    ) -> ApiFuture<Page<Order>> {
+        Box::new(future::err(Error::Unknown(
+            "get_orders_for_user_paged is not supported by this client".to_string(),
+        )))
+    }
+    /// Orders of the store, sorted by creation time.
+    /// Fails by default, so that other implementations of the trait keep compiling.
+    fn get_orders_for_store_paged(
+        &self,
+        _store_id: StoreId,
+        _page: PageParams,
+    ) -> ApiFuture<Page<Order>> {
+        Box::new(future::err(Error::Unknown(
+            "get_orders_for_store_paged is not supported by this client".to_string(),
+        )))
+    }
     fn delete_order(&self, id: OrderIdentifier) -> ApiFuture<()>;
+    /// Updates the fields set in `update_data`, e.g. to fix the address entered by the buyer.
+    /// Fails by default, so that other implementations of the trait keep compiling.
+    fn update_order(
+        &self,
+        _order_id: OrderIdentifier,
+        _update_data: OrderUpdateData,
+    ) -> ApiFuture<Option<Order>> {
+        Box::new(future::err(Error::Unknown(
+            "update_order is not supported by this client".to_string(),
+        )))
+    }
     fn set_order_state(
         &self,
         order_id: OrderIdentifier,
@@ -1154,8 +1362,37 @@ pub trait OrderClient {
         track_id: Option<String>,
         committer_role: CommitterRole,
     ) -> ApiFuture<Option<Order>>;
+    /// Sets states of several orders in a single request, returns updated orders.
+    /// Fails by default, so that other implementations of the trait keep compiling.
+    fn set_order_states(
+        &self,
+        _states: Vec<(OrderIdentifier, OrderState)>,
+        _comment: Option<String>,
+        _committer_role: CommitterRole,
+    ) -> ApiFuture<Vec<Order>> {
+        Box::new(future::err(Error::Unknown(
+            "set_order_states is not supported by this client".to_string(),
+        )))
+    }
     /// Search using the terms provided.
     fn search(&self, terms: OrderSearchTerms) -> ApiFuture<Vec<Order>>;
+    /// Single page of search results, sorted by creation time.
+    /// Fails by default, so that other implementations of the trait keep compiling.
+    fn search_paged(&self, _terms: OrderSearchTerms, _page: PageParams) -> ApiFuture<Page<Order>> {
+        Box::new(future::err(Error::Unknown(
+            "search_paged is not supported by this client".to_string(),
+        )))
+    }
+    /// All orders matching the terms, fetched page by page as the stream is consumed.
+    /// Fails by default, so that other implementations of the trait keep compiling.
+    fn export_orders(&self, _terms: OrderSearchTerms) -> ApiStream<Order> {
+        Box::new(
+            future::err(Error::Unknown(
+                "export_orders is not supported by this client".to_string(),
+            ))
+            .into_stream(),
+        )
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -1166,6 +1403,23 @@ pub struct UpdateStatePayload {
     pub committer_role: CommitterRole,
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrderUpdateData {
+    pub receiver_name: Option<ValueContainer<String>>,
+    pub receiver_phone: Option<ValueContainer<String>>,
+    pub receiver_email: Option<ValueContainer<String>>,
+    pub address: Option<ValueContainer<AddressFull>>,
+    pub delivery_company: Option<ValueContainer<Option<String>>>,
+    pub track_id: Option<ValueContainer<Option<String>>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UpdateStatesPayload {
+    pub states: Vec<(OrderIdentifier, OrderState)>,
+    pub comment: Option<String>,
+    pub committer_role: CommitterRole,
+}
+
 impl OrderClient for RestApiClient {
     fn convert_cart(
         &self,
@@ -1182,9 +1436,20 @@ impl OrderClient for RestApiClient {
         uuid: Uuid,
         currency_type: Option<CurrencyType>,
     ) -> ApiFuture<Vec<Order>> {
+        let contacts = PhoneNumber::parse(&receiver_phone)
+            .and_then(|phone| EmailAddress::parse(&receiver_email).map(|email| (phone, email)));
+        let (receiver_phone, receiver_email) = match contacts {
+            Ok(contacts) => contacts,
+            Err(e) => return Box::new(future::err(Error::Parse(e.to_string()))),
+        };
+
         http_req(
             self.http_client
-                .post(&self.build_route(&Route::OrderFromCart))
+                .idempotent_request(
+                    Method::POST,
+                    &self.build_route(&Route::OrderFromCart),
+                    conversion_id.map(|id| id.to_string()),
+                )
                 .body(JsonPayload(ConvertCartPayload {
                     conversion_id,
                     user_id,
@@ -1209,7 +1474,11 @@ impl OrderClient for RestApiClient {
     ) -> ApiFuture<Vec<Order>> {
         http_req(
             self.http_client
-                .post(&self.build_route(&Route::OrderFromBuyNow))
+                .idempotent_request(
+                    Method::POST,
+                    &self.build_route(&Route::OrderFromBuyNow),
+                    conversion_id.map(|id| id.to_string()),
+                )
                 .body(JsonPayload(BuyNowPayload {
                     conversion_id,
                     buy_now,
@@ -1236,6 +1505,26 @@ impl OrderClient for RestApiClient {
                 .get(&self.build_route(&Route::OrderDiff { order_id })),
         )
     }
+    fn search_order_diffs(
+        &self,
+        order_id: OrderIdentifier,
+        terms: DiffSearchTerms,
+    ) -> ApiFuture<Vec<OrderDiff>> {
+        let route = self.build_route(&Route::OrderDiffSearch { order_id });
+        let query = terms.to_query();
+        let url = if query.is_empty() {
+            route
+        } else {
+            format!("{}?{}", route, query)
+        };
+        http_req(self.http_client.get(&url))
+    }
+    fn get_allowed_statuses(&self) -> ApiFuture<HashMap<OrderState, HashSet<OrderState>>> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::OrdersAllowedStatuses)),
+        )
+    }
     fn get_orders_for_user(&self, user: UserId) -> ApiFuture<Vec<Order>> {
         http_req(
             self.http_client
@@ -1248,12 +1537,41 @@ impl OrderClient for RestApiClient {
                 .get(&self.build_route(&Route::OrdersByStore { store_id })),
         )
     }
+    fn get_orders_for_user_paged(&self, user: UserId, page: PageParams) -> ApiFuture<Page<Order>> {
+        http_req(self.http_client.get(&format!(
+            "{}?{}",
+            self.build_route(&Route::OrdersByUserPaged { user }),
+            page.to_query()
+        )))
+    }
+    fn get_orders_for_store_paged(
+        &self,
+        store_id: StoreId,
+        page: PageParams,
+    ) -> ApiFuture<Page<Order>> {
+        http_req(self.http_client.get(&format!(
+            "{}?{}",
+            self.build_route(&Route::OrdersByStorePaged { store_id }),
+            page.to_query()
+        )))
+    }
     fn delete_order(&self, order_id: OrderIdentifier) -> ApiFuture<()> {
         http_req(
             self.http_client
                 .delete(&self.build_route(&Route::Order { order_id })),
         )
     }
+    fn update_order(
+        &self,
+        order_id: OrderIdentifier,
+        update_data: OrderUpdateData,
+    ) -> ApiFuture<Option<Order>> {
+        http_req(
+            self.http_client
+                .patch(&self.build_route(&Route::Order { order_id }))
+                .body(JsonPayload(update_data)),
+        )
+    }
     fn set_order_state(
         &self,
         order_id: OrderIdentifier,
@@ -1273,6 +1591,22 @@ impl OrderClient for RestApiClient {
                 })),
         )
     }
+    fn set_order_states(
+        &self,
+        states: Vec<(OrderIdentifier, OrderState)>,
+        comment: Option<String>,
+        committer_role: CommitterRole,
+    ) -> ApiFuture<Vec<Order>> {
+        http_req(
+            self.http_client
+                .put(&self.build_route(&Route::OrdersStatuses))
+                .body(JsonPayload(UpdateStatesPayload {
+                    states,
+                    comment,
+                    committer_role,
+                })),
+        )
+    }
     fn search(&self, terms: OrderSearchTerms) -> ApiFuture<Vec<Order>> {
         http_req(
             self.http_client
@@ -1280,4 +1614,104 @@ impl OrderClient for RestApiClient {
                 .body(JsonPayload(terms)),
         )
     }
+    fn search_paged(&self, terms: OrderSearchTerms, page: PageParams) -> ApiFuture<Page<Order>> {
+        http_req(
+            self.http_client
+                .post(&format!(
+                    "{}?{}",
+                    self.build_route(&Route::OrderSearchPaged),
+                    page.to_query()
+                ))
+                .body(JsonPayload(terms)),
+        )
+    }
+    fn export_orders(&self, terms: OrderSearchTerms) -> ApiStream<Order> {
+        let client = self.clone();
+        Box::new(
+            stream::unfold(Some(0), move |offset| {
+                let offset = offset?;
+                let page = PageParams {
+                    offset,
+                    limit: Some(ORDERS_EXPORT_CHUNK_SIZE),
+                    sort: Some(SortOrder::Asc),
+                };
+                Some(client.search_paged(terms.clone(), page).map(move |res| {
+                    // Services responding with the older shape don't report the offset
+                    let res = Page { offset, ..res };
+                    let next = res.next_offset();
+                    (stream::iter_ok(res.items), next)
+                }))
+            })
+            .flatten(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json;
+
+    #[test]
+    fn test_diff_search_terms_query_round_trip() {
+        let terms = DiffSearchTerms {
+            committer_role: Some(CommitterRole::Seller),
+            state: Some(OrderState::Paid),
+            committed_from: Some(Utc.timestamp(1_500_000_000, 0)),
+            committed_to: Some(Utc.timestamp(1_600_000_000, 0)),
+            limit: Some(10),
+        };
+
+        assert_eq!(
+            DiffSearchTerms::from_query(&terms.to_query()),
+            Some(terms.clone())
+        );
+        assert_eq!(
+            DiffSearchTerms::from_query(""),
+            Some(DiffSearchTerms::default())
+        );
+        assert_eq!(DiffSearchTerms::from_query("limit=ten"), None);
+        assert_eq!(DiffSearchTerms::from_query("state=unknown"), None);
+    }
+
+    #[test]
+    fn test_order_diff_routes() {
+        let order_id = OrderIdentifier::Slug(OrderSlug(42));
+
+        match Route::from_path(&format!("/{}", Route::OrderDiff { order_id }.route())) {
+            Some(Route::OrderDiff { order_id: parsed }) => assert_eq!(parsed, order_id),
+            other => panic!("unexpected route: {:?}", other),
+        }
+        match Route::from_path(&format!("/{}", Route::OrderDiffSearch { order_id }.route())) {
+            Some(Route::OrderDiffSearch { order_id: parsed }) => assert_eq!(parsed, order_id),
+            other => panic!("unexpected route: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_order_update_data_serialization() {
+        let update_data = OrderUpdateData {
+            receiver_phone: Some("+79991234567".to_string().into()),
+            track_id: Some(ValueContainer { value: None }),
+            ..Default::default()
+        };
+
+        let serialized = serde_json::to_string(&update_data).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"receiver_name":null,"receiver_phone":{"value":"+79991234567"},"receiver_email":null,"address":null,"delivery_company":null,"track_id":{"value":null}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<OrderUpdateData>(&serialized).unwrap(),
+            update_data
+        );
+        assert_eq!(
+            serde_json::from_str::<OrderUpdateData>(r#"{"track_id":{"value":null}}"#).unwrap(),
+            OrderUpdateData {
+                track_id: Some(ValueContainer { value: None }),
+                ..Default::default()
+            }
+        );
+    }
 }
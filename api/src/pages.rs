@@ -45,11 +45,7 @@ impl Route {
                 .with_route(r"^/pages/by-slug/([a-zA-Z0-9-]+)$", |params| params
                     .into_iter()
                     .next()
-                    .and_then(|string_id| string_id
-                        .parse()
-                        .ok()
-                        .map(|s: PageSlug| s.0.to_lowercase().into())
-                        .map(PageIdentifier::Slug))
+                    .and_then(|string_id| string_id.parse().ok().map(PageIdentifier::Slug))
                     .map(|identifier| Route::Page { identifier }))
                 .build();
         }
@@ -76,9 +72,22 @@ pub struct NewPage {
     pub css: String,
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UpdatePage {
+    pub slug: Option<ValueContainer<PageSlug>>,
+    pub html: Option<ValueContainer<String>>,
+    pub css: Option<ValueContainer<String>>,
+}
+
 pub trait PageClient {
     fn get_page(&self, identifier: PageIdentifier) -> ApiFuture<Option<Page>>;
     fn insert_page(&self, item: NewPage) -> ApiFuture<Page>;
+    fn update_page(
+        &self,
+        identifier: PageIdentifier,
+        update: UpdatePage,
+    ) -> ApiFuture<Option<Page>>;
+    fn delete_page(&self, identifier: PageIdentifier) -> ApiFuture<Option<Page>>;
 }
 
 impl PageClient for RestApiClient {
@@ -96,4 +105,23 @@ impl PageClient for RestApiClient {
                 .body(JsonPayload(item)),
         )
     }
+
+    fn update_page(
+        &self,
+        identifier: PageIdentifier,
+        update: UpdatePage,
+    ) -> ApiFuture<Option<Page>> {
+        http_req(
+            self.http_client
+                .put(&self.build_route(&Route::Page { identifier }))
+                .body(JsonPayload(update)),
+        )
+    }
+
+    fn delete_page(&self, identifier: PageIdentifier) -> ApiFuture<Option<Page>> {
+        http_req(
+            self.http_client
+                .delete(&self.build_route(&Route::Page { identifier })),
+        )
+    }
 }
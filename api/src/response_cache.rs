@@ -0,0 +1,40 @@
+use std::fmt;
+use std::sync::Arc;
+use stq_cache::cache::Cache;
+
+/// Body of a successful GET response along with its `ETag`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub etag: String,
+    pub body: String,
+}
+
+/// Storage of `RestApiClient` responses, keyed by URL.
+/// Cache failures are ignored, the request is then sent as if nothing was cached.
+pub trait ResponseCache: Send + Sync {
+    fn get(&self, url: &str) -> Option<CachedResponse>;
+
+    fn set(&self, url: &str, response: CachedResponse);
+}
+
+impl<C> ResponseCache for C
+where
+    C: Cache<CachedResponse> + Send + Sync,
+{
+    fn get(&self, url: &str) -> Option<CachedResponse> {
+        Cache::get(self, url).ok().and_then(|response| response)
+    }
+
+    fn set(&self, url: &str, response: CachedResponse) {
+        let _ = Cache::set(self, url, response);
+    }
+}
+
+#[derive(Clone)]
+pub struct SharedResponseCache(pub Arc<ResponseCache>);
+
+impl fmt::Debug for SharedResponseCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SharedResponseCache")
+    }
+}
@@ -0,0 +1,95 @@
+use errors::Error;
+
+use reqwest::Method;
+use std::cmp;
+use std::time::Duration;
+
+/// Describes when and how often failed requests of `RestApiClient` are retried.
+/// Only requests of idempotent methods and requests sent with `Idempotency-Key` header are retried.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts including the first one
+    pub max_attempts: usize,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub backoff_multiplier: u32,
+    pub retry_on_server_errors: bool,
+    pub retry_on_network_errors: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+            backoff_multiplier: 2,
+            retry_on_server_errors: true,
+            retry_on_network_errors: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn should_retry(&self, method: &Method, attempt: usize, err: &Error) -> bool {
+        self.should_retry_request(method, false, attempt, err)
+    }
+
+    /// Same as `should_retry`, but requests sent with `Idempotency-Key` header are retried whatever the method,
+    /// as the service performs the operation once for all requests with the same key
+    pub fn should_retry_request(
+        &self,
+        method: &Method,
+        has_idempotency_key: bool,
+        attempt: usize,
+        err: &Error,
+    ) -> bool {
+        if attempt >= self.max_attempts || !(has_idempotency_key || is_idempotent(method)) {
+            return false;
+        }
+
+        match err {
+            Error::Network(_) => self.retry_on_network_errors,
+            Error::Api(status, _) => self.retry_on_server_errors && status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    /// Delay before the next attempt, `attempt` starts from 1
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let mut backoff = self.initial_backoff;
+        for _ in 1..attempt {
+            backoff = cmp::min(backoff * self.backoff_multiplier, self.max_backoff);
+        }
+        cmp::min(backoff, self.max_backoff)
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    match *method {
+        Method::GET
+        | Method::HEAD
+        | Method::PUT
+        | Method::DELETE
+        | Method::OPTIONS
+        | Method::TRACE => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_post_is_retried_with_idempotency_key() {
+        let policy = RetryPolicy::default();
+        let err = Error::Network("Connection reset".to_string());
+
+        assert!(policy.should_retry(&Method::GET, 1, &err));
+        assert!(!policy.should_retry(&Method::POST, 1, &err));
+        assert!(policy.should_retry_request(&Method::POST, true, 1, &err));
+        assert!(!policy.should_retry_request(&Method::POST, true, 3, &err));
+        assert!(!policy.should_retry_request(&Method::POST, true, 1, &Error::Timeout));
+    }
+}
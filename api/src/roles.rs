@@ -1,19 +1,26 @@
+use errors::Error;
 use rpc_client::RestApiClient;
 use types::*;
 use util::*;
 
+use futures::future;
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
 use std::fmt::Debug;
 use stq_roles::models::*;
 use stq_roles::routing::*;
-use stq_types::UserId;
+use stq_types::{RoleEntryId, UserId};
 
 impl RouteBuilder for Route {
     fn route(&self) -> String {
         match self {
             Route::Roles => "roles".into(),
+            Route::RolesBulk => "roles/bulk".into(),
+            Route::RolesByRole => "roles/by-role".into(),
             Route::RoleById(entry_id) => format!("roles/by-id/{}", entry_id),
             Route::RolesByUserId(user_id) => format!("roles/by-user-id/{}", user_id),
+            Route::RolesPurgeExpired => "roles/purge-expired".into(),
+            Route::RolesAuditByUserId(user_id) => format!("roles/audit/by-user-id/{}", user_id),
         }
     }
 }
@@ -23,8 +30,23 @@ where
     T: RoleModel + Clone + Debug + Serialize + DeserializeOwned + Send,
 {
     fn get_roles_for_user(&self, user_id: UserId) -> ApiFuture<T>;
+    fn search_roles_for_user(
+        &self,
+        user_id: UserId,
+        query: RolesQuery,
+    ) -> ApiFuture<Vec<RoleEntry<T>>>;
+    fn get_users_by_role(
+        &self,
+        role: T,
+        from: Option<UserId>,
+        count: Option<i32>,
+    ) -> ApiFuture<Vec<UserId>>;
     fn create_role(&self, item: RoleEntry<T>) -> ApiFuture<RoleEntry<T>>;
+    fn create_roles(&self, items: Vec<RoleEntry<T>>) -> ApiFuture<Vec<RoleEntry<T>>>;
+    fn update_role(&self, id: RoleEntryId, data: Value) -> ApiFuture<RoleEntry<T>>;
     fn remove_role(&self, terms: RoleSearchTerms<T>) -> ApiFuture<Option<RoleEntry<T>>>;
+    fn purge_expired_roles(&self) -> ApiFuture<Vec<RoleEntry<T>>>;
+    fn get_role_audit_for_user(&self, user_id: UserId) -> ApiFuture<Vec<RoleAuditEntry<T>>>;
 }
 
 impl<T> RolesClient<T> for RestApiClient
@@ -38,6 +60,37 @@ where
         )
     }
 
+    fn search_roles_for_user(
+        &self,
+        user_id: UserId,
+        query: RolesQuery,
+    ) -> ApiFuture<Vec<RoleEntry<T>>> {
+        match query.to_query() {
+            Ok(query) => http_req(self.http_client.get(&format!(
+                "{}?{}",
+                self.build_route(&Route::RolesByUserId(user_id)),
+                query
+            ))),
+            Err(e) => Box::new(future::err(Error::Parse(e.to_string()))),
+        }
+    }
+
+    fn get_users_by_role(
+        &self,
+        role: T,
+        from: Option<UserId>,
+        count: Option<i32>,
+    ) -> ApiFuture<Vec<UserId>> {
+        match (UsersByRoleQuery { role, from, count }).to_query() {
+            Ok(query) => http_req(self.http_client.get(&format!(
+                "{}?{}",
+                self.build_route(&Route::RolesByRole),
+                query
+            ))),
+            Err(e) => Box::new(future::err(Error::Parse(e.to_string()))),
+        }
+    }
+
     fn create_role(&self, item: RoleEntry<T>) -> ApiFuture<RoleEntry<T>> {
         http_req(
             self.http_client
@@ -46,6 +99,22 @@ where
         )
     }
 
+    fn create_roles(&self, items: Vec<RoleEntry<T>>) -> ApiFuture<Vec<RoleEntry<T>>> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::RolesBulk))
+                .body(JsonPayload(items)),
+        )
+    }
+
+    fn update_role(&self, id: RoleEntryId, data: Value) -> ApiFuture<RoleEntry<T>> {
+        http_req(
+            self.http_client
+                .put(&self.build_route(&Route::RoleById(id)))
+                .body(JsonPayload(data)),
+        )
+    }
+
     fn remove_role(&self, terms: RoleSearchTerms<T>) -> ApiFuture<Option<RoleEntry<T>>> {
         http_req(match terms {
             RoleSearchTerms::Id(id) => self
@@ -57,4 +126,18 @@ where
                 .body(JsonPayload(entry)),
         })
     }
+
+    fn purge_expired_roles(&self) -> ApiFuture<Vec<RoleEntry<T>>> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::RolesPurgeExpired)),
+        )
+    }
+
+    fn get_role_audit_for_user(&self, user_id: UserId) -> ApiFuture<Vec<RoleAuditEntry<T>>> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::RolesAuditByUserId(user_id))),
+        )
+    }
 }
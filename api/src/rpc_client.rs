@@ -1,16 +1,164 @@
+use errors::Error;
+use response_cache::{ResponseCache, SharedResponseCache};
+use retry::RetryPolicy;
+use types::{JsonBody, JsonPayload};
 use util::*;
 
 use hyper::{
-    header::{HeaderName, HeaderValue},
+    header::{HeaderName, HeaderValue, CONTENT_ENCODING, CONTENT_TYPE},
     HeaderMap,
 };
-use reqwest::async::{Client as HttpClient, ClientBuilder as HttpClientBuilder};
+use reqwest::async::{Client as HttpClient, ClientBuilder as HttpClientBuilder, RequestBuilder};
+use reqwest::Method;
+use serde::Serialize;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use stq_types::UserId;
+use uuid::Uuid;
+
+/// Request of `RestApiClient`. Keeps everything needed to send it again on retry.
+#[derive(Clone, Debug)]
+pub struct ApiRequest {
+    pub(crate) client: Arc<HttpClient>,
+    pub(crate) method: Method,
+    pub(crate) url: String,
+    /// Failure to serialize the body fails the request once it is sent
+    pub(crate) body: Result<Option<JsonBody>, Error>,
+    pub(crate) gzip_threshold: Option<usize>,
+    pub(crate) retry_policy: Option<RetryPolicy>,
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) idempotency_key: Option<String>,
+    pub(crate) response_cache: Option<SharedResponseCache>,
+}
+
+impl ApiRequest {
+    /// Sets JSON body of the request. Serialization errors don't panic,
+    /// the request fails with them once it is sent.
+    pub fn body<T>(mut self, payload: JsonPayload<T>) -> Self
+    where
+        T: Serialize,
+    {
+        self.body = payload.to_body(self.gzip_threshold).map(Some);
+        self
+    }
+
+    /// Overrides the key sent in `Idempotency-Key` header
+    pub fn idempotency_key<S>(mut self, key: S) -> Self
+    where
+        S: ToString,
+    {
+        self.idempotency_key = Some(key.to_string());
+        self
+    }
+
+    /// Time left before the deadline, `None` if there is no deadline
+    pub(crate) fn time_left(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| {
+            let now = Instant::now();
+            if deadline > now {
+                deadline - now
+            } else {
+                Duration::new(0, 0)
+            }
+        })
+    }
+
+    pub(crate) fn to_request_builder(&self) -> RequestBuilder {
+        let mut builder = self.client.request(self.method.clone(), &self.url);
+        if let Some(time_left) = self.time_left() {
+            let time_left_ms = time_left.as_secs() * 1000 + u64::from(time_left.subsec_millis());
+            builder = builder.header(REQUEST_TIMEOUT_HEADER, time_left_ms.to_string());
+        }
+        if let Some(ref idempotency_key) = self.idempotency_key {
+            builder = builder.header(IDEMPOTENCY_KEY_HEADER, idempotency_key.clone());
+        }
+        match self.body {
+            Ok(Some(ref body)) => {
+                builder = builder.header(CONTENT_TYPE, "application/json");
+                if body.gzipped {
+                    builder = builder.header(CONTENT_ENCODING, "gzip");
+                }
+                builder.body(body.bytes.clone())
+            }
+            _ => builder,
+        }
+    }
+}
+
+/// Header used by `TimeLimitedHttpClient` of stq_http to propagate time budget
+const REQUEST_TIMEOUT_HEADER: &str = "Request-timeout";
+
+/// Header identifying a logical operation, so that the callee can drop its duplicates
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+#[derive(Clone, Debug)]
+pub struct ApiHttpClient {
+    inner: Arc<HttpClient>,
+    gzip_threshold: Option<usize>,
+    retry_policy: Option<RetryPolicy>,
+    deadline: Option<Instant>,
+    response_cache: Option<SharedResponseCache>,
+}
+
+impl ApiHttpClient {
+    pub fn request(&self, method: Method, url: &str) -> ApiRequest {
+        ApiRequest {
+            client: self.inner.clone(),
+            method,
+            url: url.to_string(),
+            body: Ok(None),
+            gzip_threshold: self.gzip_threshold,
+            retry_policy: self.retry_policy.clone(),
+            deadline: self.deadline,
+            idempotency_key: None,
+            response_cache: self.response_cache.clone(),
+        }
+    }
+
+    /// Mutating request sent with `Idempotency-Key` header. The key identifies the logical operation,
+    /// it is generated for every request unless given, e.g. as an id the caller reuses when repeating
+    /// the operation. Retries of the request are sent with the same key.
+    pub fn idempotent_request(&self, method: Method, url: &str, key: Option<String>) -> ApiRequest {
+        let key = key.unwrap_or_else(|| Uuid::new_v4().to_string());
+        self.request(method, url).idempotency_key(key)
+    }
+
+    pub fn get(&self, url: &str) -> ApiRequest {
+        self.request(Method::GET, url)
+    }
+
+    pub fn post(&self, url: &str) -> ApiRequest {
+        self.request(Method::POST, url)
+    }
+
+    pub fn put(&self, url: &str) -> ApiRequest {
+        self.request(Method::PUT, url)
+    }
+
+    pub fn patch(&self, url: &str) -> ApiRequest {
+        self.request(Method::PATCH, url)
+    }
+
+    pub fn delete(&self, url: &str) -> ApiRequest {
+        self.request(Method::DELETE, url)
+    }
+}
+
+impl From<HttpClient> for ApiHttpClient {
+    fn from(v: HttpClient) -> Self {
+        Self {
+            inner: Arc::new(v),
+            gzip_threshold: None,
+            retry_policy: None,
+            deadline: None,
+            response_cache: None,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct RestApiClient {
-    pub(crate) http_client: Arc<HttpClient>,
+    pub(crate) http_client: ApiHttpClient,
     pub(crate) base_url: String,
 }
 
@@ -21,12 +169,11 @@ impl RestApiClient {
     {
         Self {
             base_url: base_url.to_string(),
-            http_client: Arc::new(
-                HttpClientBuilder::new()
-                    .default_headers(RestApiClient::get_auth_headers(caller_id))
-                    .build()
-                    .unwrap(),
-            ),
+            http_client: HttpClientBuilder::new()
+                .default_headers(RestApiClient::get_auth_headers(caller_id))
+                .build()
+                .unwrap()
+                .into(),
         }
     }
 
@@ -47,15 +194,48 @@ impl RestApiClient {
 
         Self {
             base_url: base_url.to_string(),
-            http_client: Arc::new(
-                HttpClientBuilder::new()
-                    .default_headers(default_headers)
-                    .build()
-                    .unwrap(),
-            ),
+            http_client: HttpClientBuilder::new()
+                .default_headers(default_headers)
+                .build()
+                .unwrap()
+                .into(),
         }
     }
 
+    /// Retries failed idempotent requests according to the policy
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.http_client.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Fails requests (including retries) not completed before the deadline.
+    /// Time left is also sent in `Request-timeout` header so that the callee can respect it.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.http_client.deadline = Some(deadline);
+        self
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Compresses request bodies of at least `threshold` bytes with gzip, e.g. carts with many products.
+    /// The callee must accept `Content-Encoding: gzip`.
+    pub fn with_gzip_threshold(mut self, threshold: usize) -> Self {
+        self.http_client.gzip_threshold = Some(threshold);
+        self
+    }
+
+    /// Caches bodies of GET responses having `ETag` and revalidates them with `If-None-Match`,
+    /// cached body is used when the server responds with `304 Not Modified`
+    pub fn with_response_cache<C>(mut self, cache: C) -> Self
+    where
+        C: ResponseCache + 'static,
+    {
+        self.http_client.response_cache = Some(SharedResponseCache(Arc::new(cache)));
+        self
+    }
+
     fn get_auth_headers(caller_id: Option<UserId>) -> HeaderMap {
         match caller_id {
             Some(v) => vec![(
@@ -72,3 +252,91 @@ impl RestApiClient {
         route_builder.build_route(Some(&self.base_url))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gzipped_body_headers() {
+        let client = ApiHttpClient {
+            gzip_threshold: Some(32),
+            ..ApiHttpClient::from(HttpClient::new())
+        };
+
+        let request = client
+            .post("http://localhost/cart")
+            .body(JsonPayload(vec!["product"; 10]))
+            .to_request_builder()
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(request.headers()[CONTENT_ENCODING], "gzip");
+
+        let request = client
+            .post("http://localhost/cart")
+            .body(JsonPayload("product"))
+            .to_request_builder()
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()[CONTENT_TYPE], "application/json");
+        assert!(request.headers().get(CONTENT_ENCODING).is_none());
+
+        let request = client
+            .get("http://localhost/cart")
+            .to_request_builder()
+            .build()
+            .unwrap();
+        assert!(request.headers().get(CONTENT_TYPE).is_none());
+    }
+
+    #[test]
+    fn test_idempotency_key_per_operation() {
+        let client = ApiHttpClient::from(HttpClient::new());
+        let key = |request: ApiRequest| {
+            request.to_request_builder().build().unwrap().headers()[IDEMPOTENCY_KEY_HEADER]
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let first = key(client.idempotent_request(Method::POST, "http://localhost/orders", None));
+        let second = key(client.idempotent_request(Method::POST, "http://localhost/orders", None));
+        assert_ne!(first, second);
+
+        let request = client.idempotent_request(
+            Method::POST,
+            "http://localhost/orders",
+            Some("conversion-1".to_string()),
+        );
+        assert_eq!(key(request.clone()), "conversion-1");
+        assert_eq!(key(request), "conversion-1");
+
+        let request = client.post("http://localhost/orders");
+        assert!(request
+            .to_request_builder()
+            .build()
+            .unwrap()
+            .headers()
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .is_none());
+    }
+
+    #[test]
+    fn test_unserializable_body() {
+        use futures::Future;
+        use std::collections::HashMap;
+
+        let payload = vec![((1, 2), "product")]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let request = ApiHttpClient::from(HttpClient::new())
+            .post("http://localhost/cart")
+            .body(JsonPayload(payload));
+
+        match http_req::<()>(request).wait() {
+            Err(Error::Parse(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
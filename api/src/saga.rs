@@ -0,0 +1,172 @@
+//! Sequential execution of remote calls with compensating actions.
+//!
+//! Every step of a `Saga` is an action paired with an optional compensation.
+//! Steps run one after another; if any of them fails, compensations of the
+//! steps that already succeeded are run in reverse order and the original
+//! error is returned.
+
+use types::*;
+
+use futures::future::{self, Loop};
+use futures::prelude::*;
+use futures::stream;
+use std::vec;
+
+pub type SagaAction = Box<Fn() -> ApiFuture<()> + Send>;
+
+pub struct SagaStep {
+    pub name: String,
+    action: SagaAction,
+    compensation: Option<SagaAction>,
+}
+
+impl SagaStep {
+    pub fn new<S, A>(name: S, action: A) -> Self
+    where
+        S: Into<String>,
+        A: Fn() -> ApiFuture<()> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            action: Box::new(action),
+            compensation: None,
+        }
+    }
+
+    pub fn with_compensation<C>(mut self, compensation: C) -> Self
+    where
+        C: Fn() -> ApiFuture<()> + Send + 'static,
+    {
+        self.compensation = Some(Box::new(compensation));
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct Saga {
+    steps: Vec<SagaStep>,
+}
+
+impl Saga {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_step(mut self, step: SagaStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Runs all steps sequentially. Compensation failures are ignored so that
+    /// every completed step gets a chance to be reverted.
+    pub fn run(self) -> ApiFuture<()> {
+        Box::new(future::loop_fn(
+            (self.steps.into_iter(), Vec::new()),
+            next_step,
+        ))
+    }
+}
+
+type SagaState = (vec::IntoIter<SagaStep>, Vec<SagaStep>);
+
+fn next_step((mut pending, mut completed): SagaState) -> ApiFuture<Loop<(), SagaState>> {
+    let step = match pending.next() {
+        Some(step) => step,
+        None => return Box::new(future::ok(Loop::Break(()))),
+    };
+
+    Box::new(
+        (step.action)().then(move |res| -> ApiFuture<Loop<(), SagaState>> {
+            match res {
+                Ok(()) => {
+                    completed.push(step);
+                    Box::new(future::ok(Loop::Continue((pending, completed))))
+                }
+                Err(e) => Box::new(compensate(completed).then(move |_| Err(e))),
+            }
+        }),
+    )
+}
+
+fn compensate(completed: Vec<SagaStep>) -> Box<Future<Item = (), Error = ()> + Send> {
+    Box::new(stream::iter_ok(completed.into_iter().rev()).for_each(
+        |step| -> Box<Future<Item = (), Error = ()> + Send> {
+            match step.compensation {
+                Some(compensation) => Box::new(compensation().then(|_| Ok(()))),
+                None => Box::new(future::ok(())),
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use errors::Error;
+    use std::sync::{Arc, Mutex};
+
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    fn record(log: &Log, event: &str, ok: bool) -> ApiFuture<()> {
+        log.lock().unwrap().push(event.to_string());
+        if ok {
+            Box::new(future::ok(()))
+        } else {
+            Box::new(future::err(Error::Unknown(format!("{} failed", event))))
+        }
+    }
+
+    fn step(log: &Log, name: &'static str, ok: bool) -> SagaStep {
+        let log = log.clone();
+        SagaStep::new(name, move || record(&log, name, ok))
+    }
+
+    fn undo(step: SagaStep, log: &Log, ok: bool) -> SagaStep {
+        let log = log.clone();
+        let event = format!("undo {}", step.name);
+        step.with_compensation(move || record(&log, &event, ok))
+    }
+
+    #[test]
+    fn test_steps_run_in_order() {
+        let log = Log::default();
+        Saga::new()
+            .with_step(undo(step(&log, "reserve", true), &log, true))
+            .with_step(step(&log, "charge", true))
+            .run()
+            .wait()
+            .unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["reserve", "charge"]);
+    }
+
+    #[test]
+    fn test_completed_steps_are_compensated_in_reverse_order() {
+        let log = Log::default();
+        let res = Saga::new()
+            .with_step(undo(step(&log, "reserve", true), &log, true))
+            .with_step(step(&log, "notify", true))
+            .with_step(undo(step(&log, "charge", true), &log, false))
+            .with_step(undo(step(&log, "ship", false), &log, true))
+            .with_step(step(&log, "complete", true))
+            .run()
+            .wait();
+
+        match res {
+            Err(Error::Unknown(ref message)) => assert_eq!(message, "ship failed"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        // Failed compensation of "charge" doesn't stop compensation of "reserve"
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "reserve",
+                "notify",
+                "charge",
+                "ship",
+                "undo charge",
+                "undo reserve"
+            ]
+        );
+    }
+}
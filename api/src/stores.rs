@@ -0,0 +1,233 @@
+use rpc_client::RestApiClient;
+use types::*;
+use util::*;
+
+use serde_json::Value;
+use std::time::SystemTime;
+use stq_router::{Builder as RouterBuilder, Router};
+use stq_static_resources::{Currency, ModerationStatus};
+use stq_types::*;
+
+#[derive(Clone, Debug)]
+pub enum Route {
+    Store { store_id: StoreIdentifier },
+    StoreModeration { store_id: StoreId },
+    BaseProduct { base_product_id: BaseProductId },
+    BaseProductModeration { base_product_id: BaseProductId },
+    ProductsWithAttributes { base_product_id: BaseProductId },
+}
+
+fn store_identifier_route(id: &StoreIdentifier) -> String {
+    use self::StoreIdentifier::*;
+
+    match id {
+        Id(id) => format!("by-id/{}", id),
+        Slug(slug) => format!("by-slug/{}", slug),
+    }
+}
+
+impl RouteBuilder for Route {
+    fn route(&self) -> String {
+        use self::Route::*;
+
+        match self {
+            Store { store_id } => format!("stores/{}", store_identifier_route(store_id)),
+            StoreModeration { store_id } => format!("stores/by-id/{}/moderation", store_id),
+            BaseProduct { base_product_id } => format!("base_products/{}", base_product_id),
+            BaseProductModeration { base_product_id } => {
+                format!("base_products/{}/moderation", base_product_id)
+            }
+            ProductsWithAttributes { base_product_id } => {
+                format!("base_products/{}/products_with_attributes", base_product_id)
+            }
+        }
+    }
+}
+
+impl Route {
+    pub fn from_path(s: &str) -> Option<Self> {
+        lazy_static! {
+            static ref ROUTER: Router<Route> = RouterBuilder::default()
+                .with_route(r"^/stores/by-id/(\d+)$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok().map(StoreIdentifier::Id))
+                    .map(|store_id| Route::Store { store_id }))
+                .with_route(r"^/stores/by-slug/([a-zA-Z0-9-]+)$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok().map(StoreIdentifier::Slug))
+                    .map(|store_id| Route::Store { store_id }))
+                .with_route(r"^/stores/by-id/(\d+)/moderation$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok())
+                    .map(|store_id| Route::StoreModeration { store_id }))
+                .with_route(r"^/base_products/(\d+)$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok())
+                    .map(|base_product_id| Route::BaseProduct { base_product_id }))
+                .with_route(r"^/base_products/(\d+)/moderation$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok())
+                    .map(|base_product_id| Route::BaseProductModeration {
+                        base_product_id
+                    }))
+                .with_route(
+                    r"^/base_products/(\d+)/products_with_attributes$",
+                    |params| params
+                        .into_iter()
+                        .next()
+                        .and_then(|string_id| string_id.parse().ok())
+                        .map(|base_product_id| Route::ProductsWithAttributes { base_product_id })
+                )
+                .build();
+        }
+
+        ROUTER.test(s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Store {
+    pub id: StoreId,
+    pub user_id: UserId,
+    pub is_active: bool,
+    pub name: Value,
+    pub short_description: Value,
+    pub long_description: Option<Value>,
+    pub slug: StoreSlug,
+    pub cover: Option<String>,
+    pub logo: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub country: Option<String>,
+    pub country_code: Option<Alpha3>,
+    pub status: ModerationStatus,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BaseProduct {
+    pub id: BaseProductId,
+    pub store_id: StoreId,
+    pub is_active: bool,
+    pub name: Value,
+    pub short_description: Value,
+    pub long_description: Option<Value>,
+    pub slug: BaseProductSlug,
+    pub category_id: CategoryId,
+    pub currency: Currency,
+    pub status: ModerationStatus,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Product {
+    pub id: ProductId,
+    pub base_product_id: BaseProductId,
+    pub is_active: bool,
+    pub discount: Option<f64>,
+    pub photo_main: Option<String>,
+    pub vendor_code: String,
+    pub cashback: Option<f64>,
+    pub price: ProductPrice,
+    pub currency: Currency,
+    pub pre_order: bool,
+    pub pre_order_days: i32,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProductAttributeValue {
+    pub attr_id: AttributeId,
+    pub value: AttributeValueCode,
+    pub meta_field: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProductWithAttributes {
+    pub product: Product,
+    pub attributes: Vec<ProductAttributeValue>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModerationStatusPayload {
+    pub status: ModerationStatus,
+}
+
+pub trait StoresClient {
+    fn get_store(&self, store_id: StoreIdentifier) -> ApiFuture<Option<Store>>;
+    fn get_base_product(&self, base_product_id: BaseProductId) -> ApiFuture<Option<BaseProduct>>;
+    /// All products of the base product along with their attribute values
+    fn get_products_with_attributes(
+        &self,
+        base_product_id: BaseProductId,
+    ) -> ApiFuture<Vec<ProductWithAttributes>>;
+    fn set_store_moderation_status(
+        &self,
+        store_id: StoreId,
+        status: ModerationStatus,
+    ) -> ApiFuture<Option<Store>>;
+    fn set_base_product_moderation_status(
+        &self,
+        base_product_id: BaseProductId,
+        status: ModerationStatus,
+    ) -> ApiFuture<Option<BaseProduct>>;
+}
+
+impl StoresClient for RestApiClient {
+    fn get_store(&self, store_id: StoreIdentifier) -> ApiFuture<Option<Store>> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::Store { store_id })),
+        )
+    }
+
+    fn get_base_product(&self, base_product_id: BaseProductId) -> ApiFuture<Option<BaseProduct>> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::BaseProduct { base_product_id })),
+        )
+    }
+
+    fn get_products_with_attributes(
+        &self,
+        base_product_id: BaseProductId,
+    ) -> ApiFuture<Vec<ProductWithAttributes>> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::ProductsWithAttributes { base_product_id })),
+        )
+    }
+
+    fn set_store_moderation_status(
+        &self,
+        store_id: StoreId,
+        status: ModerationStatus,
+    ) -> ApiFuture<Option<Store>> {
+        http_req(
+            self.http_client
+                .put(&self.build_route(&Route::StoreModeration { store_id }))
+                .body(JsonPayload(ModerationStatusPayload { status })),
+        )
+    }
+
+    fn set_base_product_moderation_status(
+        &self,
+        base_product_id: BaseProductId,
+        status: ModerationStatus,
+    ) -> ApiFuture<Option<BaseProduct>> {
+        http_req(
+            self.http_client
+                .put(&self.build_route(&Route::BaseProductModeration { base_product_id }))
+                .body(JsonPayload(ModerationStatusPayload { status })),
+        )
+    }
+}
@@ -1,12 +1,17 @@
 use errors::Error;
 
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use futures::prelude::*;
 use reqwest::async::Body;
 use serde::Serialize;
 use serde_json;
-use std::convert::Into;
+use std::fmt;
+use std::io::Write;
 use tokio_core::reactor::Core;
 
+pub use stq_types::ValueContainer;
+
 pub trait ApiFutureExt<T, E> {
     fn sync(self) -> Result<T, E>;
 }
@@ -20,24 +25,121 @@ impl<T> ApiFutureExt<T, Error> for ApiFuture<T> {
 
 pub type ApiFuture<T> = Box<Future<Item = T, Error = Error> + Send>;
 
+pub type ApiStream<T> = Box<Stream<Item = T, Error = Error> + Send>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SortOrder::Asc => write!(f, "asc"),
+            SortOrder::Desc => write!(f, "desc"),
+        }
+    }
+}
+
+/// Page of a listing to request. Sorting column is defined by the listing itself.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
-pub struct ValueContainer<T> {
-    pub value: T,
+pub struct PageParams {
+    pub offset: i64,
+    pub limit: Option<i64>,
+    pub sort: Option<SortOrder>,
 }
 
-impl<T> From<T> for ValueContainer<T> {
-    fn from(value: T) -> Self {
-        Self { value }
+impl PageParams {
+    pub fn to_query(&self) -> String {
+        let mut query = format!("offset={}", self.offset);
+        if let Some(limit) = self.limit {
+            query.push_str(&format!("&limit={}", limit));
+        }
+        if let Some(sort) = self.sort {
+            query.push_str(&format!("&sort={}", sort));
+        }
+        query
     }
 }
 
 pub struct JsonPayload<T>(pub T);
 
-impl<T> Into<Body> for JsonPayload<T>
+impl<T> JsonPayload<T>
+where
+    T: Serialize,
+{
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string(&self.0)
+            .map_err(|e| Error::Parse(format!("Failed to serialize payload: {}", e)))
+    }
+
+    /// Serialized payload, compressed with gzip if it has at least `gzip_threshold` bytes
+    pub fn to_body(&self, gzip_threshold: Option<usize>) -> Result<JsonBody, Error> {
+        let json = self.to_json()?;
+        match gzip_threshold {
+            Some(threshold) if json.len() >= threshold => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder
+                    .write_all(json.as_bytes())
+                    .and_then(|_| encoder.finish())
+                    .map(|bytes| JsonBody {
+                        bytes,
+                        gzipped: true,
+                    })
+                    .map_err(|e| Error::Unknown(format!("Failed to compress payload: {}", e)))
+            }
+            _ => Ok(JsonBody {
+                bytes: json.into_bytes(),
+                gzipped: false,
+            }),
+        }
+    }
+}
+
+/// Panics if the payload fails to serialize, `ApiRequest::body` reports the error instead
+impl<T> From<JsonPayload<T>> for Body
 where
     T: Serialize,
 {
-    fn into(self) -> Body {
-        serde_json::to_string(&self.0).unwrap().into()
+    fn from(v: JsonPayload<T>) -> Self {
+        v.to_json().unwrap().into()
+    }
+}
+
+/// Serialized payload, sent with `Content-Encoding: gzip` if it is compressed
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonBody {
+    pub bytes: Vec<u8>,
+    pub gzipped: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_to_body_gzip_threshold() {
+        let payload = JsonPayload(vec!["product"; 10]);
+        let json = payload.to_json().unwrap();
+
+        let body = payload.to_body(None).unwrap();
+        assert!(!body.gzipped);
+        assert_eq!(body.bytes, json.as_bytes());
+
+        let body = payload.to_body(Some(json.len() + 1)).unwrap();
+        assert!(!body.gzipped);
+        assert_eq!(body.bytes, json.as_bytes());
+
+        let body = payload.to_body(Some(json.len())).unwrap();
+        assert!(body.gzipped);
+        let mut decompressed = String::new();
+        GzDecoder::new(&body.bytes[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, json);
     }
 }
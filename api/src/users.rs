@@ -0,0 +1,173 @@
+use errors::Error;
+use rpc_client::RestApiClient;
+use types::*;
+use util::*;
+
+use chrono::prelude::*;
+use futures::future;
+use reqwest::Url;
+use stq_router::{Builder as RouterBuilder, Router};
+use stq_static_resources::{Gender, Provider};
+use stq_types::*;
+
+#[derive(Clone, Debug)]
+pub enum Route {
+    Users,
+    User { user_id: UserId },
+    UserByEmail,
+    UserBlock { user_id: UserId },
+    UserUnblock { user_id: UserId },
+}
+
+impl RouteBuilder for Route {
+    fn route(&self) -> String {
+        use self::Route::*;
+
+        match self {
+            Users => "users".to_string(),
+            User { user_id } => format!("users/{}", user_id),
+            UserByEmail => "users/by_email".to_string(),
+            UserBlock { user_id } => format!("users/{}/block", user_id),
+            UserUnblock { user_id } => format!("users/{}/unblock", user_id),
+        }
+    }
+}
+
+impl Route {
+    pub fn from_path(s: &str) -> Option<Self> {
+        lazy_static! {
+            static ref ROUTER: Router<Route> = RouterBuilder::default()
+                .with_route(r"^/users$", |_| Some(Route::Users))
+                .with_route(r"^/users/by_email$", |_| Some(Route::UserByEmail))
+                .with_route(r"^/users/(\d+)$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok())
+                    .map(|user_id| Route::User { user_id }))
+                .with_route(r"^/users/(\d+)/block$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok())
+                    .map(|user_id| Route::UserBlock { user_id }))
+                .with_route(r"^/users/(\d+)/unblock$", |params| params
+                    .into_iter()
+                    .next()
+                    .and_then(|string_id| string_id.parse().ok())
+                    .map(|user_id| Route::UserUnblock { user_id }))
+                .build();
+        }
+
+        ROUTER.test(s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    pub id: UserId,
+    pub email: String,
+    pub email_verified: bool,
+    pub phone: Option<String>,
+    pub phone_verified: bool,
+    pub is_active: bool,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub middle_name: Option<String>,
+    pub gender: Option<Gender>,
+    pub birthdate: Option<NaiveDate>,
+    pub avatar: Option<String>,
+    pub is_blocked: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NewUser {
+    pub email: String,
+    pub phone: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub middle_name: Option<String>,
+    pub gender: Option<Gender>,
+    pub birthdate: Option<NaiveDate>,
+    pub saga_id: SagaId,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NewIdentity {
+    pub email: String,
+    pub password: Option<String>,
+    pub provider: Provider,
+    pub saga_id: SagaId,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateUserPayload {
+    pub user: NewUser,
+    pub identity: NewIdentity,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpdateUser {
+    pub phone: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub middle_name: Option<String>,
+    pub gender: Option<Gender>,
+    pub birthdate: Option<NaiveDate>,
+    pub avatar: Option<String>,
+}
+
+pub trait UsersClient {
+    fn get_user(&self, user_id: UserId) -> ApiFuture<Option<User>>;
+    fn get_user_by_email(&self, email: String) -> ApiFuture<Option<User>>;
+    fn create_user(&self, user: NewUser, identity: NewIdentity) -> ApiFuture<User>;
+    fn update_user(&self, user_id: UserId, update: UpdateUser) -> ApiFuture<User>;
+    fn block_user(&self, user_id: UserId) -> ApiFuture<User>;
+    fn unblock_user(&self, user_id: UserId) -> ApiFuture<User>;
+}
+
+impl UsersClient for RestApiClient {
+    fn get_user(&self, user_id: UserId) -> ApiFuture<Option<User>> {
+        http_req(
+            self.http_client
+                .get(&self.build_route(&Route::User { user_id })),
+        )
+    }
+
+    fn get_user_by_email(&self, email: String) -> ApiFuture<Option<User>> {
+        match Url::parse_with_params(&self.build_route(&Route::UserByEmail), &[("email", email)]) {
+            Ok(url) => http_req(self.http_client.get(url.as_str())),
+            Err(e) => Box::new(future::err(Error::Unknown(format!("Invalid url: {}", e)))),
+        }
+    }
+
+    fn create_user(&self, user: NewUser, identity: NewIdentity) -> ApiFuture<User> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::Users))
+                .body(JsonPayload(CreateUserPayload { user, identity })),
+        )
+    }
+
+    fn update_user(&self, user_id: UserId, update: UpdateUser) -> ApiFuture<User> {
+        http_req(
+            self.http_client
+                .put(&self.build_route(&Route::User { user_id }))
+                .body(JsonPayload(update)),
+        )
+    }
+
+    fn block_user(&self, user_id: UserId) -> ApiFuture<User> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::UserBlock { user_id })),
+        )
+    }
+
+    fn unblock_user(&self, user_id: UserId) -> ApiFuture<User> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::UserUnblock { user_id })),
+        )
+    }
+}
@@ -1,10 +1,17 @@
 use errors::*;
+use response_cache::{CachedResponse, SharedResponseCache};
+use rpc_client::ApiRequest;
+use types::ApiFuture;
 
 use failure;
 use futures::{future, prelude::*};
-use reqwest::async::{Decoder, RequestBuilder};
+use reqwest::async::{Decoder, RequestBuilder, Response};
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::{self, Method, StatusCode};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json;
+use std::time::{Duration, Instant};
+use tokio_timer::{self, timeout, Timeout};
 
 pub fn serialize_payload<T>(v: T) -> impl Future<Item = String, Error = failure::Error>
 where
@@ -31,47 +38,195 @@ fn read_body(body: Decoder) -> Box<Future<Item = String, Error = Error> + Send>
     )
 }
 
-pub fn http_req<T>(b: RequestBuilder) -> Box<Future<Item = T, Error = Error> + Send>
+/// Sends request, retrying it according to its retry policy. Backoff requires tokio timer to be available.
+pub fn http_req<T>(request: ApiRequest) -> Box<Future<Item = T, Error = Error> + Send>
 where
     T: DeserializeOwned + Send + 'static,
 {
-    Box::new(
-        b.send()
-            .map_err(|e| {
-                if e.is_http() || e.is_redirect() {
-                    return Error::Network(format!("{:?}", e));
-                }
+    http_req_with_retries(request, 1)
+}
 
-                if let Some(status) = e.status() {
-                    return Error::Api(status, None);
-                }
+fn http_req_with_retries<T>(
+    request: ApiRequest,
+    attempt: usize,
+) -> Box<Future<Item = T, Error = Error> + Send>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    if let Err(ref e) = request.body {
+        return Box::new(future::err(e.clone()));
+    }
 
-                Error::Unknown(format!("{:?}", e))
-            })
-            .and_then(|mut rsp| {
-                let status = rsp.status();
-                match status.as_u16() {
-                    200...299 => Box::new(
-                        rsp.json::<T>()
-                            .map_err(|e| Error::Parse(format!("{:?}", e))),
-                    )
-                        as Box<Future<Item = T, Error = Error> + Send>,
-                    _ => Box::new(read_body(rsp.into_body()).then(move |res| {
-                        future::result(match res {
-                            Err(e) => Err(Error::Network(format!("{:?}", e))),
-                            Ok(s) => Err(Error::Api(
-                                status,
-                                Some(serde_json::from_str(&s).unwrap_or_else(|_| ErrorMessage {
-                                    code: 422,
-                                    description: s,
-                                    payload: None,
-                                })),
-                            )),
-                        })
-                    })),
-                }
-            }),
-    )
+    let mut response = match (&request.method, request.response_cache.clone()) {
+        (&Method::GET, Some(cache)) => {
+            send_cached_request(request.to_request_builder(), request.url.clone(), cache)
+        }
+        _ => send_request(request.to_request_builder()),
+    };
+    if request.idempotency_key.is_some() {
+        response = Box::new(response.map_err(Error::into_duplicate_request));
+    }
+    let response = match request.deadline {
+        None => response,
+        Some(deadline) => {
+            if Instant::now() >= deadline {
+                return Box::new(future::err(Error::Timeout));
+            }
+            Box::new(Timeout::new_at(response, deadline).map_err(timeout_error))
+        }
+    };
+
+    Box::new(response.or_else(move |err| {
+        let time_left = request.time_left();
+        match request.retry_policy.clone() {
+            Some(ref retry_policy)
+                if retry_policy.should_retry_request(
+                    &request.method,
+                    request.idempotency_key.is_some(),
+                    attempt,
+                    &err,
+                ) && time_left
+                    .map(|time_left| time_left > retry_policy.backoff(attempt))
+                    .unwrap_or(true) =>
+            {
+                Box::new(
+                    tokio_timer::sleep(retry_policy.backoff(attempt))
+                        .map_err(|e| Error::Unknown(format!("Tokio timer error: {:?}", e)))
+                        .and_then(move |_| http_req_with_retries(request, attempt + 1)),
+                ) as Box<Future<Item = T, Error = Error> + Send>
+            }
+            _ => Box::new(future::err(err)),
+        }
+    }))
+}
+
+fn timeout_error(e: timeout::Error<Error>) -> Error {
+    if e.is_elapsed() {
+        Error::Timeout
+    } else if e.is_inner() {
+        e.into_inner().unwrap()
+    } else {
+        Error::Unknown(format!("Tokio timer error: {:?}", e))
+    }
+}
+
+/// Fails with `Error::Timeout` if the future doesn't complete in time, dropping it.
+/// Requires tokio timer to be available.
+pub fn with_timeout<T>(future: ApiFuture<T>, timeout: Duration) -> ApiFuture<T>
+where
+    T: Send + 'static,
+{
+    Box::new(Timeout::new(future, timeout).map_err(timeout_error))
+}
+
+/// Resolves with the first future to succeed, dropping the rest, e.g. to query replicas of a service.
+/// Fails with the error of the last future to fail if none succeed.
+pub fn race_ok<T>(futures: Vec<ApiFuture<T>>) -> ApiFuture<T>
+where
+    T: Send + 'static,
+{
+    if futures.is_empty() {
+        let error = Error::Unknown("No futures to race".to_string());
+        return Box::new(future::err(error));
+    }
+
+    Box::new(future::select_ok(futures).map(|(item, _rest)| item))
+}
+
+fn send_request<T>(b: RequestBuilder) -> Box<Future<Item = T, Error = Error> + Send>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    Box::new(b.send().map_err(map_send_error).and_then(|mut rsp| {
+        let status = rsp.status();
+        match status.as_u16() {
+            200...299 => Box::new(
+                rsp.json::<T>()
+                    .map_err(|e| Error::Parse(format!("{:?}", e))),
+            ) as Box<Future<Item = T, Error = Error> + Send>,
+            _ => error_response(status, rsp),
+        }
+    }))
+}
+
+/// Sends GET request revalidating cached response with `If-None-Match`, caches responses with `ETag`
+fn send_cached_request<T>(
+    b: RequestBuilder,
+    url: String,
+    cache: SharedResponseCache,
+) -> Box<Future<Item = T, Error = Error> + Send>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let cached = cache.0.get(&url);
+    let b = match cached {
+        Some(ref cached) => b.header(IF_NONE_MATCH, cached.etag.clone()),
+        None => b,
+    };
+
+    Box::new(b.send().map_err(map_send_error).and_then(move |rsp| {
+        let status = rsp.status();
+        match (status.as_u16(), cached) {
+            (304, Some(cached)) => Box::new(future::result(parse_body(&cached.body)))
+                as Box<Future<Item = T, Error = Error> + Send>,
+            (200...299, _) => {
+                let etag = rsp
+                    .headers()
+                    .get(ETAG)
+                    .and_then(|etag| etag.to_str().ok())
+                    .map(|etag| etag.to_string());
+                Box::new(read_body(rsp.into_body()).and_then(move |body| {
+                    let res = parse_body(&body);
+                    if let (Ok(_), Some(etag)) = (&res, etag) {
+                        cache.0.set(&url, CachedResponse { etag, body });
+                    }
+                    res
+                }))
+            }
+            _ => error_response(status, rsp),
+        }
+    }))
+}
+
+fn map_send_error(e: reqwest::Error) -> Error {
+    if e.is_http() || e.is_redirect() {
+        return Error::Network(format!("{:?}", e));
+    }
+
+    if let Some(status) = e.status() {
+        return Error::Api(status, None);
+    }
+
+    Error::Unknown(format!("{:?}", e))
+}
+
+fn parse_body<T>(body: &str) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_str(body).map_err(|e| Error::Parse(format!("{:?}", e)))
+}
+
+fn error_response<T>(
+    status: StatusCode,
+    rsp: Response,
+) -> Box<Future<Item = T, Error = Error> + Send>
+where
+    T: Send + 'static,
+{
+    Box::new(read_body(rsp.into_body()).then(move |res| {
+        future::result(match res {
+            Err(e) => Err(Error::Network(format!("{:?}", e))),
+            Ok(s) => Err(Error::from_response(
+                status,
+                serde_json::from_str(&s).unwrap_or_else(|_| ErrorMessage {
+                    code: 422,
+                    description: s,
+                    payload: None,
+                }),
+            )),
+        })
+    }))
 }
 
 pub trait RouteBuilder {
@@ -90,3 +245,69 @@ pub trait RouteBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_timer::Delay;
+    use types::ApiFutureExt;
+
+    fn delayed<T: Send + 'static>(delay: Duration, result: Result<T, Error>) -> ApiFuture<T> {
+        Box::new(
+            Delay::new(Instant::now() + delay)
+                .map_err(|e| Error::Unknown(format!("Tokio timer error: {:?}", e)))
+                .and_then(move |_| result),
+        )
+    }
+
+    #[test]
+    fn test_with_timeout() {
+        let fast = delayed(Duration::from_millis(10), Ok(1));
+        assert_eq!(
+            with_timeout(fast, Duration::from_secs(10)).sync().unwrap(),
+            1
+        );
+
+        let slow = delayed(Duration::from_secs(10), Ok(1));
+        match with_timeout(slow, Duration::from_millis(10)).sync() {
+            Err(Error::Timeout) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let failed = delayed::<u32>(
+            Duration::from_millis(10),
+            Err(Error::Parse("invalid".to_string())),
+        );
+        match with_timeout(failed, Duration::from_secs(10)).sync() {
+            Err(Error::Parse(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_race_ok() {
+        let futures = vec![
+            delayed(Duration::from_secs(10), Ok(1)),
+            delayed(Duration::from_millis(10), Err(Error::Timeout)),
+            delayed(Duration::from_millis(20), Ok(3)),
+        ];
+        assert_eq!(race_ok(futures).sync().unwrap(), 3);
+
+        let futures = vec![
+            delayed::<u32>(Duration::from_millis(10), Err(Error::Timeout)),
+            delayed(
+                Duration::from_millis(20),
+                Err(Error::Parse("invalid".to_string())),
+            ),
+        ];
+        match race_ok(futures).sync() {
+            Err(Error::Parse(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        match race_ok::<u32>(vec![]).sync() {
+            Err(Error::Unknown(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -14,6 +14,9 @@ pub enum Route {
     WarehousesByStore {
         store_id: StoreId,
     },
+    WarehousesByStoreNearest {
+        store_id: StoreId,
+    },
     Warehouse {
         warehouse_id: WarehouseIdentifier,
     },
@@ -24,9 +27,18 @@ pub enum Route {
         warehouse_id: WarehouseId,
         product_id: ProductId,
     },
+    StockInWarehouseIncrement {
+        warehouse_id: WarehouseId,
+        product_id: ProductId,
+    },
+    StockInWarehouseDecrement {
+        warehouse_id: WarehouseId,
+        product_id: ProductId,
+    },
     StocksByProductId {
         product_id: ProductId,
     },
+    StocksByProductIdsSearch,
     StockById {
         stock_id: StockId,
     },
@@ -56,6 +68,9 @@ impl RouteBuilder for Route {
         match self {
             Warehouses => "warehouses".to_string(),
             WarehousesByStore { store_id } => format!("warehouses/by-store/{}", store_id),
+            WarehousesByStoreNearest { store_id } => {
+                format!("warehouses/by-store/{}/nearest", store_id)
+            }
             Warehouse { warehouse_id } => {
                 format!("warehouses/{}", warehouse_identifier_route(warehouse_id))
             }
@@ -71,7 +86,30 @@ impl RouteBuilder for Route {
                 warehouse_identifier_route(&WarehouseIdentifier::Id(*warehouse_id)),
                 product_id
             ),
+            StockInWarehouseIncrement {
+                warehouse_id,
+                product_id,
+            } => format!(
+                "{}/increment",
+                StockInWarehouse {
+                    warehouse_id: *warehouse_id,
+                    product_id: *product_id,
+                }
+                .route()
+            ),
+            StockInWarehouseDecrement {
+                warehouse_id,
+                product_id,
+            } => format!(
+                "{}/decrement",
+                StockInWarehouse {
+                    warehouse_id: *warehouse_id,
+                    product_id: *product_id,
+                }
+                .route()
+            ),
             StocksByProductId { product_id } => format!("stocks/by-product-id/{}", product_id),
+            StocksByProductIdsSearch => "stocks/by-product-ids/search".to_string(),
             StockById { stock_id } => format!("stocks/by-id/{}", stock_id),
             Stocks => "stocks".to_string(),
             Roles(route) => route.route(),
@@ -112,6 +150,24 @@ impl Route {
                             None
                         }
                     )
+                    .with_route(
+                        r"^/warehouses/by-id/([a-zA-Z0-9-]+)/products/(\d+)/(increment|decrement)$",
+                        |params| {
+                            let warehouse_id = params.get(0)?.parse().map(WarehouseId).ok()?;
+                            let product_id = params.get(1)?.parse().map(ProductId).ok()?;
+                            match *params.get(2)? {
+                                "increment" => Some(Route::StockInWarehouseIncrement {
+                                    warehouse_id,
+                                    product_id,
+                                }),
+                                "decrement" => Some(Route::StockInWarehouseDecrement {
+                                    warehouse_id,
+                                    product_id,
+                                }),
+                                _ => None,
+                            }
+                        }
+                    )
                     .with_route(r"^/warehouses/by-id/([a-zA-Z0-9-]+)$", |params| params
                         .get(0)
                         .and_then(|string_id| string_id.parse().ok().map(WarehouseIdentifier::Id))
@@ -124,10 +180,17 @@ impl Route {
                         .get(0)
                         .and_then(|string_id| string_id.parse().ok())
                         .map(|store_id| Route::WarehousesByStore { store_id }))
+                    .with_route(r"^/warehouses/by-store/(\d+)/nearest$", |params| params
+                        .get(0)
+                        .and_then(|string_id| string_id.parse().ok())
+                        .map(|store_id| Route::WarehousesByStoreNearest { store_id }))
                     .with_route(r"^/stocks/by-product-id/(\d+)$", |params| params
                         .get(0)
                         .and_then(|string_id| string_id.parse().ok())
                         .map(|product_id| Route::StocksByProductId { product_id }))
+                    .with_route(r"^/stocks/by-product-ids/search$", |_| Some(
+                        Route::StocksByProductIdsSearch
+                    ))
                     .with_route(r"^/stocks/by-id/([a-zA-Z0-9-]+)$", |params| params
                         .get(0)
                         .and_then(|string_id| string_id.parse().ok())
@@ -254,6 +317,11 @@ pub struct Stock {
     pub quantity: Quantity,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProductIdsPayload {
+    pub product_ids: Vec<ProductId>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct StockMeta {
     pub quantity: Quantity,
@@ -290,6 +358,34 @@ pub struct StockSetPayload {
     pub quantity: Quantity,
 }
 
+/// Atomic change of the stock quantity
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StockAdjustPayload {
+    pub delta: Quantity,
+}
+
+/// Query of the nearest warehouses, the point is given by its longitude and latitude
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NearestWarehousesQuery {
+    pub lon: f64,
+    pub lat: f64,
+}
+
+impl NearestWarehousesQuery {
+    pub fn to_query(&self) -> String {
+        format!("lon={}&lat={}", self.lon, self.lat)
+    }
+}
+
+impl From<GeoPoint<f64>> for NearestWarehousesQuery {
+    fn from(location: GeoPoint<f64>) -> Self {
+        Self {
+            lon: location.x(),
+            lat: location.y(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct WarehouseUpdateData {
     pub slug: Option<ValueContainer<WarehouseSlug>>,
@@ -319,6 +415,9 @@ pub trait WarehouseClient {
     fn delete_warehouse(&self, warehouse_id: WarehouseIdentifier) -> ApiFuture<Option<Warehouse>>;
     fn delete_all_warehouses(&self) -> ApiFuture<Vec<Warehouse>>;
     fn get_warehouses_for_store(&self, store_id: StoreId) -> ApiFuture<Vec<Warehouse>>;
+    /// Located warehouses of the store, nearest first, e.g. to decide on splitting a shipment
+    fn find_nearest(&self, store_id: StoreId, location: GeoPoint<f64>)
+        -> ApiFuture<Vec<Warehouse>>;
 
     fn set_product_in_warehouse(
         &self,
@@ -326,6 +425,20 @@ pub trait WarehouseClient {
         product_id: ProductId,
         quantity: Quantity,
     ) -> ApiFuture<Stock>;
+    /// Atomically increases quantity of the product in warehouse by `delta`
+    fn increment_stock(
+        &self,
+        warehouse_id: WarehouseId,
+        product_id: ProductId,
+        delta: Quantity,
+    ) -> ApiFuture<Stock>;
+    /// Atomically decreases quantity of the product in warehouse by `delta`, fails if there is not enough stock
+    fn decrement_stock(
+        &self,
+        warehouse_id: WarehouseId,
+        product_id: ProductId,
+        delta: Quantity,
+    ) -> ApiFuture<Stock>;
     fn get_product_in_warehouse(
         &self,
         warehouse_id: WarehouseId,
@@ -337,6 +450,12 @@ pub trait WarehouseClient {
 
     /// Find all products with id in all warehouses
     fn find_by_product_id(&self, product_id: ProductId) -> ApiFuture<Vec<Stock>>;
+
+    /// Find stocks of several products in all warehouses, grouped by product
+    fn find_by_product_ids(
+        &self,
+        product_ids: Vec<ProductId>,
+    ) -> ApiFuture<HashMap<ProductId, Vec<Stock>>>;
 }
 
 impl WarehouseClient for RestApiClient {
@@ -383,6 +502,18 @@ impl WarehouseClient for RestApiClient {
         )
     }
 
+    fn find_nearest(
+        &self,
+        store_id: StoreId,
+        location: GeoPoint<f64>,
+    ) -> ApiFuture<Vec<Warehouse>> {
+        http_req(self.http_client.get(&format!(
+            "{}?{}",
+            self.build_route(&Route::WarehousesByStoreNearest { store_id }),
+            NearestWarehousesQuery::from(location).to_query()
+        )))
+    }
+
     fn set_product_in_warehouse(
         &self,
         warehouse_id: WarehouseId,
@@ -398,6 +529,36 @@ impl WarehouseClient for RestApiClient {
                 .body(JsonPayload(StockSetPayload { quantity })),
         )
     }
+    fn increment_stock(
+        &self,
+        warehouse_id: WarehouseId,
+        product_id: ProductId,
+        delta: Quantity,
+    ) -> ApiFuture<Stock> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::StockInWarehouseIncrement {
+                    warehouse_id,
+                    product_id,
+                }))
+                .body(JsonPayload(StockAdjustPayload { delta })),
+        )
+    }
+    fn decrement_stock(
+        &self,
+        warehouse_id: WarehouseId,
+        product_id: ProductId,
+        delta: Quantity,
+    ) -> ApiFuture<Stock> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::StockInWarehouseDecrement {
+                    warehouse_id,
+                    product_id,
+                }))
+                .body(JsonPayload(StockAdjustPayload { delta })),
+        )
+    }
     fn get_product_in_warehouse(
         &self,
         warehouse_id: WarehouseId,
@@ -432,4 +593,15 @@ impl WarehouseClient for RestApiClient {
                 .get(&self.build_route(&Route::StocksByProductId { product_id })),
         )
     }
+
+    fn find_by_product_ids(
+        &self,
+        product_ids: Vec<ProductId>,
+    ) -> ApiFuture<HashMap<ProductId, Vec<Stock>>> {
+        http_req(
+            self.http_client
+                .post(&self.build_route(&Route::StocksByProductIdsSearch))
+                .body(JsonPayload(ProductIdsPayload { product_ids })),
+        )
+    }
 }
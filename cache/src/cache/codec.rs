@@ -0,0 +1,81 @@
+use base64;
+#[cfg(feature = "bincode-codec")]
+use bincode;
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "cbor-codec")]
+use serde_cbor;
+use serde_json;
+
+/// Converts typed values to strings stored in backend cache and back
+pub trait Codec {
+    fn encode<T: Serialize>(value: &T) -> Result<String, CodecError>;
+
+    fn decode<T: DeserializeOwned>(s: &str) -> Result<T, CodecError>;
+}
+
+#[derive(Debug, Fail)]
+pub enum CodecError {
+    #[fail(display = "An error occurred on JSON serialization/deserialization")]
+    Json(serde_json::Error),
+    #[cfg(feature = "bincode-codec")]
+    #[fail(display = "An error occurred on bincode serialization/deserialization")]
+    Bincode(bincode::Error),
+    #[cfg(feature = "cbor-codec")]
+    #[fail(display = "An error occurred on CBOR serialization/deserialization")]
+    Cbor(serde_cbor::Error),
+    #[fail(display = "An error occurred on base64 decoding")]
+    Base64(base64::DecodeError),
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize>(value: &T) -> Result<String, CodecError> {
+        serde_json::to_string(value).map_err(CodecError::Json)
+    }
+
+    fn decode<T: DeserializeOwned>(s: &str) -> Result<T, CodecError> {
+        serde_json::from_str(s).map_err(CodecError::Json)
+    }
+}
+
+/// Binary codec; the payload is base64-encoded since backends store strings
+#[cfg(feature = "bincode-codec")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode-codec")]
+impl Codec for BincodeCodec {
+    fn encode<T: Serialize>(value: &T) -> Result<String, CodecError> {
+        bincode::serialize(value)
+            .map(|bytes| base64::encode(&bytes))
+            .map_err(CodecError::Bincode)
+    }
+
+    fn decode<T: DeserializeOwned>(s: &str) -> Result<T, CodecError> {
+        base64::decode(s)
+            .map_err(CodecError::Base64)
+            .and_then(|bytes| bincode::deserialize(&bytes).map_err(CodecError::Bincode))
+    }
+}
+
+/// Binary codec; the payload is base64-encoded since backends store strings
+#[cfg(feature = "cbor-codec")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor-codec")]
+impl Codec for CborCodec {
+    fn encode<T: Serialize>(value: &T) -> Result<String, CodecError> {
+        serde_cbor::to_vec(value)
+            .map(|bytes| base64::encode(&bytes))
+            .map_err(CodecError::Cbor)
+    }
+
+    fn decode<T: DeserializeOwned>(s: &str) -> Result<T, CodecError> {
+        base64::decode(s)
+            .map_err(CodecError::Base64)
+            .and_then(|bytes| serde_cbor::from_slice(&bytes).map_err(CodecError::Cbor))
+    }
+}
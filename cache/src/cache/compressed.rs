@@ -0,0 +1,217 @@
+use base64;
+use failure::Fail;
+use std::io;
+
+use super::Cache;
+
+const PREFIX_LEN: usize = 2;
+const RAW_PREFIX: &str = "r:";
+#[cfg(feature = "gzip")]
+const GZIP_PREFIX: &str = "g:";
+#[cfg(feature = "snappy")]
+const SNAPPY_PREFIX: &str = "s:";
+
+/// Default size (in bytes) above which values get compressed
+pub const DEFAULT_THRESHOLD: usize = 1024;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "snappy")]
+    Snappy,
+}
+
+/// Wraps a string cache and transparently compresses values larger than `threshold`.
+/// Stored values are prefixed with a marker so that small uncompressed values
+/// and compressed ones can live side by side in the same backend.
+#[derive(Clone, Debug)]
+pub struct CompressedCache<C> {
+    backend: C,
+    compression: Compression,
+    threshold: usize,
+}
+
+#[derive(Debug, Fail)]
+pub enum CompressedCacheError<E>
+where
+    E: Fail,
+{
+    #[fail(display = "An error occurred in backend cache")]
+    BackendCacheError(E),
+    #[fail(display = "An error occurred on compression/decompression")]
+    CompressionError(io::Error),
+    #[fail(display = "An error occurred on base64 decoding")]
+    Base64Error(base64::DecodeError),
+    #[fail(display = "Cached value has unknown format")]
+    UnknownFormat,
+}
+
+impl<C> CompressedCache<C> {
+    pub fn new(backend: C, compression: Compression) -> Self {
+        CompressedCache {
+            backend,
+            compression,
+            threshold: DEFAULT_THRESHOLD,
+        }
+    }
+
+    pub fn with_threshold(self, threshold: usize) -> Self {
+        CompressedCache { threshold, ..self }
+    }
+
+    fn encode(&self, value: String) -> io::Result<String> {
+        if value.len() <= self.threshold {
+            return Ok(format!("{}{}", RAW_PREFIX, value));
+        }
+
+        match self.compression {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => gzip::compress(value.as_bytes())
+                .map(|bytes| format!("{}{}", GZIP_PREFIX, base64::encode(&bytes))),
+            #[cfg(feature = "snappy")]
+            Compression::Snappy => snappy::compress(value.as_bytes())
+                .map(|bytes| format!("{}{}", SNAPPY_PREFIX, base64::encode(&bytes))),
+        }
+    }
+
+    fn decode<E: Fail>(value: String) -> Result<String, CompressedCacheError<E>> {
+        let payload = value.get(PREFIX_LEN..).unwrap_or_default();
+        match value.get(..PREFIX_LEN) {
+            Some(RAW_PREFIX) => Ok(payload.to_string()),
+            #[cfg(feature = "gzip")]
+            Some(GZIP_PREFIX) => base64::decode(payload)
+                .map_err(CompressedCacheError::Base64Error)
+                .and_then(|bytes| {
+                    gzip::decompress(&bytes).map_err(CompressedCacheError::CompressionError)
+                }),
+            #[cfg(feature = "snappy")]
+            Some(SNAPPY_PREFIX) => base64::decode(payload)
+                .map_err(CompressedCacheError::Base64Error)
+                .and_then(|bytes| {
+                    snappy::decompress(&bytes).map_err(CompressedCacheError::CompressionError)
+                }),
+            _ => Err(CompressedCacheError::UnknownFormat),
+        }
+    }
+}
+
+impl<C, E> Cache<String> for CompressedCache<C>
+where
+    C: Cache<String, Error = E>,
+    E: Fail,
+{
+    type Error = CompressedCacheError<E>;
+
+    fn get(&self, key: &str) -> Result<Option<String>, Self::Error> {
+        self.backend
+            .get(key)
+            .map_err(CompressedCacheError::BackendCacheError)
+            .and_then(|value_opt| match value_opt {
+                None => Ok(None),
+                Some(value) => Self::decode(value).map(Some),
+            })
+    }
+
+    fn set(&self, key: &str, value: String) -> Result<(), Self::Error> {
+        self.encode(value)
+            .map_err(CompressedCacheError::CompressionError)
+            .and_then(|encoded| {
+                self.backend
+                    .set(key, encoded)
+                    .map_err(CompressedCacheError::BackendCacheError)
+            })
+    }
+
+    fn remove(&self, key: &str) -> Result<bool, Self::Error> {
+        self.backend
+            .remove(key)
+            .map_err(CompressedCacheError::BackendCacheError)
+    }
+}
+
+#[cfg(feature = "gzip")]
+mod gzip {
+    use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+    use std::io::{self, Read, Write};
+
+    pub fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes)?;
+        encoder.finish()
+    }
+
+    pub fn decompress(bytes: &[u8]) -> io::Result<String> {
+        let mut decoder = GzDecoder::new(bytes);
+        let mut s = String::new();
+        decoder.read_to_string(&mut s)?;
+        Ok(s)
+    }
+}
+
+#[cfg(feature = "snappy")]
+mod snappy {
+    use snap::raw::{Decoder, Encoder};
+    use std::io;
+
+    pub fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+        Encoder::new().compress_vec(bytes).map_err(From::from)
+    }
+
+    pub fn decompress(bytes: &[u8]) -> io::Result<String> {
+        let decompressed = Decoder::new().decompress_vec(bytes)?;
+        String::from_utf8(decompressed).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cache::{
+        compressed::{CompressedCache, Compression},
+        in_memory::InMemoryCache,
+        Cache,
+    };
+
+    fn check_roundtrip(compression: Compression) {
+        let backend = InMemoryCache::<String>::new();
+        let cache = CompressedCache::new(backend.clone(), compression).with_threshold(16);
+
+        let small_value = "small".to_string();
+        cache
+            .set("small", small_value.clone())
+            .expect("Failed to set value");
+        assert_eq!(
+            Some(small_value),
+            cache.get("small").expect("Failed to get value")
+        );
+
+        let large_value = "large value ".repeat(100);
+        cache
+            .set("large", large_value.clone())
+            .expect("Failed to set value");
+        let stored_value = backend
+            .get("large")
+            .expect("Failed to get value")
+            .expect("Value does not exist in backend");
+        assert!(stored_value.len() < large_value.len());
+        assert_eq!(
+            Some(large_value),
+            cache.get("large").expect("Failed to get value")
+        );
+
+        assert!(cache.remove("large").expect("Failed to remove value"));
+        assert_eq!(None, cache.get("large").expect("Failed to get value"));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_compressed_cache() {
+        check_roundtrip(Compression::Gzip);
+    }
+
+    #[cfg(feature = "snappy")]
+    #[test]
+    fn test_snappy_compressed_cache() {
+        check_roundtrip(Compression::Snappy);
+    }
+}
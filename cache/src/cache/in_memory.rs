@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
-use super::Cache;
+use super::{AtomicCache, Cache};
 
 #[derive(Clone, Debug)]
 pub struct InMemoryCache<T>(Arc<RwLock<HashMap<String, T>>>);
@@ -44,3 +44,18 @@ where
         })
     }
 }
+
+impl<T> AtomicCache<T> for InMemoryCache<T>
+where
+    T: Clone,
+{
+    fn set_if_absent(&self, key: &str, value: T) -> Result<bool, Self::Error> {
+        let lock = self.0.clone();
+        let mut hash_map = lock.write().map_err(|_| InMemoryCacheError)?;
+        if hash_map.contains_key(key) {
+            return Ok(false);
+        }
+        hash_map.insert(key.to_string(), value);
+        Ok(true)
+    }
+}
@@ -0,0 +1,633 @@
+//! Cache keys built from a namespace, a version and typed segments.
+//!
+//! Segments are serialized with serde, so that `"1"` and `1` or `"a:b"` and `"a", "b"` never make
+//! the same key. Bytes memcached doesn't allow in keys are escaped and long segments are hashed.
+//! Keys may also include a hash of the layout of the cached type, so that changing the type makes
+//! entries cached before the change missed instead of failing to deserialize.
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::Serialize;
+use serde_json;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheKey {
+    namespace: String,
+    version: u32,
+    schema: Option<u64>,
+    segments: Vec<String>,
+}
+
+impl CacheKey {
+    /// # Panics
+    ///
+    /// Panics if the namespace is empty or contains `:`, which separates parts of keys.
+    pub fn new<N: Into<String>>(namespace: N) -> Self {
+        let namespace = namespace.into();
+        assert!(
+            !namespace.is_empty() && !namespace.contains(':'),
+            "Invalid cache key namespace: {:?}",
+            namespace
+        );
+
+        Self {
+            namespace,
+            version: 0,
+            schema: None,
+            segments: vec![],
+        }
+    }
+
+    /// Set the version to bump on changes of cached values that don't change their layout,
+    /// e.g. of their meaning
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Add hash of the layout of `T`: names of its structs, enums, fields and variants and types
+    /// of its fields, including the fields of every variant. Layout is traced through
+    /// the `Deserialize` implementation, so types with custom implementations, e.g. parsing
+    /// strings, are traced until they fail to parse; names of the fields are traced before their
+    /// types though.
+    pub fn with_schema<T: DeserializeOwned>(mut self) -> Self {
+        self.schema = Some(schema_hash::<T>());
+        self
+    }
+
+    /// Add a segment, serialized to JSON. Spaces, control characters and `%` are escaped as `%XX`,
+    /// segments longer than 64 bytes are replaced with `#` and the hash of their JSON.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the segment fails to serialize, e.g. if it is a map with non-string keys.
+    pub fn with_segment<S: Serialize + ?Sized>(mut self, segment: &S) -> Self {
+        self.segments.push(encode_segment(segment));
+        self
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:v{}", self.namespace, self.version)?;
+        if let Some(schema) = self.schema {
+            write!(f, ":{:016x}", schema)?;
+        }
+        for segment in &self.segments {
+            write!(f, ":{}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Longest segment kept in keys as is, memcached keys are limited to 250 bytes
+const MAX_SEGMENT_LEN: usize = 64;
+
+pub(crate) fn encode_segment<S: Serialize + ?Sized>(segment: &S) -> String {
+    let json = serde_json::to_string(segment).expect("Cache key segment failed to serialize");
+    // JSON never starts with `#`, so hashed segments don't make the same key as short ones
+    if json.len() > MAX_SEGMENT_LEN {
+        return format!("#{:016x}", fnv1a(json.as_bytes()));
+    }
+
+    let mut encoded = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c == ' ' || c == '%' || c.is_ascii_control() {
+            encoded.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
+
+/// Hash of the layout of `T`, stable across builds and Rust versions
+pub fn schema_hash<T: DeserializeOwned>() -> u64 {
+    let mut trace = String::new();
+    let mut variants = Variants::default();
+    // A trace can't branch, so `T` is traced again while passes reach enum variants not traced yet.
+    // Tracing ends with an error once it reaches values it can't make up, the trace up to it
+    // is still deterministic.
+    loop {
+        let traced = variants.count();
+        let _ = T::deserialize(Tracer {
+            trace: &mut trace,
+            variants: &mut variants,
+            parent: None,
+            depth: 0,
+        });
+        if variants.count() == traced {
+            break;
+        }
+    }
+    fnv1a(trace.as_bytes())
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Nesting beyond which options are traced as `None` and sequences as empty,
+/// so that recursive types end
+const MAX_DEPTH: usize = 8;
+
+/// Enum identified by its name and variants
+type EnumId = (&'static str, &'static [&'static str]);
+
+/// Enum variants traced by the passes so far and enums met within each of them
+#[derive(Default)]
+struct Variants {
+    traced: HashMap<EnumId, Vec<bool>>,
+    nested: HashMap<(EnumId, usize), Vec<EnumId>>,
+}
+
+impl Variants {
+    fn count(&self) -> usize {
+        self.traced
+            .values()
+            .map(|traced| traced.iter().filter(|traced| **traced).count())
+            .sum()
+    }
+
+    /// Picks a variant not traced yet, else one leading to such a variant of a nested enum,
+    /// else the first one
+    fn pick(&mut self, id: EnumId, parent: Option<(EnumId, usize)>) -> usize {
+        if let Some(parent) = parent {
+            let nested = self.nested.entry(parent).or_default();
+            if !nested.contains(&id) {
+                nested.push(id);
+            }
+        }
+
+        let len = id.1.len();
+        let untraced = self
+            .traced
+            .entry(id)
+            .or_insert_with(|| vec![false; len])
+            .iter()
+            .position(|traced| !traced);
+        let index = untraced
+            .or_else(|| {
+                (0..len).find(|&index| self.leads_to_untraced(id, index, &mut HashSet::new()))
+            })
+            .unwrap_or(0);
+        if let Some(traced) = self.traced.get_mut(&id) {
+            traced[index] = true;
+        }
+        index
+    }
+
+    fn leads_to_untraced(
+        &self,
+        id: EnumId,
+        index: usize,
+        seen: &mut HashSet<(EnumId, usize)>,
+    ) -> bool {
+        if !seen.insert((id, index)) {
+            return false;
+        }
+        match self.nested.get(&(id, index)) {
+            Some(nested) => nested.iter().any(|nested_id| {
+                let traced = &self.traced[nested_id];
+                traced.iter().any(|traced| !traced)
+                    || (0..traced.len())
+                        .any(|index| self.leads_to_untraced(*nested_id, index, seen))
+            }),
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TraceEnd;
+
+impl fmt::Display for TraceEnd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "End of layout trace")
+    }
+}
+
+impl Error for TraceEnd {}
+
+impl de::Error for TraceEnd {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        TraceEnd
+    }
+}
+
+/// Deserializer making up values of the requested types while recording the types
+struct Tracer<'a> {
+    trace: &'a mut String,
+    variants: &'a mut Variants,
+    /// Innermost enum variant being traced
+    parent: Option<(EnumId, usize)>,
+    depth: usize,
+}
+
+impl<'a> Tracer<'a> {
+    fn push(&mut self, s: &str) {
+        self.trace.push_str(s);
+        self.trace.push(';');
+    }
+
+    fn nested(&mut self) -> Tracer<'_> {
+        Tracer {
+            trace: self.trace,
+            variants: self.variants,
+            parent: self.parent,
+            depth: self.depth + 1,
+        }
+    }
+
+    fn too_deep(&self) -> bool {
+        self.depth >= MAX_DEPTH
+    }
+}
+
+macro_rules! trace_primitive {
+    ($($method:ident => $visit:ident($($value:expr)*),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, TraceEnd> {
+                self.push(stringify!($method));
+                visitor.$visit($($value)*)
+            }
+        )*
+    };
+}
+
+impl<'a, 'de> de::Deserializer<'de> for Tracer<'a> {
+    type Error = TraceEnd;
+
+    trace_primitive! {
+        deserialize_bool => visit_bool(false),
+        deserialize_i8 => visit_i8(0),
+        deserialize_i16 => visit_i16(0),
+        deserialize_i32 => visit_i32(0),
+        deserialize_i64 => visit_i64(0),
+        deserialize_u8 => visit_u8(0),
+        deserialize_u16 => visit_u16(0),
+        deserialize_u32 => visit_u32(0),
+        deserialize_u64 => visit_u64(0),
+        deserialize_f32 => visit_f32(0.0),
+        deserialize_f64 => visit_f64(0.0),
+        deserialize_char => visit_char('\0'),
+        deserialize_str => visit_str(""),
+        deserialize_string => visit_str(""),
+        deserialize_bytes => visit_bytes(&[]),
+        deserialize_byte_buf => visit_bytes(&[]),
+        deserialize_unit => visit_unit(),
+        deserialize_identifier => visit_str(""),
+        deserialize_ignored_any => visit_unit(),
+        // Self-describing types, e.g. `serde_json::Value`, have no layout to trace
+        deserialize_any => visit_unit(),
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, TraceEnd> {
+        self.push("option");
+        if self.too_deep() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self.nested())
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TraceEnd> {
+        self.push(name);
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TraceEnd> {
+        self.push(name);
+        visitor.visit_newtype_struct(self.nested())
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, TraceEnd> {
+        self.push("seq");
+        let len = if self.too_deep() { 0 } else { 1 };
+        visitor.visit_seq(SeqTracer { tracer: self, len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        mut self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TraceEnd> {
+        self.push(&format!("tuple{}", len));
+        visitor.visit_seq(SeqTracer { tracer: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TraceEnd> {
+        self.push(name);
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, TraceEnd> {
+        self.push("map");
+        let len = if self.too_deep() { 0 } else { 1 };
+        visitor.visit_map(MapTracer {
+            tracer: self,
+            fields: &[],
+            index: 0,
+            len,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceEnd> {
+        self.push(&format!("{}{{{}}}", name, fields.join(",")));
+        if self.too_deep() {
+            return Err(TraceEnd);
+        }
+        visitor.visit_map(MapTracer {
+            tracer: self,
+            fields,
+            index: 0,
+            len: fields.len(),
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceEnd> {
+        self.push(&format!("{}{{{}}}", name, variants.join("|")));
+        if self.too_deep() || variants.is_empty() {
+            return Err(TraceEnd);
+        }
+        // One variant is traced per pass, see `schema_hash`
+        let index = self.variants.pick((name, variants), self.parent);
+        let variant = variants[index];
+        self.push(variant);
+        visitor.visit_enum(EnumTracer {
+            tracer: Tracer {
+                parent: Some(((name, variants), index)),
+                ..self
+            },
+            variant,
+        })
+    }
+}
+
+struct SeqTracer<'a> {
+    tracer: Tracer<'a>,
+    len: usize,
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for SeqTracer<'a> {
+    type Error = TraceEnd;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, TraceEnd> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(self.tracer.nested()).map(Some)
+    }
+}
+
+/// Map access over the fields of a struct, or over a single entry of a map if there are no fields
+struct MapTracer<'a> {
+    tracer: Tracer<'a>,
+    fields: &'static [&'static str],
+    index: usize,
+    len: usize,
+}
+
+impl<'a, 'de> de::MapAccess<'de> for MapTracer<'a> {
+    type Error = TraceEnd;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, TraceEnd> {
+        if self.index == self.len {
+            return Ok(None);
+        }
+        let key = match self.fields.get(self.index) {
+            Some(field) => seed.deserialize(field.into_deserializer()),
+            None => seed.deserialize(self.tracer.nested()),
+        };
+        self.index += 1;
+        key.map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, TraceEnd> {
+        seed.deserialize(self.tracer.nested())
+    }
+}
+
+struct EnumTracer<'a> {
+    tracer: Tracer<'a>,
+    variant: &'static str,
+}
+
+impl<'a, 'de> de::EnumAccess<'de> for EnumTracer<'a> {
+    type Error = TraceEnd;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), TraceEnd> {
+        let variant: de::value::StrDeserializer<TraceEnd> = self.variant.into_deserializer();
+        seed.deserialize(variant).map(|value| (value, self))
+    }
+}
+
+impl<'a, 'de> de::VariantAccess<'de> for EnumTracer<'a> {
+    type Error = TraceEnd;
+
+    fn unit_variant(self) -> Result<(), TraceEnd> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        mut self,
+        seed: T,
+    ) -> Result<T::Value, TraceEnd> {
+        seed.deserialize(self.tracer.nested())
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, TraceEnd> {
+        de::Deserializer::deserialize_tuple(self.tracer, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceEnd> {
+        de::Deserializer::deserialize_struct(self.tracer, self.variant, fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct User {
+        id: i32,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct UserWithEmail {
+        id: i32,
+        name: String,
+        email: Option<String>,
+        tags: Vec<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct UserWithNumericName {
+        id: i32,
+        name: u64,
+        tags: Vec<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum Status<R> {
+        Active,
+        Blocked { reason: R },
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum StatusWithBlockedAt {
+        Active,
+        Blocked { reason: Reason, blocked_at: u64 },
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum Reason {
+        Spam,
+        Fraud(String),
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum ReasonWithCode {
+        Spam,
+        Fraud(u64),
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum Node {
+        Leaf(i32),
+        Branch {
+            children: Vec<Node>,
+            labels: HashMap<String, String>,
+        },
+    }
+
+    #[test]
+    fn test_key_format() {
+        let key = CacheKey::new("users")
+            .with_version(2)
+            .with_segment(&42)
+            .with_segment("a:b");
+        assert_eq!(key.to_string(), "users:v2:42:\"a:b\"");
+
+        assert_ne!(
+            CacheKey::new("users").with_segment(&1).to_string(),
+            CacheKey::new("users").with_segment("1").to_string()
+        );
+        assert_ne!(
+            CacheKey::new("users").with_segment("a\":\"b").to_string(),
+            CacheKey::new("users")
+                .with_segment("a")
+                .with_segment("b")
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_schema_hash() {
+        assert_eq!(schema_hash::<User>(), schema_hash::<User>());
+        assert_ne!(schema_hash::<User>(), schema_hash::<UserWithEmail>());
+        assert_ne!(schema_hash::<User>(), schema_hash::<UserWithNumericName>());
+        assert_ne!(schema_hash::<Vec<i32>>(), schema_hash::<Vec<i64>>());
+        assert_ne!(schema_hash::<Node>(), schema_hash::<User>());
+
+        let key = CacheKey::new("users")
+            .with_schema::<User>()
+            .with_segment(&1)
+            .to_string();
+        assert!(key.starts_with("users:v0:"));
+        assert!(key.ends_with(":1"));
+        assert_eq!(key.split(':').count(), 4);
+    }
+
+    #[test]
+    fn test_segment_encoding() {
+        let key = CacheKey::new("users")
+            .with_segment("John Smith")
+            .with_segment("100%\u{7f}\n")
+            .to_string();
+        assert_eq!(key, "users:v0:\"John%20Smith\":\"100%25%7F\\n\"");
+        assert!(!key.contains(' '));
+
+        let long = "a".repeat(MAX_SEGMENT_LEN);
+        let key = CacheKey::new("users").with_segment(&long).to_string();
+        assert!(key.starts_with("users:v0:#"));
+        assert_eq!(key.len(), "users:v0:#".len() + 16);
+        assert_ne!(
+            key,
+            CacheKey::new("users")
+                .with_segment(&format!("{}b", long))
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_schema_hash_traces_all_variants() {
+        assert_ne!(
+            schema_hash::<Status<Reason>>(),
+            schema_hash::<StatusWithBlockedAt>()
+        );
+        // `Fraud` is only reached through the last variant of `Status`
+        assert_ne!(
+            schema_hash::<Option<Status<Reason>>>(),
+            schema_hash::<Option<Status<ReasonWithCode>>>()
+        );
+        assert_eq!(schema_hash::<Node>(), schema_hash::<Node>());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid cache key namespace")]
+    fn test_invalid_namespace() {
+        CacheKey::new("users:1");
+    }
+}
@@ -0,0 +1,98 @@
+use r2d2_memcache::{
+    memcache::{Client as MemcacheConnection, MemcacheError},
+    r2d2::{ManageConnection, Pool},
+};
+use std::time::Duration;
+
+use cache::Cache;
+
+#[derive(Clone, Debug)]
+pub struct MemcachedCache<M>
+where
+    M: ManageConnection<Connection = MemcacheConnection>,
+{
+    namespace: String,
+    pool: Pool<M>,
+    ttl: Option<Duration>,
+}
+
+#[derive(Debug, Fail)]
+pub enum MemcachedCacheError {
+    #[fail(display = "No available Memcached connections left")]
+    NoAvailableConnections,
+    #[fail(display = "{}", _0)]
+    MemcacheError(MemcacheError),
+}
+
+impl From<MemcacheError> for MemcachedCacheError {
+    fn from(e: MemcacheError) -> Self {
+        MemcachedCacheError::MemcacheError(e)
+    }
+}
+
+impl<M> MemcachedCache<M>
+where
+    M: ManageConnection<Connection = MemcacheConnection>,
+{
+    pub fn new(pool: Pool<M>, namespace: String) -> Self {
+        MemcachedCache {
+            namespace,
+            pool,
+            ttl: None,
+        }
+    }
+
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        MemcachedCache {
+            ttl: Some(ttl),
+            ..self
+        }
+    }
+
+    fn make_memcached_key(&self, key: &str) -> String {
+        format!("{}:{}", self.namespace, key)
+    }
+
+    /// Memcached treats zero expiration as "never expire"
+    fn expiration(&self) -> u32 {
+        self.ttl.map(|ttl| ttl.as_secs() as u32).unwrap_or(0)
+    }
+
+    fn using_connection<T, F>(&self, f: F) -> Result<T, MemcachedCacheError>
+    where
+        F: Fn(&MemcacheConnection) -> T,
+    {
+        self.pool
+            .try_get()
+            .map(|conn| f(&conn))
+            .ok_or(MemcachedCacheError::NoAvailableConnections)
+    }
+}
+
+impl<M> Cache<String> for MemcachedCache<M>
+where
+    M: ManageConnection<Connection = MemcacheConnection>,
+{
+    type Error = MemcachedCacheError;
+
+    fn get(&self, key: &str) -> Result<Option<String>, Self::Error> {
+        self.using_connection(|conn| conn.get::<String>(&self.make_memcached_key(key)))
+            .and_then(|res| res.map_err(From::from))
+    }
+
+    fn set(&self, key: &str, value: String) -> Result<(), Self::Error> {
+        self.using_connection(|conn| {
+            conn.set(
+                &self.make_memcached_key(key),
+                value.as_str(),
+                self.expiration(),
+            )
+        })
+        .and_then(|res| res.map_err(From::from))
+    }
+
+    fn remove(&self, key: &str) -> Result<bool, Self::Error> {
+        self.using_connection(|conn| conn.delete(&self.make_memcached_key(key)))
+            .and_then(|res| res.map_err(From::from))
+    }
+}
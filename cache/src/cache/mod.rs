@@ -1,11 +1,21 @@
+pub mod codec;
+#[cfg(any(feature = "gzip", feature = "snappy"))]
+pub mod compressed;
 pub mod in_memory;
+pub mod key;
+#[cfg(feature = "memcached")]
+pub mod memcached;
 pub mod null;
 pub mod redis;
 pub mod typed;
 
 use failure::Fail;
 
+pub use self::codec::{Codec, CodecError, JsonCodec};
+#[cfg(any(feature = "gzip", feature = "snappy"))]
+pub use self::compressed::{CompressedCache, CompressedCacheError, Compression};
 pub use self::in_memory::{InMemoryCache, InMemoryCacheError};
+pub use self::key::CacheKey;
 pub use self::null::NullCache;
 pub use self::typed::{TypedCache, TypedCacheError};
 
@@ -38,6 +48,21 @@ where
     }
 }
 
+/// Cache that can set a value only if the key is vacant, atomically with respect to other clients of the backend
+pub trait AtomicCache<T>: Cache<T> {
+    /// Sets the value if there is no value under the key, returns whether the value was set
+    fn set_if_absent(&self, key: &str, value: T) -> Result<bool, Self::Error>;
+}
+
+impl<C, T> AtomicCache<T> for Box<C>
+where
+    C: ?Sized + AtomicCache<T>,
+{
+    fn set_if_absent(&self, key: &str, value: T) -> Result<bool, Self::Error> {
+        (**self).set_if_absent(key, value)
+    }
+}
+
 pub trait CacheSingle<T> {
     type Error: Fail;
 
@@ -4,7 +4,7 @@ use r2d2_redis::{
 };
 use std::time::Duration;
 
-use cache::Cache;
+use cache::{AtomicCache, Cache};
 
 #[derive(Clone, Debug)]
 pub struct RedisCache<M>
@@ -100,3 +100,24 @@ where
         .and_then(|res| res.map_err(From::from))
     }
 }
+
+impl<M> AtomicCache<String> for RedisCache<M>
+where
+    M: ManageConnection<Connection = RedisConnection>,
+{
+    fn set_if_absent(&self, key: &str, value: String) -> Result<bool, Self::Error> {
+        self.using_connection(|conn| {
+            let mut command = cmd("SET");
+            command.arg(self.make_redis_key(key)).arg(&value).arg("NX");
+            if let Some(ttl) = self.ttl {
+                let ttl_millis = ttl.as_secs() * 1000 + u64::from(ttl.subsec_millis());
+                command.arg("PX").arg(ttl_millis);
+            }
+            // Redis replies with nil instead of OK if the key is already set
+            command
+                .query(conn)
+                .map(|reply: Option<String>| reply.is_some())
+        })
+        .and_then(|res| res.map_err(From::from))
+    }
+}
@@ -1,19 +1,24 @@
 use failure::Fail;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json;
+use std::borrow::Cow;
 use std::marker::PhantomData;
 
-use super::Cache;
+use super::codec::{Codec, CodecError, JsonCodec};
+use super::key::{encode_segment, CacheKey};
+use super::{AtomicCache, Cache};
 
 #[derive(Clone, Debug)]
-pub struct TypedCache<C, E, T>
+pub struct TypedCache<C, E, T, D = JsonCodec>
 where
     C: Cache<String, Error = E>,
     E: Fail,
     T: DeserializeOwned + Serialize,
+    D: Codec,
 {
     backend: C,
-    phantom: PhantomData<T>,
+    key_prefix: Option<String>,
+    phantom: PhantomData<(T, D)>,
 }
 
 #[derive(Debug, Fail)]
@@ -23,6 +28,10 @@ where
 {
     #[fail(display = "An error occurred in backend cache")]
     BackendCacheError(E),
+    #[fail(display = "An error occurred on value serialization/deserialization")]
+    CodecError(CodecError),
+    /// Former JSON error, JSON codec errors are returned as `CodecError(CodecError::Json(_))` now
+    #[deprecated(note = "match `TypedCacheError::CodecError(CodecError::Json(_))` instead")]
     #[fail(display = "An error occurred on JSON serialization/deserialization")]
     JsonError(serde_json::Error),
 }
@@ -34,53 +43,108 @@ where
     T: DeserializeOwned + Serialize,
 {
     pub fn new(backend: C) -> Self {
+        TypedCache::with_codec(backend)
+    }
+}
+
+impl<C, E, T, D> TypedCache<C, E, T, D>
+where
+    C: Cache<String, Error = E>,
+    E: Fail,
+    T: DeserializeOwned + Serialize,
+    D: Codec,
+{
+    pub fn with_codec(backend: C) -> Self {
         TypedCache {
             backend,
+            key_prefix: None,
             phantom: PhantomData,
         }
     }
+
+    /// Prefix keys with the namespace and the schema hash of `T`, so that entries cached before a change of `T`
+    /// are missed instead of failing to decode. Keys become segments of `CacheKey`.
+    pub fn with_namespace<N: Into<String>>(self, namespace: N) -> Self {
+        self.with_key_prefix(CacheKey::new(namespace).with_schema::<T>())
+    }
+
+    /// Prefix keys with the cache key, e.g. one with a version
+    pub fn with_key_prefix(mut self, prefix: CacheKey) -> Self {
+        self.key_prefix = Some(prefix.to_string());
+        self
+    }
+
+    fn backend_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        match self.key_prefix {
+            Some(ref prefix) => Cow::Owned(format!("{}:{}", prefix, encode_segment(key))),
+            None => Cow::Borrowed(key),
+        }
+    }
 }
 
-impl<C, E, T> Cache<T> for TypedCache<C, E, T>
+impl<C, E, T, D> Cache<T> for TypedCache<C, E, T, D>
 where
     C: Cache<String, Error = E>,
     E: Fail,
     T: DeserializeOwned + Serialize,
+    D: Codec,
 {
     type Error = TypedCacheError<E>;
 
     fn get(&self, key: &str) -> Result<Option<T>, Self::Error> {
         self.backend
-            .get(key)
+            .get(&self.backend_key(key))
             .map_err(|e| TypedCacheError::BackendCacheError(e))
-            .and_then(|json_opt| match json_opt {
+            .and_then(|encoded_opt| match encoded_opt {
                 None => Ok(None),
-                Some(json) => serde_json::from_str(&json)
+                Some(encoded) => D::decode(&encoded)
                     .map(Some)
-                    .map_err(|e| TypedCacheError::JsonError(e)),
+                    .map_err(|e| TypedCacheError::CodecError(e)),
             })
     }
 
     fn set(&self, key: &str, value: T) -> Result<(), Self::Error> {
-        serde_json::to_string(&value)
-            .map_err(|e| TypedCacheError::JsonError(e))
-            .and_then(|json| {
+        D::encode(&value)
+            .map_err(|e| TypedCacheError::CodecError(e))
+            .and_then(|encoded| {
                 self.backend
-                    .set(key, json)
+                    .set(&self.backend_key(key), encoded)
                     .map_err(|e| TypedCacheError::BackendCacheError(e))
             })
     }
 
     fn remove(&self, key: &str) -> Result<bool, Self::Error> {
         self.backend
-            .remove(key)
+            .remove(&self.backend_key(key))
             .map_err(|e| TypedCacheError::BackendCacheError(e))
     }
 }
 
+impl<C, E, T, D> AtomicCache<T> for TypedCache<C, E, T, D>
+where
+    C: AtomicCache<String, Error = E>,
+    E: Fail,
+    T: DeserializeOwned + Serialize,
+    D: Codec,
+{
+    fn set_if_absent(&self, key: &str, value: T) -> Result<bool, Self::Error> {
+        D::encode(&value)
+            .map_err(|e| TypedCacheError::CodecError(e))
+            .and_then(|encoded| {
+                self.backend
+                    .set_if_absent(&self.backend_key(key), encoded)
+                    .map_err(|e| TypedCacheError::BackendCacheError(e))
+            })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use cache::{in_memory::InMemoryCache, typed::TypedCache, Cache};
+    #[cfg(feature = "bincode-codec")]
+    use cache::codec::BincodeCodec;
+    #[cfg(feature = "cbor-codec")]
+    use cache::codec::CborCodec;
+    use cache::{in_memory::InMemoryCache, typed::TypedCache, AtomicCache, Cache};
 
     #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
     struct TestStruct {
@@ -119,4 +183,100 @@ mod tests {
         let missing_value = typed_cache.get(key).expect("Failed to get value");
         assert_eq!(None, missing_value);
     }
+
+    #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+    struct TestStructV2 {
+        pub s: String,
+        pub i: i64,
+    }
+
+    #[test]
+    fn test_typed_cache_with_namespace() {
+        let backend = InMemoryCache::<String>::new();
+        let typed_cache =
+            TypedCache::<_, _, TestStruct>::new(backend.clone()).with_namespace("test");
+
+        let original_value = TestStruct {
+            s: "string".to_string(),
+            i: 10,
+        };
+        typed_cache
+            .set("key", original_value.clone())
+            .expect("Failed to set value");
+        assert_eq!(
+            typed_cache.get("key").expect("Failed to get value"),
+            Some(original_value)
+        );
+        assert_eq!(backend.get("key").expect("Failed to get value"), None);
+
+        let changed_cache = TypedCache::<_, _, TestStructV2>::new(backend).with_namespace("test");
+        assert_eq!(changed_cache.get("key").expect("Failed to get value"), None);
+    }
+
+    #[test]
+    fn test_typed_cache_set_if_absent() {
+        let backend = InMemoryCache::<String>::new();
+        let typed_cache = TypedCache::<_, _, TestStruct>::new(backend);
+
+        let first = TestStruct {
+            s: "first".to_string(),
+            i: 1,
+        };
+        let second = TestStruct {
+            s: "second".to_string(),
+            i: 2,
+        };
+        assert!(typed_cache
+            .set_if_absent("key", first.clone())
+            .expect("Failed to set value"));
+        assert!(!typed_cache
+            .set_if_absent("key", second)
+            .expect("Failed to set value"));
+        assert_eq!(
+            typed_cache.get("key").expect("Failed to get value"),
+            Some(first)
+        );
+    }
+
+    #[cfg(feature = "bincode-codec")]
+    #[test]
+    fn test_typed_cache_with_bincode_codec() {
+        let backend = InMemoryCache::<String>::new();
+        let typed_cache = TypedCache::<_, _, TestStruct, BincodeCodec>::with_codec(backend);
+
+        let original_value = TestStruct {
+            s: "string".to_string(),
+            i: 10,
+        };
+
+        typed_cache
+            .set("key", original_value.clone())
+            .expect("Failed to set value");
+        let value_from_cache = typed_cache
+            .get("key")
+            .expect("Failed to get value")
+            .expect("Value does not exist in cache");
+        assert_eq!(original_value, value_from_cache);
+    }
+
+    #[cfg(feature = "cbor-codec")]
+    #[test]
+    fn test_typed_cache_with_cbor_codec() {
+        let backend = InMemoryCache::<String>::new();
+        let typed_cache = TypedCache::<_, _, TestStruct, CborCodec>::with_codec(backend);
+
+        let original_value = TestStruct {
+            s: "string".to_string(),
+            i: 10,
+        };
+
+        typed_cache
+            .set("key", original_value.clone())
+            .expect("Failed to set value");
+        let value_from_cache = typed_cache
+            .get("key")
+            .expect("Failed to get value")
+            .expect("Value does not exist in cache");
+        assert_eq!(original_value, value_from_cache);
+    }
 }
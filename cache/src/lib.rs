@@ -1,11 +1,25 @@
+extern crate base64;
+#[cfg(feature = "bincode-codec")]
+extern crate bincode;
 #[macro_use]
 extern crate failure;
+#[cfg(feature = "gzip")]
+extern crate flate2;
+extern crate futures;
+#[cfg(feature = "memcached")]
+extern crate r2d2_memcache;
 extern crate r2d2_redis;
+extern crate rand;
 extern crate serde;
+#[cfg(feature = "cbor-codec")]
+extern crate serde_cbor;
 extern crate serde_json;
+#[cfg(feature = "snappy")]
+extern crate snap;
 
 #[cfg(test)]
 #[macro_use]
 extern crate serde_derive;
 
 pub mod cache;
+pub mod lock;
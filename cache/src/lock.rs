@@ -0,0 +1,157 @@
+//! Distributed locks, so that operations like cart to order conversion are serialized across service instances.
+//!
+//! Lock is a Redis key set with `SET NX PX` to a random token. It expires after TTL, so that a crashed holder
+//! doesn't block others forever, and is released with a script deleting the key only if it still holds the token,
+//! so that a holder outliving TTL doesn't release the lock acquired by someone else.
+use futures::future;
+use futures::prelude::*;
+use r2d2_redis::{
+    r2d2::{ManageConnection, Pool},
+    redis::{cmd, Connection as RedisConnection, RedisError, Script},
+};
+use rand;
+use std::time::Duration;
+
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+else
+    return 0
+end
+"#;
+
+#[derive(Debug, Fail)]
+pub enum LockError {
+    #[fail(display = "Lock {} is held by another owner", _0)]
+    Busy(String),
+    #[fail(display = "No available Redis connections left")]
+    NoAvailableConnections,
+    #[fail(display = "{}", _0)]
+    RedisError(RedisError),
+}
+
+impl From<RedisError> for LockError {
+    fn from(e: RedisError) -> Self {
+        LockError::RedisError(e)
+    }
+}
+
+/// Acquired lock, identified by the token only its owner knows
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockGuard {
+    pub key: String,
+    token: String,
+}
+
+#[derive(Debug)]
+pub struct RedisLock<M>
+where
+    M: ManageConnection<Connection = RedisConnection>,
+{
+    namespace: String,
+    pool: Pool<M>,
+}
+
+impl<M> Clone for RedisLock<M>
+where
+    M: ManageConnection<Connection = RedisConnection>,
+{
+    fn clone(&self) -> Self {
+        RedisLock {
+            namespace: self.namespace.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl<M> RedisLock<M>
+where
+    M: ManageConnection<Connection = RedisConnection>,
+{
+    pub fn new(pool: Pool<M>, namespace: String) -> Self {
+        RedisLock { namespace, pool }
+    }
+
+    fn make_redis_key(&self, key: &str) -> String {
+        format!("{}:lock:{}", self.namespace, key)
+    }
+
+    fn using_connection<T, F>(&self, f: F) -> Result<T, LockError>
+    where
+        F: Fn(&RedisConnection) -> Result<T, RedisError>,
+    {
+        self.pool
+            .try_get()
+            .map(|conn| f(&conn))
+            .ok_or(LockError::NoAvailableConnections)
+            .and_then(|res| res.map_err(From::from))
+    }
+
+    /// Acquires the lock for `ttl`, fails with `LockError::Busy` without waiting if it is held by someone else
+    pub fn acquire(&self, key: &str, ttl: Duration) -> Result<LockGuard, LockError> {
+        let guard = LockGuard {
+            key: self.make_redis_key(key),
+            token: format!(
+                "{:016x}{:016x}",
+                rand::random::<u64>(),
+                rand::random::<u64>()
+            ),
+        };
+        let ttl_ms = ttl.as_secs() * 1000 + u64::from(ttl.subsec_millis());
+
+        // Reply is nil if the key is already set
+        let reply: Option<String> = self.using_connection(|conn| {
+            cmd("SET")
+                .arg(&guard.key)
+                .arg(&guard.token)
+                .arg("NX")
+                .arg("PX")
+                .arg(ttl_ms.max(1))
+                .query(conn)
+        })?;
+
+        match reply {
+            Some(_) => Ok(guard),
+            None => Err(LockError::Busy(key.to_string())),
+        }
+    }
+
+    /// Releases the lock, returns `false` if it has already expired
+    pub fn release(&self, guard: &LockGuard) -> Result<bool, LockError> {
+        self.using_connection(|conn| {
+            Script::new(RELEASE_SCRIPT)
+                .key(&guard.key)
+                .arg(&guard.token)
+                .invoke(conn)
+                .map(|keys_removed: u32| keys_removed > 0)
+        })
+    }
+
+    /// Runs the future holding the lock, which is acquired once the returned future is polled and released
+    /// once the future is resolved. Fails with `LockError::Busy` without running the future if the lock is held
+    /// by someone else. Failures to release are ignored, as the lock expires anyway.
+    pub fn with_lock<F>(
+        &self,
+        key: &str,
+        ttl: Duration,
+        fut: F,
+    ) -> impl Future<Item = F::Item, Error = F::Error>
+    where
+        F: Future,
+        F::Error: From<LockError>,
+    {
+        let lock = self.clone();
+        let key = key.to_string();
+
+        future::lazy(move || match lock.acquire(&key, ttl) {
+            Ok(guard) => Ok((lock, guard)),
+            Err(e) => Err(F::Error::from(e)),
+        })
+        .and_then(move |(lock, guard)| {
+            fut.then(move |res| {
+                let _ = lock.release(&guard);
+                res
+            })
+        })
+    }
+}
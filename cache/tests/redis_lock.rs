@@ -0,0 +1,77 @@
+extern crate futures;
+extern crate r2d2_redis;
+extern crate stq_cache;
+
+use futures::future;
+use futures::prelude::*;
+use r2d2_redis::{r2d2::Pool, RedisConnectionManager};
+use std::time::Duration;
+use stq_cache::lock::{LockError, RedisLock};
+
+#[test]
+fn test_redis_lock() {
+    let redis_url = std::env::vars()
+        .find(|(k, _v)| k == "REDIS_URL")
+        .map(|(_k, v)| v)
+        .unwrap_or("redis://127.0.0.1/".to_string());
+
+    let manager = RedisConnectionManager::new(redis_url.as_ref())
+        .expect("Failed to create connection manager");
+
+    let pool = Pool::builder()
+        .build(manager)
+        .expect("Failed to create connection pool");
+
+    let ttl = Duration::from_secs(3);
+    let lock = RedisLock::new(pool.clone(), "base_key".to_string());
+
+    let guard = lock.acquire("key", ttl).expect("Failed to acquire lock");
+
+    match lock.acquire("key", ttl) {
+        Err(LockError::Busy(key)) => assert_eq!("key", key),
+        res => panic!("Acquired lock held by another owner: {:?}", res),
+    }
+
+    let other_guard = lock
+        .acquire("other_key", ttl)
+        .expect("Failed to acquire another lock");
+    assert!(lock.release(&other_guard).expect("Failed to release lock"));
+
+    let was_released = lock.release(&guard).expect("Failed to release lock");
+    assert!(was_released);
+
+    let was_released_twice = lock.release(&guard).expect("Failed to release lock");
+    assert!(!was_released_twice);
+
+    let expiring_guard = lock
+        .acquire("key", Duration::from_secs(1))
+        .expect("Failed to acquire released lock");
+    std::thread::sleep(Duration::from_secs(2));
+    let new_guard = lock
+        .acquire("key", ttl)
+        .expect("Failed to acquire expired lock");
+    let expired_was_released = lock
+        .release(&expiring_guard)
+        .expect("Failed to release lock");
+    assert!(!expired_was_released);
+    lock.release(&new_guard).expect("Failed to release lock");
+
+    let value = lock
+        .with_lock(
+            "key",
+            ttl,
+            future::lazy(|| {
+                let res = lock.acquire("key", ttl);
+                assert!(res.is_err());
+                Ok::<_, LockError>(42)
+            }),
+        )
+        .wait()
+        .expect("Failed to run future holding lock");
+    assert_eq!(42, value);
+
+    let guard = lock
+        .acquire("key", ttl)
+        .expect("Lock was not released by with_lock");
+    lock.release(&guard).expect("Failed to release lock");
+}
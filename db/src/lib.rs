@@ -8,11 +8,14 @@ extern crate failure;
 extern crate futures;
 extern crate futures_state_stream;
 extern crate stq_acl;
+extern crate stq_types;
 extern crate tokio_postgres;
 
 pub mod connection;
 pub mod diesel_repo;
+pub mod outbox;
 pub mod pool;
 pub mod repo;
 pub mod sequence;
 pub mod statement;
+pub mod webhook;
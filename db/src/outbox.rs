@@ -0,0 +1,377 @@
+//! Transactional outbox.
+//!
+//! Events are stored in the same transaction as the repo write causing them and published by `OutboxRelay` afterwards,
+//! so that an event is published if and only if the write is committed. Delivery is at-least-once: an event published
+//! but not yet marked as such when the relay fails is published again, so consumers must deduplicate by event id.
+//! Events are published in the order of their ids. An event failing `max_attempts` times in a row is moved to
+//! dead letters by setting `dead_at`, so that it stops blocking later events and can be inspected or requeued by hand.
+//!
+//! The outbox table is expected to have the following schema:
+//!
+//! ```sql
+//! CREATE TABLE outbox (
+//!     id BIGSERIAL PRIMARY KEY,
+//!     event_type VARCHAR NOT NULL,
+//!     payload TEXT NOT NULL,
+//!     created_at TIMESTAMP NOT NULL DEFAULT now(),
+//!     published_at TIMESTAMP,
+//!     attempts INTEGER NOT NULL DEFAULT 0,
+//!     dead_at TIMESTAMP,
+//!     last_error TEXT
+//! );
+//! CREATE INDEX outbox_pending_idx ON outbox (id) WHERE published_at IS NULL AND dead_at IS NULL;
+//! ```
+use connection::*;
+use pool::Pool;
+use statement::{is_valid_identifier, qualified_table, InsertBuilder, Inserter, StatementError, TableName};
+
+use failure;
+use futures::future;
+use futures::prelude::*;
+use futures::stream;
+use futures_state_stream::StateStream;
+use std::rc::Rc;
+use std::time::SystemTime;
+use tokio_postgres::rows::Row;
+use tokio_postgres::types::ToSql;
+
+pub type OutboxError = failure::Error;
+pub type OutboxConnection = BoxedConnection<OutboxError>;
+pub type OutboxConnectionFuture<T> = ConnectionFuture<T, OutboxError>;
+
+/// Event to be published, inserted along with the write causing it
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventRecord {
+    pub event_type: String,
+    /// Serialized event, usually JSON
+    pub payload: String,
+}
+
+impl EventRecord {
+    pub fn new<S: Into<String>>(event_type: S, payload: String) -> Self {
+        Self {
+            event_type: event_type.into(),
+            payload,
+        }
+    }
+}
+
+impl Inserter for EventRecord {
+    fn into_insert_builder(self, table: TableName) -> InsertBuilder {
+        InsertBuilder::new(table)
+            .with_arg("event_type", self.event_type)
+            .with_arg("payload", self.payload)
+    }
+}
+
+/// Event stored in the outbox
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutboxEvent {
+    pub id: i64,
+    pub event_type: String,
+    pub payload: String,
+    pub created_at: SystemTime,
+    /// Failed publishing attempts
+    pub attempts: i32,
+}
+
+impl From<Row> for OutboxEvent {
+    fn from(row: Row) -> Self {
+        Self {
+            id: row.get("id"),
+            event_type: row.get("event_type"),
+            payload: row.get("payload"),
+            created_at: row.get("created_at"),
+            attempts: row.get("attempts"),
+        }
+    }
+}
+
+fn execute(conn: OutboxConnection, query: String, args: Vec<Box<ToSql>>) -> OutboxConnectionFuture<Vec<Row>> {
+    Box::new(conn.prepare2(&query).and_then(move |(statement, conn)| {
+        conn.query2(&statement, args)
+            .collect()
+            .map_err(move |(e, conn)| (e.context(format!("Failed to run outbox query {}", query)).into(), conn))
+    }))
+}
+
+pub struct Outbox {
+    pub table: TableName,
+}
+
+impl Outbox {
+    pub fn new<T: Into<TableName>>(table: T) -> Result<Self, OutboxError> {
+        let table = table.into();
+        if !is_valid_identifier(&table) {
+            return Err(StatementError::InvalidIdentifier {
+                identifier: table.into_owned(),
+            }
+            .into());
+        }
+        Ok(Self { table })
+    }
+
+    /// Qualify the table with the schema, e.g. one per tenant
+    pub fn with_schema(mut self, schema: &str) -> Result<Self, OutboxError> {
+        self.table = qualified_table(schema, self.table)?;
+        Ok(self)
+    }
+
+    /// Stores the event. Connection must be the transaction of the write causing the event.
+    pub fn push(&self, conn: OutboxConnection, event: EventRecord) -> OutboxConnectionFuture<OutboxEvent> {
+        let (query, args) = match event.into_insert_builder(self.table.clone()).try_build() {
+            Ok(built) => built,
+            Err(e) => return Box::new(future::err((e.into(), conn))),
+        };
+
+        Box::new(execute(conn, query, args).and_then(|(mut rows, conn)| match rows.pop() {
+            Some(row) => Ok((OutboxEvent::from(row), conn)),
+            None => Err((format_err!("No rows returned"), conn)),
+        }))
+    }
+
+    /// Locks up to `limit` oldest pending events until the end of transaction, skipping ones locked by other relays
+    pub fn lock_pending(&self, conn: OutboxConnection, limit: i64) -> OutboxConnectionFuture<Vec<OutboxEvent>> {
+        let query = format!(
+            "SELECT * FROM {} WHERE published_at IS NULL AND dead_at IS NULL ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED;",
+            self.table
+        );
+
+        Box::new(execute(conn, query, vec![Box::new(limit)]).map(|(rows, conn)| (rows.into_iter().map(OutboxEvent::from).collect(), conn)))
+    }
+
+    pub fn mark_published(&self, conn: OutboxConnection, ids: Vec<i64>) -> OutboxConnectionFuture<()> {
+        if ids.is_empty() {
+            return Box::new(future::ok(((), conn)));
+        }
+
+        let query = format!("UPDATE {} SET published_at = now() WHERE id = ANY($1);", self.table);
+
+        Box::new(execute(conn, query, vec![Box::new(ids)]).map(|(_, conn)| ((), conn)))
+    }
+
+    pub fn mark_failed(&self, conn: OutboxConnection, id: i64, error: String) -> OutboxConnectionFuture<()> {
+        let query = format!("UPDATE {} SET attempts = attempts + 1, last_error = $2 WHERE id = $1;", self.table);
+
+        Box::new(execute(conn, query, vec![Box::new(id), Box::new(error)]).map(|(_, conn)| ((), conn)))
+    }
+
+    /// Records the last failed attempt, the event is never published again
+    pub fn mark_dead(&self, conn: OutboxConnection, id: i64, error: String) -> OutboxConnectionFuture<()> {
+        let query = format!(
+            "UPDATE {} SET attempts = attempts + 1, dead_at = now(), last_error = $2 WHERE id = $1;",
+            self.table
+        );
+
+        Box::new(execute(conn, query, vec![Box::new(id), Box::new(error)]).map(|(_, conn)| ((), conn)))
+    }
+}
+
+/// Destination events are published to, e.g. HTTP webhook or message queue
+pub trait EventTransport {
+    /// Resolves once the destination has accepted the event
+    fn publish(&self, event: &OutboxEvent) -> Box<Future<Item = (), Error = failure::Error>>;
+}
+
+impl<F> EventTransport for F
+where
+    F: Fn(&OutboxEvent) -> Box<Future<Item = (), Error = failure::Error>>,
+{
+    fn publish(&self, event: &OutboxEvent) -> Box<Future<Item = (), Error = failure::Error>> {
+        self(event)
+    }
+}
+
+/// Event of a batch that failed to publish
+#[derive(Debug, PartialEq)]
+struct Failure {
+    id: i64,
+    error: String,
+    /// The event has run out of attempts and goes to dead letters
+    dead: bool,
+}
+
+/// Outcome of publishing a batch
+#[derive(Debug, Default, PartialEq)]
+struct Delivery {
+    published: Vec<i64>,
+    failed: Option<Failure>,
+}
+
+/// Publishes events in order, stopping at the first failed one
+fn dispatch<T>(transport: Rc<T>, events: Vec<OutboxEvent>, max_attempts: i32) -> Box<Future<Item = Delivery, Error = OutboxError>>
+where
+    T: EventTransport + 'static,
+{
+    Box::new(
+        stream::iter_ok::<_, OutboxError>(events).fold(Delivery::default(), move |mut delivery, event| {
+            if delivery.failed.is_some() {
+                return Box::new(future::ok(delivery)) as Box<Future<Item = Delivery, Error = OutboxError>>;
+            }
+
+            Box::new(transport.publish(&event).then(move |res| {
+                match res {
+                    Ok(()) => delivery.published.push(event.id),
+                    Err(e) => {
+                        delivery.failed = Some(Failure {
+                            id: event.id,
+                            error: e.to_string(),
+                            dead: event.attempts + 1 >= max_attempts,
+                        })
+                    }
+                };
+                Ok::<_, OutboxError>(delivery)
+            }))
+        }),
+    )
+}
+
+/// Publishes pending events of the outbox. Several relays may run at once, each event is locked by one of them.
+pub struct OutboxRelay<T> {
+    pool: Pool,
+    outbox: Rc<Outbox>,
+    transport: Rc<T>,
+    batch_size: i64,
+    max_attempts: i32,
+}
+
+impl<T> OutboxRelay<T>
+where
+    T: EventTransport + 'static,
+{
+    pub fn new(pool: Pool, outbox: Outbox, transport: T) -> Self {
+        Self {
+            pool,
+            outbox: Rc::new(outbox),
+            transport: Rc::new(transport),
+            batch_size: 100,
+            max_attempts: 10,
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: i64) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Failed attempts after which an event goes to dead letters
+    pub fn with_max_attempts(mut self, max_attempts: i32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Publishes a batch of pending events in order, resolves to the number of published ones.
+    /// Publishing stops at the first failed event, so that later events don't overtake it until it goes to dead letters.
+    pub fn relay_batch(&self) -> impl Future<Item = usize, Error = OutboxError> {
+        let outbox = self.outbox.clone();
+        let transport = self.transport.clone();
+        let batch_size = self.batch_size;
+        let max_attempts = self.max_attempts;
+
+        self.pool.run(move |conn| {
+            outbox.lock_pending(conn, batch_size).and_then(move |(events, conn)| {
+                dispatch(transport, events, max_attempts)
+                    .then(move |res| match res {
+                        Ok(delivery) => Ok((delivery, conn)),
+                        Err(e) => Err((e, conn)),
+                    })
+                    .and_then(move |(delivery, conn)| {
+                        let published = delivery.published.len();
+                        let failed = delivery.failed;
+                        outbox
+                            .mark_published(conn, delivery.published)
+                            .and_then(move |(_, conn)| match failed {
+                                Some(Failure { id, error, dead: true }) => outbox.mark_dead(conn, id, error),
+                                Some(Failure { id, error, dead: false }) => outbox.mark_failed(conn, id, error),
+                                None => Box::new(future::ok(((), conn))) as OutboxConnectionFuture<()>,
+                            })
+                            .map(move |(_, conn)| (published, conn))
+                    })
+            })
+        })
+    }
+
+    /// Relays a batch on every tick, e.g. of `tokio_core::reactor::Interval`. Failed batches are yielded
+    /// as errors without ending the stream, so that the worker keeps running through transient failures.
+    pub fn run<S>(self, ticks: S) -> impl Stream<Item = Result<usize, OutboxError>, Error = S::Error>
+    where
+        S: Stream,
+    {
+        ticks.and_then(move |_| self.relay_batch().then(Ok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_record_insert() {
+        let (query, args) = EventRecord::new("order_state_changed", "{}".to_string())
+            .into_insert_builder("outbox".into())
+            .build();
+
+        assert_eq!(query, "INSERT INTO outbox (event_type, payload) VALUES ($1, $2) RETURNING *;");
+        assert_eq!(args.len(), 2);
+    }
+
+    #[test]
+    fn test_outbox_table() {
+        let outbox = Outbox::new("outbox").unwrap().with_schema("tenant_1").unwrap();
+        assert_eq!(outbox.table, "tenant_1.outbox");
+
+        assert!(Outbox::new("outbox; DROP TABLE orders").is_err());
+        assert!(Outbox::new("outbox").unwrap().with_schema("tenant 1").is_err());
+    }
+
+    fn event(id: i64, attempts: i32) -> OutboxEvent {
+        OutboxEvent {
+            id,
+            event_type: "order_state_changed".to_string(),
+            payload: "{}".to_string(),
+            created_at: SystemTime::now(),
+            attempts,
+        }
+    }
+
+    fn transport(event: &OutboxEvent) -> Box<Future<Item = (), Error = failure::Error>> {
+        if event.id == 2 {
+            Box::new(future::err(format_err!("Destination unavailable")))
+        } else {
+            Box::new(future::ok(()))
+        }
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let transport = Rc::new(transport);
+
+        let delivery = dispatch(transport.clone(), vec![event(1, 0), event(2, 0), event(3, 0)], 3)
+            .wait()
+            .unwrap();
+        assert_eq!(
+            delivery,
+            Delivery {
+                published: vec![1],
+                failed: Some(Failure {
+                    id: 2,
+                    error: "Destination unavailable".to_string(),
+                    dead: false,
+                }),
+            }
+        );
+
+        let delivery = dispatch(transport.clone(), vec![event(2, 2), event(3, 0)], 3).wait().unwrap();
+        assert_eq!(delivery.published, Vec::<i64>::new());
+        assert_eq!(delivery.failed.map(|failure| failure.dead), Some(true));
+
+        let delivery = dispatch(transport, vec![event(3, 5)], 3).wait().unwrap();
+        assert_eq!(
+            delivery,
+            Delivery {
+                published: vec![3],
+                failed: None
+            }
+        );
+    }
+}
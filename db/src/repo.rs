@@ -1,11 +1,15 @@
 use super::connection::*;
-use super::statement::{Filter, FilteredOperation, Inserter, SelectOperation, Updater};
+use super::statement::{
+    qualified_table, Filter, FilteredOperation, FilteredOperationBuilder, Inserter, RowLock, SelectOperation, TableName, UpdateManyBuilder,
+    UpdateRow, Updater,
+};
 
 use failure;
 use futures::*;
 use futures_state_stream::*;
 use std::rc::Rc;
 use stq_acl as acl;
+use stq_types::Page;
 use tokio_postgres::rows::Row;
 use tokio_postgres::types::ToSql;
 
@@ -17,9 +21,32 @@ pub enum MultipleOperationError {
     ExtraData { extra: u32 },
 }
 
+/// Operation the repo has no implementation of
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Fail)]
+#[fail(display = "Operation is not supported: {}", operation)]
+pub struct UnsupportedOperationError {
+    pub operation: &'static str,
+}
+
 pub trait DbRepoInsert<T: 'static, I: Inserter, E: From<MultipleOperationError> + 'static> {
     fn insert(&self, conn: BoxedConnection<E>, inserter: I) -> ConnectionFuture<Vec<T>, E>;
 
+    /// Inserts all items. Default implementation inserts them one by one with `insert`,
+    /// so it is atomic only when the connection is inside a transaction.
+    fn insert_many(&self, conn: BoxedConnection<E>, inserters: Vec<I>) -> ConnectionFuture<Vec<T>, E>
+    where
+        Self: Clone + 'static,
+        I: 'static,
+    {
+        let repo = self.clone();
+        Box::new(stream::iter_ok(inserters).fold((vec![], conn), move |(mut items, conn), inserter| {
+            repo.insert(conn, inserter).map(|(inserted, conn)| {
+                items.extend(inserted);
+                (items, conn)
+            })
+        }))
+    }
+
     fn insert_exactly_one(&self, conn: BoxedConnection<E>, inserter: I) -> ConnectionFuture<T, E> {
         Box::new(self.insert(conn, inserter).and_then(|(mut data, conn)| {
             if data.len() > 1 {
@@ -49,10 +76,51 @@ pub trait DbRepoSelect<T: 'static, F: Filter, E: From<MultipleOperationError> +
         op: Option<SelectOperation>,
     ) -> ConnectionFuture<Vec<T>, E>;
 
+    /// Selects rows locking them until the end of transaction, so the connection must be inside one.
+    /// Default implementation fails, as selecting the rows without locking them would not be safe.
+    fn select_for_update(&self, conn: BoxedConnection<E>, _filter: F, _limit: Option<i32>, _lock: RowLock) -> ConnectionFuture<Vec<T>, E>
+    where
+        E: From<UnsupportedOperationError>,
+    {
+        Box::new(future::err((
+            E::from(UnsupportedOperationError {
+                operation: "select_for_update",
+            }),
+            conn,
+        )))
+    }
+
     fn select(&self, conn: BoxedConnection<E>, filter: F) -> ConnectionFuture<Vec<T>, E> {
         self.select_full(conn, filter, None, None)
     }
 
+    /// Number of rows matching the filter, counted with `SelectOperation::Count`.
+    /// Default implementation fails, as counting fetched rows would select all of them.
+    fn count(&self, conn: BoxedConnection<E>, _filter: F) -> ConnectionFuture<i64, E>
+    where
+        E: From<UnsupportedOperationError>,
+    {
+        Box::new(future::err((E::from(UnsupportedOperationError { operation: "count" }), conn)))
+    }
+
+    /// Page of rows matching the filter, which defines their order, along with the number of all matching rows.
+    /// Default implementation fetches all the rows, so implementors should override it.
+    fn select_page(&self, conn: BoxedConnection<E>, filter: F, offset: i64, limit: i32) -> ConnectionFuture<Page<T>, E> {
+        Box::new(self.select(conn, filter).map(move |(data, conn)| {
+            let total = data.len() as i64;
+            let items = data.into_iter().skip(offset.max(0) as usize).take(limit.max(0) as usize).collect();
+            (Page::new(items, total, offset, Some(i64::from(limit))), conn)
+        }))
+    }
+
+    /// Checks whether any row matches the filter, fetching at most one row
+    fn exists(&self, conn: BoxedConnection<E>, filter: F) -> ConnectionFuture<bool, E> {
+        Box::new(
+            self.select_full(conn, filter, Some(1), None)
+                .map(|(data, conn)| (!data.is_empty(), conn)),
+        )
+    }
+
     fn select_exactly_one(&self, conn: BoxedConnection<E>, filter: F) -> ConnectionFuture<T, E> {
         Box::new(self.select(conn, filter).and_then(|(mut data, conn)| {
             if data.len() > 1 {
@@ -76,6 +144,21 @@ pub trait DbRepoSelect<T: 'static, F: Filter, E: From<MultipleOperationError> +
 pub trait DbRepoUpdate<T: 'static, U: Updater, E: From<MultipleOperationError> + 'static> {
     fn update(&self, conn: BoxedConnection<E>, updater: U) -> ConnectionFuture<Vec<T>, E>;
 
+    /// Updates many rows identified by `key_column` with distinct values in a single statement, one row per updater.
+    /// Default implementation fails, as updating the rows one by one would not be atomic.
+    fn update_many_distinct(&self, conn: BoxedConnection<E>, _key_column: &'static str, _updaters: Vec<U>) -> ConnectionFuture<Vec<T>, E>
+    where
+        U: Into<UpdateRow>,
+        E: From<UnsupportedOperationError>,
+    {
+        Box::new(future::err((
+            E::from(UnsupportedOperationError {
+                operation: "update_many_distinct",
+            }),
+            conn,
+        )))
+    }
+
     fn update_exactly_one(&self, conn: BoxedConnection<E>, updater: U) -> ConnectionFuture<T, E> {
         Box::new(self.update(conn, updater).and_then(|(mut data, conn)| {
             if data.len() > 1 {
@@ -156,6 +239,71 @@ where
     })
 }
 
+/// What selects do with rows denied by afterop ACL engine. Writes always fail on denied rows, as dropping or masking
+/// them would hide the changes already made. `count` and `exists` fetch no rows to check, so they rely on select
+/// ACL engine only and count denied rows, same as `total` of pages under `Filter` policy.
+pub enum AfteropPolicy<T> {
+    /// Fails the whole select
+    Fail,
+    /// Drops denied rows, e.g. so that listings return only the rows visible to the user.
+    /// `total` of pages still counts denied rows.
+    Filter,
+    /// Replaces denied rows with the result of the transformer, e.g. with private fields cleared
+    Mask(Rc<Fn(T) -> T>),
+}
+
+impl<T> Clone for AfteropPolicy<T> {
+    fn clone(&self) -> Self {
+        match self {
+            AfteropPolicy::Fail => AfteropPolicy::Fail,
+            AfteropPolicy::Filter => AfteropPolicy::Filter,
+            AfteropPolicy::Mask(mask) => AfteropPolicy::Mask(mask.clone()),
+        }
+    }
+}
+
+impl<T> AfteropPolicy<T> {
+    pub fn mask<M>(mask: M) -> Self
+    where
+        M: Fn(T) -> T + 'static,
+    {
+        AfteropPolicy::Mask(Rc::new(mask))
+    }
+}
+
+fn bulk_select_access<T, C>(
+    acl_engine: &Rc<acl::AclEngine<(T, Action), RepoError>>,
+    policy: &AfteropPolicy<T>,
+    items: Vec<T>,
+    conn: C,
+) -> impl Future<Item = (Vec<T>, C), Error = (RepoError, C)>
+where
+    T: 'static,
+{
+    let policy = policy.clone();
+    future::join_all(items.into_iter().map({
+        let acl_engine = acl_engine.clone();
+        move |entity| acl_engine.allows((entity, Action::Select))
+    }))
+    .then(move |res| {
+        let verdicts = match res {
+            Ok(verdicts) => verdicts,
+            Err((e, _ctx)) => return Err((e, conn)),
+        };
+
+        let mut items = Vec::with_capacity(verdicts.len());
+        for (allowed, (entity, _)) in verdicts {
+            match policy {
+                _ if allowed => items.push(entity),
+                AfteropPolicy::Fail => return Err((acl::UnauthorizedError.into(), conn)),
+                AfteropPolicy::Filter => {}
+                AfteropPolicy::Mask(ref mask) => items.push(mask(entity)),
+            }
+        }
+        Ok((items, conn))
+    })
+}
+
 pub struct DbRepoImpl<T, I, F, U>
 where
     T: From<Row> + 'static,
@@ -163,12 +311,13 @@ where
     F: Filter + 'static,
     U: Updater + 'static,
 {
-    pub table: &'static str,
+    pub table: TableName,
     pub insert_acl_engine: Rc<acl::AclEngine<I, RepoError>>,
     pub select_acl_engine: Rc<acl::AclEngine<F, RepoError>>,
     pub delete_acl_engine: Rc<acl::AclEngine<F, RepoError>>,
     pub update_acl_engine: Rc<acl::AclEngine<U, RepoError>>,
     pub afterop_acl_engine: Rc<acl::AclEngine<(T, Action), RepoError>>,
+    pub afterop_policy: AfteropPolicy<T>,
 }
 
 impl<T, I, F, U> DbRepoImpl<T, I, F, U>
@@ -178,17 +327,24 @@ where
     I: Inserter + 'static,
     U: Updater + 'static,
 {
-    pub fn new(table: &'static str) -> Self {
+    pub fn new<N: Into<TableName>>(table: N) -> Self {
         Self {
-            table,
+            table: table.into(),
             insert_acl_engine: Rc::new(acl::SystemACL),
             select_acl_engine: Rc::new(acl::SystemACL),
             delete_acl_engine: Rc::new(acl::SystemACL),
             update_acl_engine: Rc::new(acl::SystemACL),
             afterop_acl_engine: Rc::new(acl::SystemACL),
+            afterop_policy: AfteropPolicy::Fail,
         }
     }
 
+    /// Qualify the table with the schema, e.g. one per tenant
+    pub fn with_schema(mut self, schema: &str) -> Result<Self, RepoError> {
+        self.table = qualified_table(schema, self.table)?;
+        Ok(self)
+    }
+
     pub fn with_insert_acl_engine<E>(mut self, acl_engine: E) -> Self
     where
         E: acl::AclEngine<I, RepoError> + 'static,
@@ -228,6 +384,12 @@ where
         self.afterop_acl_engine = Rc::new(acl_engine);
         self
     }
+
+    /// Set what selects do with rows denied by afterop ACL engine, failing by default
+    pub fn with_afterop_policy(mut self, policy: AfteropPolicy<T>) -> Self {
+        self.afterop_policy = policy;
+        self
+    }
 }
 
 fn query_debug(q: &str, args: &[Box<ToSql>]) -> String {
@@ -250,7 +412,7 @@ where
     U: Updater,
 {
     fn insert(&self, conn: RepoConnection, inserter: I) -> RepoConnectionFuture<Vec<T>> {
-        let table = self.table;
+        let table = self.table.clone();
 
         let afterop_acl_engine = self.afterop_acl_engine.clone();
 
@@ -258,13 +420,13 @@ where
             self.insert_acl_engine
                 .ensure_access(inserter)
                 .then(move |res| {
-                    future::result(match res {
-                        Ok(inserter) => {
-                            let (query, args) = inserter.into_insert_builder(table).build();
-                            Ok((query, args, conn))
-                        }
-                        Err((e, _inserter)) => Err((e, conn)),
-                    })
+                    let built = res
+                        .map_err(|(e, _inserter)| e)
+                        .and_then(|inserter| inserter.into_insert_builder(table).try_build().map_err(From::from));
+                    match built {
+                        Ok((query, args)) => Ok((query, args, conn)),
+                        Err(e) => Err((e, conn)),
+                    }
                 })
                 .and_then(move |(query, args, conn)| conn.prepare2(&query).map(move |(statement, conn)| (statement, query, args, conn)))
                 .and_then(move |(statement, query, args, conn)| {
@@ -278,43 +440,69 @@ where
                 .map_err(|(e, conn)| (e.context("Failure while running insert").into(), conn)),
         )
     }
+
+    fn insert_many(&self, conn: RepoConnection, inserters: Vec<I>) -> RepoConnectionFuture<Vec<T>> {
+        if inserters.is_empty() {
+            return Box::new(future::ok((vec![], conn)));
+        }
+
+        let table = self.table.clone();
+
+        let afterop_acl_engine = self.afterop_acl_engine.clone();
+
+        Box::new(
+            future::join_all(inserters.into_iter().map({
+                let insert_acl_engine = self.insert_acl_engine.clone();
+                move |inserter| insert_acl_engine.ensure_access(inserter)
+            }))
+            .then(move |res| {
+                let built = res
+                    .map_err(|(e, _inserter)| e)
+                    .and_then(|inserters| inserters.into_insert_builder(table).try_build().map_err(From::from));
+                match built {
+                    Ok((query, args)) => Ok((query, args, conn)),
+                    Err(e) => Err((e, conn)),
+                }
+            })
+            .and_then(move |(query, args, conn)| conn.prepare2(&query).map(move |(statement, conn)| (statement, query, args, conn)))
+            .and_then(move |(statement, query, args, conn)| {
+                let err_msg = query_debug(&query, &args);
+                conn.query2(&statement, args)
+                    .collect()
+                    .map_err(move |(e, conn)| (e.context(err_msg).into(), conn))
+            })
+            .map(|(rows, conn)| (rows.into_iter().map(T::from).collect::<Vec<T>>(), conn))
+            .and_then(move |(items, conn)| bulk_ensure_access(&afterop_acl_engine, (items, Action::Insert), conn))
+            .map_err(|(e, conn)| (e.context("Failure while running multiple insert").into(), conn)),
+        )
+    }
 }
 
-impl<T, I, F, U> DbRepoSelect<T, F, RepoError> for DbRepoImpl<T, I, F, U>
+impl<T, I, F, U> DbRepoImpl<T, I, F, U>
 where
     T: From<Row> + 'static,
     F: Filter,
     I: Inserter,
     U: Updater,
 {
-    fn select_full(
-        &self,
-        conn: RepoConnection,
-        filter: F,
-        limit: Option<i32>,
-        op: Option<SelectOperation>,
-    ) -> RepoConnectionFuture<Vec<T>> {
-        let table = self.table;
-
-        let afterop_acl_engine = self.afterop_acl_engine.clone();
+    /// Runs select built from the filter allowed by select ACL engine
+    fn query_filtered<B>(&self, conn: RepoConnection, filter: F, build: B) -> RepoConnectionFuture<Vec<Row>>
+    where
+        B: FnOnce(FilteredOperationBuilder) -> Result<(String, Vec<Box<ToSql + 'static>>), RepoError> + 'static,
+    {
+        let table = self.table.clone();
 
         Box::new(
             self.select_acl_engine
                 .ensure_access(filter)
-                .then(move |res| match res {
-                    Ok(filter) => {
-                        if let Some(limit) = limit {
-                            if limit < 1 {
-                                return Box::new(future::err((format_err!("Limit cannot be less than 1"), conn)));
-                            }
-                        }
-
-                        let (query, args) = filter
-                            .into_filtered_operation_builder(table)
-                            .build(FilteredOperation::Select { op, limit });
-                        Box::new(future::ok((query, args, conn)))
+                .then(move |res| {
+                    match res
+                        .map_err(|(e, _filter)| e)
+                        .and_then(|filter| build(filter.into_filtered_operation_builder(table)))
+                    {
+                        Ok((query, args)) => Ok((query, args, conn)),
+                        Err(e) => Err((e, conn)),
                     }
-                    Err((e, _filter)) => Box::new(future::err((e, conn))),
                 })
                 .and_then(move |(query, args, conn)| conn.prepare2(&query).map(move |(statement, conn)| (statement, query, args, conn)))
                 .and_then(move |(statement, query, args, conn)| {
@@ -322,10 +510,136 @@ where
                     conn.query2(&statement, args)
                         .collect()
                         .map_err(move |(e, conn)| (e.context(err_msg).into(), conn))
-                })
-                .map(|(rows, conn)| (rows.into_iter().map(T::from).collect::<Vec<T>>(), conn))
-                .and_then(move |(items, conn)| bulk_ensure_access(&afterop_acl_engine, (items, Action::Select), conn))
-                .map_err(|(e, conn)| (e.context("Failure while running select").into(), conn)),
+                }),
+        )
+    }
+
+    fn select_locked(
+        &self,
+        conn: RepoConnection,
+        filter: F,
+        limit: Option<i32>,
+        op: Option<SelectOperation>,
+        lock: Option<RowLock>,
+    ) -> RepoConnectionFuture<Vec<T>> {
+        let afterop_acl_engine = self.afterop_acl_engine.clone();
+        let afterop_policy = self.afterop_policy.clone();
+
+        Box::new(
+            self.query_filtered(conn, filter, move |builder| {
+                if let Some(limit) = limit {
+                    if limit < 1 {
+                        return Err(format_err!("Limit cannot be less than 1"));
+                    }
+                }
+
+                builder.try_build(FilteredOperation::Select { op, limit, lock }).map_err(From::from)
+            })
+            .map(|(rows, conn)| (rows.into_iter().map(T::from).collect::<Vec<T>>(), conn))
+            .and_then(move |(items, conn)| bulk_select_access(&afterop_acl_engine, &afterop_policy, items, conn))
+            .map_err(|(e, conn)| (e.context("Failure while running select").into(), conn)),
+        )
+    }
+}
+
+impl<T, I, F, U> DbRepoSelect<T, F, RepoError> for DbRepoImpl<T, I, F, U>
+where
+    T: From<Row> + 'static,
+    F: Filter,
+    I: Inserter,
+    U: Updater,
+{
+    fn select_full(
+        &self,
+        conn: RepoConnection,
+        filter: F,
+        limit: Option<i32>,
+        op: Option<SelectOperation>,
+    ) -> RepoConnectionFuture<Vec<T>> {
+        self.select_locked(conn, filter, limit, op, None)
+    }
+
+    fn select_for_update(&self, conn: RepoConnection, filter: F, limit: Option<i32>, lock: RowLock) -> RepoConnectionFuture<Vec<T>> {
+        self.select_locked(conn, filter, limit, None, Some(lock))
+    }
+
+    /// Runs `SELECT ... LIMIT 1`. The row is not checked by afterop ACL engine, see `AfteropPolicy`.
+    fn exists(&self, conn: RepoConnection, filter: F) -> RepoConnectionFuture<bool> {
+        Box::new(
+            self.query_filtered(conn, filter, |builder| {
+                builder
+                    .try_build(FilteredOperation::Select {
+                        op: None,
+                        limit: Some(1),
+                        lock: None,
+                    })
+                    .map_err(From::from)
+            })
+            .map(|(rows, conn)| (!rows.is_empty(), conn))
+            .map_err(|(e, conn)| (e.context("Failure while running exists").into(), conn)),
+        )
+    }
+
+    /// Runs a single select counting the rows with a window function, so that the filter may order them.
+    /// Page past the end of the listing has no rows to count with, so its `total` is 0.
+    fn select_page(&self, conn: RepoConnection, filter: F, offset: i64, limit: i32) -> RepoConnectionFuture<Page<T>> {
+        let afterop_acl_engine = self.afterop_acl_engine.clone();
+        let afterop_policy = self.afterop_policy.clone();
+
+        Box::new(
+            self.query_filtered(conn, filter, move |builder| {
+                if limit < 1 {
+                    return Err(format_err!("Limit cannot be less than 1"));
+                }
+                if offset < 0 {
+                    return Err(format_err!("Offset cannot be negative"));
+                }
+
+                builder
+                    .with_offset(Some(offset))
+                    .with_total_count()
+                    .try_build(FilteredOperation::Select {
+                        op: None,
+                        limit: Some(limit),
+                        lock: None,
+                    })
+                    .map_err(From::from)
+            })
+            .map(|(rows, conn)| {
+                let total: i64 = match rows.first() {
+                    Some(row) => row.get("total_count"),
+                    None => 0,
+                };
+                (rows.into_iter().map(T::from).collect::<Vec<T>>(), total, conn)
+            })
+            .and_then(move |(items, total, conn)| {
+                bulk_select_access(&afterop_acl_engine, &afterop_policy, items, conn)
+                    .map(move |(items, conn)| (Page::new(items, total, offset, Some(i64::from(limit))), conn))
+            })
+            .map_err(|(e, conn)| (e.context("Failure while running select").into(), conn)),
+        )
+    }
+
+    /// Runs `SELECT count(*)`. Rows are not checked by afterop ACL engine, see `AfteropPolicy`.
+    fn count(&self, conn: RepoConnection, filter: F) -> RepoConnectionFuture<i64> {
+        Box::new(
+            self.query_filtered(conn, filter, |builder| {
+                builder
+                    .try_build(FilteredOperation::Select {
+                        op: Some(SelectOperation::Count),
+                        limit: None,
+                        lock: None,
+                    })
+                    .map_err(From::from)
+            })
+            .and_then(|(mut rows, conn)| match rows.pop() {
+                Some(row) => {
+                    let count: i64 = row.get(0);
+                    Ok((count, conn))
+                }
+                None => Err((format_err!("No rows returned"), conn)),
+            })
+            .map_err(|(e, conn)| (e.context("Failure while running count").into(), conn)),
         )
     }
 }
@@ -338,7 +652,7 @@ where
     U: Updater,
 {
     fn update(&self, conn: RepoConnection, updater: U) -> RepoConnectionFuture<Vec<T>> {
-        let table = self.table;
+        let table = self.table.clone();
 
         let afterop_acl_engine = self.afterop_acl_engine.clone();
 
@@ -346,13 +660,13 @@ where
             self.update_acl_engine
                 .ensure_access(updater)
                 .then(move |res| {
-                    future::result(match res {
-                        Ok(updater) => {
-                            let (query, args) = updater.into_update_builder(table).build();
-                            Ok((query, args, conn))
-                        }
-                        Err((e, _updater)) => Err((e, conn)),
-                    })
+                    let built = res
+                        .map_err(|(e, _updater)| e)
+                        .and_then(|updater| updater.into_update_builder(table).try_build().map_err(From::from));
+                    match built {
+                        Ok((query, args)) => Ok((query, args, conn)),
+                        Err(e) => Err((e, conn)),
+                    }
                 })
                 .and_then(move |(query, args, conn)| conn.prepare2(&query).map(move |(statement, conn)| (statement, query, args, conn)))
                 .and_then(move |(statement, query, args, conn)| {
@@ -366,6 +680,50 @@ where
                 .map_err(|(e, conn)| (e.context("Failure while running update").into(), conn)),
         )
     }
+
+    fn update_many_distinct(&self, conn: RepoConnection, key_column: &'static str, updaters: Vec<U>) -> RepoConnectionFuture<Vec<T>>
+    where
+        U: Into<UpdateRow>,
+    {
+        if updaters.is_empty() {
+            return Box::new(future::ok((vec![], conn)));
+        }
+
+        let table = self.table.clone();
+
+        let afterop_acl_engine = self.afterop_acl_engine.clone();
+
+        Box::new(
+            future::join_all(updaters.into_iter().map({
+                let update_acl_engine = self.update_acl_engine.clone();
+                move |updater| update_acl_engine.ensure_access(updater)
+            }))
+            .then(move |res| {
+                let built = res.map_err(|(e, _updater)| e).and_then(|updaters| {
+                    updaters
+                        .into_iter()
+                        .map(Into::into)
+                        .fold(UpdateManyBuilder::new(table, key_column), UpdateManyBuilder::with_row)
+                        .try_build()
+                        .map_err(From::from)
+                });
+                match built {
+                    Ok((query, args)) => Ok((query, args, conn)),
+                    Err(e) => Err((e, conn)),
+                }
+            })
+            .and_then(move |(query, args, conn)| conn.prepare2(&query).map(move |(statement, conn)| (statement, query, args, conn)))
+            .and_then(move |(statement, query, args, conn)| {
+                let err_msg = query_debug(&query, &args);
+                conn.query2(&statement, args)
+                    .collect()
+                    .map_err(move |(e, conn)| (e.context(err_msg).into(), conn))
+            })
+            .map(|(rows, conn)| (rows.into_iter().map(T::from).collect::<Vec<T>>(), conn))
+            .and_then(move |(items, conn)| bulk_ensure_access(&afterop_acl_engine, (items, Action::Update), conn))
+            .map_err(|(e, conn)| (e.context("Failure while running multiple update").into(), conn)),
+        )
+    }
 }
 
 impl<T, I, F, U> DbRepoDelete<T, F, RepoError> for DbRepoImpl<T, I, F, U>
@@ -376,7 +734,7 @@ where
     U: Updater,
 {
     fn delete(&self, conn: RepoConnection, filter: F) -> RepoConnectionFuture<Vec<T>> {
-        let table = self.table;
+        let table = self.table.clone();
 
         let afterop_acl_engine = self.afterop_acl_engine.clone();
 
@@ -384,13 +742,16 @@ where
             self.delete_acl_engine
                 .ensure_access(filter)
                 .then(move |res| {
-                    future::result(match res {
-                        Ok(filter) => {
-                            let (query, args) = filter.into_filtered_operation_builder(table).build(FilteredOperation::Delete);
-                            Ok((query, args, conn))
-                        }
-                        Err((e, _filter)) => Err((e, conn)),
-                    })
+                    let built = res.map_err(|(e, _filter)| e).and_then(|filter| {
+                        filter
+                            .into_filtered_operation_builder(table)
+                            .try_build(FilteredOperation::Delete)
+                            .map_err(From::from)
+                    });
+                    match built {
+                        Ok((query, args)) => Ok((query, args, conn)),
+                        Err(e) => Err((e, conn)),
+                    }
                 })
                 .and_then(move |(query, args, conn)| conn.prepare2(&query).map(move |(statement, conn)| (statement, query, args, conn)))
                 .and_then(move |(statement, query, args, conn)| {
@@ -414,3 +775,87 @@ where
     U: Updater,
 {
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select_with_policy(policy: AfteropPolicy<i32>) -> Result<Vec<i32>, RepoError> {
+        let acl_engine: Rc<acl::AclEngine<(i32, Action), RepoError>> =
+            Rc::new(acl::InfallibleSyncACLFn(|ctx: &mut (i32, Action)| ctx.0 % 2 == 0));
+
+        bulk_select_access(&acl_engine, &policy, vec![1, 2, 3, 4], ())
+            .wait()
+            .map(|(items, _)| items)
+            .map_err(|(e, _)| e)
+    }
+
+    struct NoConnection;
+
+    impl Connection<RepoError> for NoConnection {
+        fn prepare2(self: Box<Self>, _query: &str) -> ConnectionFuture<::tokio_postgres::stmt::Statement, RepoError> {
+            unimplemented!()
+        }
+        fn query2(
+            self: Box<Self>,
+            _statement: &::tokio_postgres::stmt::Statement,
+            _params: Vec<Box<ToSql>>,
+        ) -> Box<StateStream<Item = Row, State = RepoConnection, Error = RepoError>> {
+            unimplemented!()
+        }
+        fn commit2(self: Box<Self>) -> ConnectionFuture<(), RepoError> {
+            unimplemented!()
+        }
+        fn rollback2(self: Box<Self>) -> ConnectionFuture<(), RepoError> {
+            unimplemented!()
+        }
+        fn unwrap_tokio_postgres(self: Box<Self>) -> ::tokio_postgres::Connection {
+            unimplemented!()
+        }
+    }
+
+    struct NumberInserter(i32);
+
+    impl Inserter for NumberInserter {
+        fn into_insert_builder(self, table: TableName) -> ::statement::InsertBuilder {
+            ::statement::InsertBuilder::new(table).with_arg("number", self.0)
+        }
+    }
+
+    /// Inserts numbers failing on negative ones
+    #[derive(Clone)]
+    struct NumberRepo;
+
+    impl DbRepoInsert<i32, NumberInserter, RepoError> for NumberRepo {
+        fn insert(&self, conn: RepoConnection, inserter: NumberInserter) -> RepoConnectionFuture<Vec<i32>> {
+            if inserter.0 < 0 {
+                Box::new(future::err((format_err!("Negative number"), conn)))
+            } else {
+                Box::new(future::ok((vec![inserter.0], conn)))
+            }
+        }
+    }
+
+    fn insert_many(numbers: Vec<i32>) -> Result<Vec<i32>, RepoError> {
+        let inserters = numbers.into_iter().map(NumberInserter).collect();
+        NumberRepo
+            .insert_many(Box::new(NoConnection), inserters)
+            .wait()
+            .map(|(items, _)| items)
+            .map_err(|(e, _)| e)
+    }
+
+    #[test]
+    fn test_default_insert_many() {
+        assert_eq!(insert_many(vec![1, 2, 3]).unwrap(), vec![1, 2, 3]);
+        assert_eq!(insert_many(vec![]).unwrap(), Vec::<i32>::new());
+        assert!(insert_many(vec![1, -2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_afterop_policy() {
+        assert!(select_with_policy(AfteropPolicy::Fail).is_err());
+        assert_eq!(select_with_policy(AfteropPolicy::Filter).unwrap(), vec![2, 4]);
+        assert_eq!(select_with_policy(AfteropPolicy::mask(|_| 0)).unwrap(), vec![0, 2, 0, 4]);
+    }
+}
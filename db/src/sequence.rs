@@ -5,6 +5,7 @@ use futures::future;
 use futures::prelude::*;
 use futures_state_stream::StateStream;
 use std::fmt::Display;
+use std::marker::PhantomData;
 use tokio_postgres::rows::Row;
 use tokio_postgres::types::FromSql;
 
@@ -29,6 +30,11 @@ unsafe impl Sequenceable for i64 {
 
 pub trait Sequence<T: Sequenceable> {
     fn next_val(&self, conn: SequenceConnection) -> SequenceConnectionFuture<T>;
+    /// Allocates `n` values in a single roundtrip. Values are ascending and contiguous
+    /// unless other sessions draw from the sequence at the same time.
+    fn next_vals(&self, conn: SequenceConnection, n: u32) -> SequenceConnectionFuture<Vec<T>>;
+    /// Value last returned by the sequence in any session
+    fn current_val(&self, conn: SequenceConnection) -> SequenceConnectionFuture<T>;
     fn reset(&self, conn: SequenceConnection, to: Option<T>) -> SequenceConnectionFuture<()>;
 }
 
@@ -67,6 +73,56 @@ where
         )
     }
 
+    fn next_vals(&self, conn: SequenceConnection, n: u32) -> SequenceConnectionFuture<Vec<T>> {
+        if n == 0 {
+            return Box::new(future::ok((vec![], conn)));
+        }
+
+        let sequence = self.sequence;
+
+        let err_msg = format!("Failed to allocate {} values of sequence {}", n, sequence);
+
+        Box::new(
+            conn.prepare2(&format!(
+                "SELECT nextval(\'{}\') AS value FROM generate_series(1, {}) ORDER BY value;",
+                sequence, n
+            ))
+            .and_then(move |(stmt, conn)| {
+                conn.query2(&stmt, vec![])
+                    .collect()
+                    .map_err(move |(e, conn)| (e.context(err_msg).into(), conn))
+                    .and_then(move |(rows, conn)| {
+                        future::result(if rows.len() == n as usize {
+                            Ok((rows.into_iter().map(T::unmarshal_sequence_row).collect(), conn))
+                        } else {
+                            Err((format_err!("Expected {} rows, got {}", n, rows.len()), conn))
+                        })
+                    })
+            }),
+        )
+    }
+
+    fn current_val(&self, conn: SequenceConnection) -> SequenceConnectionFuture<T> {
+        let sequence = self.sequence;
+
+        let err_msg = format!("Failed to get current value of sequence {}", sequence);
+
+        Box::new(
+            conn.prepare2(&format!("SELECT last_value FROM {};", sequence))
+                .and_then(|(stmt, conn)| {
+                    conn.query2(&stmt, vec![])
+                        .collect()
+                        .map_err(move |(e, conn)| (e.context(err_msg).into(), conn))
+                        .and_then(|(mut rows, conn)| {
+                            future::result(match rows.pop() {
+                                None => Err((format_err!("No rows returned"), conn)),
+                                Some(row) => Ok((T::unmarshal_sequence_row(row), conn)),
+                            })
+                        })
+                }),
+        )
+    }
+
     fn reset(&self, conn: SequenceConnection, to: Option<T>) -> SequenceConnectionFuture<()> {
         let sequence = self.sequence;
 
@@ -89,3 +145,39 @@ where
         })
     }
 }
+
+/// Sequence producing newtype ids, e.g. `OrderSlug`, instead of raw integers
+pub struct TypedSequence<T> {
+    inner: SequenceImpl,
+    _marker: PhantomData<T>,
+}
+
+impl<T> TypedSequence<T>
+where
+    T: From<i32> + Into<i32> + 'static,
+{
+    pub fn new(sequence: &'static str) -> Self {
+        Self {
+            inner: SequenceImpl::new(sequence),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn next_val(&self, conn: SequenceConnection) -> SequenceConnectionFuture<T> {
+        Box::new(Sequence::<i32>::next_val(&self.inner, conn).map(|(v, conn)| (T::from(v), conn)))
+    }
+
+    pub fn next_vals(&self, conn: SequenceConnection, n: u32) -> SequenceConnectionFuture<Vec<T>> {
+        let next_vals = Sequence::<i32>::next_vals(&self.inner, conn, n);
+        Box::new(next_vals.map(|(vs, conn)| (vs.into_iter().map(T::from).collect(), conn)))
+    }
+
+    pub fn current_val(&self, conn: SequenceConnection) -> SequenceConnectionFuture<T> {
+        let current_val = Sequence::<i32>::current_val(&self.inner, conn);
+        Box::new(current_val.map(|(v, conn)| (T::from(v), conn)))
+    }
+
+    pub fn reset(&self, conn: SequenceConnection, to: Option<T>) -> SequenceConnectionFuture<()> {
+        self.inner.reset(conn, to.map(Into::<i32>::into))
+    }
+}
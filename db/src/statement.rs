@@ -1,11 +1,137 @@
 use either::Either;
 use std;
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use tokio_postgres::types::ToSql;
 
+/// Checks that `s` is a plain SQL identifier, optionally qualified with a schema, e.g. `public.users`.
+/// Only such identifiers are interpolated into queries by the builders.
+pub fn is_valid_identifier(s: &str) -> bool {
+    s.split('.').all(|part| {
+        let mut chars = part.chars();
+        match chars.next() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            _ => false,
+        }
+    })
+}
+
+/// Quotes an identifier, e.g. a column named after an SQL keyword. Parts of a qualified identifier are quoted separately,
+/// so `public.user` becomes `"public"."user"`. Quoted identifiers are case-sensitive, see `checked_identifier`.
+pub fn quote_identifier(s: &str) -> String {
+    s.split('.')
+        .map(|part| format!("\"{}\"", part.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Keywords PostgreSQL doesn't allow as plain column or table names
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const RESERVED_KEYWORDS: &[&str] = &[
+    "all", "analyse", "analyze", "and", "any", "array", "as", "asc", "asymmetric", "both", "case", "cast", "check", "collate",
+    "column", "constraint", "create", "current_catalog", "current_date", "current_role", "current_time", "current_timestamp",
+    "current_user", "default", "deferrable", "desc", "distinct", "do", "else", "end", "except", "false", "fetch", "for", "foreign",
+    "from", "grant", "group", "having", "in", "initially", "intersect", "into", "lateral", "leading", "limit", "localtime",
+    "localtimestamp", "not", "null", "offset", "on", "only", "or", "order", "placing", "primary", "references", "returning",
+    "select", "session_user", "some", "symmetric", "table", "then", "to", "trailing", "true", "union", "unique", "user", "using",
+    "variadic", "when", "where", "window", "with",
+];
+
+/// Identifier as is, or an error if it is not valid. Only parts that are reserved keywords are quoted, lowercased the way
+/// PostgreSQL folds plain identifiers, so that quoting doesn't make names case-sensitive.
+fn checked_identifier(s: &str) -> Result<String, StatementError> {
+    if !is_valid_identifier(s) {
+        return Err(StatementError::InvalidIdentifier { identifier: s.to_string() });
+    }
+
+    Ok(s.split('.')
+        .map(|part| {
+            let folded = part.to_ascii_lowercase();
+            if RESERVED_KEYWORDS.contains(&folded.as_str()) {
+                quote_identifier(&folded)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("."))
+}
+
+fn checked_identifiers(columns: &[&'static str]) -> Result<Vec<String>, StatementError> {
+    columns.iter().map(|column| checked_identifier(column)).collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Fail)]
+pub enum StatementError {
+    #[fail(display = "All rows of bulk update of {} must set the same columns", table)]
+    MismatchedColumns { table: String },
+    #[fail(display = "Rows of bulk update of {} set no columns", table)]
+    NoValues { table: String },
+    #[fail(display = "Invalid SQL identifier: {:?}", identifier)]
+    InvalidIdentifier { identifier: String },
+    #[fail(display = "Invalid extra SQL statement: {:?}", extra)]
+    InvalidExtra { extra: String },
+}
+
+/// Name of a table, either static or built at runtime, e.g. for a tenant schema or a test table
+pub type TableName = Cow<'static, str>;
+
+/// Qualifies the table with the schema, replacing the schema it is already qualified with, e.g. `tenant_1.users`
+pub fn qualified_table<T: Into<TableName>>(schema: &str, table: T) -> Result<TableName, StatementError> {
+    let table = table.into();
+    let name = table.rsplit('.').next().unwrap_or_default();
+    let table = format!("{}.{}", schema, name);
+    if is_valid_identifier(&table) {
+        Ok(table.into())
+    } else {
+        Err(StatementError::InvalidIdentifier { identifier: table })
+    }
+}
+
+/// Extra statements may not terminate the query or comment out its rest. Semicolons and comment markers are allowed
+/// inside string literals, which may not contain backslashes though, as those escape quotes in `E'...'` literals.
+fn checked_extra(s: &str) -> Result<&str, StatementError> {
+    let mut in_literal = false;
+    let mut chars = s.chars().peekable();
+    let mut valid = true;
+    while let Some(c) = chars.next() {
+        match (in_literal, c, chars.peek()) {
+            (_, '\'', _) => in_literal = !in_literal,
+            (true, '\\', _) | (false, ';', _) | (false, '-', Some('-')) | (false, '/', Some('*')) => {
+                valid = false;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    if valid && !in_literal {
+        Ok(s)
+    } else {
+        Err(StatementError::InvalidExtra { extra: s.to_string() })
+    }
+}
+
+/// Distance from the location in `column` to the point with longitude and latitude in the arguments `lon_arg` and `lat_arg`.
+/// PostGIS measures it in meters, `earthdistance` in statute miles, and plain `point` in degrees.
+#[cfg(feature = "postgis")]
+fn distance_expr(column: &str, lon_arg: usize, lat_arg: usize) -> String {
+    format!("{} <-> ST_SetSRID(ST_MakePoint(${}, ${}), 4326)", column, lon_arg, lat_arg)
+}
+
+#[cfg(all(feature = "earthdistance", not(feature = "postgis")))]
+fn distance_expr(column: &str, lon_arg: usize, lat_arg: usize) -> String {
+    format!("{} <@> point(${}, ${})", column, lon_arg, lat_arg)
+}
+
+#[cfg(not(any(feature = "earthdistance", feature = "postgis")))]
+fn distance_expr(column: &str, lon_arg: usize, lat_arg: usize) -> String {
+    format!("{} <-> point(${}, ${})", column, lon_arg, lat_arg)
+}
+
 pub trait Filter {
-    fn into_filtered_operation_builder(self, table: &'static str) -> FilteredOperationBuilder;
+    fn into_filtered_operation_builder(self, table: TableName) -> FilteredOperationBuilder;
 }
 
 impl<L, R> Filter for Either<L, R>
@@ -13,7 +139,7 @@ where
     L: Filter,
     R: Filter,
 {
-    fn into_filtered_operation_builder(self, table: &'static str) -> FilteredOperationBuilder {
+    fn into_filtered_operation_builder(self, table: TableName) -> FilteredOperationBuilder {
         use self::Either::*;
 
         match self {
@@ -24,7 +150,7 @@ where
 }
 
 pub trait Inserter {
-    fn into_insert_builder(self, table: &'static str) -> InsertBuilder;
+    fn into_insert_builder(self, table: TableName) -> InsertBuilder;
 }
 
 impl<L, R> Inserter for Either<L, R>
@@ -32,7 +158,7 @@ where
     L: Inserter,
     R: Inserter,
 {
-    fn into_insert_builder(self, table: &'static str) -> InsertBuilder {
+    fn into_insert_builder(self, table: TableName) -> InsertBuilder {
         use self::Either::*;
 
         match self {
@@ -42,8 +168,23 @@ where
     }
 }
 
+/// Multi-row insert. Empty vector produces an invalid query, so callers should check for it beforehand.
+impl<I> Inserter for Vec<I>
+where
+    I: Inserter,
+{
+    fn into_insert_builder(self, table: TableName) -> InsertBuilder {
+        let mut builders = self.into_iter().map(|v| v.into_insert_builder(table.clone()));
+
+        match builders.next() {
+            Some(first) => builders.fold(first, InsertBuilder::with_rows_from),
+            None => InsertBuilder::new(table),
+        }
+    }
+}
+
 pub trait Updater {
-    fn into_update_builder(self, table: &'static str) -> UpdateBuilder;
+    fn into_update_builder(self, table: TableName) -> UpdateBuilder;
 }
 
 impl<L, R> Updater for Either<L, R>
@@ -51,7 +192,7 @@ where
     L: Updater,
     R: Updater,
 {
-    fn into_update_builder(self, table: &'static str) -> UpdateBuilder {
+    fn into_update_builder(self, table: TableName) -> UpdateBuilder {
         use self::Either::*;
 
         match self {
@@ -76,10 +217,37 @@ impl SelectOperation {
     }
 }
 
+/// Locking of selected rows until the end of transaction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RowLock {
+    /// Waits for concurrent transactions holding the rows
+    ForUpdate,
+    /// Omits rows locked by concurrent transactions, e.g. to pick free stock items
+    ForUpdateSkipLocked,
+    /// Fails if any of the rows is locked by concurrent transaction
+    ForUpdateNoWait,
+}
+
+impl RowLock {
+    fn to_sql(self) -> &'static str {
+        use self::RowLock::*;
+
+        match self {
+            ForUpdate => "FOR UPDATE",
+            ForUpdateSkipLocked => "FOR UPDATE SKIP LOCKED",
+            ForUpdateNoWait => "FOR UPDATE NOWAIT",
+        }
+    }
+}
+
 /// Filtering operation
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FilteredOperation {
-    Select { op: Option<SelectOperation>, limit: Option<i32> },
+    Select {
+        op: Option<SelectOperation>,
+        limit: Option<i32>,
+        lock: Option<RowLock>,
+    },
     Delete,
 }
 
@@ -93,29 +261,64 @@ pub enum ComparisonMode {
     IN,
 }
 
-type ColumnFilters = Vec<(ComparisonMode, Box<ToSql + 'static>)>;
+/// Right-hand side of a filtering condition
+enum FilterValue {
+    Arg(Box<ToSql + 'static>),
+    /// Argument the column is compared with unless the column is `NULL`
+    ArgOrNull(Box<ToSql + 'static>),
+    /// Another column, e.g. of the outer query to correlate subquery with
+    Column(&'static str),
+    Subquery(FilteredOperationBuilder),
+}
+
+type ColumnFilters = Vec<(ComparisonMode, FilterValue)>;
 type Filters = BTreeMap<&'static str, ColumnFilters>;
+/// Subqueries of `[NOT] EXISTS` conditions
+type ExistsFilters = Vec<(bool, FilteredOperationBuilder)>;
 
-fn build_where_from_filters(filters: Filters, mut i: usize) -> (String, Vec<Box<ToSql + 'static>>) {
-    let mut query = String::new();
+fn build_where_from_filters(
+    filters: Filters,
+    exists: ExistsFilters,
+    mut i: usize,
+) -> Result<(String, Vec<Box<ToSql + 'static>>), StatementError> {
+    let mut conditions = vec![];
     let mut args = vec![];
 
-    let mut started = false;
-
     for (col, filter) in filters {
+        let col = checked_identifier(col)?;
         for (mode, value) in filter {
-            if started {
-                query.push_str(" AND ");
-            }
-            query.push_str(&format!("{} {}", col, mode.arg(i)));
-            args.push(value);
-
-            started = true;
-            i += 1;
+            conditions.push(match value {
+                FilterValue::Arg(value) => {
+                    let condition = format!("{} {}", col, mode.arg(i));
+                    args.push(value);
+                    i += 1;
+                    condition
+                }
+                FilterValue::ArgOrNull(value) => {
+                    let condition = format!("({0} IS NULL OR {0} {1})", col, mode.arg(i));
+                    args.push(value);
+                    i += 1;
+                    condition
+                }
+                FilterValue::Column(other) => format!("{} {}", col, mode.operand(&checked_identifier(other)?)),
+                FilterValue::Subquery(subquery) => {
+                    let (subquery, subquery_args) = subquery.build_subquery(i)?;
+                    i += subquery_args.len();
+                    args.extend(subquery_args);
+                    format!("{} {} ({})", col, mode, subquery)
+                }
+            });
         }
     }
 
-    (query, args)
+    for (negated, subquery) in exists {
+        let (subquery, subquery_args) = subquery.build_subquery(i)?;
+        i += subquery_args.len();
+        args.extend(subquery_args);
+        conditions.push(format!("{}EXISTS ({})", if negated { "NOT " } else { "" }, subquery));
+    }
+
+    Ok((conditions.join(" AND "), args))
 }
 
 impl fmt::Display for ComparisonMode {
@@ -139,10 +342,14 @@ impl fmt::Display for ComparisonMode {
 
 impl ComparisonMode {
     fn arg(&self, arg_number: usize) -> String {
+        self.operand(&format!("${}", arg_number))
+    }
+
+    fn operand(&self, operand: &str) -> String {
         use self::ComparisonMode::*;
         match self {
-            IN => format!("= any(${})", arg_number),
-            _ => format!("{} ${}", self, arg_number),
+            IN => format!("= any({})", operand),
+            _ => format!("{} {}", self, operand),
         }
     }
 }
@@ -207,20 +414,34 @@ impl<T> Range<T> {
 
 /// Construct a simple select or delete query.
 pub struct FilteredOperationBuilder {
-    table: &'static str,
+    table: TableName,
     extra: &'static str,
     filters: Filters,
     limit: Option<i32>,
+    offset: Option<i64>,
+    columns: Vec<&'static str>,
+    distinct: bool,
+    total_count: bool,
+    exists: ExistsFilters,
+    nearest: Option<(&'static str, f64, f64)>,
+    order_by: Vec<&'static str>,
 }
 
 impl FilteredOperationBuilder {
     /// Create a new builder
-    pub fn new(table: &'static str) -> Self {
+    pub fn new<T: Into<TableName>>(table: T) -> Self {
         Self {
-            table,
+            table: table.into(),
             extra: Default::default(),
             filters: Default::default(),
             limit: Default::default(),
+            offset: Default::default(),
+            columns: Default::default(),
+            distinct: false,
+            total_count: false,
+            exists: Default::default(),
+            nearest: None,
+            order_by: Default::default(),
         }
     }
 
@@ -254,80 +475,251 @@ impl FilteredOperationBuilder {
             ],
             In(values) => vec![(ComparisonMode::IN, Box::new(values))],
         };
+        let new_filters = new_filters
+            .into_iter()
+            .map(|(mode, value)| (mode, FilterValue::Arg(value)))
+            .collect();
 
         self.filters.insert(column, new_filters);
         self
     }
 
+    /// Add filtering arguments, keeping rows with `NULL` in the column, e.g. roles without expiration date
+    pub fn with_nullable_filter<T, R>(mut self, column: &'static str, range: R) -> Self
+    where
+        T: ToSql + 'static,
+        R: Into<Range<T>>,
+    {
+        self = self.with_filter(column, range);
+        if let Some(filters) = self.filters.remove(column) {
+            let filters = filters
+                .into_iter()
+                .map(|(mode, value)| match value {
+                    FilterValue::Arg(value) => (mode, FilterValue::ArgOrNull(value)),
+                    value => (mode, value),
+                })
+                .collect();
+            self.filters.insert(column, filters);
+        }
+        self
+    }
+
+    /// Filter by values selected by the subquery: `column IN (SELECT ...)`
+    pub fn with_in_subquery(mut self, column: &'static str, subquery: FilteredOperationBuilder) -> Self {
+        self.filters
+            .insert(column, vec![(ComparisonMode::IN, FilterValue::Subquery(subquery))]);
+        self
+    }
+
+    /// Filter by equality to another column, e.g. `orders.store_id` of the outer query in a correlated subquery
+    pub fn with_column_filter(mut self, column: &'static str, other_column: &'static str) -> Self {
+        self.filters
+            .insert(column, vec![(ComparisonMode::EQ, FilterValue::Column(other_column))]);
+        self
+    }
+
+    /// Keep rows for which the subquery returns any rows: `EXISTS (SELECT ...)`
+    pub fn with_exists(mut self, subquery: FilteredOperationBuilder) -> Self {
+        self.exists.push((false, subquery));
+        self
+    }
+
+    /// Keep rows for which the subquery returns no rows: `NOT EXISTS (SELECT ...)`
+    pub fn with_not_exists(mut self, subquery: FilteredOperationBuilder) -> Self {
+        self.exists.push((true, subquery));
+        self
+    }
+
     pub fn with_limit(mut self, limit: Option<i32>) -> Self {
         self.limit = limit;
         self
     }
 
+    /// Skip first `offset` rows of selection, ignored for deletion
+    pub fn with_offset(mut self, offset: Option<i64>) -> Self {
+        self.offset = offset;
+        self
+    }
+
     /// Add additional statements before the semicolon
     pub fn with_extra(mut self, extra: &'static str) -> Self {
         self.extra = extra;
         self
     }
 
-    /// Build a query
+    /// Select only these columns instead of all of them, ignored for deletion.
+    /// Repos build entities from all columns, so projections are meant for row types made of the selected columns.
+    pub fn with_columns(mut self, columns: &[&'static str]) -> Self {
+        self.columns = columns.to_vec();
+        self
+    }
+
+    /// Deduplicate selected rows, or count distinct values of the selected columns
+    pub fn distinct(mut self) -> Self {
+        self.distinct = true;
+        self
+    }
+
+    /// Adds `total_count` column with the number of all rows matching the filters, regardless of limit and offset
+    pub fn with_total_count(mut self) -> Self {
+        self.total_count = true;
+        self
+    }
+
+    /// Order selected rows by distance from the location in `column` to the point, nearest first, e.g. to find the `limit` nearest
+    /// warehouses. The column is `point(longitude, latitude)`, unless it is PostGIS `geography` with `postgis` feature.
+    /// Ordering is added after extra statements, so they may not have their own `ORDER BY`. Aggregating and distinct selects
+    /// are not ordered.
+    pub fn with_nearest(mut self, column: &'static str, longitude: f64, latitude: f64) -> Self {
+        self.nearest = Some((column, longitude, latitude));
+        self
+    }
+
+    /// Order selected rows by the columns, ignored for deletion and aggregation. Ordering follows the one by distance
+    /// if any, and is added after extra statements, so they may not have their own `ORDER BY`.
+    pub fn with_order_by(mut self, columns: &[&'static str]) -> Self {
+        self.order_by = columns.to_vec();
+        self
+    }
+
+    fn select_clause(&self, op: Option<SelectOperation>, table: &str) -> Result<String, StatementError> {
+        let distinct = if self.distinct { "DISTINCT " } else { "" };
+        let columns = checked_identifiers(&self.columns)?;
+
+        Ok(match op {
+            None => format!(
+                "SELECT {}{}{}",
+                distinct,
+                if columns.is_empty() { "*".to_string() } else { columns.join(", ") },
+                if self.total_count {
+                    ", count(*) OVER () AS total_count"
+                } else {
+                    ""
+                }
+            ),
+            Some(op) => {
+                let target = match columns.len() {
+                    0 if self.distinct => format!("{}.*", table),
+                    0 => "*".to_string(),
+                    1 => columns[0].clone(),
+                    _ => format!("({})", columns.join(", ")),
+                };
+                format!("SELECT {}({}{})", op.to_sql(), distinct, target)
+            }
+        })
+    }
+
+    /// Build a query.
+    ///
+    /// Panics if the table, columns or extra statements are not valid, see `try_build`.
     pub fn build(self, op: FilteredOperation) -> (String, Vec<Box<ToSql + 'static>>) {
-        let (where_q, args) = build_where_from_filters(self.filters, 1);
+        self.try_build(op).unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        let out = format!(
-            "{} FROM {}{}{}{};",
-            &match op {
-                FilteredOperation::Select { op, .. } => match op {
-                    None => "SELECT *".to_string(),
-                    Some(op) => format!("SELECT {}(*)", op.to_sql()),
-                },
-                FilteredOperation::Delete => "DELETE".to_string(),
+    /// Build a query, failing if the table, columns or extra statements are not valid
+    pub fn try_build(self, op: FilteredOperation) -> Result<(String, Vec<Box<ToSql + 'static>>), StatementError> {
+        let (query, args) = self.build_from(op, 1)?;
+        Ok((format!("{};", query), args))
+    }
+
+    /// Builds a select to be nested into another query, which already has `first_arg - 1` arguments
+    fn build_subquery(self, first_arg: usize) -> Result<(String, Vec<Box<ToSql + 'static>>), StatementError> {
+        let limit = self.limit;
+        self.build_from(
+            FilteredOperation::Select {
+                op: None,
+                limit,
+                lock: None,
             },
-            self.table,
+            first_arg,
+        )
+    }
+
+    fn build_from(self, op: FilteredOperation, first_arg: usize) -> Result<(String, Vec<Box<ToSql + 'static>>), StatementError> {
+        let table = checked_identifier(&self.table)?;
+        let extra = checked_extra(self.extra)?;
+        let operation = match op {
+            FilteredOperation::Select { op, .. } => self.select_clause(op, &table)?,
+            FilteredOperation::Delete => "DELETE".to_string(),
+        };
+
+        let (where_q, mut args) = build_where_from_filters(self.filters, self.exists, first_arg)?;
+
+        let mut order_by = vec![];
+        if let FilteredOperation::Select { op: None, .. } = op {
+            match self.nearest {
+                // Distinct selects can't be ordered by an expression not in the select list
+                Some((column, longitude, latitude)) if !self.distinct => {
+                    let lon_arg = first_arg + args.len();
+                    args.push(Box::new(longitude));
+                    args.push(Box::new(latitude));
+                    order_by.push(distance_expr(&checked_identifier(column)?, lon_arg, lon_arg + 1));
+                }
+                _ => {}
+            }
+            order_by.extend(checked_identifiers(&self.order_by)?);
+        }
+        let order_by = if order_by.is_empty() {
+            String::new()
+        } else {
+            format!(" ORDER BY {}", order_by.join(", "))
+        };
+
+        let out = format!(
+            "{} FROM {}{}{}{}{}",
+            operation,
+            table,
             if !where_q.is_empty() {
                 format!(" WHERE {}", where_q)
             } else {
                 "".to_string()
             },
-            if !self.extra.is_empty() {
-                format!(" {}", self.extra)
-            } else {
-                "".to_string()
-            },
+            if !extra.is_empty() { format!(" {}", extra) } else { "".to_string() },
+            order_by,
             &match op {
                 FilteredOperation::Delete => " RETURNING *".to_string(),
-                FilteredOperation::Select { limit, .. } => {
-                    if let Some(v) = limit {
-                        format!(" LIMIT {}", v)
-                    } else {
-                        "".to_string()
-                    }
-                }
+                FilteredOperation::Select { limit, lock, .. } => format!(
+                    "{}{}{}",
+                    limit.map(|v| format!(" LIMIT {}", v)).unwrap_or_default(),
+                    self.offset.map(|v| format!(" OFFSET {}", v)).unwrap_or_default(),
+                    lock.map(|v| format!(" {}", v.to_sql())).unwrap_or_default()
+                ),
             }
         );
 
-        (out, args)
+        Ok((out, args))
     }
 }
 
+type InsertRow = BTreeMap<&'static str, Box<ToSql + 'static>>;
+
 /// Construct a simple insert query.
 pub struct InsertBuilder {
-    table: &'static str,
+    table: TableName,
     extra: &'static str,
-    values: BTreeMap<&'static str, Box<ToSql + 'static>>,
+    rows: Vec<InsertRow>,
 }
 
 impl InsertBuilder {
-    pub fn new(table: &'static str) -> Self {
+    pub fn new<T: Into<TableName>>(table: T) -> Self {
         Self {
-            table,
+            table: table.into(),
             extra: Default::default(),
-            values: Default::default(),
+            rows: vec![Default::default()],
         }
     }
 
+    /// Add value to the last row
     pub fn with_arg<V: ToSql + 'static>(mut self, k: &'static str, v: V) -> Self {
-        self.values.insert(k, Box::new(v));
+        if let Some(row) = self.rows.last_mut() {
+            row.insert(k, Box::new(v));
+        }
+        self
+    }
+
+    /// Append rows of another builder, turning this into a multi-row insert
+    pub fn with_rows_from(mut self, other: InsertBuilder) -> Self {
+        self.rows.extend(other.rows);
         self
     }
 
@@ -337,50 +729,128 @@ impl InsertBuilder {
         self
     }
 
-    /// Builds a query
+    /// Builds a query. Columns missing in some of the rows are filled with `DEFAULT`.
+    ///
+    /// Panics if the table, columns or extra statements are not valid, see `try_build`.
     pub fn build(self) -> (String, Vec<Box<ToSql + 'static>>) {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Builds a query, failing if the table, columns or extra statements are not valid.
+    /// Columns missing in some of the rows are filled with `DEFAULT`.
+    pub fn try_build(self) -> Result<(String, Vec<Box<ToSql + 'static>>), StatementError> {
+        let extra = checked_extra(self.extra)?;
         let mut args = vec![];
-        let mut query = format!("INSERT INTO {}", self.table);
-
-        let mut col_string = String::new();
-        let mut arg_string = String::new();
-        for (i, (col, arg)) in self.values.into_iter().enumerate() {
-            let arg_index = i + 1;
-            if arg_index > 1 {
-                col_string.push_str(", ");
-                arg_string.push_str(", ");
+        let mut query = format!("INSERT INTO {}", checked_identifier(&self.table)?);
+
+        let columns = self
+            .rows
+            .iter()
+            .flat_map(|row| row.keys().cloned())
+            .collect::<BTreeSet<&'static str>>();
+
+        let col_string = checked_identifiers(&columns.iter().cloned().collect::<Vec<_>>())?.join(", ");
+
+        let mut values_string = String::new();
+        for (row_index, mut row) in self.rows.into_iter().enumerate() {
+            if row_index > 0 {
+                values_string.push_str(", ");
             }
 
-            col_string.push_str(&col);
-            arg_string.push_str(&format!("${}", arg_index));
-            args.push(arg);
+            let mut arg_string = String::new();
+            for (i, col) in columns.iter().enumerate() {
+                if i > 0 {
+                    arg_string.push_str(", ");
+                }
+
+                match row.remove(col) {
+                    Some(arg) => {
+                        args.push(arg);
+                        arg_string.push_str(&format!("${}", args.len()));
+                    }
+                    None => arg_string.push_str("DEFAULT"),
+                }
+            }
+            values_string.push_str(&format!("({})", &arg_string));
         }
-        query = format!("{} ({}) VALUES ({})", &query, &col_string, &arg_string);
+        query = format!("{} ({}) VALUES {}", &query, &col_string, &values_string);
 
-        if !self.extra.is_empty() {
-            query.push_str(&format!(" {}", &self.extra));
+        if !extra.is_empty() {
+            query.push_str(&format!(" {}", extra));
         }
 
         query.push_str(" RETURNING *;");
 
-        (query, args)
+        Ok((query, args))
+    }
+}
+
+/// New value of a column, computed by the database so that concurrent updates don't overwrite each other
+#[derive(Debug)]
+pub enum SetExpr {
+    /// `column = $n`
+    Value(Box<ToSql + 'static>),
+    /// `column = column + $n`
+    Increment(Box<ToSql + 'static>),
+    /// `column = column - $n`
+    Decrement(Box<ToSql + 'static>),
+    /// `column = DEFAULT`
+    Default,
+}
+
+impl SetExpr {
+    fn to_sql(&self, column: &str, arg_number: usize) -> String {
+        use self::SetExpr::*;
+
+        match self {
+            Value(_) => format!("{} = ${}", column, arg_number),
+            Increment(_) => format!("{0} = {0} + ${1}", column, arg_number),
+            Decrement(_) => format!("{0} = {0} - ${1}", column, arg_number),
+            Default => format!("{} = DEFAULT", column),
+        }
+    }
+
+    fn into_arg(self) -> Option<Box<ToSql + 'static>> {
+        use self::SetExpr::*;
+
+        match self {
+            Value(v) | Increment(v) | Decrement(v) => Some(v),
+            Default => None,
+        }
     }
 }
 
 /// Construct a simple update query.
 pub struct UpdateBuilder {
     extra: &'static str,
-    values: BTreeMap<&'static str, Box<ToSql + 'static>>,
+    values: BTreeMap<&'static str, SetExpr>,
     filters: FilteredOperationBuilder,
 }
 
 impl UpdateBuilder {
     /// Add values to set
     pub fn with_value<V: ToSql + 'static>(mut self, column: &'static str, value: V) -> Self {
-        self.values.insert(column, Box::new(value));
+        self.values.insert(column, SetExpr::Value(Box::new(value)));
         self
     }
 
+    /// Add expression to set the column to
+    pub fn with_expr(mut self, column: &'static str, expr: SetExpr) -> Self {
+        self.values.insert(column, expr);
+        self
+    }
+
+    /// Atomically add `delta` to the column, e.g. to return items to stock
+    pub fn with_increment<V: ToSql + 'static>(self, column: &'static str, delta: V) -> Self {
+        self.with_expr(column, SetExpr::Increment(Box::new(delta)))
+    }
+
+    /// Atomically subtract `delta` from the column, e.g. to reserve stock. Combine with a filter on the column
+    /// to keep it from going negative.
+    pub fn with_decrement<V: ToSql + 'static>(self, column: &'static str, delta: V) -> Self {
+        self.with_expr(column, SetExpr::Decrement(Box::new(delta)))
+    }
+
     /// Add additional statements before the semicolon
     pub fn with_extra(mut self, extra: &'static str) -> Self {
         self.extra = extra;
@@ -388,9 +858,21 @@ impl UpdateBuilder {
     }
 
     /// Builds an UPDATE query if update values are set and SELECT query otherwise.
+    ///
+    /// Panics if the table, columns or extra statements are not valid, see `try_build`.
     pub fn build(self) -> (String, Vec<Box<ToSql + 'static>>) {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Builds an UPDATE query if update values are set and SELECT query otherwise,
+    /// failing if the table, columns or extra statements are not valid.
+    pub fn try_build(self) -> Result<(String, Vec<Box<ToSql + 'static>>), StatementError> {
         if self.values.is_empty() {
-            return self.filters.build(FilteredOperation::Select { op: None, limit: None });
+            return self.filters.try_build(FilteredOperation::Select {
+                op: None,
+                limit: None,
+                lock: None,
+            });
         }
 
         let mut values = vec![];
@@ -398,23 +880,26 @@ impl UpdateBuilder {
         let mut arg_index = 1;
 
         let mut value_string = String::new();
-        for (col, arg) in self.values {
+        for (col, expr) in self.values {
             if value_string.is_empty() {
                 value_string.push_str("SET ");
             } else {
                 value_string.push_str(", ");
             }
 
-            value_string.push_str(&format!("{} = ${}", col, arg_index));
-            arg_index += 1;
-            values.push(arg);
+            value_string.push_str(&expr.to_sql(&checked_identifier(col)?, arg_index));
+            if let Some(arg) = expr.into_arg() {
+                arg_index += 1;
+                values.push(arg);
+            }
         }
 
-        let (filter_string, filters) = build_where_from_filters(self.filters.filters, arg_index);
+        let extra = checked_extra(self.extra)?;
+        let (filter_string, filters) = build_where_from_filters(self.filters.filters, self.filters.exists, arg_index)?;
 
         let mut query = format!(
             "UPDATE {} {}{}",
-            self.filters.table,
+            checked_identifier(&self.filters.table)?,
             &value_string,
             if !filter_string.is_empty() {
                 format!(" WHERE {}", &filter_string)
@@ -423,15 +908,15 @@ impl UpdateBuilder {
             }
         );
 
-        if !self.extra.is_empty() {
-            query.push_str(&format!(" {}", self.extra));
+        if !extra.is_empty() {
+            query.push_str(&format!(" {}", extra));
         }
 
         query.push_str(" RETURNING *;");
 
         let args = std::iter::Iterator::chain(values.into_iter(), filters.into_iter()).collect::<Vec<Box<ToSql + 'static>>>();
 
-        (query, args)
+        Ok((query, args))
     }
 }
 
@@ -445,6 +930,113 @@ impl From<FilteredOperationBuilder> for UpdateBuilder {
     }
 }
 
+/// Row of a bulk update: value of the key column identifying the row and new values of other columns
+pub struct UpdateRow {
+    key: Box<ToSql + 'static>,
+    values: BTreeMap<&'static str, Box<ToSql + 'static>>,
+}
+
+impl UpdateRow {
+    pub fn new<K: ToSql + 'static>(key: K) -> Self {
+        Self {
+            key: Box::new(key),
+            values: Default::default(),
+        }
+    }
+
+    pub fn with_value<V: ToSql + 'static>(mut self, column: &'static str, value: V) -> Self {
+        self.values.insert(column, Box::new(value));
+        self
+    }
+}
+
+/// Construct an update of many rows with distinct values in one statement:
+/// `UPDATE t SET q = v.q FROM (VALUES ...) AS v (id, q) WHERE t.id = v.id`.
+/// All rows must set the same non-empty set of columns.
+pub struct UpdateManyBuilder {
+    table: TableName,
+    key_column: &'static str,
+    rows: Vec<UpdateRow>,
+}
+
+impl UpdateManyBuilder {
+    pub fn new<T: Into<TableName>>(table: T, key_column: &'static str) -> Self {
+        Self {
+            table: table.into(),
+            key_column,
+            rows: vec![],
+        }
+    }
+
+    pub fn with_row(mut self, row: UpdateRow) -> Self {
+        self.rows.push(row);
+        self
+    }
+
+    /// Builds a query, failing if rows set no columns or different columns, or if the table or columns are not valid
+    pub fn try_build(self) -> Result<(String, Vec<Box<ToSql + 'static>>), StatementError> {
+        let columns = self
+            .rows
+            .first()
+            .map(|row| row.values.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        if columns.is_empty() {
+            return Err(StatementError::NoValues {
+                table: self.table.to_string(),
+            });
+        }
+        if self.rows.iter().any(|row| !row.values.keys().eq(columns.iter())) {
+            return Err(StatementError::MismatchedColumns {
+                table: self.table.to_string(),
+            });
+        }
+        let table = checked_identifier(&self.table)?;
+        let key_column = checked_identifier(self.key_column)?;
+        let quoted_columns = checked_identifiers(&columns)?;
+        let all_columns = Some(key_column.clone())
+            .into_iter()
+            .chain(quoted_columns.iter().cloned())
+            .collect::<Vec<_>>();
+
+        // Parameters in VALUES have no type, so the first row of typed NULLs lets the database infer them from the table.
+        // It matches no rows, as NULL key is not equal to anything.
+        let mut rows_string = format!(
+            "({})",
+            all_columns
+                .iter()
+                .map(|col| format!("(NULL::{}).{}", table, col))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let mut args = vec![];
+        for mut row in self.rows {
+            args.push(row.key);
+            let mut arg_string = format!("${}", args.len());
+            for col in &columns {
+                args.push(row.values.remove(col).unwrap());
+                arg_string.push_str(&format!(", ${}", args.len()));
+            }
+            rows_string.push_str(&format!(", ({})", arg_string));
+        }
+
+        let query = format!(
+            "UPDATE {0} SET {1} FROM (VALUES {2}) AS v ({3}) WHERE {0}.{4} = v.{4} RETURNING {0}.*;",
+            table,
+            quoted_columns
+                .iter()
+                .map(|col| format!("{0} = v.{0}", col))
+                .collect::<Vec<_>>()
+                .join(", "),
+            rows_string,
+            all_columns.join(", "),
+            key_column,
+        );
+
+        Ok((query, args))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -477,12 +1069,478 @@ mod tests {
             .build(FilteredOperation::Select {
                 op: Some(SelectOperation::Count),
                 limit: Some(5),
+                lock: None,
             });
 
         assert_eq!(res.0, expectation.0);
         assert_eq!(format!("{:?}", res.1), format!("{:?}", expectation.1));
     }
 
+    #[test]
+    fn test_select_builder_with_offset() {
+        let res = FilteredOperationBuilder::new("my_table")
+            .with_filter("filter_column1", 3)
+            .with_extra("ORDER BY filter_column1")
+            .with_offset(Some(10))
+            .try_build(FilteredOperation::Select {
+                op: None,
+                limit: Some(5),
+                lock: None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            res.0,
+            "SELECT * FROM my_table WHERE filter_column1 = $1 ORDER BY filter_column1 LIMIT 5 OFFSET 10;"
+        );
+    }
+
+    #[test]
+    fn test_select_builder_with_total_count() {
+        let res = FilteredOperationBuilder::new("my_table")
+            .with_filter("filter_column1", 3)
+            .with_extra("ORDER BY filter_column1")
+            .with_offset(Some(10))
+            .with_total_count()
+            .try_build(FilteredOperation::Select {
+                op: None,
+                limit: Some(5),
+                lock: None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            res.0,
+            "SELECT *, count(*) OVER () AS total_count FROM my_table WHERE filter_column1 = $1 ORDER BY filter_column1 LIMIT 5 OFFSET 10;"
+        );
+    }
+
+    #[test]
+    fn test_select_builder_with_nullable_filter_and_order() {
+        let res = FilteredOperationBuilder::new("roles")
+            .with_filter("name", "store_manager")
+            .with_nullable_filter::<i64, _>(
+                "expires_at",
+                Range::From(RangeLimit {
+                    value: 100,
+                    inclusive: false,
+                }),
+            )
+            .with_order_by(&["user_id", "id"])
+            .try_build(FilteredOperation::Select {
+                op: None,
+                limit: Some(10),
+                lock: None,
+            })
+            .unwrap();
+        assert_eq!(
+            res.0,
+            "SELECT * FROM roles WHERE (expires_at IS NULL OR expires_at > $1) AND name = $2 ORDER BY user_id, id LIMIT 10;"
+        );
+        assert_eq!(res.1.len(), 2);
+
+        let (query, _) = FilteredOperationBuilder::new("roles")
+            .with_order_by(&["user_id", "id"])
+            .try_build(FilteredOperation::Delete)
+            .unwrap();
+        assert_eq!(query, "DELETE FROM roles RETURNING *;");
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "earthdistance", feature = "postgis")))]
+    fn test_select_builder_with_nearest() {
+        let res = FilteredOperationBuilder::new("warehouses")
+            .with_filter("store_id", 3)
+            .with_nearest("location", 37.6, 55.7)
+            .try_build(FilteredOperation::Select {
+                op: None,
+                limit: Some(2),
+                lock: None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            res.0,
+            "SELECT * FROM warehouses WHERE store_id = $1 ORDER BY location <-> point($2, $3) LIMIT 2;"
+        );
+        assert_eq!(res.1.len(), 3);
+
+        let (query, _) = FilteredOperationBuilder::new("warehouses")
+            .with_nearest("location", 37.6, 55.7)
+            .try_build(FilteredOperation::Delete)
+            .unwrap();
+        assert_eq!(query, "DELETE FROM warehouses RETURNING *;");
+
+        let res = FilteredOperationBuilder::new("warehouses")
+            .with_filter("store_id", 3)
+            .with_nearest("location", 37.6, 55.7)
+            .try_build(FilteredOperation::Select {
+                op: Some(SelectOperation::Count),
+                limit: None,
+                lock: None,
+            })
+            .unwrap();
+        assert_eq!(res.0, "SELECT count(*) FROM warehouses WHERE store_id = $1;");
+        assert_eq!(res.1.len(), 1);
+
+        let res = FilteredOperationBuilder::new("warehouses")
+            .with_columns(&["store_id"])
+            .distinct()
+            .with_nearest("location", 37.6, 55.7)
+            .try_build(FilteredOperation::Select {
+                op: None,
+                limit: None,
+                lock: None,
+            })
+            .unwrap();
+        assert_eq!(res.0, "SELECT DISTINCT store_id FROM warehouses;");
+    }
+
+    #[test]
+    fn test_select_builder_with_lock() {
+        let res = FilteredOperationBuilder::new("my_table")
+            .with_filter("filter_column1", 3)
+            .try_build(FilteredOperation::Select {
+                op: None,
+                limit: Some(1),
+                lock: Some(RowLock::ForUpdateSkipLocked),
+            })
+            .unwrap();
+
+        assert_eq!(
+            res.0,
+            "SELECT * FROM my_table WHERE filter_column1 = $1 LIMIT 1 FOR UPDATE SKIP LOCKED;"
+        );
+
+        let res = FilteredOperationBuilder::new("my_table")
+            .try_build(FilteredOperation::Select {
+                op: None,
+                limit: None,
+                lock: Some(RowLock::ForUpdateNoWait),
+            })
+            .unwrap();
+
+        assert_eq!(res.0, "SELECT * FROM my_table FOR UPDATE NOWAIT;");
+    }
+
+    #[test]
+    fn test_select_builder_with_columns() {
+        let res = FilteredOperationBuilder::new("orders")
+            .with_filter("state", "paid")
+            .with_columns(&["store_id"])
+            .distinct()
+            .try_build(FilteredOperation::Select {
+                op: None,
+                limit: None,
+                lock: None,
+            })
+            .unwrap();
+        assert_eq!(res.0, "SELECT DISTINCT store_id FROM orders WHERE state = $1;");
+
+        let res = FilteredOperationBuilder::new("orders")
+            .with_columns(&["id", "store_id"])
+            .try_build(FilteredOperation::Select {
+                op: None,
+                limit: Some(5),
+                lock: None,
+            })
+            .unwrap();
+        assert_eq!(res.0, "SELECT id, store_id FROM orders LIMIT 5;");
+
+        let count = |b: FilteredOperationBuilder| {
+            b.try_build(FilteredOperation::Select {
+                op: Some(SelectOperation::Count),
+                limit: None,
+                lock: None,
+            })
+            .unwrap()
+            .0
+        };
+        assert_eq!(
+            count(FilteredOperationBuilder::new("orders").with_columns(&["store_id"]).distinct()),
+            "SELECT count(DISTINCT store_id) FROM orders;"
+        );
+        assert_eq!(
+            count(
+                FilteredOperationBuilder::new("orders")
+                    .with_columns(&["store_id", "customer"])
+                    .distinct()
+            ),
+            "SELECT count(DISTINCT (store_id, customer)) FROM orders;"
+        );
+        assert_eq!(
+            count(FilteredOperationBuilder::new("orders").distinct()),
+            "SELECT count(DISTINCT orders.*) FROM orders;"
+        );
+    }
+
+    #[test]
+    fn test_select_builder_with_subqueries() {
+        let res = FilteredOperationBuilder::new("orders")
+            .with_filter("state", "paid")
+            .with_in_subquery(
+                "product_id",
+                FilteredOperationBuilder::new("products")
+                    .with_columns(&["id"])
+                    .with_filter("store_id", 10),
+            )
+            .with_not_exists(
+                FilteredOperationBuilder::new("disputes")
+                    .with_column_filter("order_id", "orders.id")
+                    .with_filter("status", "open"),
+            )
+            .try_build(FilteredOperation::Select {
+                op: None,
+                limit: Some(5),
+                lock: None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            res.0,
+            "SELECT * FROM orders WHERE product_id in (SELECT id FROM products WHERE store_id = $1) \
+             AND state = $2 AND NOT EXISTS (SELECT * FROM disputes WHERE order_id = orders.id \
+             AND status = $3) LIMIT 5;"
+        );
+        assert_eq!(format!("{:?}", res.1), format!("{:?}", vec![&10 as &ToSql, &"paid", &"open"]));
+    }
+
+    #[test]
+    fn test_update_builder_with_expressions() {
+        let res = UpdateBuilder::from(
+            FilteredOperationBuilder::new("stocks")
+                .with_filter::<i32, _>("quantity", Range::From(RangeLimit { value: 3, inclusive: true })),
+        )
+        .with_decrement("quantity", 3)
+        .with_increment("reserved", 3)
+        .with_expr("updated_at", SetExpr::Default)
+        .try_build()
+        .unwrap();
+
+        assert_eq!(
+            res.0,
+            "UPDATE stocks SET quantity = quantity - $1, reserved = reserved + $2, updated_at = DEFAULT WHERE quantity >= $3 RETURNING *;"
+        );
+        assert_eq!(format!("{:?}", res.1), format!("{:?}", vec![3, 3, 3]));
+    }
+
+    #[test]
+    fn test_update_many_builder() {
+        let res = UpdateManyBuilder::new("orders", "id")
+            .with_row(UpdateRow::new(1).with_value("track_id", "A1").with_value("company", "dhl"))
+            .with_row(UpdateRow::new(2).with_value("company", "ups").with_value("track_id", "B2"))
+            .try_build()
+            .unwrap();
+
+        assert_eq!(
+            res.0,
+            "UPDATE orders SET company = v.company, track_id = v.track_id \
+             FROM (VALUES ((NULL::orders).id, (NULL::orders).company, (NULL::orders).track_id), \
+             ($1, $2, $3), ($4, $5, $6)) AS v (id, company, track_id) \
+             WHERE orders.id = v.id RETURNING orders.*;"
+        );
+        assert_eq!(
+            format!("{:?}", res.1),
+            format!("{:?}", vec![&1 as &ToSql, &"dhl", &"A1", &2, &"ups", &"B2"])
+        );
+    }
+
+    #[test]
+    fn test_schema_qualified_table() {
+        let table = qualified_table("tenant_1", "orders").unwrap();
+        assert_eq!(table, "tenant_1.orders");
+        assert_eq!(qualified_table("tenant_2", table.clone()).unwrap(), "tenant_2.orders");
+
+        let res = FilteredOperationBuilder::new(table.clone())
+            .with_filter("id", 1)
+            .try_build(FilteredOperation::Select {
+                op: None,
+                limit: None,
+                lock: None,
+            })
+            .unwrap();
+        assert_eq!(res.0, "SELECT * FROM tenant_1.orders WHERE id = $1;");
+
+        let res = InsertBuilder::new(table.clone()).with_arg("id", 1).try_build().unwrap();
+        assert_eq!(res.0, "INSERT INTO tenant_1.orders (id) VALUES ($1) RETURNING *;");
+
+        let res = UpdateManyBuilder::new(table, "id")
+            .with_row(UpdateRow::new(1).with_value("company", "dhl"))
+            .try_build()
+            .unwrap();
+        assert_eq!(
+            res.0,
+            "UPDATE tenant_1.orders SET company = v.company \
+             FROM (VALUES ((NULL::tenant_1.orders).id, (NULL::tenant_1.orders).company), ($1, $2)) \
+             AS v (id, company) WHERE tenant_1.orders.id = v.id RETURNING tenant_1.orders.*;"
+        );
+    }
+
+    #[test]
+    fn test_qualified_table_with_invalid_schema() {
+        assert_eq!(
+            qualified_table("tenant; DROP TABLE orders", "orders"),
+            Err(StatementError::InvalidIdentifier {
+                identifier: "tenant; DROP TABLE orders.orders".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_update_many_builder_with_different_columns() {
+        let res = UpdateManyBuilder::new("orders", "id")
+            .with_row(UpdateRow::new(1).with_value("track_id", "A1"))
+            .with_row(UpdateRow::new(2).with_value("company", "ups"))
+            .try_build();
+        assert_eq!(
+            res.err(),
+            Some(StatementError::MismatchedColumns {
+                table: "orders".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_update_many_builder_without_values() {
+        let res = UpdateManyBuilder::new("orders", "id").with_row(UpdateRow::new(1)).try_build();
+        assert_eq!(
+            res.err(),
+            Some(StatementError::NoValues {
+                table: "orders".to_string()
+            })
+        );
+
+        let res = UpdateManyBuilder::new("orders", "id").try_build();
+        assert_eq!(
+            res.err(),
+            Some(StatementError::NoValues {
+                table: "orders".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_update_builder_with_subquery() {
+        let res = UpdateBuilder::from(
+            FilteredOperationBuilder::new("stocks").with_in_subquery(
+                "product_id",
+                FilteredOperationBuilder::new("products")
+                    .with_columns(&["id"])
+                    .with_filter("store_id", 10),
+            ),
+        )
+        .with_value("quantity", 0)
+        .try_build()
+        .unwrap();
+
+        assert_eq!(
+            res.0,
+            "UPDATE stocks SET quantity = $1 WHERE product_id in (SELECT id FROM products WHERE store_id = $2) RETURNING *;"
+        );
+    }
+
+    #[test]
+    fn test_identifier_validation() {
+        assert!(is_valid_identifier("user_id"));
+        assert!(is_valid_identifier("_Column2"));
+        assert!(is_valid_identifier("public.users"));
+        assert!(!is_valid_identifier(""));
+        assert!(!is_valid_identifier("2column"));
+        assert!(!is_valid_identifier("public."));
+        assert!(!is_valid_identifier("id = 1 OR 1"));
+        assert!(!is_valid_identifier("id; DROP TABLE users"));
+
+        assert_eq!(quote_identifier("order"), "\"order\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn test_only_reserved_keywords_are_quoted() {
+        let res = FilteredOperationBuilder::new("public.user")
+            .with_filter("Order", 1)
+            .with_filter("userId", 2)
+            .with_order_by(&["Created_At"])
+            .build(FilteredOperation::Select {
+                op: None,
+                limit: None,
+                lock: None,
+            });
+        assert_eq!(
+            res.0,
+            "SELECT * FROM public.\"user\" WHERE \"order\" = $1 AND userId = $2 ORDER BY Created_At;"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid SQL identifier")]
+    fn test_build_panics_on_invalid_identifier() {
+        InsertBuilder::new("my_table").with_arg("id) VALUES (1", 3).build();
+    }
+
+    #[test]
+    fn test_filter_column_injection() {
+        let select = FilteredOperation::Select {
+            op: None,
+            limit: None,
+            lock: None,
+        };
+        let res = FilteredOperationBuilder::new("my_table")
+            .with_filter("1 = 1 OR id", 3)
+            .try_build(select);
+        assert_eq!(
+            res.err(),
+            Some(StatementError::InvalidIdentifier {
+                identifier: "1 = 1 OR id".to_string()
+            })
+        );
+
+        let res = FilteredOperationBuilder::new("my_table; DROP TABLE users").try_build(select);
+        assert_eq!(
+            res.err(),
+            Some(StatementError::InvalidIdentifier {
+                identifier: "my_table; DROP TABLE users".to_string()
+            })
+        );
+
+        let res = InsertBuilder::new("my_table").with_arg("id) VALUES (1", 3).try_build();
+        assert!(res.is_err());
+
+        let res = UpdateBuilder::from(FilteredOperationBuilder::new("my_table"))
+            .with_value("id = 1, admin", 1)
+            .try_build();
+        assert!(res.is_err());
+
+        let res = UpdateManyBuilder::new("my_table", "id")
+            .with_row(UpdateRow::new(1).with_value("admin = true, id", 1))
+            .try_build();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_extra_statement_injection() {
+        let select = FilteredOperation::Select {
+            op: None,
+            limit: None,
+            lock: None,
+        };
+        for &extra in &[
+            "ORDER BY id; DROP TABLE my_table",
+            "ORDER BY id -- ",
+            "ORDER BY id /* ",
+            "ORDER BY name = 'a",
+            "ORDER BY name = E'\\'; DROP TABLE my_table; --'",
+        ] {
+            let res = FilteredOperationBuilder::new("my_table").with_extra(extra).try_build(select);
+            assert_eq!(res.err(), Some(StatementError::InvalidExtra { extra: extra.to_string() }));
+        }
+
+        let res = FilteredOperationBuilder::new("my_table")
+            .with_extra("ORDER BY name = '--; /* it''s not a comment' DESC")
+            .try_build(select)
+            .unwrap();
+        assert_eq!(res.0, "SELECT * FROM my_table ORDER BY name = '--; /* it''s not a comment' DESC;");
+    }
+
     #[test]
     fn test_update_builder() {
         let res = UpdateBuilder::from(
@@ -517,4 +1575,41 @@ mod tests {
         assert_eq!(res.0, expectation.0);
         assert_eq!(format!("{:?}", res.1), format!("{:?}", expectation.1));
     }
+
+    #[test]
+    fn test_insert_builder() {
+        let res = InsertBuilder::new("my_table").with_arg("column1", 1).with_arg("column2", 2).build();
+
+        let expectation = (
+            "INSERT INTO my_table (column1, column2) VALUES ($1, $2) RETURNING *;",
+            vec![1, 2]
+                .into_iter()
+                .map(|v| Box::new(v) as Box<ToSql + 'static>)
+                .collect::<Vec<Box<ToSql + 'static>>>(),
+        );
+
+        assert_eq!(res.0, expectation.0);
+        assert_eq!(format!("{:?}", res.1), format!("{:?}", expectation.1));
+    }
+
+    #[test]
+    fn test_multiple_insert_builder() {
+        let res = InsertBuilder::new("my_table")
+            .with_arg("column1", 1)
+            .with_arg("column2", 2)
+            .with_rows_from(InsertBuilder::new("my_table").with_arg("column1", 3))
+            .try_build()
+            .unwrap();
+
+        let expectation = (
+            "INSERT INTO my_table (column1, column2) VALUES ($1, $2), ($3, DEFAULT) RETURNING *;",
+            vec![1, 2, 3]
+                .into_iter()
+                .map(|v| Box::new(v) as Box<ToSql + 'static>)
+                .collect::<Vec<Box<ToSql + 'static>>>(),
+        );
+
+        assert_eq!(res.0, expectation.0);
+        assert_eq!(format!("{:?}", res.1), format!("{:?}", expectation.1));
+    }
 }
@@ -0,0 +1,213 @@
+//! Persisted webhook deliveries, so that retries survive restarts of the delivering service.
+//!
+//! The deliveries table is expected to have the following schema:
+//!
+//! ```sql
+//! CREATE TABLE webhook_deliveries (
+//!     id BIGSERIAL PRIMARY KEY,
+//!     url VARCHAR NOT NULL,
+//!     event_type VARCHAR NOT NULL,
+//!     payload TEXT NOT NULL,
+//!     signature VARCHAR NOT NULL,
+//!     attempts INTEGER NOT NULL DEFAULT 0,
+//!     next_attempt_at TIMESTAMP NOT NULL DEFAULT now(),
+//!     delivered_at TIMESTAMP,
+//!     dead_at TIMESTAMP,
+//!     last_error TEXT
+//! );
+//! CREATE INDEX webhook_deliveries_due_idx ON webhook_deliveries (next_attempt_at) WHERE delivered_at IS NULL AND dead_at IS NULL;
+//! ```
+use connection::*;
+use statement::{is_valid_identifier, qualified_table, InsertBuilder, Inserter, StatementError, TableName};
+
+use failure;
+use futures::future;
+use futures::prelude::*;
+use futures_state_stream::StateStream;
+use std::time::{Duration, SystemTime};
+use tokio_postgres::rows::Row;
+use tokio_postgres::types::ToSql;
+
+pub type WebhookError = failure::Error;
+pub type WebhookConnection = BoxedConnection<WebhookError>;
+pub type WebhookConnectionFuture<T> = ConnectionFuture<T, WebhookError>;
+
+/// Delivery to be attempted as soon as possible
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewWebhookDelivery {
+    pub url: String,
+    pub event_type: String,
+    /// JSON body of the request
+    pub payload: String,
+    /// Signature of the payload, stored so that the signing secret is never persisted
+    pub signature: String,
+}
+
+impl Inserter for NewWebhookDelivery {
+    fn into_insert_builder(self, table: TableName) -> InsertBuilder {
+        InsertBuilder::new(table)
+            .with_arg("url", self.url)
+            .with_arg("event_type", self.event_type)
+            .with_arg("payload", self.payload)
+            .with_arg("signature", self.signature)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub url: String,
+    pub event_type: String,
+    pub payload: String,
+    pub signature: String,
+    /// Failed delivery attempts
+    pub attempts: i32,
+    pub next_attempt_at: SystemTime,
+    pub last_error: Option<String>,
+}
+
+impl From<Row> for WebhookDelivery {
+    fn from(row: Row) -> Self {
+        Self {
+            id: row.get("id"),
+            url: row.get("url"),
+            event_type: row.get("event_type"),
+            payload: row.get("payload"),
+            signature: row.get("signature"),
+            attempts: row.get("attempts"),
+            next_attempt_at: row.get("next_attempt_at"),
+            last_error: row.get("last_error"),
+        }
+    }
+}
+
+fn execute(conn: WebhookConnection, query: String, args: Vec<Box<ToSql>>) -> WebhookConnectionFuture<Vec<Row>> {
+    Box::new(conn.prepare2(&query).and_then(move |(statement, conn)| {
+        conn.query2(&statement, args)
+            .collect()
+            .map_err(move |(e, conn)| (e.context(format!("Failed to run webhook deliveries query {}", query)).into(), conn))
+    }))
+}
+
+fn expect_one(mut rows: Vec<Row>) -> Result<WebhookDelivery, WebhookError> {
+    rows.pop().map(WebhookDelivery::from).ok_or_else(|| format_err!("No rows returned"))
+}
+
+fn duration_to_millis(duration: Duration) -> i64 {
+    duration.as_secs() as i64 * 1000 + i64::from(duration.subsec_millis())
+}
+
+pub struct WebhookDeliveries {
+    pub table: TableName,
+}
+
+impl WebhookDeliveries {
+    pub fn new<T: Into<TableName>>(table: T) -> Result<Self, WebhookError> {
+        let table = table.into();
+        if !is_valid_identifier(&table) {
+            return Err(StatementError::InvalidIdentifier {
+                identifier: table.into_owned(),
+            }
+            .into());
+        }
+        Ok(Self { table })
+    }
+
+    /// Qualify the table with the schema, e.g. one per tenant
+    pub fn with_schema(mut self, schema: &str) -> Result<Self, WebhookError> {
+        self.table = qualified_table(schema, self.table)?;
+        Ok(self)
+    }
+
+    pub fn schedule(&self, conn: WebhookConnection, delivery: NewWebhookDelivery) -> WebhookConnectionFuture<WebhookDelivery> {
+        let (query, args) = match delivery.into_insert_builder(self.table.clone()).try_build() {
+            Ok(built) => built,
+            Err(e) => return Box::new(future::err((e.into(), conn))),
+        };
+
+        Box::new(execute(conn, query, args).and_then(|(rows, conn)| match expect_one(rows) {
+            Ok(delivery) => Ok((delivery, conn)),
+            Err(e) => Err((e, conn)),
+        }))
+    }
+
+    /// Locks up to `limit` deliveries due by now until the end of transaction, skipping ones locked by other workers
+    pub fn lock_due(&self, conn: WebhookConnection, limit: i64) -> WebhookConnectionFuture<Vec<WebhookDelivery>> {
+        let query = format!(
+            "SELECT * FROM {} WHERE delivered_at IS NULL AND dead_at IS NULL AND next_attempt_at <= now() \
+             ORDER BY next_attempt_at LIMIT $1 FOR UPDATE SKIP LOCKED;",
+            self.table
+        );
+
+        Box::new(
+            execute(conn, query, vec![Box::new(limit)]).map(|(rows, conn)| (rows.into_iter().map(WebhookDelivery::from).collect(), conn)),
+        )
+    }
+
+    pub fn mark_delivered(&self, conn: WebhookConnection, id: i64) -> WebhookConnectionFuture<()> {
+        let query = format!("UPDATE {} SET delivered_at = now() WHERE id = $1;", self.table);
+
+        Box::new(execute(conn, query, vec![Box::new(id)]).map(|(_, conn)| ((), conn)))
+    }
+
+    /// Records a failed attempt, postponing the next one by `delay` from now
+    pub fn reschedule(&self, conn: WebhookConnection, id: i64, delay: Duration, error: String) -> WebhookConnectionFuture<()> {
+        let query = format!(
+            "UPDATE {} SET attempts = attempts + 1, next_attempt_at = now() + $2 * interval '1 millisecond', last_error = $3 \
+             WHERE id = $1;",
+            self.table
+        );
+
+        Box::new(
+            execute(
+                conn,
+                query,
+                vec![Box::new(id), Box::new(duration_to_millis(delay)), Box::new(error)],
+            )
+            .map(|(_, conn)| ((), conn)),
+        )
+    }
+
+    /// Records the last failed attempt, the delivery is never attempted again
+    pub fn mark_dead(&self, conn: WebhookConnection, id: i64, error: String) -> WebhookConnectionFuture<WebhookDelivery> {
+        let query = format!(
+            "UPDATE {} SET attempts = attempts + 1, dead_at = now(), last_error = $2 WHERE id = $1 RETURNING *;",
+            self.table
+        );
+
+        Box::new(
+            execute(conn, query, vec![Box::new(id), Box::new(error)]).and_then(|(rows, conn)| match expect_one(rows) {
+                Ok(delivery) => Ok((delivery, conn)),
+                Err(e) => Err((e, conn)),
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_delivery_insert() {
+        let (query, args) = NewWebhookDelivery {
+            url: "https://example.com/hooks".to_string(),
+            event_type: "order_paid".to_string(),
+            payload: "{}".to_string(),
+            signature: "sha256=00".to_string(),
+        }
+        .into_insert_builder("webhook_deliveries".into())
+        .build();
+
+        assert_eq!(
+            query,
+            "INSERT INTO webhook_deliveries (event_type, payload, signature, url) VALUES ($1, $2, $3, $4) RETURNING *;"
+        );
+        assert_eq!(args.len(), 4);
+    }
+
+    #[test]
+    fn test_duration_to_millis() {
+        assert_eq!(duration_to_millis(Duration::new(90, 5_000_000)), 90_005);
+    }
+}
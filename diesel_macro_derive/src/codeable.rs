@@ -0,0 +1,141 @@
+use heck::CamelCase;
+use proc_macro2::TokenStream;
+use syn::{Attribute, Data, DeriveInput, Fields, Ident, Lit, Meta, NestedMeta};
+
+use compile_error;
+
+/// Variant of `stq_http::errors::ErrorKind` named `kind` in snake case, as `ErrorKind::name` returns it.
+/// Kinds are not listed here, so that unknown ones fail to compile as missing variants of `ErrorKind` itself.
+fn kind_variant(kind: &str) -> Option<String> {
+    let is_snake_case = !kind.is_empty() && kind.chars().all(|c| c.is_ascii_lowercase() || c == '_');
+    if is_snake_case {
+        Some(kind.to_camel_case())
+    } else {
+        None
+    }
+}
+
+/// Options of the type or a variant, set with `#[codeable(...)]`
+#[derive(Default)]
+struct Options {
+    /// Variant of `ErrorKind`
+    kind: Option<Ident>,
+    /// Single field is serialized as the payload
+    payload: bool,
+}
+
+fn options(attrs: &[Attribute]) -> Result<Options, String> {
+    let mut options = Options::default();
+    for meta in attrs.iter().filter_map(|attr| attr.interpret_meta()) {
+        let list = match meta {
+            Meta::List(ref list) if list.ident == "codeable" => list.nested.clone(),
+            _ => continue,
+        };
+        for nested in list {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(ref name_value)) if name_value.ident == "kind" => {
+                    let value = match name_value.lit {
+                        Lit::Str(ref s) => s.clone(),
+                        _ => return Err("Codeable: kind must be a string".to_string()),
+                    };
+                    let variant = match kind_variant(&value.value()) {
+                        Some(variant) => variant,
+                        None => return Err(format!("Codeable: invalid error kind \"{}\"", value.value())),
+                    };
+                    options.kind = Some(Ident::new(&variant, value.span()));
+                }
+                NestedMeta::Meta(Meta::Word(ref word)) if word == "payload" => options.payload = true,
+                _ => return Err("Codeable: attributes must be either kind = \"...\" or payload".to_string()),
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Pattern binding the single field of the variant or struct as `value`
+fn payload_pattern(path: TokenStream, fields: &Fields) -> Result<TokenStream, String> {
+    match *fields {
+        Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => Ok(quote! { #path(ref value) }),
+        Fields::Named(ref fields) if fields.named.len() == 1 => {
+            let ident = fields.named.iter().next().unwrap().ident.clone().unwrap();
+            Ok(quote! { #path { #ident: ref value } })
+        }
+        _ => Err("Codeable: payload can only be carried by a single field".to_string()),
+    }
+}
+
+/// Generates `Codeable` and `PayloadCarrier` impls responding with status of the error kind
+pub fn expand(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let type_options = match options(&input.attrs) {
+        Ok(options) => options,
+        Err(e) => return compile_error(&e),
+    };
+    let default_kind = type_options.kind.clone().unwrap_or_else(|| Ident::new("Internal", name.span()));
+
+    let mut patterns = vec![];
+    let mut payload_patterns = vec![];
+    let mut kinds = vec![];
+    let mut payloads = vec![];
+    match input.data {
+        Data::Enum(ref data) => {
+            for variant in &data.variants {
+                let options = match options(&variant.attrs) {
+                    Ok(options) => options,
+                    Err(e) => return compile_error(&e),
+                };
+                let id = &variant.ident;
+                let pattern = quote! { #name::#id { .. } };
+                let (payload_pattern, payload) = if options.payload {
+                    match payload_pattern(quote! { #name::#id }, &variant.fields) {
+                        Ok(payload_pattern) => (payload_pattern, quote! { ::serde_json::to_value(value).ok() }),
+                        Err(e) => return compile_error(&e),
+                    }
+                } else {
+                    (pattern.clone(), quote! { None })
+                };
+                patterns.push(pattern);
+                payload_patterns.push(payload_pattern);
+                kinds.push(options.kind.unwrap_or_else(|| default_kind.clone()));
+                payloads.push(payload);
+            }
+        }
+        Data::Struct(ref data) => {
+            let pattern = quote! { #name { .. } };
+            let (payload_pattern, payload) = if type_options.payload {
+                match payload_pattern(quote! { #name }, &data.fields) {
+                    Ok(payload_pattern) => (payload_pattern, quote! { ::serde_json::to_value(value).ok() }),
+                    Err(e) => return compile_error(&e),
+                }
+            } else {
+                (pattern.clone(), quote! { None })
+            };
+            patterns.push(pattern);
+            payload_patterns.push(payload_pattern);
+            kinds.push(default_kind);
+            payloads.push(payload);
+        }
+        Data::Union(_) => return compile_error("Codeable can not be derived for unions"),
+    }
+
+    quote! {
+        impl #impl_generics ::stq_http::errors::Codeable for #name #ty_generics #where_clause {
+            fn code(&self) -> ::hyper::StatusCode {
+                let kind: ::stq_http::errors::ErrorKind = match *self {
+                    #(#patterns => ::stq_http::errors::ErrorKind::#kinds,)*
+                };
+                kind.status()
+            }
+        }
+
+        impl #impl_generics ::stq_http::errors::PayloadCarrier for #name #ty_generics #where_clause {
+            fn payload(&self) -> Option<::serde_json::Value> {
+                match *self {
+                    #(#payload_patterns => #payloads,)*
+                }
+            }
+        }
+    }
+}
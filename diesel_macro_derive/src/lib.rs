@@ -7,24 +7,150 @@ extern crate syn;
 #[macro_use]
 extern crate quote;
 
+mod codeable;
+mod newtype;
+mod postgres_enum;
+mod statement;
+mod update_data;
+
 use heck::SnakeCase;
 use proc_macro2::Span;
-use syn::{Data, DeriveInput, Fields, Ident, LitByteStr, Type};
+use syn::{Attribute, Data, DeriveInput, Fields, GenericArgument, Ident, Index, Lit, LitByteStr, Meta, NestedMeta, PathArguments, Type};
+
+/// Derives `<Name>UpdateData` partial update struct, see `update_data::expand`.
+///
+/// Fields can be marked with `#[update_data(id)]` to filter updated rows by them,
+/// `#[update_data(skip)]` to forbid their update and `#[update_data(column = "...")]` to rename the column.
+/// Name of the update struct can be set with `#[update_data(name = "...")]` on the struct.
+#[proc_macro_derive(UpdateData, attributes(update_data))]
+pub fn derive_update_data(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = match syn::parse(input) {
+        Ok(input) => input,
+        Err(e) => return compile_error(&format!("UpdateData: failed to parse input: {}", e)).into(),
+    };
+
+    update_data::expand(&input).into()
+}
+
+/// Derives `stq_db::statement::Filter` filtering by every field that is set.
+///
+/// Fields must be `Option<T>`, or `Option<Range<T>>` if marked with `#[db_filter(range)]`.
+/// Column name is the field name unless set with `#[db_filter(column = "...")]`, fields marked with `#[db_filter(skip)]` are ignored.
+#[proc_macro_derive(DbFilter, attributes(db_filter))]
+pub fn derive_db_filter(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = match syn::parse(input) {
+        Ok(input) => input,
+        Err(e) => return compile_error(&format!("DbFilter: failed to parse input: {}", e)).into(),
+    };
+
+    statement::expand_filter(&input).into()
+}
+
+/// Derives `stq_db::statement::Inserter` inserting every field.
+///
+/// Column name is the field name unless set with `#[db_inserter(column = "...")]`,
+/// fields marked with `#[db_inserter(skip)]` are not inserted.
+#[proc_macro_derive(DbInserter, attributes(db_inserter))]
+pub fn derive_db_inserter(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = match syn::parse(input) {
+        Ok(input) => input,
+        Err(e) => return compile_error(&format!("DbInserter: failed to parse input: {}", e)).into(),
+    };
+
+    statement::expand_inserter(&input).into()
+}
+
+/// Derives conversions of single field tuple struct newtypes: `From`/`Into` the inner type, `Display`, `FromStr`
+/// and diesel impls as `DieselTypes` does, along with `AsRef<str>` for `String` and `new()` for `Uuid` newtypes.
+///
+/// Impls can be turned off with `#[stq_newtype(skip_display, skip_from_str, skip_from_inner, skip_diesel)]`,
+/// `skip_from_inner` leaves out `From<Inner>` for newtypes that validate their value.
+/// Inner type is detected by its name unless set with `#[stq_newtype(inner = "String")]`.
+#[proc_macro_derive(StqNewtype, attributes(stq_newtype))]
+pub fn derive_stq_newtype(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = match syn::parse(input) {
+        Ok(input) => input,
+        Err(e) => return compile_error(&format!("StqNewtype: failed to parse input: {}", e)).into(),
+    };
+
+    newtype::expand(&input).into()
+}
+
+/// Derives `stq_http::errors::Codeable` and `PayloadCarrier`, responding with status of the error kind,
+/// see `stq_http::errors::ErrorKind` for the catalogue.
+///
+/// Kind is set with `#[codeable(kind = "not_found")]` on a variant, or on the type as a default for all variants,
+/// errors of no kind are internal ones. Single field of a variant marked with `#[codeable(payload)]`
+/// is serialized as the payload. Requires `stq_http`, `hyper` and `serde_json` crates at the crate root.
+#[proc_macro_derive(Codeable, attributes(codeable))]
+pub fn derive_codeable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = match syn::parse(input) {
+        Ok(input) => input,
+        Err(e) => return compile_error(&format!("Codeable: failed to parse input: {}", e)).into(),
+    };
 
-#[proc_macro_derive(DieselTypes)]
+    codeable::expand(&input).into()
+}
+
+/// Derives postgres `ToSql`/`FromSql` storing the enum as text, plus `Display` and `FromStr`.
+///
+/// Variants are stored as snake cased names unless set with `#[postgres_enum(rename = "...")]`.
+/// `Display` and `FromStr` use the stored value unless set with `#[postgres_enum(display = "...")]`.
+/// `FromStr` is not generated with `#[postgres_enum(skip_from_str)]` on the enum.
+/// Requires `postgres` (with macros) and `postgres_protocol` crates at the crate root.
+#[proc_macro_derive(PostgresEnum, attributes(postgres_enum))]
+pub fn derive_postgres_enum(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = match syn::parse(input) {
+        Ok(input) => input,
+        Err(e) => return compile_error(&format!("PostgresEnum: failed to parse input: {}", e)).into(),
+    };
+
+    postgres_enum::expand(&input).into()
+}
+
+/// Derives diesel conversions for single field structs and fieldless enums.
+///
+/// Enums are stored as snake cased variant names by default. String stored for a variant can be changed with
+/// `#[diesel_types(rename = "...")]`. With `#[diesel_types(db_type = "Int2")]` on the enum its variants are stored
+/// as `SmallInt` codes, which are variant indices unless set with `#[diesel_types(code = N)]`.
+/// Stored strings and codes must be unique:
+///
+/// ```compile_fail
+/// extern crate diesel;
+/// #[macro_use]
+/// extern crate stq_diesel_macro_derive;
+///
+/// #[derive(Clone, Copy, Debug, DieselTypes)]
+/// #[diesel_types(db_type = "Int2")]
+/// enum Status {
+///     Active,
+///     #[diesel_types(code = 0)]
+///     Blocked,
+/// }
+/// # fn main() {}
+/// ```
+#[proc_macro_derive(DieselTypes, attributes(diesel_types))]
 pub fn derive_diesel_types(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     // Parse the input tokens into a syntax tree.
-    let input: DeriveInput = syn::parse(input).unwrap();
+    let input: DeriveInput = match syn::parse(input) {
+        Ok(input) => input,
+        Err(e) => return compile_error(&format!("DieselTypes: failed to parse input: {}", e)).into(),
+    };
+
+    diesel_types(&input).into()
+}
 
+/// Generates module with diesel impls for the type
+fn diesel_types(input: &DeriveInput) -> proc_macro2::TokenStream {
     // Used in the quasi-quotation below as `#name`.
-    let name = input.ident;
+    let name = &input.ident;
     let type_name = format!("diesel_impls_for_{}", name);
 
     let mod_name = Ident::new(type_name.to_lowercase().as_ref(), Span::call_site());
 
-    let diesel_impls = get_diesel_impls(&input.data, &name);
+    let diesel_impls = get_diesel_impls(&input.data, &input.attrs, name);
 
-    let expanded = quote! {
+    quote! {
         mod #mod_name {
             #![allow(unused_imports)]
 
@@ -48,137 +174,207 @@ pub fn derive_diesel_types(input: proc_macro::TokenStream) -> proc_macro::TokenS
             #diesel_impls
 
         }
-    };
+    }
+}
 
-    // Hand the output tokens back to the compiler.
-    expanded.into()
+fn compile_error(msg: &str) -> proc_macro2::TokenStream {
+    quote! {
+        compile_error!(#msg);
+    }
 }
 
 fn match_types_names_to_diesel_types(type_name: &str) -> Option<proc_macro2::TokenStream> {
     match type_name.to_lowercase() {
         ref x if x == "uuid" => Some(quote! {::diesel::sql_types::Uuid}),
+        ref x if x == "bool" => Some(quote! {::diesel::sql_types::Bool}),
+        ref x if x == "i16" => Some(quote! {::diesel::sql_types::SmallInt}),
         ref x if x == "i32" => Some(quote! {::diesel::sql_types::Integer}),
         ref x if x == "i64" => Some(quote! {::diesel::sql_types::BigInt}),
         ref x if x == "string" => Some(quote! {::diesel::sql_types::VarChar}),
+        ref x if x == "f32" => Some(quote! {::diesel::sql_types::Float}),
         ref x if x == "f64" => Some(quote! {::diesel::sql_types::Double}),
+        ref x if x == "naivedatetime" || x == "systemtime" => Some(quote! {::diesel::sql_types::Timestamp}),
+        ref x if x == "naivedate" => Some(quote! {::diesel::sql_types::Date}),
+        ref x if x == "naivetime" => Some(quote! {::diesel::sql_types::Time}),
+        ref x if x == "datetime" => Some(quote! {::diesel::sql_types::Timestamptz}),
         _ => None,
     }
 }
 
-fn get_diesel_impls(data: &Data, name: &Ident) -> proc_macro2::TokenStream {
+/// SQL type of the field, `Vec<T>` is mapped to an array of SQL type of `T`
+fn get_diesel_type(ty: &Type) -> Option<proc_macro2::TokenStream> {
+    let segment = match *ty {
+        Type::Path(ref p) => p.path.segments.iter().last()?,
+        _ => return None,
+    };
+
+    if segment.ident == "Vec" {
+        let inner = match segment.arguments {
+            PathArguments::AngleBracketed(ref args) => match args.args.iter().next()? {
+                GenericArgument::Type(ref inner) => get_diesel_type(inner)?,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        return Some(quote! {::diesel::sql_types::Array<#inner>});
+    }
+
+    match_types_names_to_diesel_types(&segment.ident.to_string())
+}
+
+fn get_diesel_impls(data: &Data, attrs: &[Attribute], name: &Ident) -> proc_macro2::TokenStream {
     match *data {
         Data::Struct(ref data) => {
-            match data.fields {
-                Fields::Unnamed(ref fields) => {
-                    // Expands to an expression like
-                    fields
-                        .unnamed
-                        .iter()
-                        .map(|f| {
-                            let diesel_type = match &f.ty {
-                                Type::Path(p) => p
-                                    .path
-                                    .segments
-                                    .iter()
-                                    .filter_map(|segment| match_types_names_to_diesel_types(&segment.ident.to_string()))
-                                    .nth(0)
-                                    .unwrap(),
-                                _ => unimplemented!(),
-                            };
-                            quote! {
-                                impl<'a> AsExpression<#diesel_type> for &'a #name {
-                                    type Expression = Bound<#diesel_type, &'a #name>;
-
-                                    fn as_expression(self) -> Self::Expression {
-                                        Bound::new(self)
-                                    }
-                                }
-
-                                impl AsExpression<#diesel_type> for #name {
-                                    type Expression = Bound<#diesel_type, #name>;
-
-                                    fn as_expression(self) -> Self::Expression {
-                                        Bound::new(self)
-                                    }
-                                }
-
-                                impl ToSql<#diesel_type, Pg> for #name {
-                                    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> Result<IsNull, Box<Error + Send + Sync>> {
-                                        ToSql::<#diesel_type, Pg>::to_sql(&self.0, out)
-                                    }
-                                }
-
-                                impl FromSqlRow<#diesel_type, Pg> for #name {
-                                    fn build_from_row<T: Row<Pg>>(row: &mut T) -> Result<Self, Box<Error + Send + Sync>> {
-                                        FromSql::<#diesel_type, Pg>::from_sql(row.take()).map(#name)
-                                    }
-                                }
-
-                                impl FromSql<#diesel_type, Pg> for #name
-                                {
-                                    fn from_sql(raw: Option<&<Pg as Backend>::RawValue>) -> Result<Self, Box<Error + Send + Sync>> {
-                                        FromSql::<#diesel_type, Pg>::from_sql(raw).map(#name)
-                                    }
-                                }
-
-                                impl Queryable<#diesel_type, Pg> for #name {
-                                    type Row = Self;
-
-                                    fn build(row: Self::Row) -> Self {
-                                        row
-                                    }
-                                }
-
-                                impl<'a> AsExpression<Nullable<#diesel_type>> for &'a #name {
-                                    type Expression = Bound<Nullable<#diesel_type>, &'a #name>;
-
-                                    fn as_expression(self) -> Self::Expression {
-                                        Bound::new(self)
-                                    }
-                                }
-
-                                impl AsExpression<Nullable<#diesel_type>> for #name {
-                                    type Expression = Bound<Nullable<#diesel_type>, #name>;
-
-                                    fn as_expression(self) -> Self::Expression {
-                                        Bound::new(self)
-                                    }
-                                }
-
-                                impl ToSql<Nullable<#diesel_type>, Pg> for #name {
-                                    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> Result<IsNull, Box<Error + Send + Sync>> {
-                                        ToSql::<Nullable<#diesel_type>, Pg>::to_sql(&self.0, out)
-                                    }
-                                }
-                            }
-                        })
-                        .nth(0)
-                        .unwrap()
+            // Accessor of the wrapped value and constructor from it
+            let (field, member, constructor) = match data.fields {
+                Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => {
+                    let member = Index::from(0);
+                    (fields.unnamed.iter().next().unwrap(), quote! {#member}, quote! {#name})
+                }
+                Fields::Named(ref fields) if fields.named.len() == 1 => {
+                    let field = fields.named.iter().next().unwrap();
+                    let ident = field.ident.clone().unwrap();
+                    (field, quote! {#ident}, quote! {|value| #name { #ident: value }})
+                }
+                _ => return compile_error("DieselTypes can only be derived for structs with a single field"),
+            };
+
+            let diesel_type = match get_diesel_type(&field.ty) {
+                Some(diesel_type) => diesel_type,
+                None => return compile_error(&format!("DieselTypes: unsupported field type of {}", name)),
+            };
+
+            quote! {
+                impl<'a> AsExpression<#diesel_type> for &'a #name {
+                    type Expression = Bound<#diesel_type, &'a #name>;
+
+                    fn as_expression(self) -> Self::Expression {
+                        Bound::new(self)
+                    }
+                }
+
+                impl AsExpression<#diesel_type> for #name {
+                    type Expression = Bound<#diesel_type, #name>;
+
+                    fn as_expression(self) -> Self::Expression {
+                        Bound::new(self)
+                    }
+                }
+
+                impl ToSql<#diesel_type, Pg> for #name {
+                    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> Result<IsNull, Box<Error + Send + Sync>> {
+                        ToSql::<#diesel_type, Pg>::to_sql(&self.#member, out)
+                    }
+                }
+
+                impl FromSqlRow<#diesel_type, Pg> for #name {
+                    fn build_from_row<T: Row<Pg>>(row: &mut T) -> Result<Self, Box<Error + Send + Sync>> {
+                        FromSql::<#diesel_type, Pg>::from_sql(row.take()).map(#constructor)
+                    }
+                }
+
+                impl FromSql<#diesel_type, Pg> for #name
+                {
+                    fn from_sql(raw: Option<&<Pg as Backend>::RawValue>) -> Result<Self, Box<Error + Send + Sync>> {
+                        FromSql::<#diesel_type, Pg>::from_sql(raw).map(#constructor)
+                    }
+                }
+
+                impl Queryable<#diesel_type, Pg> for #name {
+                    type Row = Self;
+
+                    fn build(row: Self::Row) -> Self {
+                        row
+                    }
+                }
+
+                impl<'a> AsExpression<Nullable<#diesel_type>> for &'a #name {
+                    type Expression = Bound<Nullable<#diesel_type>, &'a #name>;
+
+                    fn as_expression(self) -> Self::Expression {
+                        Bound::new(self)
+                    }
+                }
+
+                impl AsExpression<Nullable<#diesel_type>> for #name {
+                    type Expression = Bound<Nullable<#diesel_type>, #name>;
+
+                    fn as_expression(self) -> Self::Expression {
+                        Bound::new(self)
+                    }
+                }
+
+                impl ToSql<Nullable<#diesel_type>, Pg> for #name {
+                    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> Result<IsNull, Box<Error + Send + Sync>> {
+                        ToSql::<Nullable<#diesel_type>, Pg>::to_sql(&self.#member, out)
+                    }
                 }
-                _ => unimplemented!(),
             }
         }
         Data::Enum(ref data) => {
+            if data.variants.iter().any(|variant| match variant.fields {
+                Fields::Unit => false,
+                _ => true,
+            }) {
+                return compile_error("DieselTypes: variants must be fieldless");
+            }
             let variant_ids: Vec<proc_macro2::TokenStream> = data
                 .variants
                 .iter()
                 .map(|variant| {
-                    if let Fields::Unit = variant.fields {
-                        let id = &variant.ident;
-                        quote! {
-                            #name::#id
-                        }
-                    } else {
-                        panic!("Variants must be fieldless")
+                    let id = &variant.ident;
+                    quote! {
+                        #name::#id
                     }
                 })
                 .collect();
-            let variants_db: Vec<LitByteStr> = data
+            let variants_rs: &[proc_macro2::TokenStream] = &variant_ids;
+
+            let db_type = match enum_db_type(attrs) {
+                Ok(db_type) => db_type,
+                Err(e) => return compile_error(&e),
+            };
+            let variant_options = match data
                 .variants
                 .iter()
-                .map(|variant| LitByteStr::new(variant.ident.to_string().to_snake_case().as_bytes(), Span::call_site()))
+                .map(|variant| variant_options(&variant.attrs))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(options) => options,
+                Err(e) => return compile_error(&e),
+            };
+
+            if db_type == EnumDbType::Int2 {
+                if variant_options.iter().any(|options| options.rename.is_some()) {
+                    return compile_error("DieselTypes: rename can not be used with db_type = \"Int2\"");
+                }
+                let codes: Vec<i16> = variant_options
+                    .iter()
+                    .enumerate()
+                    .map(|(i, options)| options.code.unwrap_or(i as i16))
+                    .collect();
+                if let Some(code) = first_duplicate(&codes) {
+                    return compile_error(&format!("DieselTypes: duplicate code {} in {}", code, name));
+                }
+                return get_int2_enum_impls(name, variants_rs, &codes);
+            }
+
+            if variant_options.iter().any(|options| options.code.is_some()) {
+                return compile_error("DieselTypes: code can only be used with db_type = \"Int2\"");
+            }
+            let values: Vec<String> = data
+                .variants
+                .iter()
+                .zip(variant_options.into_iter())
+                .map(|(variant, options)| options.rename.unwrap_or_else(|| variant.ident.to_string().to_snake_case()))
+                .collect();
+            if let Some(value) = first_duplicate(&values) {
+                return compile_error(&format!("DieselTypes: duplicate value \"{}\" in {}", value, name));
+            }
+            let variants_db: Vec<LitByteStr> = values
+                .iter()
+                .map(|value| LitByteStr::new(value.as_bytes(), Span::call_site()))
                 .collect();
-            let variants_rs: &[proc_macro2::TokenStream] = &variant_ids;
             let variants_db: &[LitByteStr] = &variants_db;
             quote! {
                 impl NotNull for #name {}
@@ -253,6 +449,145 @@ fn get_diesel_impls(data: &Data, name: &Ident) -> proc_macro2::TokenStream {
                 }
             }
         }
-        Data::Union(_) => unimplemented!(),
+        Data::Union(_) => compile_error("DieselTypes can not be derived for unions"),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EnumDbType {
+    VarChar,
+    Int2,
+}
+
+#[derive(Default)]
+struct VariantOptions {
+    rename: Option<String>,
+    code: Option<i16>,
+}
+
+fn diesel_types_meta(attrs: &[Attribute]) -> Result<Vec<Meta>, String> {
+    let mut out = vec![];
+    for meta in attrs.iter().filter_map(|attr| attr.interpret_meta()) {
+        match meta {
+            Meta::List(ref list) if list.ident == "diesel_types" => {
+                for nested in list.nested.iter() {
+                    match *nested {
+                        NestedMeta::Meta(ref meta) => out.push(meta.clone()),
+                        NestedMeta::Literal(_) => return Err("DieselTypes: unexpected literal in diesel_types attribute".to_string()),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+fn enum_db_type(attrs: &[Attribute]) -> Result<EnumDbType, String> {
+    let mut db_type = EnumDbType::VarChar;
+    for meta in diesel_types_meta(attrs)? {
+        match meta {
+            Meta::NameValue(ref name_value) if name_value.ident == "db_type" => {
+                db_type = match name_value.lit {
+                    Lit::Str(ref s) if s.value() == "VarChar" => EnumDbType::VarChar,
+                    Lit::Str(ref s) if s.value() == "Int2" => EnumDbType::Int2,
+                    _ => return Err("DieselTypes: db_type must be either \"VarChar\" or \"Int2\"".to_string()),
+                }
+            }
+            other => return Err(format!("DieselTypes: unknown enum attribute {}", other.name())),
+        }
+    }
+    Ok(db_type)
+}
+
+fn variant_options(attrs: &[Attribute]) -> Result<VariantOptions, String> {
+    let mut options = VariantOptions::default();
+    for meta in diesel_types_meta(attrs)? {
+        match meta {
+            Meta::NameValue(ref name_value) if name_value.ident == "rename" => match name_value.lit {
+                Lit::Str(ref s) => options.rename = Some(s.value()),
+                _ => return Err("DieselTypes: rename must be a string".to_string()),
+            },
+            Meta::NameValue(ref name_value) if name_value.ident == "code" => match name_value.lit {
+                Lit::Int(ref i) if i.value() <= i16::max_value() as u64 => options.code = Some(i.value() as i16),
+                _ => return Err("DieselTypes: code must be a non-negative Int2 value".to_string()),
+            },
+            other => return Err(format!("DieselTypes: unknown variant attribute {}", other.name())),
+        }
+    }
+    Ok(options)
+}
+
+fn first_duplicate<T: PartialEq>(values: &[T]) -> Option<&T> {
+    values
+        .iter()
+        .enumerate()
+        .find(|&(i, value)| values[..i].contains(value))
+        .map(|(_, value)| value)
+}
+
+fn get_int2_enum_impls(name: &Ident, variants_rs: &[proc_macro2::TokenStream], codes: &[i16]) -> proc_macro2::TokenStream {
+    // quote can't use the same variable twice in a repetition
+    let (variants_rs_from, codes_from) = (variants_rs, codes);
+    quote! {
+        impl NotNull for #name {}
+        impl SingleValue for #name {}
+        impl Queryable<SmallInt, Pg> for #name {
+            type Row = #name;
+            fn build(row: Self::Row) -> Self {
+                row
+            }
+        }
+        impl AsExpression<SmallInt> for #name {
+            type Expression = Bound<SmallInt, #name>;
+            fn as_expression(self) -> Self::Expression {
+                Bound::new(self)
+            }
+        }
+        impl<'a> AsExpression<SmallInt> for &'a #name {
+            type Expression = Bound<SmallInt, &'a #name>;
+            fn as_expression(self) -> Self::Expression {
+                Bound::new(self)
+            }
+        }
+        impl<'a> AsExpression<Nullable<SmallInt>> for &'a #name {
+            type Expression = Bound<Nullable<SmallInt>, &'a #name>;
+            fn as_expression(self) -> Self::Expression {
+                Bound::new(self)
+            }
+        }
+        impl AsExpression<Nullable<SmallInt>> for #name {
+            type Expression = Bound<Nullable<SmallInt>, #name>;
+            fn as_expression(self) -> Self::Expression {
+                Bound::new(self)
+            }
+        }
+        impl ToSql<SmallInt, Pg> for #name {
+            fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> Result<IsNull, Box<Error + Send + Sync>> {
+                let code: i16 = match *self {
+                    #(#variants_rs => #codes,)*
+                };
+                ToSql::<SmallInt, Pg>::to_sql(&code, out)
+            }
+        }
+        impl ToSql<Nullable<SmallInt>, Pg> for #name {
+            fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> Result<IsNull, Box<Error + Send + Sync>> {
+                ToSql::<SmallInt, Pg>::to_sql(self, out)
+            }
+        }
+        impl FromSqlRow<SmallInt, Pg> for #name {
+            fn build_from_row<R: Row<Pg>>(row: &mut R) -> Result<Self, Box<Error + Send + Sync>> {
+                FromSql::<SmallInt, Pg>::from_sql(row.take())
+            }
+        }
+        impl FromSql<SmallInt, Pg> for #name
+        {
+            fn from_sql(raw: Option<&<Pg as Backend>::RawValue>) -> Result<Self, Box<Error + Send + Sync>> {
+                match <i16 as FromSql<SmallInt, Pg>>::from_sql(raw)? {
+                    #(#codes_from => Ok(#variants_rs_from),)*
+                    v => Err(format!("Unrecognized enum variant code: {}", v).into()),
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,143 @@
+use proc_macro2::TokenStream;
+use syn::{Attribute, Data, DeriveInput, Fields, Lit, Meta, NestedMeta, Type};
+
+use {compile_error, diesel_types};
+
+/// Options of a newtype, set with `#[stq_newtype(...)]`
+#[derive(Default)]
+struct NewtypeOptions {
+    skip_display: bool,
+    skip_from_str: bool,
+    skip_from_inner: bool,
+    skip_diesel: bool,
+    /// Inner type name, detected from the field type by default
+    inner: Option<String>,
+}
+
+fn newtype_options(attrs: &[Attribute]) -> Result<NewtypeOptions, String> {
+    let mut options = NewtypeOptions::default();
+    for meta in attrs.iter().filter_map(|attr| attr.interpret_meta()) {
+        let list = match meta {
+            Meta::List(ref list) if list.ident == "stq_newtype" => list.nested.clone(),
+            _ => continue,
+        };
+        for nested in list {
+            match nested {
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "skip_display" => options.skip_display = true,
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "skip_from_str" => options.skip_from_str = true,
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "skip_from_inner" => options.skip_from_inner = true,
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "skip_diesel" => options.skip_diesel = true,
+                NestedMeta::Meta(Meta::NameValue(ref name_value)) if name_value.ident == "inner" => match name_value.lit {
+                    Lit::Str(ref s) => options.inner = Some(s.value()),
+                    _ => return Err("StqNewtype: inner must be a string".to_string()),
+                },
+                _ => return Err("StqNewtype: unknown stq_newtype attribute".to_string()),
+            }
+        }
+    }
+    Ok(options)
+}
+
+fn type_name(ty: &Type) -> Option<String> {
+    match *ty {
+        Type::Path(ref path) => path.path.segments.iter().last().map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Generates conversions of the newtype with its inner type, optional `Display`, `FromStr` and diesel impls,
+/// and helpers specific to the inner type.
+pub fn expand(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+
+    let inner = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => &fields.unnamed.iter().next().unwrap().ty,
+            _ => return compile_error("StqNewtype can only be derived for tuple structs with a single field"),
+        },
+        _ => return compile_error("StqNewtype can only be derived for structs"),
+    };
+
+    let options = match newtype_options(&input.attrs) {
+        Ok(options) => options,
+        Err(e) => return compile_error(&e),
+    };
+
+    let display_impl = if options.skip_display {
+        quote! {}
+    } else {
+        quote! {
+            impl ::std::fmt::Display for #name {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                    ::std::fmt::Display::fmt(&self.0, f)
+                }
+            }
+        }
+    };
+
+    let from_str_impl = if options.skip_from_str {
+        quote! {}
+    } else {
+        quote! {
+            impl ::std::str::FromStr for #name {
+                type Err = <#inner as ::std::str::FromStr>::Err;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    <#inner as ::std::str::FromStr>::from_str(s).map(#name)
+                }
+            }
+        }
+    };
+
+    let from_inner_impl = if options.skip_from_inner {
+        quote! {}
+    } else {
+        quote! {
+            impl From<#inner> for #name {
+                fn from(v: #inner) -> Self {
+                    #name(v)
+                }
+            }
+        }
+    };
+
+    let diesel_impls = if options.skip_diesel {
+        quote! {}
+    } else {
+        diesel_types(input)
+    };
+
+    let inner_name = options.inner.or_else(|| type_name(inner)).unwrap_or_default();
+    let inner_impls = match inner_name.as_str() {
+        "String" => quote! {
+            impl ::std::convert::AsRef<str> for #name {
+                fn as_ref(&self) -> &str {
+                    &self.0
+                }
+            }
+        },
+        "Uuid" => quote! {
+            impl #name {
+                pub fn new() -> Self {
+                    #name(<#inner>::new_v4())
+                }
+            }
+        },
+        _ => quote! {},
+    };
+
+    quote! {
+        #from_inner_impl
+
+        impl From<#name> for #inner {
+            fn from(v: #name) -> Self {
+                v.0
+            }
+        }
+
+        #display_impl
+        #from_str_impl
+        #diesel_impls
+        #inner_impls
+    }
+}
@@ -0,0 +1,197 @@
+use heck::SnakeCase;
+use proc_macro2::{Span, TokenStream};
+use syn::{Attribute, Data, DeriveInput, Fields, Ident, Lit, LitStr, Meta, NestedMeta};
+
+use compile_error;
+
+/// Options of a variant, set with `#[postgres_enum(...)]`
+#[derive(Default)]
+struct VariantOptions {
+    /// Value stored in database, snake cased variant name by default
+    rename: Option<String>,
+    /// Value used by `Display` and `FromStr`, same as database one by default
+    display: Option<String>,
+}
+
+fn variant_options(attrs: &[Attribute]) -> Result<VariantOptions, String> {
+    let mut options = VariantOptions::default();
+    for meta in attrs.iter().filter_map(|attr| attr.interpret_meta()) {
+        let list = match meta {
+            Meta::List(ref list) if list.ident == "postgres_enum" => list.nested.clone(),
+            _ => continue,
+        };
+        for nested in list {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(ref name_value)) => {
+                    let value = match name_value.lit {
+                        Lit::Str(ref s) => s.value(),
+                        _ => return Err(format!("PostgresEnum: {} must be a string", name_value.ident)),
+                    };
+                    if name_value.ident == "rename" {
+                        options.rename = Some(value);
+                    } else if name_value.ident == "display" {
+                        options.display = Some(value);
+                    } else {
+                        return Err(format!("PostgresEnum: unknown attribute {}", name_value.ident));
+                    }
+                }
+                _ => return Err("PostgresEnum: attributes must be in form of name = \"value\"".to_string()),
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Options of the enum, set with `#[postgres_enum(...)]`
+#[derive(Default)]
+struct EnumOptions {
+    /// `FromStr` is implemented by hand, e.g. to keep its former error type
+    skip_from_str: bool,
+}
+
+fn enum_options(attrs: &[Attribute]) -> Result<EnumOptions, String> {
+    let mut options = EnumOptions::default();
+    for meta in attrs.iter().filter_map(|attr| attr.interpret_meta()) {
+        let list = match meta {
+            Meta::List(ref list) if list.ident == "postgres_enum" => list.nested.clone(),
+            _ => continue,
+        };
+        for nested in list {
+            match nested {
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "skip_from_str" => options.skip_from_str = true,
+                _ => return Err("PostgresEnum: unknown enum attribute".to_string()),
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Generates postgres `ToSql`/`FromSql` storing the enum as text, along with `Display` and `FromStr`
+pub fn expand(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let mod_name = Ident::new(&format!("postgres_enum_impls_for_{}", name).to_lowercase(), Span::call_site());
+
+    let enum_options = match enum_options(&input.attrs) {
+        Ok(options) => options,
+        Err(e) => return compile_error(&e),
+    };
+
+    let variants = match input.data {
+        Data::Enum(ref data) => &data.variants,
+        _ => return compile_error("PostgresEnum can only be derived for enums"),
+    };
+
+    let mut variant_ids = vec![];
+    let mut db_values = vec![];
+    let mut display_values = vec![];
+    for variant in variants {
+        match variant.fields {
+            Fields::Unit => {}
+            _ => return compile_error("PostgresEnum: variants must be fieldless"),
+        }
+        let options = match variant_options(&variant.attrs) {
+            Ok(options) => options,
+            Err(e) => return compile_error(&e),
+        };
+        let db_value = options.rename.unwrap_or_else(|| variant.ident.to_string().to_snake_case());
+        let display_value = options.display.unwrap_or_else(|| db_value.clone());
+        let id = &variant.ident;
+        variant_ids.push(quote! { #name::#id });
+        db_values.push(LitStr::new(&db_value, Span::call_site()));
+        display_values.push(LitStr::new(&display_value, Span::call_site()));
+    }
+
+    // quote can't use the same variable twice in a repetition
+    let (variant_ids, variant_ids_from_str, variant_ids_to_sql, variant_ids_from_sql) =
+        (&variant_ids, &variant_ids, &variant_ids, &variant_ids);
+    let (db_values, db_values_from_sql) = (&db_values, &db_values);
+    let (display_values, display_values_from_str) = (&display_values, &display_values);
+
+    let unknown_variant = LitStr::new(&format!("Unknown {} variant: {{}}", name), Span::call_site());
+
+    let from_str_impl = if enum_options.skip_from_str {
+        quote! {}
+    } else {
+        quote! {
+            impl FromStr for #name {
+                type Err = Box<Error>;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    Ok(match s {
+                        #(#display_values_from_str => #variant_ids_from_str,)*
+                        other => {
+                            return Err(format!("Unrecognized enum variant: {}", other).into());
+                        }
+                    })
+                }
+            }
+        }
+    };
+
+    quote! {
+        mod #mod_name {
+            #![allow(unused_imports)]
+
+            use std::error::Error;
+            use std::fmt;
+            use std::str::FromStr;
+
+            use postgres;
+            use postgres::types::{FromSql, IsNull, ToSql, Type};
+            use postgres_protocol::types::{text_from_sql, text_to_sql};
+
+            use super::#name;
+
+            impl fmt::Display for #name {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(
+                        f,
+                        "{}",
+                        match *self {
+                            #(#variant_ids => #display_values,)*
+                        }
+                    )
+                }
+            }
+
+            #from_str_impl
+
+            impl ToSql for #name {
+                to_sql_checked!();
+
+                fn to_sql(&self, _ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+                    text_to_sql(
+                        match *self {
+                            #(#variant_ids_to_sql => #db_values,)*
+                        },
+                        out,
+                    );
+                    Ok(IsNull::No)
+                }
+
+                fn accepts(ty: &Type) -> bool {
+                    <&str as ToSql>::accepts(ty)
+                }
+            }
+
+            impl<'a> FromSql<'a> for #name {
+                fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<Error + Sync + Send>> {
+                    text_from_sql(raw).and_then(|buf| {
+                        Ok(match buf {
+                            #(#db_values_from_sql => #variant_ids_from_sql,)*
+                            other => {
+                                return Err(Box::new(postgres::error::conversion(
+                                    format!(#unknown_variant, other).into(),
+                                )));
+                            }
+                        })
+                    })
+                }
+
+                fn accepts(ty: &Type) -> bool {
+                    <&str as FromSql>::accepts(ty)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{Attribute, Data, DeriveInput, Field, GenericArgument, Ident, Lit, LitStr, Meta, NestedMeta, PathArguments, Type};
+
+use compile_error;
+
+/// Options of a field, set with `#[db_filter(...)]` or `#[db_inserter(...)]`
+#[derive(Default)]
+struct FieldOptions {
+    /// Column name, field name by default
+    column: Option<String>,
+    /// Field is not mapped to a column
+    skip: bool,
+    /// Field holds `Range` instead of exact value, filters only
+    range: bool,
+}
+
+/// Parses options of a field from `#[<attr>(...)]`, `range` is accepted only if `allow_range` is set
+fn field_options(attrs: &[Attribute], derive: &str, attr: &str, allow_range: bool) -> Result<FieldOptions, String> {
+    let mut options = FieldOptions::default();
+    for meta in attrs.iter().filter_map(|attr| attr.interpret_meta()) {
+        let list = match meta {
+            Meta::List(ref list) if list.ident == attr => list.nested.clone(),
+            _ => continue,
+        };
+        for nested in list {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(ref name_value)) if name_value.ident == "column" => match name_value.lit {
+                    Lit::Str(ref s) => options.column = Some(s.value()),
+                    _ => return Err(format!("{}: column must be a string", derive)),
+                },
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "skip" => options.skip = true,
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "range" && allow_range => options.range = true,
+                NestedMeta::Meta(ref other) => return Err(format!("{}: unknown {} field attribute {}", derive, attr, other.name())),
+                NestedMeta::Literal(_) => return Err(format!("{}: unexpected literal in {} attribute", derive, attr)),
+            }
+        }
+    }
+    Ok(options)
+}
+
+fn named_fields<'a>(input: &'a DeriveInput, derive: &str) -> Result<Vec<&'a Field>, String> {
+    match input.data {
+        Data::Struct(ref data) => {
+            let fields = data.fields.iter().collect::<Vec<_>>();
+            if fields.iter().any(|field| field.ident.is_none()) {
+                return Err(format!("{} can only be derived for structs with named fields", derive));
+            }
+            Ok(fields)
+        }
+        _ => Err(format!("{} can only be derived for structs", derive)),
+    }
+}
+
+fn column_name(field: &Field, options: &FieldOptions) -> LitStr {
+    let column = options.column.clone().unwrap_or_else(|| field.ident.as_ref().unwrap().to_string());
+    LitStr::new(&column, Span::call_site())
+}
+
+/// Returns `T` of `Wrapper<T>`, where wrapper is matched by the last path segment
+fn wrapped_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let segment = match *ty {
+        Type::Path(ref path) => path.path.segments.iter().last()?,
+        _ => return None,
+    };
+    if segment.ident != wrapper {
+        return None;
+    }
+    match segment.arguments {
+        PathArguments::AngleBracketed(ref args) => match args.args.iter().next()? {
+            GenericArgument::Type(ref ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Generates `Filter` impl adding a filter for every field that is set.
+/// Fields must be `Option<T>` or, with `#[db_filter(range)]`, `Option<Range<T>>`.
+pub fn expand_filter(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match named_fields(input, "DbFilter") {
+        Ok(fields) => fields,
+        Err(e) => return compile_error(&e),
+    };
+
+    let mut filters = vec![];
+    for field in fields {
+        let options = match field_options(&field.attrs, "DbFilter", "db_filter", true) {
+            Ok(options) => options,
+            Err(e) => return compile_error(&e),
+        };
+        if options.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().unwrap();
+        let column = column_name(field, &options);
+        let value_type = match wrapped_type(&field.ty, "Option") {
+            Some(ty) if options.range => wrapped_type(ty, "Range"),
+            other => other,
+        };
+        let value_type = match value_type {
+            Some(ty) => ty,
+            None if options.range => return compile_error(&format!("DbFilter: field {} must be Option<Range<_>>", ident)),
+            None => return compile_error(&format!("DbFilter: field {} must be Option<_>", ident)),
+        };
+        filters.push(quote! {
+            if let Some(value) = self.#ident {
+                b = b.with_filter::<#value_type, _>(#column, value);
+            }
+        });
+    }
+
+    quote! {
+        impl #impl_generics ::stq_db::statement::Filter for #name #ty_generics #where_clause {
+            fn into_filtered_operation_builder(self, table: ::stq_db::statement::TableName) -> ::stq_db::statement::FilteredOperationBuilder {
+                #[allow(unused_mut)]
+                let mut b = ::stq_db::statement::FilteredOperationBuilder::new(table);
+                #(#filters)*
+                b
+            }
+        }
+    }
+}
+
+/// Generates `Inserter` impl inserting every field into its column.
+pub fn expand_inserter(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match named_fields(input, "DbInserter") {
+        Ok(fields) => fields,
+        Err(e) => return compile_error(&e),
+    };
+
+    let mut idents: Vec<&Ident> = vec![];
+    let mut columns = vec![];
+    for field in fields {
+        let options = match field_options(&field.attrs, "DbInserter", "db_inserter", false) {
+            Ok(options) => options,
+            Err(e) => return compile_error(&e),
+        };
+        if options.skip {
+            continue;
+        }
+        idents.push(field.ident.as_ref().unwrap());
+        columns.push(column_name(field, &options));
+    }
+
+    quote! {
+        impl #impl_generics ::stq_db::statement::Inserter for #name #ty_generics #where_clause {
+            fn into_insert_builder(self, table: ::stq_db::statement::TableName) -> ::stq_db::statement::InsertBuilder {
+                ::stq_db::statement::InsertBuilder::new(table)
+                    #(.with_arg(#columns, self.#idents))*
+            }
+        }
+    }
+}
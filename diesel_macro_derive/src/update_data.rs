@@ -0,0 +1,170 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{Attribute, Data, DeriveInput, Fields, Ident, Lit, LitStr, Meta, NestedMeta, Type};
+
+use compile_error;
+
+/// Options of a field, set with `#[update_data(...)]`
+#[derive(Default)]
+struct FieldOptions {
+    /// Field identifies the row to update, it is kept as is in the update struct
+    id: bool,
+    /// Field can't be updated
+    skip: bool,
+    /// Column name, field name by default
+    column: Option<String>,
+}
+
+struct UpdateField {
+    ident: Ident,
+    ty: Type,
+    column: LitStr,
+}
+
+fn update_data_meta(attrs: &[Attribute]) -> Result<Vec<Meta>, String> {
+    let mut out = vec![];
+    for meta in attrs.iter().filter_map(|attr| attr.interpret_meta()) {
+        match meta {
+            Meta::List(ref list) if list.ident == "update_data" => {
+                for nested in list.nested.iter() {
+                    match *nested {
+                        NestedMeta::Meta(ref meta) => out.push(meta.clone()),
+                        NestedMeta::Literal(_) => return Err("UpdateData: unexpected literal in update_data attribute".to_string()),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+fn field_options(attrs: &[Attribute]) -> Result<FieldOptions, String> {
+    let mut options = FieldOptions::default();
+    for meta in update_data_meta(attrs)? {
+        match meta {
+            Meta::Word(ref ident) if ident == "id" => options.id = true,
+            Meta::Word(ref ident) if ident == "skip" => options.skip = true,
+            Meta::NameValue(ref name_value) if name_value.ident == "column" => match name_value.lit {
+                Lit::Str(ref s) => options.column = Some(s.value()),
+                _ => return Err("UpdateData: column must be a string".to_string()),
+            },
+            other => return Err(format!("UpdateData: unknown update_data field attribute {}", other.name())),
+        }
+    }
+    Ok(options)
+}
+
+fn update_struct_name(input: &DeriveInput) -> Result<Ident, String> {
+    let mut name = format!("{}UpdateData", input.ident);
+    for meta in update_data_meta(&input.attrs)? {
+        match meta {
+            Meta::NameValue(ref name_value) if name_value.ident == "name" => match name_value.lit {
+                Lit::Str(ref s) => name = s.value(),
+                _ => return Err("UpdateData: name must be a string".to_string()),
+            },
+            other => return Err(format!("UpdateData: unknown update_data struct attribute {}", other.name())),
+        }
+    }
+    Ok(Ident::new(&name, Span::call_site()))
+}
+
+/// Generates `<Name>UpdateData` struct with every updatable field wrapped into `Option<ValueContainer<_>>`,
+/// its `apply` method and, if the struct has fields marked with `#[update_data(id)]`, an `Updater` impl
+/// that updates the row with these ids.
+pub fn expand(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let update_name = match update_struct_name(input) {
+        Ok(update_name) => update_name,
+        Err(e) => return compile_error(&e),
+    };
+
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return compile_error("UpdateData can only be derived for structs with named fields"),
+        },
+        _ => return compile_error("UpdateData can only be derived for structs"),
+    };
+
+    let mut ids = vec![];
+    let mut updates = vec![];
+    for field in fields {
+        let options = match field_options(&field.attrs) {
+            Ok(options) => options,
+            Err(e) => return compile_error(&e),
+        };
+        if options.skip {
+            continue;
+        }
+        let ident = field.ident.clone().unwrap();
+        let column = LitStr::new(&options.column.unwrap_or_else(|| ident.to_string()), Span::call_site());
+        let update_field = UpdateField {
+            ident,
+            ty: field.ty.clone(),
+            column,
+        };
+        if options.id {
+            ids.push(update_field);
+        } else {
+            updates.push(update_field);
+        }
+    }
+
+    let id_idents: &Vec<&Ident> = &ids.iter().map(|f| &f.ident).collect();
+    let id_types: &Vec<&Type> = &ids.iter().map(|f| &f.ty).collect();
+    let id_columns: &Vec<&LitStr> = &ids.iter().map(|f| &f.column).collect();
+    let update_idents: &Vec<&Ident> = &updates.iter().map(|f| &f.ident).collect();
+    let update_types: &Vec<&Type> = &updates.iter().map(|f| &f.ty).collect();
+    let update_columns: &Vec<&LitStr> = &updates.iter().map(|f| &f.column).collect();
+    // quote can't use the same variable twice in a repetition
+    let base_idents = update_idents;
+    let id_types_filter = id_types;
+
+    let derives = if ids.is_empty() {
+        quote! { #[derive(Clone, Debug, Default, Serialize, Deserialize)] }
+    } else {
+        quote! { #[derive(Clone, Debug, Serialize, Deserialize)] }
+    };
+
+    let updater_impl = if ids.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            impl ::stq_db::statement::Updater for #update_name {
+                fn into_update_builder(self, table: ::stq_db::statement::TableName) -> ::stq_db::statement::UpdateBuilder {
+                    let filter = ::stq_db::statement::FilteredOperationBuilder::new(table)
+                        #(.with_filter::<#id_types_filter, _>(#id_columns, self.#id_idents))*;
+                    let mut builder = ::stq_db::statement::UpdateBuilder::from(filter);
+                    #(
+                        if let Some(container) = self.#update_idents {
+                            builder = builder.with_value(#update_columns, container.value);
+                        }
+                    )*
+                    builder
+                }
+            }
+        }
+    };
+
+    quote! {
+        #derives
+        #vis struct #update_name {
+            #(pub #id_idents: #id_types,)*
+            #(pub #update_idents: Option<::stq_types::ValueContainer<#update_types>>,)*
+        }
+
+        impl #update_name {
+            /// Sets new values of the fields to `base`
+            pub fn apply(self, base: &mut #name) {
+                #(
+                    if let Some(container) = self.#update_idents {
+                        base.#base_idents = container.value;
+                    }
+                )*
+            }
+        }
+
+        #updater_impl
+    }
+}
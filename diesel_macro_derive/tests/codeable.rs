@@ -0,0 +1,72 @@
+extern crate hyper;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate stq_diesel_macro_derive;
+extern crate stq_http;
+
+use hyper::StatusCode;
+use stq_http::errors::{Codeable, ErrorKind, PayloadCarrier};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Conflicting {
+    pub id: i32,
+}
+
+#[derive(Debug, Codeable)]
+#[codeable(kind = "validation")]
+pub enum ServiceError {
+    Invalid,
+    #[codeable(kind = "not_found")]
+    NotFound,
+    #[codeable(kind = "conflict", payload)]
+    Conflict(Conflicting),
+    #[codeable(kind = "unauthorized")]
+    Unauthorized {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Codeable)]
+pub struct Unexpected;
+
+#[derive(Debug, Codeable)]
+#[codeable(kind = "forbidden", payload)]
+pub struct Forbidden {
+    pub action: String,
+}
+
+#[test]
+fn test_variants_respond_with_status_of_their_kind() {
+    assert_eq!(ServiceError::Invalid.code(), StatusCode::BadRequest);
+    assert_eq!(ServiceError::NotFound.code(), StatusCode::NotFound);
+    assert_eq!(ServiceError::Conflict(Conflicting { id: 1 }).code(), StatusCode::Conflict);
+    assert_eq!(
+        ServiceError::Unauthorized {
+            reason: "Expired".to_string()
+        }
+        .code(),
+        StatusCode::Unauthorized
+    );
+}
+
+#[test]
+fn test_errors_of_no_kind_are_internal() {
+    assert_eq!(Unexpected.code(), ErrorKind::Internal.status());
+    assert_eq!(Unexpected.payload(), None);
+}
+
+#[test]
+fn test_payload_is_serialized_field() {
+    assert_eq!(ServiceError::Conflict(Conflicting { id: 1 }).payload(), Some(json!({ "id": 1 })));
+    assert_eq!(ServiceError::NotFound.payload(), None);
+
+    let forbidden = Forbidden {
+        action: "delete".to_string(),
+    };
+    assert_eq!(forbidden.code(), StatusCode::Forbidden);
+    assert_eq!(forbidden.payload(), Some(json!("delete")));
+}
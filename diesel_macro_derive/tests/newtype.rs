@@ -0,0 +1,51 @@
+extern crate diesel;
+#[macro_use]
+extern crate stq_diesel_macro_derive;
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, StqNewtype)]
+pub struct ProductId(pub i32);
+
+#[derive(Clone, Debug, PartialEq, StqNewtype)]
+pub struct StoreName(pub String);
+
+/// Skipped impls are written by hand, they would conflict with generated ones otherwise
+#[derive(Clone, Debug, PartialEq, StqNewtype)]
+#[stq_newtype(skip_display, skip_from_str, skip_from_inner, skip_diesel)]
+pub struct Code(pub String);
+
+impl From<String> for Code {
+    fn from(v: String) -> Self {
+        Code(v.to_uppercase())
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[test]
+fn test_conversions() {
+    assert_eq!(ProductId::from(1), ProductId(1));
+    assert_eq!(i32::from(ProductId(1)), 1);
+    assert_eq!(StoreName::from("shop".to_string()).as_ref(), "shop");
+    assert_eq!(String::from(StoreName("shop".to_string())), "shop");
+}
+
+#[test]
+fn test_display_and_from_str() {
+    assert_eq!(ProductId(42).to_string(), "42");
+    assert_eq!("42".parse::<ProductId>(), Ok(ProductId(42)));
+    assert!("x".parse::<ProductId>().is_err());
+    assert_eq!("shop".parse::<StoreName>(), Ok(StoreName("shop".to_string())));
+}
+
+#[test]
+fn test_skipped_impls() {
+    let code = Code::from("abc".to_string());
+    assert_eq!(code, Code("ABC".to_string()));
+    assert_eq!(code.to_string(), "#ABC");
+}
@@ -0,0 +1,7 @@
+extern crate trybuild;
+
+#[test]
+fn test_invalid_attributes_fail_to_compile() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,10 @@
+#[macro_use]
+extern crate stq_diesel_macro_derive;
+
+#[derive(DbFilter)]
+pub struct ProductFilter {
+    #[db_filter(colum = "store_id")]
+    pub store: Option<i32>,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+#[macro_use]
+extern crate stq_diesel_macro_derive;
+
+use std::ops::Range;
+
+#[derive(DbInserter)]
+pub struct NewProduct {
+    #[db_inserter(range)]
+    pub price: Range<f64>,
+}
+
+fn main() {}
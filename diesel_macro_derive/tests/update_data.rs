@@ -0,0 +1,82 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate stq_diesel_macro_derive;
+extern crate stq_types;
+
+use stq_types::ValueContainer;
+
+#[derive(Clone, Debug, PartialEq, UpdateData)]
+pub struct Warehouse {
+    pub name: String,
+    #[update_data(column = "store_location")]
+    pub location: Option<String>,
+    #[update_data(skip)]
+    pub created_at: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, UpdateData)]
+#[update_data(name = "ProductPatch")]
+pub struct Product {
+    pub price: f64,
+}
+
+fn warehouse() -> Warehouse {
+    Warehouse {
+        name: "Main".to_string(),
+        location: None,
+        created_at: 1,
+    }
+}
+
+#[test]
+fn test_apply_sets_only_given_fields() {
+    let mut base = warehouse();
+    WarehouseUpdateData {
+        location: Some(ValueContainer::from(Some("Berlin".to_string()))),
+        ..Default::default()
+    }
+    .apply(&mut base);
+
+    assert_eq!(
+        base,
+        Warehouse {
+            location: Some("Berlin".to_string()),
+            ..warehouse()
+        }
+    );
+}
+
+#[test]
+fn test_default_leaves_base_as_is() {
+    let mut base = warehouse();
+    WarehouseUpdateData::default().apply(&mut base);
+    assert_eq!(base, warehouse());
+}
+
+#[test]
+fn test_serialization() {
+    let update = WarehouseUpdateData {
+        name: Some(ValueContainer::from("Main".to_string())),
+        location: Some(ValueContainer::from(None)),
+    };
+    let json = serde_json::to_value(&update).unwrap();
+    assert_eq!(json, json!({ "name": { "value": "Main" }, "location": { "value": null } }));
+
+    let update: WarehouseUpdateData = serde_json::from_str(r#"{ "name": null, "location": { "value": "Berlin" } }"#).unwrap();
+    assert!(update.name.is_none());
+    assert_eq!(update.location, Some(ValueContainer::from(Some("Berlin".to_string()))));
+}
+
+#[test]
+fn test_custom_name() {
+    let mut base = Product { price: 1.0 };
+    ProductPatch {
+        price: Some(ValueContainer::from(2.5)),
+    }
+    .apply(&mut base);
+    assert_eq!(base, Product { price: 2.5 });
+}
@@ -0,0 +1,157 @@
+//! Feature flags, so that rollouts like new delivery methods can be toggled without redeploys.
+//!
+//! Flags are checked for a context, usually built from the request. Unknown flags are disabled.
+#[cfg(feature = "redis")]
+#[macro_use]
+extern crate failure;
+#[cfg(feature = "redis")]
+extern crate r2d2_redis;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate stq_types;
+
+#[cfg(feature = "redis")]
+pub mod redis;
+pub mod static_flags;
+
+pub use static_flags::StaticFlags;
+
+use std::collections::{BTreeMap, HashMap};
+
+use stq_types::UserId;
+
+/// Whom the flag is checked for
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FlagContext {
+    pub user_id: Option<UserId>,
+    /// Other properties flags can target, e.g. store id or country
+    pub attributes: HashMap<String, String>,
+}
+
+impl FlagContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_user_id(mut self, user_id: UserId) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    pub fn with_attribute<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.attributes.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Flag definition. Flag is enabled for a context if any of the conditions holds.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Flag {
+    /// Enabled for everyone
+    pub enabled: bool,
+    pub users: Vec<UserId>,
+    /// Percentage of users, who are chosen by hash of the user id and the flag name,
+    /// so that the same users get the flag as the percentage grows
+    pub percentage: u8,
+    /// Allowed values of context attributes, e.g. `{"store_id": ["1", "2"]}`
+    pub attributes: HashMap<String, Vec<String>>,
+}
+
+impl Flag {
+    pub fn is_enabled_for(&self, name: &str, context: &FlagContext) -> bool {
+        if self.enabled {
+            return true;
+        }
+
+        if let Some(user_id) = context.user_id {
+            if self.users.contains(&user_id) || rollout_bucket(name, user_id) < u32::from(self.percentage) {
+                return true;
+            }
+        }
+
+        self.attributes.iter().any(|(key, allowed)| match context.attributes.get(key) {
+            Some(value) => allowed.contains(value),
+            None => false,
+        })
+    }
+}
+
+/// Bucket from 0 to 99 of the user in the rollout of the flag. FNV-1a is used, so that buckets are stable
+/// across instances and releases.
+fn rollout_bucket(name: &str, user_id: UserId) -> u32 {
+    let hash = format!("{}:{}", name, user_id.0)
+        .bytes()
+        .fold(0x811c_9dc5u32, |hash, byte| (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193));
+    hash % 100
+}
+
+pub trait FeatureFlags: Send + Sync {
+    fn is_enabled(&self, flag: &str, context: &FlagContext) -> bool;
+
+    /// Definitions of all known flags
+    fn flags(&self) -> BTreeMap<String, Flag>;
+}
+
+/// Flags as a JSON object, e.g. for `SystemInfoServiceImpl::with_feature_flags`
+pub fn report(flags: &FeatureFlags) -> serde_json::Value {
+    serde_json::to_value(flags.flags()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag() {
+        let context = FlagContext::new().with_user_id(UserId(1)).with_attribute("store_id", "7");
+
+        assert!(!Flag::default().is_enabled_for("new_delivery", &context));
+        assert!(!Flag::default().is_enabled_for("new_delivery", &FlagContext::new()));
+
+        let flag = Flag {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(flag.is_enabled_for("new_delivery", &FlagContext::new()));
+
+        let flag = Flag {
+            users: vec![UserId(1)],
+            ..Default::default()
+        };
+        assert!(flag.is_enabled_for("new_delivery", &context));
+        assert!(!flag.is_enabled_for("new_delivery", &FlagContext::new().with_user_id(UserId(2))));
+
+        let mut attributes = HashMap::new();
+        attributes.insert("store_id".to_string(), vec!["7".to_string(), "8".to_string()]);
+        let flag = Flag {
+            attributes,
+            ..Default::default()
+        };
+        assert!(flag.is_enabled_for("new_delivery", &context));
+        assert!(!flag.is_enabled_for("new_delivery", &FlagContext::new().with_attribute("store_id", "9")));
+    }
+
+    #[test]
+    fn test_percentage_rollout() {
+        let enabled_users = |percentage| {
+            let flag = Flag {
+                percentage,
+                ..Default::default()
+            };
+            (0..1000)
+                .filter(|id| flag.is_enabled_for("new_delivery", &FlagContext::new().with_user_id(UserId(*id))))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(enabled_users(0).is_empty());
+        assert_eq!(enabled_users(100).len(), 1000);
+
+        let ten_percent = enabled_users(10);
+        assert!(ten_percent.len() > 50 && ten_percent.len() < 150);
+        let twenty_percent = enabled_users(20);
+        assert!(ten_percent.iter().all(|id| twenty_percent.contains(id)));
+    }
+}
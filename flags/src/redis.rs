@@ -0,0 +1,146 @@
+//! Flags stored in Redis, so that they can be changed at runtime for all instances at once.
+//!
+//! Flags live in the `<namespace>:feature_flags` hash, a field per flag holding its JSON definition.
+//! Definitions are cached locally and reloaded once `refresh_interval` passes. If Redis is unavailable,
+//! the last loaded definitions stay in use.
+use r2d2_redis::{
+    r2d2::{ManageConnection, Pool},
+    redis::{cmd, Connection as RedisConnection, RedisError},
+};
+use serde_json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use {FeatureFlags, Flag, FlagContext};
+
+#[derive(Debug, Fail)]
+pub enum RedisFlagsError {
+    #[fail(display = "No available Redis connections left")]
+    NoAvailableConnections,
+    #[fail(display = "{}", _0)]
+    RedisError(RedisError),
+    #[fail(display = "Invalid definition of flag {}: {}", _0, _1)]
+    InvalidFlag(String, String),
+}
+
+impl From<RedisError> for RedisFlagsError {
+    fn from(e: RedisError) -> Self {
+        RedisFlagsError::RedisError(e)
+    }
+}
+
+#[derive(Default)]
+struct Cached {
+    flags: BTreeMap<String, Flag>,
+    refreshed_at: Option<Instant>,
+}
+
+pub struct RedisFlags<M>
+where
+    M: ManageConnection<Connection = RedisConnection>,
+{
+    key: String,
+    pool: Pool<M>,
+    refresh_interval: Duration,
+    cached: RwLock<Cached>,
+}
+
+impl<M> RedisFlags<M>
+where
+    M: ManageConnection<Connection = RedisConnection>,
+{
+    pub fn new(pool: Pool<M>, namespace: String) -> Self {
+        RedisFlags {
+            key: format!("{}:feature_flags", namespace),
+            pool,
+            refresh_interval: Duration::from_secs(10),
+            cached: Default::default(),
+        }
+    }
+
+    pub fn with_refresh_interval(self, refresh_interval: Duration) -> Self {
+        RedisFlags { refresh_interval, ..self }
+    }
+
+    fn using_connection<T, F>(&self, f: F) -> Result<T, RedisFlagsError>
+    where
+        F: Fn(&RedisConnection) -> Result<T, RedisError>,
+    {
+        self.pool
+            .try_get()
+            .map(|conn| f(&conn))
+            .ok_or(RedisFlagsError::NoAvailableConnections)
+            .and_then(|res| res.map_err(From::from))
+    }
+
+    /// Reloads definitions from Redis. Called on demand by `is_enabled`, but services may call it at startup
+    /// to fail early if flags can't be loaded.
+    pub fn refresh(&self) -> Result<(), RedisFlagsError> {
+        let res = self
+            .using_connection(|conn| cmd("HGETALL").arg(&self.key).query::<HashMap<String, String>>(conn))
+            .and_then(|definitions| {
+                definitions
+                    .into_iter()
+                    .map(|(name, definition)| match serde_json::from_str(&definition) {
+                        Ok(flag) => Ok((name, flag)),
+                        Err(e) => Err(RedisFlagsError::InvalidFlag(name, e.to_string())),
+                    })
+                    .collect::<Result<BTreeMap<_, _>, _>>()
+            });
+
+        // Failed refresh is not retried before the interval passes, so that an unavailable Redis
+        // is not queried on every check
+        let mut cached = self.cached.write().unwrap();
+        cached.refreshed_at = Some(Instant::now());
+        res.map(|flags| cached.flags = flags)
+    }
+
+    pub fn set_flag(&self, name: &str, flag: &Flag) -> Result<(), RedisFlagsError> {
+        let definition = serde_json::to_string(flag).map_err(|e| RedisFlagsError::InvalidFlag(name.to_string(), e.to_string()))?;
+
+        self.using_connection(|conn| cmd("HSET").arg(&self.key).arg(name).arg(&definition).query::<()>(conn))?;
+        self.cached.write().unwrap().flags.insert(name.to_string(), flag.clone());
+        Ok(())
+    }
+
+    pub fn remove_flag(&self, name: &str) -> Result<bool, RedisFlagsError> {
+        let removed = self.using_connection(|conn| cmd("HDEL").arg(&self.key).arg(name).query::<u32>(conn))?;
+        self.cached.write().unwrap().flags.remove(name);
+        Ok(removed > 0)
+    }
+
+    fn refresh_if_stale(&self) {
+        let stale = match self.cached.read().unwrap().refreshed_at {
+            Some(refreshed_at) => refreshed_at.elapsed() >= self.refresh_interval,
+            None => true,
+        };
+
+        if stale {
+            let _ = self.refresh();
+        }
+    }
+}
+
+impl<M> FeatureFlags for RedisFlags<M>
+where
+    M: ManageConnection<Connection = RedisConnection>,
+{
+    fn is_enabled(&self, flag: &str, context: &FlagContext) -> bool {
+        self.refresh_if_stale();
+
+        self.cached
+            .read()
+            .unwrap()
+            .flags
+            .get(flag)
+            .map(|def| def.is_enabled_for(flag, context))
+            .unwrap_or(false)
+    }
+
+    fn flags(&self) -> BTreeMap<String, Flag> {
+        self.refresh_if_stale();
+
+        self.cached.read().unwrap().flags.clone()
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::{BTreeMap, HashMap};
+
+use {FeatureFlags, Flag, FlagContext};
+
+/// Flags fixed at startup, usually deserialized from the service config
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StaticFlags(BTreeMap<String, Flag>);
+
+impl StaticFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_flag<S: Into<String>>(mut self, name: S, flag: Flag) -> Self {
+        self.0.insert(name.into(), flag);
+        self
+    }
+}
+
+impl From<HashMap<String, Flag>> for StaticFlags {
+    fn from(flags: HashMap<String, Flag>) -> Self {
+        StaticFlags(flags.into_iter().collect())
+    }
+}
+
+impl FeatureFlags for StaticFlags {
+    fn is_enabled(&self, flag: &str, context: &FlagContext) -> bool {
+        self.0.get(flag).map(|def| def.is_enabled_for(flag, context)).unwrap_or(false)
+    }
+
+    fn flags(&self) -> BTreeMap<String, Flag> {
+        self.0.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json;
+
+    use report;
+
+    #[test]
+    fn test_static_flags() {
+        let flags: StaticFlags = serde_json::from_str(r#"{"new_delivery": {"enabled": true}, "old_checkout": {}}"#).unwrap();
+
+        assert!(flags.is_enabled("new_delivery", &FlagContext::new()));
+        assert!(!flags.is_enabled("old_checkout", &FlagContext::new()));
+        assert!(!flags.is_enabled("unknown", &FlagContext::new()));
+
+        let flags = flags.with_flag("old_checkout", Flag::default());
+        assert_eq!(report(&flags)["new_delivery"]["enabled"], serde_json::Value::Bool(true));
+        assert_eq!(report(&flags)["old_checkout"]["percentage"], serde_json::Value::from(0));
+    }
+}
@@ -0,0 +1,155 @@
+use futures::future;
+use futures::Future;
+use hyper;
+use hyper::header::{ContentType, Header, Headers};
+use serde::de::Deserialize;
+use serde::ser::Serialize;
+use serde_json;
+
+use super::{Error, HttpClient, Response};
+
+/// Request built step by step, see `HttpClient::request_builder`
+pub struct HttpRequestBuilder<'c, C: 'c> {
+    client: &'c C,
+    method: hyper::Method,
+    url: String,
+    body: Option<String>,
+    headers: Headers,
+    /// Error of building the request, reported once it is sent so that calls can be chained
+    error: Option<Error>,
+}
+
+impl<'c, C: HttpClient> HttpRequestBuilder<'c, C> {
+    pub fn new<U: Into<String>>(client: &'c C, method: hyper::Method, url: U) -> Self {
+        Self {
+            client,
+            method,
+            url: url.into(),
+            body: None,
+            headers: Headers::new(),
+            error: None,
+        }
+    }
+
+    /// Set the header, replacing the one of the same type
+    pub fn header<H: Header>(mut self, header: H) -> Self {
+        self.headers.set(header);
+        self
+    }
+
+    /// Set all the headers, replacing the ones with the same names
+    pub fn headers(mut self, headers: Headers) -> Self {
+        self.headers.extend(headers.iter());
+        self
+    }
+
+    pub fn body<B: Into<String>>(mut self, body: B) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Set the body to the payload serialized to JSON, along with `Content-Type: application/json`
+    pub fn json<T: Serialize + ?Sized>(mut self, payload: &T) -> Self {
+        match serde_json::to_string(payload) {
+            Ok(body) => {
+                self.body = Some(body);
+                self.headers.set(ContentType::json());
+            }
+            Err(e) => self.error = Some(Error::Parse(format!("Failed to serialize request body: {}", e))),
+        }
+        self
+    }
+
+    /// Sends the request, responses with non-2xx status fail with `Error::Api`
+    pub fn send(self) -> Box<Future<Item = Response, Error = Error> + Send> {
+        match self.error {
+            Some(e) => Box::new(future::err(e)),
+            None => self.client.request(self.method, self.url, self.body, Some(self.headers)),
+        }
+    }
+
+    /// Sends the request, responses of any status are returned
+    pub fn send_full(self) -> Box<Future<Item = Response, Error = Error> + Send> {
+        match self.error {
+            Some(e) => Box::new(future::err(e)),
+            None => self.client.request_full(self.method, self.url, self.body, Some(self.headers)),
+        }
+    }
+
+    /// Sends the request and parses the response body as JSON
+    pub fn send_json<T>(self) -> Box<Future<Item = T, Error = Error> + Send>
+    where
+        T: for<'a> Deserialize<'a> + 'static + Send,
+    {
+        Box::new(self.send().and_then(|response| response.json()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use hyper::header::Authorization;
+    use hyper::Method;
+
+    use super::*;
+
+    type SentRequest = (hyper::Method, String, Option<String>, Option<Headers>);
+
+    #[derive(Clone, Default)]
+    struct MockHttpClient {
+        requests: Arc<Mutex<Vec<SentRequest>>>,
+    }
+
+    impl HttpClient for MockHttpClient {
+        fn request(
+            &self,
+            method: hyper::Method,
+            url: String,
+            body: Option<String>,
+            headers: Option<Headers>,
+        ) -> Box<Future<Item = Response, Error = Error> + Send> {
+            let response = Response::from(body.clone().unwrap_or_default());
+            self.requests.lock().unwrap().push((method, url, body, headers));
+            Box::new(future::ok(response))
+        }
+    }
+
+    #[test]
+    fn test_request_builder() {
+        let client = MockHttpClient::default();
+        let mut payload = HashMap::new();
+        payload.insert("name", "Alice");
+
+        let echo = client
+            .post("http://users/users")
+            .json(&payload)
+            .header(Authorization("1".to_string()))
+            .send_json::<HashMap<String, String>>()
+            .wait()
+            .unwrap();
+        assert_eq!(echo.get("name").map(String::as_str), Some("Alice"));
+
+        let (method, url, body, headers) = client.requests.lock().unwrap().pop().unwrap();
+        let headers = headers.unwrap();
+        assert_eq!(method, Method::Post);
+        assert_eq!(url, "http://users/users");
+        assert_eq!(body, Some(r#"{"name":"Alice"}"#.to_string()));
+        assert_eq!(headers.get::<ContentType>(), Some(&ContentType::json()));
+        assert_eq!(headers.get::<Authorization<String>>(), Some(&Authorization("1".to_string())));
+
+        let empty = client.get("http://users/users/1").send_json::<Option<String>>().wait().unwrap();
+        assert_eq!(empty, None);
+    }
+
+    #[test]
+    fn test_request_builder_with_invalid_json() {
+        let client = MockHttpClient::default();
+        let mut payload = HashMap::new();
+        payload.insert(vec![1], 1);
+
+        assert!(client.put("http://users/users/1").json(&payload).send().wait().is_err());
+        assert!(client.requests.lock().unwrap().is_empty());
+    }
+}
@@ -1,6 +1,8 @@
+pub mod builder;
 pub mod time_limited;
 pub mod with_headers;
 
+pub use self::builder::*;
 pub use self::time_limited::*;
 pub use self::with_headers::*;
 
@@ -14,6 +16,7 @@ use futures::prelude::*;
 use futures::sync::{mpsc, oneshot};
 use hyper;
 use hyper::header::{Authorization, Headers};
+use hyper::StatusCode;
 use hyper_tls::HttpsConnector;
 use juniper::FieldError;
 use serde::de::Deserialize;
@@ -24,10 +27,73 @@ use tokio_core::reactor::Handle;
 use errors::ErrorMessage;
 use request_util::read_body;
 
+/// Response of a downstream service along with its status and headers, e.g. `Retry-After`, `Link` or rate limit ones
 #[derive(Clone, Debug)]
-pub struct Response(String);
+pub struct Response {
+    status: StatusCode,
+    headers: Headers,
+    body: String,
+}
+
+impl Response {
+    pub fn new(status: StatusCode, headers: Headers, body: String) -> Self {
+        Self { status, headers, body }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    pub fn into_body(self) -> String {
+        self.body
+    }
+
+    /// Parses the body as JSON, empty body is parsed as `null`
+    pub fn json<T>(&self) -> Result<T, Error>
+    where
+        T: for<'a> Deserialize<'a>,
+    {
+        if self.body.is_empty() {
+            serde_json::from_value(serde_json::Value::Null)
+        } else {
+            serde_json::from_str::<T>(&self.body)
+        }
+        .map_err(|e| Error::Parse(e.to_string()))
+    }
+
+    /// Turns responses with non-2xx status into `Error::Api`, parsing `ErrorMessage` from the body
+    pub fn error_for_status(self) -> Result<Self, Error> {
+        if self.status.is_success() {
+            return Ok(self);
+        }
+
+        let Response { status, body, .. } = self;
+        let message = serde_json::from_str::<ErrorMessage>(&body).unwrap_or_else(|_| ErrorMessage {
+            code: 422,
+            description: body,
+            payload: None,
+        });
+        Err(Error::Api(status, Some(message)))
+    }
+}
+
+/// Successful response with the body and no headers, e.g. for mock clients
+impl From<String> for Response {
+    fn from(body: String) -> Self {
+        Self::new(StatusCode::Ok, Headers::new(), body)
+    }
+}
 
 pub trait HttpClient: Send + Sync + 'static {
+    /// Sends the request, responses with non-2xx status fail with `Error::Api`
     fn request(
         &self,
         method: hyper::Method,
@@ -36,6 +102,18 @@ pub trait HttpClient: Send + Sync + 'static {
         headers: Option<Headers>,
     ) -> Box<Future<Item = Response, Error = Error> + Send>;
 
+    /// Sends the request, responses of any status are returned, e.g. to honor `Retry-After` of 429 and 503 ones.
+    /// Default implementation is `request`, so implementors should override it.
+    fn request_full(
+        &self,
+        method: hyper::Method,
+        url: String,
+        body: Option<String>,
+        headers: Option<Headers>,
+    ) -> Box<Future<Item = Response, Error = Error> + Send> {
+        self.request(method, url, body, headers)
+    }
+
     fn request_json<T>(
         &self,
         method: hyper::Method,
@@ -47,21 +125,59 @@ pub trait HttpClient: Send + Sync + 'static {
         T: for<'a> Deserialize<'a> + 'static + Send,
         Self: Sized,
     {
-        Box::new(self.request(method, url, body, headers).and_then(|response| {
-            if response.0.is_empty() {
-                serde_json::from_value(serde_json::Value::Null)
-            } else {
-                serde_json::from_str::<T>(&response.0)
-            }
-            .map_err(|e| Error::Parse(e.to_string()))
-        }))
+        Box::new(self.request(method, url, body, headers).and_then(|response| response.json()))
+    }
+
+    /// Builds a request instead of listing its parts, e.g. `client.post(url).json(&payload).send_json::<T>()`
+    fn request_builder<U: Into<String>>(&self, method: hyper::Method, url: U) -> HttpRequestBuilder<'_, Self>
+    where
+        Self: Sized,
+    {
+        HttpRequestBuilder::new(self, method, url)
+    }
+
+    fn get<U: Into<String>>(&self, url: U) -> HttpRequestBuilder<'_, Self>
+    where
+        Self: Sized,
+    {
+        self.request_builder(hyper::Method::Get, url)
+    }
+
+    fn post<U: Into<String>>(&self, url: U) -> HttpRequestBuilder<'_, Self>
+    where
+        Self: Sized,
+    {
+        self.request_builder(hyper::Method::Post, url)
+    }
+
+    fn put<U: Into<String>>(&self, url: U) -> HttpRequestBuilder<'_, Self>
+    where
+        Self: Sized,
+    {
+        self.request_builder(hyper::Method::Put, url)
+    }
+
+    fn patch<U: Into<String>>(&self, url: U) -> HttpRequestBuilder<'_, Self>
+    where
+        Self: Sized,
+    {
+        self.request_builder(hyper::Method::Patch, url)
+    }
+
+    fn delete<U: Into<String>>(&self, url: U) -> HttpRequestBuilder<'_, Self>
+    where
+        Self: Sized,
+    {
+        self.request_builder(hyper::Method::Delete, url)
     }
 }
 
-pub type ClientResult = Result<String, Error>;
+pub type ClientResult = Result<Response, Error>;
 
 pub type HyperClient = hyper::Client<HttpsConnector<hyper::client::HttpConnector>>;
 
+/// Config of `Client`, usually a section of the service config
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Config {
     pub http_client_retries: usize,
     pub http_client_buffer_size: usize,
@@ -184,25 +300,10 @@ impl Client {
         let work_with_timeout = work
             .and_then(move |res| {
                 let status = res.status();
-                let body_future: Box<Future<Item = String, Error = Error>> = Box::new(read_body(res.body()).map_err(Error::Network));
-                match status.as_u16() {
-                    200...299 => body_future,
-
-                    _ => Box::new(body_future.and_then(move |body| {
-                        let message = serde_json::from_str::<ErrorMessage>(&body).ok();
-                        let error = Error::Api(
-                            status,
-                            message.or_else(|| {
-                                Some(ErrorMessage {
-                                    code: 422,
-                                    description: body,
-                                    payload: None,
-                                })
-                            }),
-                        );
-                        future::err(error)
-                    })),
-                }
+                let headers = res.headers().clone();
+                read_body(res.body())
+                    .map_err(Error::Network)
+                    .map(move |body| Response::new(status, headers, body))
             })
             .then(|result| callback.send(result))
             .map(|_| ())
@@ -267,7 +368,28 @@ impl ClientHandle {
         body: Option<String>,
         headers: Option<Headers>,
     ) -> Box<Future<Item = String, Error = Error> + Send> {
-        Box::new(self.send_request_with_retries(method, url, body, headers, None, self.max_retries))
+        Box::new(self.checked_request(method, url, body, headers).map(Response::into_body))
+    }
+
+    fn checked_request(
+        &self,
+        method: hyper::Method,
+        url: String,
+        body: Option<String>,
+        headers: Option<Headers>,
+    ) -> Box<Future<Item = Response, Error = Error> + Send> {
+        let url_clone = url.clone();
+        let method_clone = method.clone();
+
+        Box::new(
+            self.send_request_with_retries(method, url, body, headers, None, self.max_retries)
+                .and_then(move |response| {
+                    response.error_for_status().map_err(|err| {
+                        error!("{} {} : {}", method_clone, url_clone, err);
+                        err
+                    })
+                }),
+        )
     }
 
     fn send_request_with_retries(
@@ -278,7 +400,7 @@ impl ClientHandle {
         headers: Option<Headers>,
         last_err: Option<Error>,
         retries: usize,
-    ) -> Box<Future<Item = String, Error = Error> + Send> {
+    ) -> Box<Future<Item = Response, Error = Error> + Send> {
         if retries == 0 {
             let error = last_err.unwrap_or_else(|| Error::Unknown("Unexpected missing error in send_request_with_retries".to_string()));
             Box::new(future::err(error))
@@ -314,7 +436,7 @@ impl ClientHandle {
         url: String,
         body: Option<String>,
         headers: Option<hyper::Headers>,
-    ) -> Box<Future<Item = String, Error = Error> + Send> {
+    ) -> Box<Future<Item = Response, Error = Error> + Send> {
         debug!(
             "Starting outbound http request: {} {} with body {} and headers {}",
             method,
@@ -360,7 +482,17 @@ impl HttpClient for ClientHandle {
         body: Option<String>,
         headers: Option<Headers>,
     ) -> Box<Future<Item = Response, Error = Error> + Send> {
-        Box::new(self.simple_request(method, url, body, headers).map(Response))
+        self.checked_request(method, url, body, headers)
+    }
+
+    fn request_full(
+        &self,
+        method: hyper::Method,
+        url: String,
+        body: Option<String>,
+        headers: Option<Headers>,
+    ) -> Box<Future<Item = Response, Error = Error> + Send> {
+        Box::new(self.send_request_with_retries(method, url, body, headers, None, self.max_retries))
     }
 }
 
@@ -374,6 +506,16 @@ impl<T: HttpClient> HttpClient for Box<T> {
     ) -> Box<Future<Item = Response, Error = Error> + Send> {
         (**self).request(method, url, body, headers)
     }
+
+    fn request_full(
+        &self,
+        method: hyper::Method,
+        url: String,
+        body: Option<String>,
+        headers: Option<Headers>,
+    ) -> Box<Future<Item = Response, Error = Error> + Send> {
+        (**self).request_full(method, url, body, headers)
+    }
 }
 
 struct Payload {
@@ -453,3 +595,29 @@ impl Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::RetryAfter;
+
+    #[test]
+    fn test_error_for_status() {
+        let mut headers = Headers::new();
+        headers.set(RetryAfter::Delay(Duration::from_secs(5)));
+        let response = Response::new(StatusCode::ServiceUnavailable, headers, "Overloaded".to_string());
+
+        assert_eq!(
+            response.headers().get::<RetryAfter>(),
+            Some(&RetryAfter::Delay(Duration::from_secs(5)))
+        );
+        match response.error_for_status() {
+            Err(Error::Api(StatusCode::ServiceUnavailable, Some(message))) => assert_eq!(message.description, "Overloaded"),
+            res => panic!("Unexpected result {:?}", res),
+        }
+
+        let response = Response::from("{}".to_string());
+        assert_eq!(response.error_for_status().unwrap().body(), "{}");
+    }
+}
@@ -5,7 +5,7 @@ use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use super::{Error, HttpClient, Response};
-use request_util::RequestTimeout as RequestTimeoutHeader;
+use request_util::{get_request_timeout, RequestTimeout as RequestTimeoutHeader};
 
 #[derive(Clone)]
 pub struct TimeLimitedHttpClient<S: HttpClient> {
@@ -22,16 +22,24 @@ impl<S: HttpClient> TimeLimitedHttpClient<S> {
             time_left: Arc::new(Mutex::new(time_limit)),
         }
     }
-}
 
-impl<S: HttpClient> HttpClient for TimeLimitedHttpClient<S> {
-    fn request(
+    /// Client limited by what is left of the inbound request budget, so that outbound requests
+    /// don't outlive the caller. Requests without `Request-timeout` header get `default_time_limit`.
+    pub fn for_request(client: S, req: &hyper::Request, default_time_limit: Duration) -> Self {
+        Self::new(client, get_request_timeout(req).unwrap_or(default_time_limit))
+    }
+
+    fn limited_request<F>(
         &self,
         method: hyper::Method,
         url: String,
         body: Option<String>,
         headers: Option<Headers>,
-    ) -> Box<Future<Item = Response, Error = Error> + Send> {
+        send: F,
+    ) -> Box<Future<Item = Response, Error = Error> + Send>
+    where
+        F: FnOnce(&S, hyper::Method, String, Option<String>, Option<Headers>) -> Box<Future<Item = Response, Error = Error> + Send>,
+    {
         let time_left_mutex = self.time_left.clone();
         let time_left_before_request = { *time_left_mutex.lock().unwrap() };
         let time_left_before_request_ms = time_left_before_request.as_secs() as u32 * 1000 + time_left_before_request.subsec_millis();
@@ -49,37 +57,56 @@ impl<S: HttpClient> HttpClient for TimeLimitedHttpClient<S> {
         );
 
         let start_time = Instant::now();
-        let request = self
-            .inner
-            .request(method.clone(), url.clone(), body, Some(headers))
-            .map(move |response| {
-                // time_left can be updated by a cloned client on another thread (parallel requests)
-                // so we calculate the minimum of the current time_left of the client
-                // and the time_left that was calculated for this request
-
-                let elapsed_time = Instant::now() - start_time;
-                let time_left_after_request = time_left_before_request.checked_sub(elapsed_time).unwrap_or(Duration::new(0, 0));
-                let new_time_left = {
-                    let mut time_left_current = time_left_mutex.lock().unwrap();
-                    let new_time_left = cmp::min(*time_left_current, time_left_after_request);
-                    *time_left_current = new_time_left;
-                    new_time_left
-                };
-
-                let elapsed_time_ms = elapsed_time.as_secs() as u32 * 1000 + elapsed_time.subsec_millis();
-                let new_time_left_ms = new_time_left.as_secs() as u32 * 1000 + new_time_left.subsec_millis();
-                debug!(
-                    "Got response for {} {}. Elapsed time (ms): {}. Time remaining for client (ms): {}",
-                    &method, &url, elapsed_time_ms, new_time_left_ms,
-                );
-
-                response
-            });
+        let request = send(&self.inner, method.clone(), url.clone(), body, Some(headers)).map(move |response| {
+            // time_left can be updated by a cloned client on another thread (parallel requests)
+            // so we calculate the minimum of the current time_left of the client
+            // and the time_left that was calculated for this request
+
+            let elapsed_time = Instant::now() - start_time;
+            let time_left_after_request = time_left_before_request.checked_sub(elapsed_time).unwrap_or(Duration::new(0, 0));
+            let new_time_left = {
+                let mut time_left_current = time_left_mutex.lock().unwrap();
+                let new_time_left = cmp::min(*time_left_current, time_left_after_request);
+                *time_left_current = new_time_left;
+                new_time_left
+            };
+
+            let elapsed_time_ms = elapsed_time.as_secs() as u32 * 1000 + elapsed_time.subsec_millis();
+            let new_time_left_ms = new_time_left.as_secs() as u32 * 1000 + new_time_left.subsec_millis();
+            debug!(
+                "Got response for {} {}. Elapsed time (ms): {}. Time remaining for client (ms): {}",
+                &method, &url, elapsed_time_ms, new_time_left_ms,
+            );
+
+            response
+        });
 
         Box::new(request)
     }
 }
 
+impl<S: HttpClient> HttpClient for TimeLimitedHttpClient<S> {
+    fn request(
+        &self,
+        method: hyper::Method,
+        url: String,
+        body: Option<String>,
+        headers: Option<Headers>,
+    ) -> Box<Future<Item = Response, Error = Error> + Send> {
+        self.limited_request(method, url, body, headers, S::request)
+    }
+
+    fn request_full(
+        &self,
+        method: hyper::Method,
+        url: String,
+        body: Option<String>,
+        headers: Option<Headers>,
+    ) -> Box<Future<Item = Response, Error = Error> + Send> {
+        self.limited_request(method, url, body, headers, S::request_full)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::VecDeque;
@@ -254,7 +281,7 @@ mod tests {
                             body,
                             headers,
                         });
-                        Response(String::new())
+                        Response::from(String::new())
                     }),
             )
         }
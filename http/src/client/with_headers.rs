@@ -17,6 +17,16 @@ impl<S: HttpClient> HttpClientWithDefaultHeaders<S> {
             headers: Arc::new(Mutex::new(headers)),
         }
     }
+
+    fn merged_headers(&self, headers: Option<Headers>) -> Headers {
+        let mut existing_headers = (*self.headers.lock().unwrap()).clone();
+
+        if let Some(headers) = headers {
+            existing_headers.extend(headers.iter());
+        };
+
+        existing_headers
+    }
 }
 
 impl<S: HttpClient> HttpClient for HttpClientWithDefaultHeaders<S> {
@@ -27,15 +37,17 @@ impl<S: HttpClient> HttpClient for HttpClientWithDefaultHeaders<S> {
         body: Option<String>,
         headers: Option<Headers>,
     ) -> Box<Future<Item = Response, Error = Error> + Send> {
-        let self_headers = self.headers.clone();
-        let mut existing_headers = (*self_headers.lock().unwrap()).clone();
-
-        if let Some(headers) = headers {
-            existing_headers.extend(headers.iter());
-        };
+        self.inner.request(method, url, body, Some(self.merged_headers(headers)))
+    }
 
-        let request = self.inner.request(method, url, body, Some(existing_headers));
-        Box::new(request)
+    fn request_full(
+        &self,
+        method: hyper::Method,
+        url: String,
+        body: Option<String>,
+        headers: Option<Headers>,
+    ) -> Box<Future<Item = Response, Error = Error> + Send> {
+        self.inner.request_full(method, url, body, Some(self.merged_headers(headers)))
     }
 }
 
@@ -130,7 +142,7 @@ mod tests {
                 headers,
             });
 
-            Box::new(future::ok(Response(String::new())))
+            Box::new(future::ok(Response::from(String::new())))
         }
     }
 }
@@ -0,0 +1,125 @@
+//! Services built for each request, so that controllers get clients and repos already set up for the caller,
+//! e.g. `TimeLimitedHttpClient::for_request`, a DB pool handle and `RepoLogin` of the user, instead of every
+//! service wiring them in its controller.
+//!
+//! Factory gets the request once `Application` has set its time budget, so that the budget of clients built
+//! from `Request-timeout` header doesn't include time spent before the controller is called.
+use std::rc::Rc;
+
+use failure;
+use futures::prelude::*;
+use hyper::Request;
+
+use controller::{Controller, ControllerFuture};
+
+pub type ContextFuture<C> = Box<Future<Item = C, Error = failure::Error>>;
+
+/// Builds services for a request. Implemented for closures `Fn(&Request) -> impl IntoFuture<Item = C>`.
+pub trait ServiceFactory {
+    type Context;
+
+    fn create(&self, request: &Request) -> ContextFuture<Self::Context>;
+}
+
+impl<F, R> ServiceFactory for F
+where
+    F: Fn(&Request) -> R,
+    R: IntoFuture<Error = failure::Error>,
+    R::Future: 'static,
+{
+    type Context = R::Item;
+
+    fn create(&self, request: &Request) -> ContextFuture<R::Item> {
+        Box::new(self(request).into_future())
+    }
+}
+
+/// Controller getting services built for the request
+pub trait ContextController {
+    type Context;
+
+    fn call(&self, request: Request, context: Self::Context) -> ControllerFuture;
+}
+
+/// Controller building context with the factory before calling the inner controller.
+/// Failures of the factory are responded the same way as failures of the controller.
+pub struct ScopedController<F, C> {
+    factory: F,
+    controller: Rc<C>,
+}
+
+impl<F, C> ScopedController<F, C>
+where
+    F: ServiceFactory<Context = C::Context>,
+    C: ContextController,
+{
+    pub fn new(factory: F, controller: C) -> Self {
+        Self {
+            factory,
+            controller: Rc::new(controller),
+        }
+    }
+}
+
+impl<F, C> Controller for ScopedController<F, C>
+where
+    F: ServiceFactory<Context = C::Context>,
+    C: ContextController + 'static,
+{
+    fn call(&self, request: Request) -> ControllerFuture {
+        let controller = self.controller.clone();
+        Box::new(
+            self.factory
+                .create(&request)
+                .and_then(move |context| controller.call(request, context)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::Authorization;
+    use hyper::Method;
+
+    struct Context {
+        user_id: Option<String>,
+    }
+
+    struct EchoController;
+
+    impl ContextController for EchoController {
+        type Context = Context;
+
+        fn call(&self, request: Request, context: Context) -> ControllerFuture {
+            Box::new(Ok(format!("{} {:?}", request.path(), context.user_id)).into_future())
+        }
+    }
+
+    #[test]
+    fn test_scoped_controller() {
+        let controller = ScopedController::new(
+            |req: &Request| -> Result<Context, failure::Error> {
+                match req.headers().get::<Authorization<String>>() {
+                    Some(auth) if auth.0.is_empty() => Err(format_err!("Invalid user id")),
+                    auth => Ok(Context {
+                        user_id: auth.map(|auth| auth.0.clone()),
+                    }),
+                }
+            },
+            EchoController,
+        );
+
+        let mut req = Request::new(Method::Get, "/users".parse().unwrap());
+        req.headers_mut().set(Authorization("42".to_string()));
+        assert_eq!(Controller::call(&controller, req).wait().unwrap(), "/users Some(\"42\")");
+
+        let req = Request::new(Method::Get, "/users".parse().unwrap());
+        assert_eq!(Controller::call(&controller, req).wait().unwrap(), "/users None");
+
+        let mut req = Request::new(Method::Get, "/users".parse().unwrap());
+        req.headers_mut().set(Authorization(String::new()));
+        assert!(Controller::call(&controller, req).wait().is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use std;
 use std::str;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use chrono::prelude::*;
 use failure;
@@ -8,18 +9,27 @@ use failure::Fail;
 use futures::future::{self, Either};
 use futures::prelude::*;
 use hyper;
-use hyper::header::{AccessControlAllowHeaders, AccessControlAllowMethods, AccessControlRequestHeaders, ContentLength, ContentType};
+use hyper::header::{
+    AccessControlAllowHeaders, AccessControlAllowMethods, AccessControlRequestHeaders, ContentLength, ContentType, RetryAfter,
+};
 use hyper::server::Service;
 use hyper::Method::{Get, Options, Post};
 use hyper::{mime, Error, Headers, StatusCode};
 use hyper::{Request, Response};
 use serde_json;
+use tokio_core::reactor::{Handle, Timeout};
 
 use log::{self, Level};
 
-use request_util::{get_correlation_token, try_read_body};
+use request_util::{
+    get_api_version, get_correlation_token, get_request_timeout, split_version_prefix, try_read_body, AcceptVersion, RequestTimeout,
+};
 
+use context::{ContextController, ScopedController, ServiceFactory};
 use errors::*;
+use idempotency::{self, IdempotencyRecord, IdempotencyStore, IdempotentReplayed};
+use logging::{LogContext, WithLogContext};
+use rate_limit::RateLimiter;
 use system::{SystemService, SystemServiceImpl};
 
 pub type ControllerFuture = Box<Future<Item = String, Error = failure::Error>>;
@@ -27,6 +37,12 @@ pub type ControllerFuture = Box<Future<Item = String, Error = failure::Error>>;
 /// The meat of your application. Best used with RouteParser in utils.
 pub trait Controller {
     fn call(&self, request: Request) -> ControllerFuture;
+
+    /// Called instead of `call` by applications with API versioning enabled.
+    /// Version prefix is already stripped from the request path.
+    fn call_versioned(&self, request: Request, _version: u32) -> ControllerFuture {
+        self.call(request)
+    }
 }
 
 pub type ServerFuture = Box<Future<Item = Response, Error = hyper::Error>>;
@@ -36,6 +52,13 @@ pub struct Application<E: Fail + Codeable + PayloadCarrier> {
     pub controller: Arc<dyn Controller>,
     pub system_service: Box<SystemService>,
     pub middleware: Arc<Fn(Response) -> Response>,
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// API version of requests without version prefix and `Accept-Version` header, `None` if versioning is disabled
+    pub default_api_version: Option<u32>,
+    /// Handle to run request deadline timers on, `None` if deadlines are not enforced
+    pub deadline_handle: Option<Handle>,
+    /// Store of responses to requests with `Idempotency-Key` header, `None` if the header is ignored
+    pub idempotency_store: Option<Arc<IdempotencyStore>>,
     _error_type: std::marker::PhantomData<E>,
 }
 
@@ -50,6 +73,7 @@ where
 
     fn call(&self, req: Request) -> ServerFuture {
         let call_start = Local::now();
+        let call_started = Instant::now();
 
         let correlation_token = get_correlation_token(&req);
 
@@ -73,60 +97,84 @@ where
                 _ => {
                     let token = correlation_token.clone();
 
-                    Box::new(
-                        match req.uri().path() {
-                            "/healthcheck" => {
-                                Box::new(self.system_service.healthcheck().then(|res| {
-                                    let response = match res {
-                                        Ok(data) => Self::response_with_json(data.clone()),
-                                        Err(err) => Self::response_with_error(&err),
-                                    };
-
-                                    future::ok(response)
-                                })) as ServerFuture
-                            },
-                            _ => {
-                                let controller = self.controller.clone();
-                                let level = log::max_level();
-
-                                let fut = if level == Level::Debug || level == Level::Trace {
-                                    let (method, uri, http_version, headers, body) = req.deconstruct();
-                                    Either::A(
-                                        try_read_body(body)
-                                            .map_err(From::from)
-                                            .and_then(move |bytes| {
-                                                {
-                                                    let body_log = match str::from_utf8(&bytes) {
-                                                        Ok(data) => data,
-                                                        Err(_) => "`can not parse body to string`",
-                                                    };
-                                                    debug!(
-                                                        "Server received Request, method: {}, url: {}, headers: {:#?}, body: {}, correlation token: {}",
-                                                        method, uri, headers, body_log, token
-                                                    );
-                                                }
-
-                                                let mut req = Request::new(method, uri);
-                                                req.set_body(bytes);
-                                                req.set_version(http_version);
-                                                std::mem::replace(req.headers_mut(), headers);
-
-                                                Ok(req)
-                                            }).and_then(move |req| controller.call(req)),
-                                    )
-                                } else {
-                                    Either::B(self.controller.call(req))
-                                };
+                    let retry_after = match self.rate_limiter {
+                        Some(ref rate_limiter) if req.uri().path() != "/healthcheck" => rate_limiter.check(&req).err(),
+                        _ => None,
+                    };
 
-                                Box::new(fut.then({
-                                let token = correlation_token.clone();
+                    let idempotency = match (&self.idempotency_store, retry_after) {
+                        (Some(store), None) => idempotency::request_key(&req).map(|key| (store.clone(), key)),
+                        _ => None,
+                    };
 
-                                move |res| {
+                    let serve = {
+                        let controller = self.controller.clone();
+                        let default_api_version = self.default_api_version;
+                        let deadline_handle = self.deadline_handle.clone();
+                        let correlation_token = correlation_token.clone();
+
+                        move |req: Request, idempotency: Option<(Arc<IdempotencyStore>, String, String)>| {
+                            let token = correlation_token.clone();
+                            let (req, version) = match default_api_version {
+                                Some(default_version) => {
+                                    let (req, version) = Self::resolve_api_version(req, default_version);
+                                    (req, Some(version))
+                                }
+                                None => (req, None),
+                            };
+                            let deadline = match deadline_handle {
+                                Some(ref handle) => get_request_timeout(&req).map(|timeout| (handle.clone(), timeout)),
+                                None => None,
+                            };
+                            let deadline_at = deadline.as_ref().map(|(_, timeout)| call_started + *timeout);
+                            let log_context = LogContext::from_request(&req, correlation_token.clone());
+                            let level = log::max_level();
+
+                            let fut = if level == Level::Debug || level == Level::Trace {
+                                let (method, uri, http_version, headers, body) = req.deconstruct();
+                                Either::A(
+                                    try_read_body(body)
+                                        .map_err(From::from)
+                                        .and_then(move |bytes| {
+                                            {
+                                                let body_log = match str::from_utf8(&bytes) {
+                                                    Ok(data) => data,
+                                                    Err(_) => "`can not parse body to string`",
+                                                };
+                                                debug!(
+                                                    "Server received Request, method: {}, url: {}, headers: {:#?}, body: {}, correlation token: {}",
+                                                    method, uri, headers, body_log, token
+                                                );
+                                            }
+
+                                            let mut req = Request::new(method, uri);
+                                            req.set_body(bytes);
+                                            req.set_version(http_version);
+                                            std::mem::replace(req.headers_mut(), headers);
+
+                                            Ok(req)
+                                        }).and_then(move |req| Self::call_controller(&*controller, req, version, deadline_at)),
+                                )
+                            } else {
+                                // Called lazily, so that the controller logs with the request context
+                                Either::B(future::lazy(move || Self::call_controller(&*controller, req, version, deadline_at)))
+                            };
+                            let fut = WithLogContext::new(log_context, fut);
+                            let fut = match deadline {
+                                Some((handle, timeout)) => with_deadline(Box::new(fut), &handle, timeout),
+                                None => Box::new(fut) as ControllerFuture,
+                            };
+
+                            Box::new(fut.then(move |res| {
                                 let (response, body) = match res {
                                     Ok(data) => (Self::response_with_json(data.clone()), data),
                                     Err(err) => (Self::response_with_error(&err), Self::error_to_body(&err)),
                                 };
 
+                                if let Some((store, key, body_hash)) = idempotency {
+                                    idempotency::complete(&*store, &key, &body_hash, response.status(), body.clone());
+                                }
+
                                 let dt = Local::now() - call_start;
                                 debug!(
                                     "Server send Response, status: {}, headers: {:#?}, body: {:?}, elapsed time = {}.{:03}, correlation token: {}",
@@ -135,14 +183,50 @@ where
                                     body,
                                     dt.num_seconds(),
                                     dt.num_milliseconds(),
-                                    token
+                                    correlation_token
                                 );
 
                                 future::ok(response)
-                            }
-                        }))
+                            })) as ServerFuture
                         }
-                    }) as ServerFuture
+                    };
+
+                    match (req.uri().path(), retry_after, idempotency) {
+                        ("/healthcheck", _, _) => Box::new(self.system_service.healthcheck().then(|res| {
+                            let response = match res {
+                                Ok(data) => Self::response_with_json(data.clone()),
+                                Err(err) => Self::response_with_error(&err),
+                            };
+
+                            future::ok(response)
+                        })) as ServerFuture,
+                        (_, Some(retry_after), _) => {
+                            warn!("Request rate limit exceeded, correlation token: {}", token);
+                            Box::new(future::ok(Self::response_with_retry_after(retry_after))) as ServerFuture
+                        }
+                        (_, _, Some((store, key))) => {
+                            // Body is read before the key is taken, so that retries are told from different requests
+                            let (method, uri, http_version, headers, body) = req.deconstruct();
+                            Box::new(try_read_body(body).and_then(move |bytes| {
+                                let body_hash = idempotency::body_hash(&bytes);
+                                match idempotency::begin(&*store, &key, &body_hash) {
+                                    Some(record) => {
+                                        debug!("Replaying response to idempotent request, correlation token: {}", token);
+                                        Box::new(future::ok(Self::response_with_idempotency_record(record, &body_hash))) as ServerFuture
+                                    }
+                                    None => {
+                                        let mut req = Request::new(method, uri);
+                                        req.set_body(bytes);
+                                        req.set_version(http_version);
+                                        std::mem::replace(req.headers_mut(), headers);
+
+                                        serve(req, Some((store, key, body_hash)))
+                                    }
+                                }
+                            })) as ServerFuture
+                        }
+                        _ => serve(req, None),
+                    }
                 }
             }.map({
                 let middleware = self.middleware.clone();
@@ -164,6 +248,10 @@ where
             controller: Arc::new(controller),
             middleware: Arc::new(|resp| resp),
             system_service: Box::new(SystemServiceImpl::default()),
+            rate_limiter: None,
+            default_api_version: None,
+            deadline_handle: None,
+            idempotency_store: None,
             _error_type: Default::default(),
         }
     }
@@ -177,6 +265,15 @@ where
         self
     }
 
+    /// Replaces controller in the application with one getting services built by `factory` for each request
+    pub fn with_scoped_controller<F, C>(self, factory: F, controller: C) -> Self
+    where
+        F: ServiceFactory<Context = C::Context> + 'static,
+        C: ContextController + 'static,
+    {
+        self.with_controller(ScopedController::new(factory, controller))
+    }
+
     /// Installs custom healthcheck handler
     pub fn with_system_service<T>(mut self, system_service: T) -> Self
     where
@@ -195,6 +292,75 @@ where
         self
     }
 
+    /// Limits request rate, rejecting excessive requests with `429 Too Many Requests`.
+    /// The limiter should be shared between applications serving different connections.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Enables API versioning: version is taken from `/vN` path prefix, which is stripped before routing,
+    /// or from `Accept-Version` header, and passed to `Controller::call_versioned`.
+    /// Requests specifying neither are served with `default_version`.
+    pub fn with_api_versioning(mut self, default_version: u32) -> Self {
+        self.default_api_version = Some(default_version);
+        self
+    }
+
+    /// Strips version prefix from the request path and sets `Accept-Version` header to the resolved version
+    fn resolve_api_version(mut req: Request, default_version: u32) -> (Request, u32) {
+        let prefixed = split_version_prefix(req.path()).map(|(version, path)| {
+            let uri = match req.query() {
+                Some(query) => format!("{}?{}", path, query),
+                None => path.to_string(),
+            };
+            (version, uri)
+        });
+
+        let version = match prefixed {
+            Some((version, uri)) => {
+                if let Ok(uri) = uri.parse() {
+                    req.set_uri(uri);
+                }
+                version
+            }
+            None => get_api_version(&req).unwrap_or(default_version),
+        };
+        req.headers_mut().set(AcceptVersion(version));
+
+        (req, version)
+    }
+
+    /// Enforces time budget set by callers in `Request-timeout` header: controller future is dropped
+    /// and `504 Gateway Timeout` is responded once it is exceeded. Controllers should limit outbound requests
+    /// with `TimeLimitedHttpClient::for_request`, which gets the budget left when the controller is called.
+    pub fn with_request_deadlines(mut self, handle: Handle) -> Self {
+        self.deadline_handle = Some(handle);
+        self
+    }
+
+    /// Handles `POST` requests with `Idempotency-Key` header once per key, replaying the recorded response
+    /// to retries. Keys used concurrently are rejected with `423 Locked`, keys reused with a different body
+    /// with `422 Unprocessable Entity`.
+    pub fn with_idempotency(mut self, store: Arc<IdempotencyStore>) -> Self {
+        self.idempotency_store = Some(store);
+        self
+    }
+
+    fn call_controller(controller: &Controller, mut req: Request, version: Option<u32>, deadline_at: Option<Instant>) -> ControllerFuture {
+        // Time spent before the controller is called is taken from the budget passed further
+        if let Some(deadline_at) = deadline_at {
+            let time_left = time_left(deadline_at, Instant::now());
+            let time_left_ms = time_left.as_secs() * 1000 + u64::from(time_left.subsec_millis());
+            req.headers_mut().set(RequestTimeout(time_left_ms.to_string()));
+        }
+
+        match version {
+            Some(version) => controller.call_versioned(req, version),
+            None => controller.call(req),
+        }
+    }
+
     /// Responds with success, logs response body
     fn response_with_json(body: String) -> Response {
         Self::response_with_body(body).with_status(StatusCode::Ok)
@@ -209,6 +375,44 @@ where
         Self::response_with_body(mes).with_status(hyper::StatusCode::try_from(error_data.code).unwrap())
     }
 
+    fn response_with_retry_after(retry_after: Duration) -> Response {
+        // Retry-After is given in whole seconds, so it is rounded up not to invite retrying too early
+        let retry_after_secs = retry_after.as_secs() + if retry_after.subsec_nanos() > 0 { 1 } else { 0 };
+        let error_data = ErrorMessage {
+            code: StatusCode::TooManyRequests.as_u16(),
+            description: "Too many requests".to_string(),
+            payload: None,
+        };
+        let mes = serde_json::to_string(&error_data).unwrap();
+        Self::response_with_body(mes)
+            .with_status(StatusCode::TooManyRequests)
+            .with_header(RetryAfter::Delay(Duration::from_secs(retry_after_secs)))
+    }
+
+    fn response_with_idempotency_record(record: IdempotencyRecord, body_hash: &str) -> Response {
+        let (status, description) = match record {
+            ref record if record.body_hash() != body_hash => (
+                StatusCode::UnprocessableEntity,
+                "Idempotency key is already used for a request with a different body",
+            ),
+            IdempotencyRecord::Completed { status, body, .. } => {
+                return Self::response_with_body(body)
+                    .with_status(StatusCode::try_from(status).unwrap_or(StatusCode::Ok))
+                    .with_header(IdempotentReplayed(true))
+            }
+            // Not `409 Conflict`, which clients take for the operation being already performed
+            IdempotencyRecord::InProgress { .. } => (StatusCode::Locked, "Request with the same idempotency key is in progress"),
+        };
+
+        let error_data = ErrorMessage {
+            code: status.as_u16(),
+            description: description.to_string(),
+            payload: None,
+        };
+        let mes = serde_json::to_string(&error_data).unwrap();
+        Self::response_with_body(mes).with_status(status)
+    }
+
     fn error_to_body(error: &failure::Error) -> String {
         let error_data = ErrorMessageWrapper::<E>::from(&error).inner;
 
@@ -222,3 +426,66 @@ where
             .with_body(body)
     }
 }
+
+/// Fails with `DeadlineExceeded` if the future doesn't resolve within `timeout`
+fn with_deadline(fut: ControllerFuture, handle: &Handle, timeout: Duration) -> ControllerFuture {
+    let timer = match Timeout::new(timeout, handle) {
+        Ok(timer) => timer,
+        Err(e) => {
+            error!("Could not set request deadline: {}", e);
+            return fut;
+        }
+    };
+
+    Box::new(fut.select2(timer).then(move |res| match res {
+        Ok(Either::A((data, _))) => Ok(data),
+        Err(Either::A((err, _))) => Err(err),
+        Ok(Either::B(((), _))) => Err(DeadlineExceeded(timeout).into()),
+        Err(Either::B((err, _))) => Err(err.into()),
+    }))
+}
+
+/// Time left before the deadline, zero once it has passed
+fn time_left(deadline_at: Instant, now: Instant) -> Duration {
+    if deadline_at > now {
+        deadline_at - now
+    } else {
+        Duration::new(0, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio_core::reactor::Core;
+
+    fn delayed(handle: &Handle, delay: Duration) -> ControllerFuture {
+        Box::new(
+            Timeout::new(delay, handle)
+                .unwrap()
+                .map(|_| "done".to_string())
+                .map_err(failure::Error::from),
+        )
+    }
+
+    #[test]
+    fn test_with_deadline() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+
+        let fut = with_deadline(delayed(&handle, Duration::from_millis(10)), &handle, Duration::from_secs(10));
+        assert_eq!(core.run(fut).unwrap(), "done");
+
+        let fut = with_deadline(delayed(&handle, Duration::from_secs(10)), &handle, Duration::from_millis(10));
+        let err = core.run(fut).unwrap_err();
+        assert_eq!(err.downcast_ref::<DeadlineExceeded>().map(|e| e.0), Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn test_time_left() {
+        let now = Instant::now();
+        assert_eq!(time_left(now + Duration::from_millis(250), now), Duration::from_millis(250));
+        assert_eq!(time_left(now, now + Duration::from_millis(250)), Duration::new(0, 0));
+    }
+}
@@ -1,7 +1,8 @@
 use failure::{Context, Error, Fail};
 use hyper::StatusCode;
-use serde_json::Value;
+use serde_json::{self, Value};
 use std;
+use validator::ValidationErrors;
 
 pub trait Codeable {
     fn code(&self) -> StatusCode;
@@ -11,11 +12,112 @@ pub trait PayloadCarrier {
     fn payload(&self) -> Option<Value>;
 }
 
+/// Standard error catalogue of stq services, so that clients get the same status for the same kind of failure
+/// from every service. Service errors derive `Codeable` with `#[codeable(kind = "...")]` using the names below.
+///
+/// | Kind           | Name             | Status                      | Used for                                             |
+/// |----------------|------------------|-----------------------------|------------------------------------------------------|
+/// | `Validation`   | `"validation"`   | `400 Bad Request`           | malformed or invalid input, see `ValidationFailed`   |
+/// | `Unauthorized` | `"unauthorized"` | `401 Unauthorized`          | missing or invalid credentials                       |
+/// | `Forbidden`    | `"forbidden"`    | `403 Forbidden`             | caller is not permitted to perform the action        |
+/// | `NotFound`     | `"not_found"`    | `404 Not Found`             | requested entity does not exist                      |
+/// | `Conflict`     | `"conflict"`     | `409 Conflict`              | duplicate entity or the action conflicts with state  |
+/// | `Internal`     | `"internal"`     | `500 Internal Server Error` | everything else, the default                         |
+///
+/// Every error is responded with the same envelope, see `ErrorMessage`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    Validation,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorKind::Validation => StatusCode::BadRequest,
+            ErrorKind::Unauthorized => StatusCode::Unauthorized,
+            ErrorKind::Forbidden => StatusCode::Forbidden,
+            ErrorKind::NotFound => StatusCode::NotFound,
+            ErrorKind::Conflict => StatusCode::Conflict,
+            ErrorKind::Internal => StatusCode::InternalServerError,
+        }
+    }
+
+    /// Name used in `#[codeable(kind = "...")]`, the name of the variant in snake case
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Validation => "validation",
+            ErrorKind::Unauthorized => "unauthorized",
+            ErrorKind::Forbidden => "forbidden",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Conflict => "conflict",
+            ErrorKind::Internal => "internal",
+        }
+    }
+}
+
+impl Codeable for ErrorKind {
+    fn code(&self) -> StatusCode {
+        self.status()
+    }
+}
+
+/// Request body failed validation. Errors by field are carried in the payload in the format of `validator` crate:
+/// `{"field": [{"code": "...", "message": "...", "params": {...}}]}`.
+#[derive(Debug, Fail)]
+#[fail(display = "Validation of request body failed")]
+pub struct ValidationFailed(pub ValidationErrors);
+
+impl Codeable for ValidationFailed {
+    fn code(&self) -> StatusCode {
+        ErrorKind::Validation.status()
+    }
+}
+
+impl PayloadCarrier for ValidationFailed {
+    fn payload(&self) -> Option<Value> {
+        serde_json::to_value(&self.0).ok()
+    }
+}
+
+/// Request was not served within the time budget set by the caller in `Request-timeout` header
+#[derive(Debug, Fail)]
+#[fail(display = "Request deadline of {:?} exceeded", _0)]
+pub struct DeadlineExceeded(pub ::std::time::Duration);
+
+impl Codeable for DeadlineExceeded {
+    fn code(&self) -> StatusCode {
+        StatusCode::GatewayTimeout
+    }
+}
+
+impl PayloadCarrier for DeadlineExceeded {
+    fn payload(&self) -> Option<Value> {
+        None
+    }
+}
+
+/// Code and payload of errors raised by `stq_http` itself rather than by the service
+fn http_error_data(e: &Fail) -> Option<(u16, Option<Value>)> {
+    if let Some(e) = e.downcast_ref::<ValidationFailed>() {
+        Some((e.code().as_u16(), e.payload()))
+    } else if let Some(e) = e.downcast_ref::<DeadlineExceeded>() {
+        Some((e.code().as_u16(), e.payload()))
+    } else {
+        None
+    }
+}
+
 pub struct ErrorMessageWrapper<E: Fail + Codeable> {
     pub inner: ErrorMessage,
     _type: std::marker::PhantomData<E>,
 }
 
+/// Error envelope of all stq services' responses
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ErrorMessage {
     pub code: u16,
@@ -36,7 +138,9 @@ where
             acc
         });
 
-        let mut code = 500;
+        let http_error = e.iter_chain().filter_map(http_error_data).next();
+
+        let mut code = http_error.as_ref().map(|(code, _)| *code).unwrap_or(500);
         let mut payload = None;
 
         for cause in e.iter_chain() {
@@ -53,6 +157,11 @@ where
             }
         }
 
+        // Payload of http errors, e.g. validation ones, is kept if the service error wrapping them carries none
+        if payload.is_none() {
+            payload = http_error.and_then(|(_, payload)| payload);
+        }
+
         Self {
             inner: ErrorMessage {
                 code,
@@ -63,3 +172,60 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+
+    use validator::ValidationError;
+
+    #[derive(Debug, Fail)]
+    enum ServiceError {
+        #[fail(display = "Parse error")]
+        Parse,
+    }
+
+    impl Codeable for ServiceError {
+        fn code(&self) -> StatusCode {
+            StatusCode::UnprocessableEntity
+        }
+    }
+
+    impl PayloadCarrier for ServiceError {
+        fn payload(&self) -> Option<Value> {
+            None
+        }
+    }
+
+    fn validation_failed() -> ValidationFailed {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "email",
+            ValidationError {
+                code: Cow::from("email"),
+                message: Some(Cow::from("Incorrect email format")),
+                params: HashMap::new(),
+            },
+        );
+        ValidationFailed(errors)
+    }
+
+    #[test]
+    fn test_validation_errors_are_carried_in_payload() {
+        let expected_payload: Value =
+            serde_json::from_str(r#"{"email": [{"code": "email", "message": "Incorrect email format", "params": {}}]}"#).unwrap();
+
+        let error: Error = validation_failed().into();
+        let message = ErrorMessageWrapper::<ServiceError>::from(&error).inner;
+        assert_eq!(message.code, 400);
+        assert_eq!(message.payload, Some(expected_payload.clone()));
+
+        let error: Error = validation_failed().context(ServiceError::Parse).into();
+        let message = ErrorMessageWrapper::<ServiceError>::from(&error).inner;
+        assert_eq!(message.code, 422);
+        assert_eq!(message.payload, Some(expected_payload));
+    }
+}
@@ -0,0 +1,318 @@
+//! Protection from double submission, e.g. of a cart being converted into orders.
+//!
+//! `POST` requests with `Idempotency-Key` header are handled once per key: the response is recorded and replayed
+//! with `Idempotent-Replayed: true` header to retries, while retries arriving before the response is ready are
+//! rejected with `423 Locked`. Keys are scoped by the user and the path, so that clients can't collide with each other.
+//! Records hold the hash of the request body, reusing the key with a different body is rejected with
+//! `422 Unprocessable Entity`.
+//!
+//! Server errors are not recorded, so that the request can be retried. Records of requests dropped mid-flight,
+//! e.g. by a crash, are never completed, so stores should expire records after a while.
+use failure;
+use hex;
+use hyper::header::Authorization;
+use hyper::{Method, Request, StatusCode};
+use sha2::{Digest, Sha256};
+
+use request_util::IdempotencyKey;
+
+header! { (IdempotentReplayed, "Idempotent-Replayed") => [bool] }
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum IdempotencyRecord {
+    /// Request with the key is being handled
+    InProgress {
+        body_hash: String,
+    },
+    Completed {
+        body_hash: String,
+        status: u16,
+        body: String,
+    },
+}
+
+impl IdempotencyRecord {
+    /// Hash of the body of the request the record was made for
+    pub fn body_hash(&self) -> &str {
+        match self {
+            IdempotencyRecord::InProgress { body_hash } => body_hash,
+            IdempotencyRecord::Completed { body_hash, .. } => body_hash,
+        }
+    }
+}
+
+pub trait IdempotencyStore: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<IdempotencyRecord>, failure::Error>;
+
+    fn set(&self, key: &str, record: IdempotencyRecord) -> Result<(), failure::Error>;
+
+    /// Sets the record if the key is vacant, atomically with respect to other instances of the service.
+    /// Returns whether the record was set.
+    fn set_if_absent(&self, key: &str, record: IdempotencyRecord) -> Result<bool, failure::Error>;
+
+    fn remove(&self, key: &str) -> Result<(), failure::Error>;
+}
+
+/// Any `stq_cache` cache of records supporting atomic set is a store, e.g. `TypedCache` over `RedisCache` with TTL
+#[cfg(feature = "cache")]
+impl<C> IdempotencyStore for C
+where
+    C: ::stq_cache::cache::AtomicCache<IdempotencyRecord> + Send + Sync,
+{
+    fn get(&self, key: &str) -> Result<Option<IdempotencyRecord>, failure::Error> {
+        ::stq_cache::cache::Cache::get(self, key).map_err(failure::Error::from)
+    }
+
+    fn set(&self, key: &str, record: IdempotencyRecord) -> Result<(), failure::Error> {
+        ::stq_cache::cache::Cache::set(self, key, record).map_err(failure::Error::from)
+    }
+
+    fn set_if_absent(&self, key: &str, record: IdempotencyRecord) -> Result<bool, failure::Error> {
+        ::stq_cache::cache::AtomicCache::set_if_absent(self, key, record).map_err(failure::Error::from)
+    }
+
+    fn remove(&self, key: &str) -> Result<(), failure::Error> {
+        ::stq_cache::cache::Cache::remove(self, key)
+            .map(|_| ())
+            .map_err(failure::Error::from)
+    }
+}
+
+/// Key the response to the request is recorded under, `None` if the request is not subject to idempotency
+pub fn request_key(req: &Request) -> Option<String> {
+    if *req.method() != Method::Post {
+        return None;
+    }
+
+    let key = req.headers().get::<IdempotencyKey>()?;
+    let user = req
+        .headers()
+        .get::<Authorization<String>>()
+        .map(|auth| auth.0.clone())
+        .unwrap_or_default();
+
+    Some(format!("{}:{}:{}", user, req.path(), key.0))
+}
+
+/// Hash of the request body, recorded to tell retries from different requests reusing the key
+pub fn body_hash(body: &[u8]) -> String {
+    hex::encode(Sha256::digest(body))
+}
+
+/// Number of attempts to take a key other requests keep taking and releasing
+const BEGIN_ATTEMPTS: usize = 3;
+
+fn try_begin(store: &IdempotencyStore, key: &str, record: IdempotencyRecord) -> Result<Option<IdempotencyRecord>, failure::Error> {
+    for _ in 0..BEGIN_ATTEMPTS {
+        if store.set_if_absent(key, record.clone())? {
+            return Ok(None);
+        }
+        // The record may have been released or may have expired since, then the key is free to take again
+        if let Some(existing) = store.get(key)? {
+            return Ok(Some(existing));
+        }
+    }
+    // The key keeps being taken by other requests, so the request is rejected as a concurrent retry
+    Ok(Some(record))
+}
+
+/// Marks the request with the key as in progress, returns the existing record if the key was used before.
+/// Store failures are logged and the request is handled as if the key was new.
+pub fn begin(store: &IdempotencyStore, key: &str, body_hash: &str) -> Option<IdempotencyRecord> {
+    let record = IdempotencyRecord::InProgress {
+        body_hash: body_hash.to_string(),
+    };
+
+    try_begin(store, key, record).unwrap_or_else(|e| {
+        error!("Idempotency store failed to begin request with key {}: {}", key, e);
+        None
+    })
+}
+
+/// Records the response to the request with the key, server errors release the key instead
+pub fn complete(store: &IdempotencyStore, key: &str, body_hash: &str, status: StatusCode, body: String) {
+    let res = if status.is_server_error() {
+        store.remove(key)
+    } else {
+        store.set(
+            key,
+            IdempotencyRecord::Completed {
+                body_hash: body_hash.to_string(),
+                status: status.as_u16(),
+                body,
+            },
+        )
+    };
+
+    if let Err(e) = res {
+        error!("Idempotency store failed to complete request with key {}: {}", key, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryStore(Mutex<HashMap<String, IdempotencyRecord>>);
+
+    impl IdempotencyStore for MemoryStore {
+        fn get(&self, key: &str) -> Result<Option<IdempotencyRecord>, failure::Error> {
+            Ok(self.0.lock().unwrap().get(key).cloned())
+        }
+
+        fn set(&self, key: &str, record: IdempotencyRecord) -> Result<(), failure::Error> {
+            self.0.lock().unwrap().insert(key.to_string(), record);
+            Ok(())
+        }
+
+        fn set_if_absent(&self, key: &str, record: IdempotencyRecord) -> Result<bool, failure::Error> {
+            let mut records = self.0.lock().unwrap();
+            if records.contains_key(key) {
+                return Ok(false);
+            }
+            records.insert(key.to_string(), record);
+            Ok(true)
+        }
+
+        fn remove(&self, key: &str) -> Result<(), failure::Error> {
+            self.0.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_request_key() {
+        let mut req = Request::new(Method::Post, "/carts/convert?x=1".parse().unwrap());
+        assert_eq!(request_key(&req), None);
+
+        req.headers_mut().set(IdempotencyKey("abc".to_string()));
+        assert_eq!(request_key(&req), Some(":/carts/convert:abc".to_string()));
+
+        req.headers_mut().set(Authorization("42".to_string()));
+        assert_eq!(request_key(&req), Some("42:/carts/convert:abc".to_string()));
+
+        req.set_method(Method::Get);
+        assert_eq!(request_key(&req), None);
+    }
+
+    #[test]
+    fn test_begin_and_complete() {
+        let store = MemoryStore::default();
+        let hash = body_hash(b"{}");
+
+        assert_eq!(begin(&store, "key", &hash), None);
+        assert_eq!(
+            begin(&store, "key", &hash),
+            Some(IdempotencyRecord::InProgress { body_hash: hash.clone() })
+        );
+
+        complete(&store, "key", &hash, StatusCode::Ok, "{}".to_string());
+        let completed = IdempotencyRecord::Completed {
+            body_hash: hash.clone(),
+            status: 200,
+            body: "{}".to_string(),
+        };
+        assert_eq!(begin(&store, "key", &hash), Some(completed));
+
+        assert_eq!(begin(&store, "other", &hash), None);
+        complete(&store, "other", &hash, StatusCode::InternalServerError, "{}".to_string());
+        assert_eq!(begin(&store, "other", &hash), None);
+    }
+
+    #[test]
+    fn test_begin_with_different_body() {
+        let store = MemoryStore::default();
+        let hash = body_hash(b"{\"id\":1}");
+        let other_hash = body_hash(b"{\"id\":2}");
+        assert_ne!(hash, other_hash);
+
+        assert_eq!(begin(&store, "key", &hash), None);
+        let record = begin(&store, "key", &other_hash).unwrap();
+        assert_eq!(record.body_hash(), hash);
+    }
+
+    /// Store losing the race for the key: it is taken every time, but the record is read only on `reads`-th attempt
+    struct RacingStore {
+        reads: usize,
+        attempts: Mutex<usize>,
+    }
+
+    impl IdempotencyStore for RacingStore {
+        fn get(&self, _key: &str) -> Result<Option<IdempotencyRecord>, failure::Error> {
+            let mut attempts = self.attempts.lock().unwrap();
+            *attempts += 1;
+            Ok(if *attempts == self.reads {
+                Some(IdempotencyRecord::InProgress {
+                    body_hash: "other".to_string(),
+                })
+            } else {
+                None
+            })
+        }
+
+        fn set(&self, _key: &str, _record: IdempotencyRecord) -> Result<(), failure::Error> {
+            Ok(())
+        }
+
+        fn set_if_absent(&self, _key: &str, _record: IdempotencyRecord) -> Result<bool, failure::Error> {
+            Ok(false)
+        }
+
+        fn remove(&self, _key: &str) -> Result<(), failure::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_begin_when_key_is_taken_again() {
+        let hash = body_hash(b"{}");
+
+        // Key released and taken by another request in between, the record is re-read
+        let store = RacingStore {
+            reads: 2,
+            attempts: Mutex::new(0),
+        };
+        assert_eq!(
+            begin(&store, "key", &hash),
+            Some(IdempotencyRecord::InProgress {
+                body_hash: "other".to_string(),
+            })
+        );
+
+        // Record is never read, the request is not handled
+        let store = RacingStore {
+            reads: 0,
+            attempts: Mutex::new(0),
+        };
+        assert_eq!(
+            begin(&store, "key", &hash),
+            Some(IdempotencyRecord::InProgress { body_hash: hash.clone() })
+        );
+        assert_eq!(*store.attempts.lock().unwrap(), BEGIN_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_begin_is_exclusive() {
+        let store = ::std::sync::Arc::new(MemoryStore::default());
+        let hash = body_hash(b"{}");
+
+        let threads = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                let hash = hash.clone();
+                ::std::thread::spawn(move || begin(&*store, "key", &hash))
+            })
+            .collect::<Vec<_>>();
+        let started = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .filter(Option::is_none)
+            .count();
+
+        assert_eq!(started, 1);
+    }
+}
@@ -1,6 +1,9 @@
 #[macro_use]
 extern crate failure;
 extern crate futures;
+extern crate hex;
+#[cfg(feature = "webhooks")]
+extern crate hmac;
 #[macro_use]
 extern crate hyper;
 extern crate hyper_tls;
@@ -8,17 +11,30 @@ extern crate hyper_tls;
 extern crate juniper;
 #[macro_use]
 extern crate log;
+extern crate lru_cache;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate chrono;
 extern crate serde_json;
+extern crate sha2;
+#[cfg(feature = "cache")]
+extern crate stq_cache;
+#[cfg(feature = "webhooks")]
+extern crate stq_db;
+extern crate stq_static_resources;
 extern crate tokio_core;
 extern crate validator;
 
 pub mod client;
+pub mod context;
 pub mod controller;
 pub mod errors;
+pub mod idempotency;
+pub mod logging;
 pub mod query_util;
+pub mod rate_limit;
 pub mod request_util;
 pub mod system;
+#[cfg(feature = "webhooks")]
+pub mod webhook;
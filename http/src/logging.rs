@@ -0,0 +1,122 @@
+//! Request context for log records, so that records logged by controllers can be told apart by request.
+//!
+//! Context is set for the current thread while the request future is polled, loggers add its fields to records
+//! by calling `current_fields`, e.g. `stq_logging::init_with_context(config, stq_http::logging::current_fields)`.
+//! Futures spawned separately don't inherit the context unless wrapped with `WithLogContext::inherit`.
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use futures::prelude::*;
+use hyper::header::Authorization;
+use hyper::Request;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<LogContext>>> = RefCell::new(None);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogContext {
+    correlation_token: String,
+    user_id: Option<String>,
+    /// Method and path of the request
+    route: String,
+}
+
+impl LogContext {
+    pub fn from_request(req: &Request, correlation_token: String) -> Self {
+        Self {
+            correlation_token,
+            user_id: req.headers().get::<Authorization<String>>().map(|auth| auth.0.clone()),
+            route: format!("{} {}", req.method(), req.path()),
+        }
+    }
+
+    /// Fields to add to log records, user id is left out for anonymous requests
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![("correlation_token", self.correlation_token.clone())];
+        if let Some(ref user_id) = self.user_id {
+            fields.push(("user_id", user_id.clone()));
+        }
+        fields.push(("route", self.route.clone()));
+        fields
+    }
+}
+
+/// Fields of the context of the request being handled on the current thread, empty outside of requests
+pub fn current_fields() -> Vec<(&'static str, String)> {
+    CURRENT.with(|current| current.borrow().as_ref().map(|context| context.fields()).unwrap_or_default())
+}
+
+/// Restores the context replaced for the duration of `poll`, even if it panics
+struct ContextReset(Option<Arc<LogContext>>);
+
+impl Drop for ContextReset {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Future polled with the log context set
+pub struct WithLogContext<F> {
+    context: Option<Arc<LogContext>>,
+    inner: F,
+}
+
+impl<F: Future> WithLogContext<F> {
+    pub fn new(context: LogContext, inner: F) -> Self {
+        Self {
+            context: Some(Arc::new(context)),
+            inner,
+        }
+    }
+
+    /// Carries the context of the current request over to the future, e.g. one spawned on the reactor
+    pub fn inherit(inner: F) -> Self {
+        Self {
+            context: CURRENT.with(|current| current.borrow().clone()),
+            inner,
+        }
+    }
+}
+
+impl<F: Future> Future for WithLogContext<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let _reset = ContextReset(CURRENT.with(|current| current.replace(self.context.clone())));
+        self.inner.poll()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::future;
+    use hyper::Method;
+
+    #[test]
+    fn test_context_is_set_while_polled() {
+        let mut req = Request::new(Method::Post, "/carts/convert?x=1".parse().unwrap());
+        req.headers_mut().set(Authorization("42".to_string()));
+        let context = LogContext::from_request(&req, "token".to_string());
+
+        let fut = WithLogContext::new(
+            context,
+            future::lazy(|| {
+                let outer = current_fields();
+                WithLogContext::inherit(future::lazy(|| Ok::<_, ()>(current_fields()))).map(move |inner| (outer, inner))
+            }),
+        );
+
+        let expected = vec![
+            ("correlation_token", "token".to_string()),
+            ("user_id", "42".to_string()),
+            ("route", "POST /carts/convert".to_string()),
+        ];
+        assert_eq!(fut.wait(), Ok((expected.clone(), expected)));
+        assert!(current_fields().is_empty());
+    }
+}
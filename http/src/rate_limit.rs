@@ -0,0 +1,272 @@
+//! Request rate limiting with leaky buckets.
+//!
+//! Every client, identified by a key extractor, has a bucket per rule. Each request adds a drop to the bucket,
+//! which leaks at the rate of the rule. Requests overflowing the bucket are rejected with `429 Too Many Requests`.
+//! Requests matching no rule are not limited, requests having no key are rejected rather than sharing a bucket.
+//!
+//! Keys must not come from headers clients can set, or clients would get a fresh bucket with every request.
+//! See `client_key`, which counts authenticated users by their id and anonymous clients by their IP.
+//!
+//! Buckets are stored as the time they drain at, so that no floating point arithmetic is involved.
+use std::cmp;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use hyper::Request;
+use lru_cache::LruCache;
+
+use request_util::XForwardedFor;
+
+/// Extracts the key requests are counted by, e.g. client IP
+pub type KeyExtractor = Arc<Fn(&Request) -> Option<String> + Send + Sync>;
+/// Authenticates the request, returning id of the user, `None` for anonymous or unauthenticated requests
+pub type Authenticator = Arc<Fn(&Request) -> Option<String> + Send + Sync>;
+pub type RequestMatcher = Arc<Fn(&Request) -> bool + Send + Sync>;
+
+/// Allows bursts of up to `burst` requests, refilling at `burst` requests per `period`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    pub fn new(burst: u32, period: Duration) -> Self {
+        Self { burst, period }
+    }
+
+    /// Time for one request to leak
+    fn drop_interval(&self) -> Duration {
+        self.period / self.burst.max(1)
+    }
+}
+
+/// Request rate limiter shared between connections of the application
+pub struct RateLimiter {
+    key_extractor: KeyExtractor,
+    rules: Vec<(RequestMatcher, RateLimit)>,
+    /// Time buckets drain at, by rule and client key
+    buckets: Mutex<LruCache<(usize, String), Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(key_extractor: KeyExtractor) -> Self {
+        Self {
+            key_extractor,
+            rules: vec![],
+            buckets: Mutex::new(LruCache::new(100_000)),
+        }
+    }
+
+    /// Limits requests matching `matcher`. The first matching rule applies, so more specific rules go first.
+    pub fn with_rule<F>(mut self, matcher: F, limit: RateLimit) -> Self
+    where
+        F: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        self.rules.push((Arc::new(matcher), limit));
+        self
+    }
+
+    /// Limits requests with path starting with `prefix`
+    pub fn with_path_rule(self, prefix: &'static str, limit: RateLimit) -> Self {
+        self.with_rule(move |req: &Request| req.path().starts_with(prefix), limit)
+    }
+
+    /// Limits requests matching no other rule
+    pub fn with_default_rule(self, limit: RateLimit) -> Self {
+        self.with_rule(|_: &Request| true, limit)
+    }
+
+    /// Least recently used buckets are dropped when there are more than `max_buckets` of them
+    pub fn with_max_buckets(self, max_buckets: usize) -> Self {
+        self.buckets.lock().unwrap().set_capacity(max_buckets);
+        self
+    }
+
+    /// Counts the request, returns time to wait before retrying if it exceeds the limit.
+    /// Requests having no key are rejected, to wait for the whole period of the rule.
+    pub fn check(&self, req: &Request) -> Result<(), Duration> {
+        let rule = match self.rules.iter().position(|(matcher, _)| matcher(req)) {
+            Some(rule) => rule,
+            None => return Ok(()),
+        };
+
+        match (self.key_extractor)(req) {
+            Some(key) => self.check_key(rule, key, Instant::now()),
+            None => Err(self.rules[rule].1.period),
+        }
+    }
+
+    fn check_key(&self, rule: usize, key: String, now: Instant) -> Result<(), Duration> {
+        let limit = self.rules[rule].1;
+        let drop_interval = limit.drop_interval();
+        // Bucket overflows if it drains later than this
+        let capacity = limit.period - drop_interval;
+
+        let mut buckets = self.buckets.lock().unwrap();
+
+        let key = (rule, key);
+        let drained_at = match buckets.get_mut(&key) {
+            Some(drained_at) => cmp::max(*drained_at, now),
+            None => now,
+        };
+
+        let level = drained_at - now;
+        if level <= capacity {
+            buckets.insert(key, drained_at + drop_interval);
+            Ok(())
+        } else {
+            Err(level - capacity)
+        }
+    }
+}
+
+/// Counts requests by client IP recorded in `X-Forwarded-For` header by the outermost of `trusted_proxies` proxies
+/// in front of the service, counting the ingress one. Each proxy appends the IP of its peer, so entries left of the ones
+/// appended by trusted proxies are set by the client and are ignored.
+pub fn forwarded_ip_key(trusted_proxies: usize) -> KeyExtractor {
+    Arc::new(move |req: &Request| {
+        let ips = req
+            .headers()
+            .get::<XForwardedFor>()?
+            .0
+            .split(',')
+            .map(|ip| ip.trim())
+            .collect::<Vec<_>>();
+        ips.len()
+            .checked_sub(trusted_proxies)
+            .and_then(|i| ips.get(i))
+            .filter(|ip| !ip.is_empty())
+            .map(|ip| ip.to_string())
+    })
+}
+
+/// Counts requests by client IP recorded by `trusted_proxies` proxies, see `forwarded_ip_key`,
+/// falling back to the IP of the peer for requests not passed through all of them
+// `Request::remote_addr` is the only way to get the peer in hyper 0.11
+#[allow(deprecated)]
+pub fn client_ip_key(trusted_proxies: usize) -> KeyExtractor {
+    let forwarded_ip_key = forwarded_ip_key(trusted_proxies);
+    Arc::new(move |req: &Request| forwarded_ip_key(req).or_else(|| req.remote_addr().map(|addr| addr.ip().to_string())))
+}
+
+/// Counts requests by id of the user authenticated by `authenticate`, falling back to client IP for anonymous clients,
+/// see `client_ip_key`. Ids must come from verified credentials, not from headers clients can set.
+pub fn client_key(authenticate: Authenticator, trusted_proxies: usize) -> KeyExtractor {
+    let ip_key = client_ip_key(trusted_proxies);
+    Arc::new(move |req: &Request| {
+        authenticate(req)
+            .map(|key| format!("user:{}", key))
+            .or_else(|| ip_key(req).map(|key| format!("ip:{}", key)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::Authorization;
+    use hyper::Method;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(forwarded_ip_key(1))
+            .with_path_rule("/cart", RateLimit::new(2, Duration::from_secs(10)))
+            .with_default_rule(RateLimit::new(100, Duration::from_secs(1)))
+    }
+
+    fn key(key: &str) -> String {
+        key.to_string()
+    }
+
+    /// Accepts the only valid token of user 1
+    fn authenticate() -> Authenticator {
+        Arc::new(|req: &Request| match req.headers().get::<Authorization<String>>() {
+            Some(auth) if auth.0 == "token-1" => Some("1".to_string()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_bucket_overflow_and_leak() {
+        let limiter = limiter();
+        let start = Instant::now();
+
+        assert_eq!(limiter.check_key(0, key("1"), start), Ok(()));
+        assert_eq!(limiter.check_key(0, key("1"), start), Ok(()));
+        assert_eq!(limiter.check_key(0, key("1"), start), Err(Duration::from_secs(5)));
+        // Other clients and rules have their own buckets
+        assert_eq!(limiter.check_key(0, key("2"), start), Ok(()));
+        assert_eq!(limiter.check_key(1, key("1"), start), Ok(()));
+
+        // One request leaks in 5 seconds
+        assert_eq!(
+            limiter.check_key(0, key("1"), start + Duration::from_secs(2)),
+            Err(Duration::from_secs(3))
+        );
+        assert_eq!(limiter.check_key(0, key("1"), start + Duration::from_secs(5)), Ok(()));
+    }
+
+    #[test]
+    fn test_least_recently_used_buckets_are_dropped() {
+        let limiter = limiter().with_max_buckets(2);
+        let start = Instant::now();
+
+        limiter.check_key(0, key("1"), start).unwrap();
+        limiter.check_key(0, key("2"), start).unwrap();
+        limiter.check_key(0, key("1"), start).unwrap();
+        limiter.check_key(0, key("3"), start).unwrap();
+
+        let mut buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert!(buckets.contains_key(&(0, key("1"))));
+        assert!(!buckets.contains_key(&(0, key("2"))));
+    }
+
+    #[test]
+    fn test_forwarded_ip_key() {
+        let mut req = Request::new(Method::Get, "/cart".parse().unwrap());
+        assert_eq!(forwarded_ip_key(1)(&req), None);
+
+        // The client has prepended 1.1.1.1, the ingress proxy has appended 10.0.0.1 and the inner one 10.0.0.2
+        req.headers_mut().set(XForwardedFor("1.1.1.1, 10.0.0.1, 10.0.0.2".to_string()));
+        assert_eq!(forwarded_ip_key(1)(&req), Some(key("10.0.0.2")));
+        assert_eq!(forwarded_ip_key(2)(&req), Some(key("10.0.0.1")));
+        assert_eq!(forwarded_ip_key(4)(&req), None);
+        assert_eq!(forwarded_ip_key(0)(&req), None);
+    }
+
+    #[test]
+    fn test_client_key() {
+        let client_key = client_key(authenticate(), 1);
+
+        let mut req = Request::new(Method::Get, "/cart".parse().unwrap());
+        assert_eq!(client_key(&req), None);
+
+        req.headers_mut().set(XForwardedFor("1.1.1.1, 10.0.0.1".to_string()));
+        assert_eq!(client_key(&req), Some(key("ip:10.0.0.1")));
+
+        // Unverified credentials don't get a bucket of their own
+        req.headers_mut().set(Authorization("2".to_string()));
+        assert_eq!(client_key(&req), Some(key("ip:10.0.0.1")));
+
+        req.headers_mut().set(Authorization("token-1".to_string()));
+        assert_eq!(client_key(&req), Some(key("user:1")));
+    }
+
+    #[test]
+    fn test_requests_without_key_are_rejected() {
+        let limiter = limiter();
+        assert_eq!(
+            limiter.check(&Request::new(Method::Get, "/cart".parse().unwrap())),
+            Err(Duration::from_secs(10))
+        );
+        assert_eq!(
+            limiter.check(&Request::new(Method::Get, "/other".parse().unwrap())),
+            Err(Duration::from_secs(1))
+        );
+
+        let mut req = Request::new(Method::Get, "/cart".parse().unwrap());
+        req.headers_mut().set(XForwardedFor("10.0.0.1".to_string()));
+        assert_eq!(limiter.check(&req), Ok(()));
+    }
+}
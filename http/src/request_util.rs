@@ -6,6 +6,11 @@ use hyper;
 use serde::de::Deserialize;
 use serde::ser::Serialize;
 use serde_json;
+use std::time::Duration;
+use stq_static_resources::{parse_user_agent, UserAgentInfo};
+use validator::Validate;
+
+use errors::ValidationFailed;
 
 header! { (SessionId, "Session-Id") => [String] }
 header! { (Currency, "Currency") => [String] }
@@ -15,6 +20,9 @@ header! { (RequestTimeout, "Request-timeout") => [String] }
 header! { (XWSSE, "X-WSSE") => [String] }
 header! { (StripeSignature, "Stripe-Signature") => [String] }
 header! { (Sign, "Sign") => [String] }
+header! { (XForwardedFor, "X-Forwarded-For") => [String] }
+header! { (AcceptVersion, "Accept-Version") => [u32] }
+header! { (IdempotencyKey, "Idempotency-Key") => [String] }
 
 #[derive(Clone, Debug, Fail)]
 pub enum ParseError {
@@ -24,15 +32,11 @@ pub enum ParseError {
     ConvertError,
 }
 
-/// Transforms request body with the following pipeline:
-///
-///   1. Parse request body into entity of type T (T must implement `serde::de::Deserialize` trait)
-///
-///   2. Validate entity (T must implement `validator::Validate`)
+/// Parses request body into entity of type T (T must implement `serde::de::Deserialize` trait).
+/// Empty body is parsed as JSON `null`.
 ///
-/// Fails with `error::Error::UnprocessableEntity` if step 1 fails.
-///
-/// Fails with `error::Error::BadRequest` with message if step 2 fails.
+/// Fails with `ParseError::ConvertError` if body is not a valid JSON representation of T.
+/// The entity is not validated, see `parse_and_validate_body`.
 pub fn parse_body<T>(body: hyper::Body) -> Box<Future<Item = T, Error = failure::Error>>
 where
     T: for<'a> Deserialize<'a> + 'static,
@@ -55,6 +59,26 @@ where
     )
 }
 
+/// Transforms request body with the following pipeline:
+///
+///   1. Parse request body into entity of type T, see `parse_body`
+///
+///   2. Validate entity (T must implement `validator::Validate`)
+///
+/// Fails with `ParseError::ConvertError` if step 1 fails.
+///
+/// Fails with `ValidationFailed` if step 2 fails, which is responded with `400 Bad Request`
+/// and validation errors by field in the payload.
+pub fn parse_and_validate_body<T>(body: hyper::Body) -> Box<Future<Item = T, Error = failure::Error>>
+where
+    T: for<'a> Deserialize<'a> + Validate + 'static,
+{
+    Box::new(parse_body::<T>(body).and_then(|entity: T| match entity.validate() {
+        Ok(()) => Ok(entity),
+        Err(errors) => Err(ValidationFailed(errors).into()),
+    }))
+}
+
 /// Reads body of request and response in Future format
 pub fn read_body(body: hyper::Body) -> Box<Future<Item = String, Error = hyper::Error> + Send> {
     Box::new(
@@ -98,3 +122,66 @@ pub fn get_correlation_token(req: &hyper::Request) -> String {
         None => String::default(),
     }
 }
+
+/// Time budget of the request set by the caller in `Request-timeout` header, in milliseconds
+pub fn get_request_timeout(req: &hyper::Request) -> Option<Duration> {
+    req.headers()
+        .get::<RequestTimeout>()
+        .and_then(|timeout| timeout.0.trim().parse::<u64>().ok())
+        .map(Duration::from_millis)
+}
+
+/// Detects client device and project from `User-Agent` header, `None` if the header is missing or not recognized
+pub fn get_user_agent_info(req: &hyper::Request) -> Option<UserAgentInfo> {
+    req.headers()
+        .get::<hyper::header::UserAgent>()
+        .and_then(|user_agent| parse_user_agent(&user_agent.to_string()))
+}
+
+/// API version requested in `Accept-Version` header
+pub fn get_api_version(req: &hyper::Request) -> Option<u32> {
+    req.headers().get::<AcceptVersion>().map(|version| version.0)
+}
+
+/// Splits API version prefix off the path, e.g. `/v2/users/1` into `2` and `/users/1`
+pub fn split_version_prefix(path: &str) -> Option<(u32, &str)> {
+    let rest = path.trim_start_matches('/');
+    if !rest.starts_with('v') {
+        return None;
+    }
+
+    let (version, path) = match rest.find('/') {
+        Some(i) => (&rest[1..i], &rest[i..]),
+        None => (&rest[1..], "/"),
+    };
+    if version.is_empty() || !version.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    version.parse().ok().map(|version| (version, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_version_prefix() {
+        assert_eq!(split_version_prefix("/v2/users/1"), Some((2, "/users/1")));
+        assert_eq!(split_version_prefix("/v10"), Some((10, "/")));
+        assert_eq!(split_version_prefix("/vendors/1"), None);
+        assert_eq!(split_version_prefix("/v/users"), None);
+        assert_eq!(split_version_prefix("/users/v1"), None);
+    }
+
+    #[test]
+    fn test_get_request_timeout() {
+        let mut req = hyper::Request::new(hyper::Method::Get, "/users/1".parse().unwrap());
+        assert_eq!(get_request_timeout(&req), None);
+
+        req.headers_mut().set(RequestTimeout(" 1500 ".to_string()));
+        assert_eq!(get_request_timeout(&req), Some(Duration::from_millis(1500)));
+
+        req.headers_mut().set(RequestTimeout("1.5s".to_string()));
+        assert_eq!(get_request_timeout(&req), None);
+    }
+}
@@ -1,6 +1,12 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
 use failure;
 use futures::future;
 use futures::prelude::*;
+use serde::ser::Serialize;
+use serde_json;
 
 /// System service, responsible for common endpoints like healthcheck
 pub trait SystemService {
@@ -8,6 +14,30 @@ pub trait SystemService {
     fn healthcheck(&self) -> Box<Future<Item = String, Error = failure::Error>>;
 }
 
+/// Build of the running service, usually filled by the caller from `env!("CARGO_PKG_VERSION")` and the like
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub name: String,
+    pub version: String,
+    pub git_hash: Option<String>,
+}
+
+/// Healthcheck response of a service with build info, lets deploy tooling verify what is actually running
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SystemInfo {
+    pub status: String,
+    pub name: String,
+    pub version: String,
+    pub git_hash: Option<String>,
+    /// Seconds since the service start
+    pub uptime: u64,
+    pub config_checksum: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feature_flags: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<serde_json::Value>,
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 pub struct SystemServiceImpl;
 
@@ -17,3 +47,147 @@ impl SystemService for SystemServiceImpl {
         Box::new(future::ok("\"Ok\"".to_string()))
     }
 }
+
+/// Responds to healthcheck with `SystemInfo`, or with `"Ok"` like `SystemServiceImpl` if no build info is provided
+#[derive(Clone)]
+pub struct SystemInfoServiceImpl {
+    build_info: Option<BuildInfo>,
+    config_checksum: Option<String>,
+    feature_flags: Option<Arc<Fn() -> serde_json::Value>>,
+    jobs: Option<Arc<Fn() -> serde_json::Value>>,
+    started_at: Instant,
+}
+
+impl SystemInfoServiceImpl {
+    pub fn new(build_info: BuildInfo) -> Self {
+        Self {
+            build_info: Some(build_info),
+            ..Default::default()
+        }
+    }
+
+    /// Reports checksum of the config, so that services running with different configs can be told apart
+    pub fn with_config<C: Serialize>(mut self, config: &C) -> Self {
+        self.config_checksum = Some(config_checksum(config));
+        self
+    }
+
+    /// Reports current state of feature flags, e.g. `stq_flags::report` of the service flags
+    pub fn with_feature_flags<F>(mut self, report: F) -> Self
+    where
+        F: Fn() -> serde_json::Value + 'static,
+    {
+        self.feature_flags = Some(Arc::new(report));
+        self
+    }
+
+    /// Reports statuses of background jobs, e.g. `stq_jobs::JobStatuses::report` of the started scheduler
+    pub fn with_jobs<F>(mut self, report: F) -> Self
+    where
+        F: Fn() -> serde_json::Value + 'static,
+    {
+        self.jobs = Some(Arc::new(report));
+        self
+    }
+
+    pub fn info(&self) -> Option<SystemInfo> {
+        self.build_info.as_ref().map(|build_info| SystemInfo {
+            status: "Ok".to_string(),
+            name: build_info.name.clone(),
+            version: build_info.version.clone(),
+            git_hash: build_info.git_hash.clone(),
+            uptime: self.started_at.elapsed().as_secs(),
+            config_checksum: self.config_checksum.clone(),
+            feature_flags: self.feature_flags.as_ref().map(|report| report()),
+            jobs: self.jobs.as_ref().map(|report| report()),
+        })
+    }
+}
+
+impl Default for SystemInfoServiceImpl {
+    fn default() -> Self {
+        Self {
+            build_info: None,
+            config_checksum: None,
+            feature_flags: None,
+            jobs: None,
+            started_at: Instant::now(),
+        }
+    }
+}
+
+impl fmt::Debug for SystemInfoServiceImpl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SystemInfoServiceImpl")
+            .field("build_info", &self.build_info)
+            .field("config_checksum", &self.config_checksum)
+            .field("feature_flags", &self.feature_flags.is_some())
+            .field("jobs", &self.jobs.is_some())
+            .field("started_at", &self.started_at)
+            .finish()
+    }
+}
+
+impl SystemService for SystemInfoServiceImpl {
+    /// Healthcheck endpoint, always returns OK status
+    fn healthcheck(&self) -> Box<Future<Item = String, Error = failure::Error>> {
+        match self.info() {
+            Some(info) => Box::new(future::result(serde_json::to_string(&info).map_err(From::from))),
+            None => Box::new(future::ok("\"Ok\"".to_string())),
+        }
+    }
+}
+
+/// FNV-1a hash of the config serialized to JSON. Config goes through `serde_json::Value` first,
+/// which sorts map keys, so that the checksum doesn't depend on the order of `HashMap` entries.
+fn config_checksum<C: Serialize>(config: &C) -> String {
+    let serialized = serde_json::to_value(config)
+        .and_then(|value| serde_json::to_string(&value))
+        .unwrap_or_default();
+
+    let hash = serialized.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_healthcheck() {
+        assert_eq!(SystemServiceImpl.healthcheck().wait().unwrap(), "\"Ok\"");
+        assert_eq!(SystemInfoServiceImpl::default().healthcheck().wait().unwrap(), "\"Ok\"");
+
+        let mut config = HashMap::new();
+        config.insert("database", "postgresql://localhost/db");
+        let build_info = BuildInfo {
+            name: "users".to_string(),
+            version: "1.2.0".to_string(),
+            git_hash: Some("4c70161".to_string()),
+        };
+        let service = SystemInfoServiceImpl::new(build_info).with_config(&config);
+
+        let info: SystemInfo = serde_json::from_str(&service.healthcheck().wait().unwrap()).unwrap();
+        assert_eq!(info.status, "Ok");
+        assert_eq!(info.name, "users");
+        assert_eq!(info.version, "1.2.0");
+        assert_eq!(info.git_hash, Some("4c70161".to_string()));
+        assert_eq!(info.uptime, 0);
+        assert_eq!(info.config_checksum, Some(config_checksum(&config)));
+        assert_ne!(config_checksum(&config), config_checksum(&HashMap::<&str, &str>::new()));
+        assert_eq!(info.feature_flags, None);
+
+        let service = service.with_feature_flags(|| serde_json::Value::Bool(true));
+        let info: SystemInfo = serde_json::from_str(&service.healthcheck().wait().unwrap()).unwrap();
+        assert_eq!(info.feature_flags, Some(serde_json::Value::Bool(true)));
+        assert_eq!(info.jobs, None);
+
+        let service = service.with_jobs(|| serde_json::Value::from(vec!["sweep_expired_orders"]));
+        let info: SystemInfo = serde_json::from_str(&service.healthcheck().wait().unwrap()).unwrap();
+        assert_eq!(info.jobs, Some(serde_json::Value::from(vec!["sweep_expired_orders"])));
+    }
+}
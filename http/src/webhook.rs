@@ -0,0 +1,295 @@
+//! Delivery of JSON events to endpoints registered by external integrations, e.g. stores notified of their orders.
+//!
+//! Every request is signed with the secret shared with the endpoint: `X-Webhook-Signature` header holds `sha256=`
+//! followed by the hex encoded HMAC-SHA256 of the body. Deliveries are persisted with `stq_db::webhook`, failed ones
+//! are retried following `RetrySchedule` and reported as dead letters once it is exhausted.
+use std::cmp;
+use std::rc::Rc;
+use std::time::Duration;
+
+use futures::future::{self, Loop};
+use futures::prelude::*;
+use hex;
+use hmac::{Hmac, Mac};
+use hyper::header::{ContentType, Headers};
+use hyper::Method;
+use serde::ser::Serialize;
+use serde_json;
+use sha2::Sha256;
+use stq_db::pool::Pool;
+use stq_db::webhook::{NewWebhookDelivery, WebhookConnection, WebhookConnectionFuture, WebhookDeliveries, WebhookDelivery, WebhookError};
+
+use client::HttpClient;
+
+header! { (WebhookSignature, "X-Webhook-Signature") => [String] }
+header! { (WebhookEvent, "X-Webhook-Event") => [String] }
+header! { (WebhookDeliveryId, "X-Webhook-Delivery") => [i64] }
+
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+fn mac(secret: &[u8], body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_varkey(secret).expect("HMAC accepts keys of any length");
+    mac.input(body);
+    mac
+}
+
+/// Value of `X-Webhook-Signature` header for the body
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    format!("{}{}", SIGNATURE_PREFIX, hex::encode(mac(secret, body).result().code()))
+}
+
+/// Checks `X-Webhook-Signature` header of a received webhook in constant time
+pub fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> bool {
+    if !signature.starts_with(SIGNATURE_PREFIX) {
+        return false;
+    }
+
+    match hex::decode(&signature[SIGNATURE_PREFIX.len()..]) {
+        Ok(code) => mac(secret, body).verify(&code).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Exponential backoff between delivery attempts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetrySchedule {
+    /// Delay after the first failed attempt
+    pub initial_delay: Duration,
+    pub multiplier: u32,
+    pub max_delay: Duration,
+    /// Attempts after which the delivery is dead
+    pub max_attempts: u32,
+}
+
+impl Default for RetrySchedule {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(30),
+            multiplier: 2,
+            max_delay: Duration::from_secs(6 * 60 * 60),
+            max_attempts: 12,
+        }
+    }
+}
+
+impl RetrySchedule {
+    /// Delay before the next attempt given the number of failed ones, `None` if the delivery should be given up
+    pub fn delay(&self, failed_attempts: u32) -> Option<Duration> {
+        if failed_attempts >= self.max_attempts {
+            return None;
+        }
+
+        let mut delay = self.initial_delay;
+        for _ in 1..failed_attempts {
+            delay = match delay.checked_mul(self.multiplier) {
+                Some(delay) if delay < self.max_delay => delay,
+                _ => return Some(self.max_delay),
+            };
+        }
+        Some(cmp::min(delay, self.max_delay))
+    }
+}
+
+/// Endpoint registered by an integration
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    pub secret: String,
+}
+
+pub struct WebhookClient<C> {
+    client: Rc<C>,
+    pool: Pool,
+    deliveries: Rc<WebhookDeliveries>,
+    retry_schedule: RetrySchedule,
+    batch_size: i64,
+    dead_letter_reporter: Rc<Fn(&WebhookDelivery)>,
+}
+
+impl<C> WebhookClient<C>
+where
+    C: HttpClient,
+{
+    pub fn new(client: C, pool: Pool, deliveries: WebhookDeliveries) -> Self {
+        Self {
+            client: Rc::new(client),
+            pool,
+            deliveries: Rc::new(deliveries),
+            retry_schedule: RetrySchedule::default(),
+            batch_size: 100,
+            dead_letter_reporter: Rc::new(|delivery: &WebhookDelivery| {
+                error!(
+                    "Webhook delivery {} of {} to {} failed {} times, giving up. Last error: {}",
+                    delivery.id,
+                    delivery.event_type,
+                    delivery.url,
+                    delivery.attempts,
+                    delivery.last_error.clone().unwrap_or_default()
+                )
+            }),
+        }
+    }
+
+    pub fn with_retry_schedule(mut self, retry_schedule: RetrySchedule) -> Self {
+        self.retry_schedule = retry_schedule;
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: i64) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Called with every delivery given up, e.g. to alert or notify the integration owner. Dead letters are logged by default.
+    pub fn with_dead_letter_reporter<F>(mut self, reporter: F) -> Self
+    where
+        F: Fn(&WebhookDelivery) + 'static,
+    {
+        self.dead_letter_reporter = Rc::new(reporter);
+        self
+    }
+
+    /// Schedules delivery of the event to every endpoint. Passing the transaction of the write causing the event
+    /// guarantees the event is delivered if and only if the write is committed.
+    pub fn enqueue<T: Serialize>(
+        &self,
+        conn: WebhookConnection,
+        endpoints: &[WebhookEndpoint],
+        event_type: &str,
+        event: &T,
+    ) -> WebhookConnectionFuture<Vec<WebhookDelivery>> {
+        let payload = match serde_json::to_string(event) {
+            Ok(payload) => payload,
+            Err(e) => return Box::new(future::err((format_err!("Failed to serialize webhook event: {}", e), conn))),
+        };
+
+        let new_deliveries = endpoints
+            .iter()
+            .map(|endpoint| NewWebhookDelivery {
+                url: endpoint.url.clone(),
+                event_type: event_type.to_string(),
+                signature: sign(endpoint.secret.as_bytes(), payload.as_bytes()),
+                payload: payload.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let deliveries = self.deliveries.clone();
+        Box::new(future::loop_fn(
+            (conn, new_deliveries.into_iter(), vec![]),
+            move |(conn, mut new_deliveries, mut scheduled)| match new_deliveries.next() {
+                Some(new_delivery) => future::Either::A(deliveries.schedule(conn, new_delivery).map(move |(delivery, conn)| {
+                    scheduled.push(delivery);
+                    Loop::Continue((conn, new_deliveries, scheduled))
+                })),
+                None => future::Either::B(future::ok(Loop::Break((scheduled, conn)))),
+            },
+        ))
+    }
+
+    /// Attempts a batch of due deliveries concurrently, resolves to the number of delivered ones
+    pub fn deliver_due(&self) -> impl Future<Item = usize, Error = WebhookError> {
+        let client = self.client.clone();
+        let deliveries = self.deliveries.clone();
+        let retry_schedule = self.retry_schedule;
+        let dead_letter_reporter = self.dead_letter_reporter.clone();
+        let batch_size = self.batch_size;
+
+        self.pool.run(move |conn| {
+            deliveries.lock_due(conn, batch_size).and_then(move |(due, conn)| {
+                let attempts = due
+                    .into_iter()
+                    .map(move |delivery| send(&*client, &delivery).then(move |res| Ok::<_, WebhookError>((delivery, res))));
+
+                future::join_all(attempts).then(move |res| match res {
+                    Ok(outcomes) => future::Either::A(future::loop_fn(
+                        (conn, outcomes.into_iter(), 0),
+                        move |(conn, mut outcomes, delivered)| match outcomes.next() {
+                            Some((delivery, Ok(()))) => future::Either::A(future::Either::A(
+                                deliveries
+                                    .mark_delivered(conn, delivery.id)
+                                    .map(move |(_, conn)| Loop::Continue((conn, outcomes, delivered + 1))),
+                            )),
+                            Some((delivery, Err(e))) => {
+                                let failed_attempts = delivery.attempts as u32 + 1;
+                                let recorded = match retry_schedule.delay(failed_attempts) {
+                                    Some(delay) => deliveries.reschedule(conn, delivery.id, delay, e.to_string()),
+                                    None => {
+                                        let dead_letter_reporter = dead_letter_reporter.clone();
+                                        Box::new(deliveries.mark_dead(conn, delivery.id, e.to_string()).map(move |(delivery, conn)| {
+                                            dead_letter_reporter(&delivery);
+                                            ((), conn)
+                                        }))
+                                    }
+                                };
+                                future::Either::A(future::Either::B(
+                                    recorded.map(move |(_, conn)| Loop::Continue((conn, outcomes, delivered))),
+                                ))
+                            }
+                            None => future::Either::B(future::ok(Loop::Break((delivered, conn)))),
+                        },
+                    )),
+                    Err(e) => future::Either::B(future::err((e, conn))),
+                })
+            })
+        })
+    }
+
+    /// Attempts due deliveries on every tick, e.g. of `tokio_core::reactor::Interval`. Failed batches are yielded
+    /// as errors without ending the stream, so that the worker keeps running through transient failures.
+    pub fn run<S>(self, ticks: S) -> impl Stream<Item = Result<usize, WebhookError>, Error = S::Error>
+    where
+        S: Stream,
+    {
+        ticks.and_then(move |_| self.deliver_due().then(Ok))
+    }
+}
+
+fn send<C: HttpClient>(client: &C, delivery: &WebhookDelivery) -> impl Future<Item = (), Error = WebhookError> {
+    let mut headers = Headers::new();
+    headers.set(ContentType::json());
+    headers.set(WebhookSignature(delivery.signature.clone()));
+    headers.set(WebhookEvent(delivery.event_type.clone()));
+    headers.set(WebhookDeliveryId(delivery.id));
+
+    client
+        .request(Method::Post, delivery.url.clone(), Some(delivery.payload.clone()), Some(headers))
+        .map(|_| ())
+        .map_err(WebhookError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231, test case 2
+        let signature = sign(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(signature, "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+
+        assert!(verify_signature(b"Jefe", b"what do ya want for nothing?", &signature));
+        assert!(!verify_signature(b"Jefe", b"what do ya want for something?", &signature));
+        assert!(!verify_signature(b"Jeff", b"what do ya want for nothing?", &signature));
+        assert!(!verify_signature(
+            b"Jefe",
+            b"what do ya want for nothing?",
+            &signature[SIGNATURE_PREFIX.len()..]
+        ));
+    }
+
+    #[test]
+    fn test_retry_schedule() {
+        let schedule = RetrySchedule {
+            initial_delay: Duration::from_secs(10),
+            multiplier: 3,
+            max_delay: Duration::from_secs(100),
+            max_attempts: 5,
+        };
+
+        assert_eq!(schedule.delay(1), Some(Duration::from_secs(10)));
+        assert_eq!(schedule.delay(2), Some(Duration::from_secs(30)));
+        assert_eq!(schedule.delay(3), Some(Duration::from_secs(90)));
+        assert_eq!(schedule.delay(4), Some(Duration::from_secs(100)));
+        assert_eq!(schedule.delay(5), None);
+    }
+}
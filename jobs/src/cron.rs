@@ -0,0 +1,205 @@
+//! Cron expressions in the usual five field format: minute, hour, day of month, month and day of week.
+//!
+//! Fields are `*`, values, ranges `a-b` and steps `*/n`, `a/n` or `a-b/n`, separated by commas. Sunday is both 0 and 7.
+//! If both day of month and day of week are restricted, a day matching either of them is enough, as in cron.
+//! Shortcuts like `@hourly` and `@daily` are supported too. Times are in UTC.
+use chrono::prelude::*;
+use chrono::Duration as ChronoDuration;
+use std::str::FromStr;
+
+#[derive(Debug, Fail)]
+#[fail(display = "Invalid cron expression \"{}\": {}", _0, _1)]
+pub struct CronError(pub String, pub String);
+
+/// Parsed cron expression, fields are bit sets of allowed values
+#[derive(Clone, Debug, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether any day field starts with `*`, in which case days must match both fields
+    any_day: bool,
+}
+
+impl CronSchedule {
+    /// First time matching the schedule after `after`, `None` if there is no such time within 5 years,
+    /// e.g. for February 30th
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = after.naive_utc().with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let limit = time + ChronoDuration::days(5 * 366);
+
+        while time < limit {
+            time = if !contains(self.months, time.month()) {
+                let (year, month) = if time.month() == 12 {
+                    (time.year() + 1, 1)
+                } else {
+                    (time.year(), time.month() + 1)
+                };
+                NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?
+            } else if !self.matches_day(time.date()) {
+                time.date().succ_opt()?.and_hms_opt(0, 0, 0)?
+            } else if !contains(self.hours, time.hour()) {
+                time.date().and_hms_opt(time.hour(), 0, 0)? + ChronoDuration::hours(1)
+            } else if !contains(self.minutes, time.minute()) {
+                time + ChronoDuration::minutes(1)
+            } else {
+                return Some(Utc.from_utc_datetime(&time));
+            };
+        }
+
+        None
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day_of_month = contains(self.days_of_month, date.day());
+        let day_of_week = contains(self.days_of_week, date.weekday().num_days_from_sunday());
+
+        if self.any_day {
+            day_of_month && day_of_week
+        } else {
+            day_of_month || day_of_week
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = CronError;
+
+    fn from_str(expression: &str) -> Result<Self, CronError> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expanded => expanded,
+        };
+        let error = |e| CronError(expression.to_string(), e);
+
+        let fields = expanded.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return Err(error(format!("expected 5 fields, got {}", fields.len())));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7).map_err(error)?;
+        if contains(days_of_week, 7) {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(CronSchedule {
+            minutes: parse_field(fields[0], 0, 59).map_err(error)?,
+            hours: parse_field(fields[1], 0, 23).map_err(error)?,
+            days_of_month: parse_field(fields[2], 1, 31).map_err(error)?,
+            months: parse_field(fields[3], 1, 12).map_err(error)?,
+            days_of_week,
+            any_day: fields[2].starts_with('*') || fields[4].starts_with('*'),
+        })
+    }
+}
+
+fn contains(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+fn parse_number(s: &str) -> Result<u32, String> {
+    s.parse().map_err(|_| format!("{} is not a number", s))
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.find('/') {
+            Some(i) => (&part[..i], Some(parse_number(&part[i + 1..])?)),
+            None => (part, None),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else {
+            match range.find('-') {
+                Some(i) => (parse_number(&range[..i])?, parse_number(&range[i + 1..])?),
+                // Step from a value runs up to the maximum, e.g. `5/15` for minutes is 5, 20, 35 and 50
+                None => {
+                    let value = parse_number(range)?;
+                    (value, if step.is_some() { max } else { value })
+                }
+            }
+        };
+
+        if start < min || end > max || start > end {
+            return Err(format!("{} is out of range {}-{}", part, min, max));
+        }
+        let step = match step {
+            Some(0) => return Err(format!("{} has zero step", part)),
+            Some(step) => step as usize,
+            None => 1,
+        };
+
+        for value in (start..=end).step_by(step) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next_after(expression: &str, after: &str) -> Option<String> {
+        let schedule: CronSchedule = expression.parse().unwrap();
+        schedule
+            .next_after(after.parse().unwrap())
+            .map(|next| next.format("%Y-%m-%d %H:%M").to_string())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!("* * * * *".parse::<CronSchedule>().is_ok());
+        assert!("*/15 0-6,22 1 1-12/3 1".parse::<CronSchedule>().is_ok());
+        assert!("0 0 * * MON".parse::<CronSchedule>().is_err());
+        assert!("* * * *".parse::<CronSchedule>().is_err());
+        assert!("60 * * * *".parse::<CronSchedule>().is_err());
+        assert!("5-1 * * * *".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+        assert_eq!("@daily".parse::<CronSchedule>().unwrap(), "0 0 * * *".parse().unwrap());
+        assert_eq!("0 0 * * 7".parse::<CronSchedule>().unwrap(), "0 0 * * 0".parse().unwrap());
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!(
+            next_after("* * * * *", "2018-12-31T23:59:30Z"),
+            Some("2019-01-01 00:00".to_string())
+        );
+        assert_eq!(
+            next_after("*/15 * * * *", "2018-06-01T10:15:00Z"),
+            Some("2018-06-01 10:30".to_string())
+        );
+        assert_eq!(
+            next_after("5/20 3 * * *", "2018-06-01T03:50:00Z"),
+            Some("2018-06-02 03:05".to_string())
+        );
+        assert_eq!(
+            next_after("0 0 29 2 *", "2018-06-01T00:00:00Z"),
+            Some("2020-02-29 00:00".to_string())
+        );
+        assert_eq!(next_after("0 0 30 2 *", "2018-06-01T00:00:00Z"), None);
+        // 2018-06-01 is Friday
+        assert_eq!(
+            next_after("30 12 * * 1-5", "2018-06-01T13:00:00Z"),
+            Some("2018-06-04 12:30".to_string())
+        );
+        assert_eq!(
+            next_after("0 0 15 * 0", "2018-06-01T00:00:00Z"),
+            Some("2018-06-03 00:00".to_string())
+        );
+        assert_eq!(
+            next_after("0 0 2 * 0", "2018-06-01T00:00:00Z"),
+            Some("2018-06-02 00:00".to_string())
+        );
+    }
+}
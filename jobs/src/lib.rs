@@ -0,0 +1,399 @@
+//! Background jobs run on the reactor on a schedule, e.g. sweeps of expired orders or cache refreshes.
+//!
+//! The next run of a job is scheduled once the previous one finishes, so that runs of a job never overlap
+//! within an instance. With feature `redis`, runs are serialized across instances with a Redis lock too,
+//! and a run is skipped if another instance holds the lock. Failures are logged and recorded in job statuses,
+//! which services report with `SystemInfoServiceImpl::with_jobs`.
+//!
+//! ```no_run
+//! extern crate failure;
+//! extern crate futures;
+//! extern crate stq_jobs;
+//! extern crate tokio_core;
+//!
+//! use std::time::Duration;
+//!
+//! use futures::future;
+//! use stq_jobs::{Job, Schedule, Scheduler};
+//! use tokio_core::reactor::Core;
+//!
+//! fn main() {
+//!     let mut core = Core::new().unwrap();
+//!     let statuses = Scheduler::new()
+//!         .with_job(Job::new("refresh_rates", Schedule::Interval(Duration::from_secs(60)), || {
+//!             future::ok::<(), failure::Error>(())
+//!         }))
+//!         .with_job(Job::new("sweep_expired_orders", Schedule::cron("*/5 * * * *").unwrap(), || {
+//!             future::ok::<(), failure::Error>(())
+//!         }))
+//!         .start(&core.handle());
+//!
+//!     core.run(future::empty::<(), ()>()).unwrap();
+//!     println!("{}", statuses.report());
+//! }
+//! ```
+extern crate chrono;
+#[macro_use]
+extern crate failure;
+extern crate futures;
+#[macro_use]
+extern crate log;
+#[cfg(feature = "redis")]
+extern crate r2d2_redis;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+#[cfg(feature = "redis")]
+extern crate stq_cache;
+extern crate tokio_core;
+
+pub mod cron;
+
+pub use cron::{CronError, CronSchedule};
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::prelude::*;
+use chrono::Duration as ChronoDuration;
+use futures::future::{self, Loop};
+use futures::prelude::*;
+#[cfg(feature = "redis")]
+use r2d2_redis::{r2d2::ManageConnection, redis::Connection as RedisConnection};
+#[cfg(feature = "redis")]
+use stq_cache::lock::{LockError, RedisLock};
+use tokio_core::reactor::{Handle, Timeout};
+
+pub type JobFuture = Box<Future<Item = (), Error = failure::Error>>;
+
+/// Wraps a run into a lock shared by instances
+type RunLocked = Fn(&str, Duration, JobFuture) -> JobFuture;
+
+/// Run is skipped, because another instance holds the job lock
+#[derive(Debug, Fail)]
+#[fail(display = "Job is run by another instance")]
+struct RunElsewhere;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schedule {
+    /// Fixed delay between the end of a run and the start of the next one
+    Interval(Duration),
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    pub fn cron(expression: &str) -> Result<Self, CronError> {
+        expression.parse().map(Schedule::Cron)
+    }
+
+    /// Time to wait for the next run, `None` if there are no more runs
+    fn delay(&self, now: DateTime<Utc>) -> Option<Duration> {
+        match *self {
+            Schedule::Interval(interval) => Some(interval),
+            Schedule::Cron(ref cron) => cron.next_after(now).map(|next| (next - now).to_std().unwrap_or_default()),
+        }
+    }
+}
+
+pub struct Job {
+    name: String,
+    schedule: Schedule,
+    lock_ttl: Duration,
+    run: Box<Fn() -> JobFuture>,
+}
+
+impl Job {
+    pub fn new<S, F, R>(name: S, schedule: Schedule, run: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn() -> R + 'static,
+        R: IntoFuture<Item = (), Error = failure::Error>,
+        R::Future: 'static,
+    {
+        Self {
+            name: name.into(),
+            schedule,
+            lock_ttl: Duration::from_secs(300),
+            run: Box::new(move || Box::new(run().into_future())),
+        }
+    }
+
+    /// Time the lock is held for at most, should exceed the longest run. Defaults to 5 minutes.
+    pub fn with_lock_ttl(mut self, lock_ttl: Duration) -> Self {
+        self.lock_ttl = lock_ttl;
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub running: bool,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<u64>,
+    /// Error of the last finished run, `None` if it succeeded
+    pub last_error: Option<String>,
+    pub runs: u64,
+    pub failures: u64,
+    /// Runs skipped, because another instance was running the job
+    pub skipped: u64,
+}
+
+/// Statuses of the started jobs, shared with the scheduler
+#[derive(Clone, Debug, Default)]
+pub struct JobStatuses(Arc<Mutex<BTreeMap<String, JobStatus>>>);
+
+impl JobStatuses {
+    pub fn get(&self, name: &str) -> Option<JobStatus> {
+        self.0.lock().unwrap().get(name).cloned()
+    }
+
+    pub fn all(&self) -> BTreeMap<String, JobStatus> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// Statuses as a JSON object, e.g. for `SystemInfoServiceImpl::with_jobs`
+    pub fn report(&self) -> serde_json::Value {
+        serde_json::to_value(self.all()).unwrap_or_default()
+    }
+
+    fn update<F: FnOnce(&mut JobStatus)>(&self, name: &str, f: F) {
+        f(self.0.lock().unwrap().entry(name.to_string()).or_default())
+    }
+}
+
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Vec<Job>,
+    lock: Option<Rc<RunLocked>>,
+    statuses: JobStatuses,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_job(mut self, job: Job) -> Self {
+        assert!(
+            self.jobs.iter().all(|registered| registered.name != job.name),
+            "Job {} is already registered",
+            job.name
+        );
+        self.jobs.push(job);
+        self
+    }
+
+    /// Runs each job holding lock `job:<name>`, so that only one instance runs it at a time
+    #[cfg(feature = "redis")]
+    pub fn with_redis_lock<M>(mut self, lock: RedisLock<M>) -> Self
+    where
+        M: ManageConnection<Connection = RedisConnection>,
+    {
+        self.lock = Some(Rc::new(move |name: &str, ttl: Duration, run: JobFuture| -> JobFuture {
+            Box::new(lock.with_lock(&format!("job:{}", name), ttl, run).map_err(|e| {
+                let busy = match e.downcast_ref::<LockError>() {
+                    Some(LockError::Busy(_)) => true,
+                    _ => false,
+                };
+                if busy {
+                    RunElsewhere.into()
+                } else {
+                    e
+                }
+            }))
+        }));
+        self
+    }
+
+    pub fn statuses(&self) -> JobStatuses {
+        self.statuses.clone()
+    }
+
+    /// Spawns the jobs on the reactor, where they run until it stops
+    pub fn start(self, handle: &Handle) -> JobStatuses {
+        for job in self.jobs {
+            self.statuses.update(&job.name, |_| ());
+            handle.spawn(Self::schedule(
+                Rc::new(job),
+                self.lock.clone(),
+                self.statuses.clone(),
+                handle.clone(),
+            ));
+        }
+        self.statuses
+    }
+
+    fn schedule(job: Rc<Job>, lock: Option<Rc<RunLocked>>, statuses: JobStatuses, handle: Handle) -> impl Future<Item = (), Error = ()> {
+        future::loop_fn((), move |()| -> Box<Future<Item = Loop<(), ()>, Error = ()>> {
+            let now = Utc::now();
+            let delay = match job.schedule.delay(now) {
+                Some(delay) => delay,
+                None => {
+                    warn!("Job {} has no more runs scheduled", job.name);
+                    return Box::new(future::ok(Loop::Break(())));
+                }
+            };
+            let timeout = match Timeout::new(delay, &handle) {
+                Ok(timeout) => timeout,
+                Err(e) => {
+                    error!("Could not schedule job {}: {}", job.name, e);
+                    return Box::new(future::ok(Loop::Break(())));
+                }
+            };
+            statuses.update(&job.name, |status| {
+                status.next_run_at = ChronoDuration::from_std(delay).ok().map(|delay| now + delay)
+            });
+
+            let job = job.clone();
+            let lock = lock.clone();
+            let statuses = statuses.clone();
+            Box::new(
+                timeout
+                    .map_err(|_| ())
+                    .and_then(move |()| Self::run(&job, lock, statuses))
+                    .map(|()| Loop::Continue(())),
+            )
+        })
+    }
+
+    fn run(job: &Rc<Job>, lock: Option<Rc<RunLocked>>, statuses: JobStatuses) -> impl Future<Item = (), Error = ()> {
+        // Status is updated once the lock is acquired
+        let run = {
+            let job = job.clone();
+            let statuses = statuses.clone();
+            Box::new(future::lazy(move || {
+                statuses.update(&job.name, |status| {
+                    status.running = true;
+                    status.next_run_at = None;
+                    status.last_started_at = Some(Utc::now());
+                });
+                (job.run)()
+            }))
+        };
+        let run = match lock {
+            Some(lock) => lock(&job.name, job.lock_ttl, run),
+            None => run,
+        };
+
+        let job = job.clone();
+        run.then(move |res| {
+            let skipped = match res {
+                Err(ref e) => e.downcast_ref::<RunElsewhere>().is_some(),
+                Ok(_) => false,
+            };
+
+            statuses.update(&job.name, |status| {
+                if skipped {
+                    status.skipped += 1;
+                    return;
+                }
+
+                let finished_at = Utc::now();
+                status.running = false;
+                status.last_finished_at = Some(finished_at);
+                status.last_duration_ms = status
+                    .last_started_at
+                    .map(|started_at| (finished_at - started_at).num_milliseconds().max(0) as u64);
+                status.last_error = res.as_ref().err().map(|e| e.to_string());
+                status.runs += 1;
+                if res.is_err() {
+                    status.failures += 1;
+                }
+            });
+
+            match res {
+                Err(_) if skipped => debug!("Job {} is run by another instance, skipping", job.name),
+                Err(e) => error!("Job {} failed: {}", job.name, e),
+                Ok(()) => debug!("Job {} finished", job.name),
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    use tokio_core::reactor::Core;
+
+    #[test]
+    fn test_scheduler() {
+        let mut core = Core::new().unwrap();
+        let runs = Rc::new(Cell::new(0));
+
+        let mut scheduler = Scheduler::new()
+            .with_job(Job::new("count", Schedule::Interval(Duration::from_millis(10)), {
+                let runs = runs.clone();
+                move || {
+                    runs.set(runs.get() + 1);
+                    Ok(())
+                }
+            }))
+            .with_job(Job::new("fail", Schedule::Interval(Duration::from_millis(10)), || {
+                Err(format_err!("Database is unavailable"))
+            }));
+        scheduler.lock = Some(Rc::new(|name: &str, _, run: JobFuture| -> JobFuture {
+            if name == "fail" {
+                Box::new(future::err(RunElsewhere.into()))
+            } else {
+                run
+            }
+        }));
+        let statuses = scheduler
+            .with_job(Job::new("daily", Schedule::cron("@daily").unwrap(), || Ok(())))
+            .start(&core.handle());
+
+        core.run(Timeout::new(Duration::from_millis(100), &core.handle()).unwrap()).unwrap();
+
+        let count = statuses.get("count").unwrap();
+        assert!(count.runs >= 2);
+        assert_eq!(count.runs, runs.get());
+        assert_eq!(count.failures, 0);
+        assert_eq!(count.last_error, None);
+        assert!(count.last_finished_at.is_some());
+
+        let fail = statuses.get("fail").unwrap();
+        assert_eq!(fail.runs, 0);
+        assert!(fail.skipped >= 2);
+        assert_eq!(fail.last_started_at, None);
+
+        let daily = statuses.get("daily").unwrap();
+        assert_eq!(daily.runs, 0);
+        assert!(daily.next_run_at.unwrap() > Utc::now());
+        assert_eq!(statuses.report()["daily"]["runs"], serde_json::Value::from(0));
+    }
+
+    #[test]
+    fn test_failed_runs_are_recorded() {
+        let mut core = Core::new().unwrap();
+        let statuses = Scheduler::new()
+            .with_job(Job::new("fail", Schedule::Interval(Duration::from_millis(10)), || {
+                Err(format_err!("Database is unavailable"))
+            }))
+            .start(&core.handle());
+
+        core.run(Timeout::new(Duration::from_millis(50), &core.handle()).unwrap()).unwrap();
+
+        let fail = statuses.get("fail").unwrap();
+        assert!(fail.runs >= 1);
+        assert_eq!(fail.failures, fail.runs);
+        assert_eq!(fail.last_error, Some("Database is unavailable".to_string()));
+        assert!(!fail.running);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_duplicate_jobs() {
+        Scheduler::new()
+            .with_job(Job::new("count", Schedule::Interval(Duration::from_secs(1)), || Ok(())))
+            .with_job(Job::new("count", Schedule::Interval(Duration::from_secs(1)), || Ok(())));
+    }
+}
@@ -13,6 +13,9 @@ use std::env;
 use std::io::Write;
 use std::sync::Arc;
 
+/// Structured fields added to every record, e.g. correlation token of the request being handled
+pub type Fields = Vec<(&'static str, String)>;
+
 pub struct CombinedLogger {
     pub inner: Vec<Arc<Log>>,
     pub filter: Box<Fn(&Record) -> bool + Send + Sync>,
@@ -47,6 +50,43 @@ impl Log for CombinedLogger {
     }
 }
 
+/// GELF logger sending fields of the context as additional fields of the message
+pub struct GelfLogger {
+    logger: gelf::Logger,
+    context: Arc<Fn() -> Fields + Send + Sync>,
+}
+
+impl GelfLogger {
+    pub fn new<F>(logger: gelf::Logger, context: F) -> Self
+    where
+        F: Fn() -> Fields + Send + Sync + 'static,
+    {
+        Self {
+            logger,
+            context: Arc::new(context),
+        }
+    }
+}
+
+impl Log for GelfLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let mut message = gelf::Message::from(record);
+        for (key, value) in (self.context)() {
+            // Fails only for names reserved by GELF, which context fields don't use
+            let _ = message.set_metadata(key, value);
+        }
+        self.logger.log_message(message);
+    }
+
+    fn flush(&self) {
+        self.logger.flush();
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GrayLogConfig {
     /// Endpoint to send messages to
@@ -55,17 +95,35 @@ pub struct GrayLogConfig {
 }
 
 pub fn init(graylog_config: Option<&GrayLogConfig>) {
+    init_with_context(graylog_config, Vec::new)
+}
+
+/// Same as `init`, but fields returned by `context` are added to every record, e.g. `stq_http::logging::current_fields`.
+/// Stdout gets them as `key="value"` pairs after the message, GrayLog gets them as additional fields.
+pub fn init_with_context<F>(graylog_config: Option<&GrayLogConfig>, context: F)
+where
+    F: Fn() -> Fields + Send + Sync + 'static,
+{
+    let context = Arc::new(context);
+
     let mut builder = EnvLogBuilder::new();
     builder
-        .format(|formatter, record| {
-            let now = Utc::now();
-            writeln!(
-                formatter,
-                "{} - {:5} - {}",
-                now.to_rfc3339(),
-                record.level(),
-                record.args()
-            )
+        .format({
+            let context = context.clone();
+            move |formatter, record| {
+                let now = Utc::now();
+                write!(
+                    formatter,
+                    "{} - {:5} - {}",
+                    now.to_rfc3339(),
+                    record.level(),
+                    record.args()
+                )?;
+                for (key, value) in context() {
+                    write!(formatter, " {}={:?}", key, value)?;
+                }
+                writeln!(formatter)
+            }
         })
         .filter(None, LogLevelFilter::Info);
 
@@ -96,6 +154,7 @@ pub fn init(graylog_config: Option<&GrayLogConfig>) {
         // separate logs
         logger.set_default_metadata("source_type", "backend");
 
+        let logger = GelfLogger::new(logger, move || context());
         combined_logger.inner.push(Arc::new(logger));
     }
 
@@ -0,0 +1,43 @@
+use chrono::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+use serde_json;
+use uuid::Uuid;
+
+use MqError;
+
+/// Message with metadata common to all events
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    /// Unique id of the message, consumers use it to skip redelivered messages they have already handled
+    pub id: Uuid,
+    pub event_type: String,
+    /// Correlation token of the request causing the event, passed on to requests made while handling it
+    pub correlation_token: String,
+    pub occurred_at: DateTime<Utc>,
+    pub payload: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn new<S: Into<String>>(event_type: S, correlation_token: String, payload: T) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event_type: event_type.into(),
+            correlation_token,
+            occurred_at: Utc::now(),
+            payload,
+        }
+    }
+}
+
+impl<T: Serialize> Envelope<T> {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MqError> {
+        serde_json::to_vec(self).map_err(|e| MqError::Serialize(e.to_string()))
+    }
+}
+
+impl<T: DeserializeOwned> Envelope<T> {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MqError> {
+        serde_json::from_slice(bytes).map_err(|e| MqError::Parse(e.to_string()))
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use futures::future;
+use futures::prelude::*;
+use futures::sync::mpsc;
+
+use {Consumer, Delivery, MqError, MqFuture, MqStream, Publisher};
+
+#[derive(Default)]
+struct Topic {
+    /// Messages published while there were no consumers
+    pending: VecDeque<Delivery>,
+    unacked: HashMap<u64, Delivery>,
+    consumers: Vec<mpsc::UnboundedSender<Delivery>>,
+    next_consumer: usize,
+    published: Vec<Vec<u8>>,
+}
+
+impl Topic {
+    /// Hands the message to consumers in turn, keeping it pending if there are none
+    fn deliver(&mut self, mut delivery: Delivery) {
+        while !self.consumers.is_empty() {
+            let i = self.next_consumer % self.consumers.len();
+            self.unacked.insert(delivery.delivery_tag, delivery.clone());
+            match self.consumers[i].unbounded_send(delivery) {
+                Ok(()) => {
+                    self.next_consumer = i + 1;
+                    return;
+                }
+                Err(e) => {
+                    delivery = e.into_inner();
+                    self.unacked.remove(&delivery.delivery_tag);
+                    self.consumers.remove(i);
+                }
+            }
+        }
+        self.pending.push_back(delivery);
+    }
+}
+
+#[derive(Default)]
+struct State {
+    topics: HashMap<String, Topic>,
+    next_delivery_tag: u64,
+}
+
+/// Queue living in the process memory, a test double for services using message queues.
+/// Clones share the messages.
+#[derive(Clone, Default)]
+pub struct InMemoryQueue(Arc<Mutex<State>>);
+
+impl InMemoryQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All messages ever published to the topic
+    pub fn published(&self, topic: &str) -> Vec<Vec<u8>> {
+        let state = self.0.lock().unwrap();
+        state.topics.get(topic).map(|topic| topic.published.clone()).unwrap_or_default()
+    }
+
+    /// Number of messages delivered to consumers but not acked yet
+    pub fn unacked(&self, topic: &str) -> usize {
+        let state = self.0.lock().unwrap();
+        state.topics.get(topic).map(|topic| topic.unacked.len()).unwrap_or(0)
+    }
+}
+
+impl Publisher for InMemoryQueue {
+    fn publish(&self, topic: &str, body: Vec<u8>) -> MqFuture<()> {
+        let mut state = self.0.lock().unwrap();
+        state.next_delivery_tag += 1;
+        let delivery = Delivery {
+            topic: topic.to_string(),
+            delivery_tag: state.next_delivery_tag,
+            redelivered: false,
+            body: body.clone(),
+        };
+
+        let topic = state.topics.entry(topic.to_string()).or_insert_with(Topic::default);
+        topic.published.push(body);
+        topic.deliver(delivery);
+
+        Box::new(future::ok(()))
+    }
+}
+
+impl Consumer for InMemoryQueue {
+    fn consume(&self, topic: &str) -> MqStream<Delivery> {
+        let (tx, rx) = mpsc::unbounded();
+
+        let mut state = self.0.lock().unwrap();
+        let topic = state.topics.entry(topic.to_string()).or_insert_with(Topic::default);
+        topic.consumers.push(tx);
+        while let Some(delivery) = topic.pending.pop_front() {
+            topic.deliver(delivery);
+        }
+
+        Box::new(rx.map_err(|_| MqError::Connection("In-memory queue is closed".to_string())))
+    }
+
+    fn ack(&self, delivery: &Delivery) -> MqFuture<()> {
+        let mut state = self.0.lock().unwrap();
+        let acked = state
+            .topics
+            .get_mut(&delivery.topic)
+            .and_then(|topic| topic.unacked.remove(&delivery.delivery_tag));
+
+        Box::new(future::result(
+            acked.map(|_| ()).ok_or(MqError::UnknownDelivery(delivery.delivery_tag)),
+        ))
+    }
+
+    fn nack(&self, delivery: &Delivery, requeue: bool) -> MqFuture<()> {
+        let mut state = self.0.lock().unwrap();
+        let topic = match state.topics.get_mut(&delivery.topic) {
+            Some(topic) => topic,
+            None => return Box::new(future::err(MqError::UnknownDelivery(delivery.delivery_tag))),
+        };
+
+        match topic.unacked.remove(&delivery.delivery_tag) {
+            Some(mut delivery) => {
+                if requeue {
+                    delivery.redelivered = true;
+                    topic.deliver(delivery);
+                }
+                Box::new(future::ok(()))
+            }
+            None => Box::new(future::err(MqError::UnknownDelivery(delivery.delivery_tag))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Envelope;
+
+    #[test]
+    fn test_publish_and_consume() {
+        let queue = InMemoryQueue::new();
+        let envelope = Envelope::new("order_paid", "token".to_string(), 42);
+        queue.publish_envelope("billing", &envelope).wait().unwrap();
+
+        let mut deliveries = queue.consume("billing").wait();
+        let delivery = deliveries.next().unwrap().unwrap();
+        assert_eq!(delivery.envelope::<i32>().unwrap(), envelope);
+        assert!(!delivery.redelivered);
+        assert_eq!(queue.unacked("billing"), 1);
+
+        queue.ack(&delivery).wait().unwrap();
+        assert_eq!(queue.unacked("billing"), 0);
+        assert_eq!(queue.ack(&delivery).wait(), Err(MqError::UnknownDelivery(delivery.delivery_tag)));
+        assert_eq!(queue.published("billing"), vec![envelope.to_bytes().unwrap()]);
+    }
+
+    #[test]
+    fn test_nack_requeues() {
+        let queue = InMemoryQueue::new();
+        let mut deliveries = queue.consume("notifications").wait();
+        queue.publish("notifications", b"{}".to_vec()).wait().unwrap();
+
+        let delivery = deliveries.next().unwrap().unwrap();
+        queue.nack(&delivery, true).wait().unwrap();
+
+        let redelivery = deliveries.next().unwrap().unwrap();
+        assert!(redelivery.redelivered);
+        assert_eq!(redelivery.body, delivery.body);
+
+        queue.nack(&redelivery, false).wait().unwrap();
+        assert_eq!(queue.unacked("notifications"), 0);
+    }
+}
@@ -0,0 +1,88 @@
+//! Message queue abstraction, so that services can move saga steps off synchronous HTTP.
+//!
+//! Messages are JSON `Envelope`s published to topics. Delivery is at-least-once: a message is redelivered
+//! until it is acked, so consumers must deduplicate by envelope id.
+extern crate chrono;
+#[macro_use]
+extern crate failure;
+extern crate futures;
+#[cfg(feature = "rabbitmq")]
+extern crate lapin_futures;
+#[cfg(feature = "rabbitmq")]
+#[macro_use]
+extern crate log;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+#[cfg(feature = "rabbitmq")]
+extern crate tokio;
+extern crate uuid;
+
+pub mod envelope;
+pub mod in_memory;
+#[cfg(feature = "rabbitmq")]
+pub mod rabbitmq;
+
+pub use envelope::Envelope;
+pub use in_memory::InMemoryQueue;
+
+use futures::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+
+#[derive(Clone, Debug, Fail, PartialEq)]
+pub enum MqError {
+    #[fail(display = "Failed to serialize message: {}", _0)]
+    Serialize(String),
+    #[fail(display = "Failed to parse message: {}", _0)]
+    Parse(String),
+    #[fail(display = "Message queue connection error: {}", _0)]
+    Connection(String),
+    #[fail(display = "Delivery {} is not awaiting acknowledgement", _0)]
+    UnknownDelivery(u64),
+}
+
+pub type MqFuture<T> = Box<Future<Item = T, Error = MqError>>;
+pub type MqStream<T> = Box<Stream<Item = T, Error = MqError>>;
+
+/// Received message. It must be acked once handled, otherwise it is redelivered.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delivery {
+    pub topic: String,
+    pub delivery_tag: u64,
+    /// Message was delivered before, but not acked
+    pub redelivered: bool,
+    pub body: Vec<u8>,
+}
+
+impl Delivery {
+    pub fn envelope<T: DeserializeOwned>(&self) -> Result<Envelope<T>, MqError> {
+        Envelope::from_bytes(&self.body)
+    }
+}
+
+pub trait Publisher {
+    /// Resolves once the broker has accepted the message
+    fn publish(&self, topic: &str, body: Vec<u8>) -> MqFuture<()>;
+
+    fn publish_envelope<T: Serialize>(&self, topic: &str, envelope: &Envelope<T>) -> MqFuture<()>
+    where
+        Self: Sized,
+    {
+        match envelope.to_bytes() {
+            Ok(body) => self.publish(topic, body),
+            Err(e) => Box::new(futures::future::err(e)),
+        }
+    }
+}
+
+pub trait Consumer {
+    /// Messages of the topic, published both before and after the call
+    fn consume(&self, topic: &str) -> MqStream<Delivery>;
+
+    fn ack(&self, delivery: &Delivery) -> MqFuture<()>;
+
+    /// Rejects the message, returning it to the topic for redelivery if `requeue` is set
+    fn nack(&self, delivery: &Delivery, requeue: bool) -> MqFuture<()>;
+}
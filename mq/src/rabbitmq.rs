@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use futures::future;
+use futures::prelude::*;
+use lapin_futures::channel::{BasicConsumeOptions, BasicProperties, BasicPublishOptions, BasicQosOptions, Channel, QueueDeclareOptions};
+use lapin_futures::client::{Client, ConnectionOptions};
+use lapin_futures::types::FieldTable;
+use tokio;
+use tokio::net::TcpStream;
+
+use {Consumer, Delivery, MqError, MqFuture, MqStream, Publisher};
+
+#[derive(Clone, Debug)]
+pub struct RabbitMqConfig {
+    pub addr: SocketAddr,
+    pub username: String,
+    pub password: String,
+    pub vhost: String,
+    /// Maximum number of unacked messages delivered to a consumer
+    pub prefetch_count: u16,
+}
+
+fn connection_error<E: ToString>(e: E) -> MqError {
+    MqError::Connection(e.to_string())
+}
+
+/// RabbitMQ client over a single channel. Topics are durable queues, messages are published through the default
+/// exchange with the queue name as the routing key and are persistent.
+#[derive(Clone)]
+pub struct RabbitMqClient {
+    channel: Channel<TcpStream>,
+    /// Queues declared by this client, so that they are not declared on every publish
+    declared: Arc<Mutex<HashSet<String>>>,
+}
+
+impl RabbitMqClient {
+    /// Connects to the broker, the connection heartbeat is spawned on the default tokio executor
+    pub fn connect(config: RabbitMqConfig) -> impl Future<Item = Self, Error = MqError> {
+        let options = ConnectionOptions {
+            username: config.username,
+            password: config.password,
+            vhost: config.vhost,
+            ..Default::default()
+        };
+        let prefetch_count = config.prefetch_count;
+
+        TcpStream::connect(&config.addr)
+            .map_err(connection_error)
+            .and_then(move |stream| Client::connect(stream, options).map_err(connection_error))
+            .and_then(|(client, heartbeat)| {
+                tokio::spawn(heartbeat.map_err(|e| error!("RabbitMQ heartbeat failed: {}", e)));
+                client.create_channel().map_err(connection_error)
+            })
+            .and_then(move |channel| {
+                channel
+                    .basic_qos(BasicQosOptions {
+                        prefetch_count,
+                        ..Default::default()
+                    })
+                    .map_err(connection_error)
+                    .map(move |_| RabbitMqClient {
+                        channel,
+                        declared: Default::default(),
+                    })
+            })
+    }
+
+    fn declare_queue(&self, topic: &str) -> MqFuture<()> {
+        if self.declared.lock().unwrap().contains(topic) {
+            return Box::new(future::ok(()));
+        }
+
+        let declared = self.declared.clone();
+        let topic = topic.to_string();
+        Box::new(
+            self.channel
+                .queue_declare(
+                    &topic,
+                    QueueDeclareOptions {
+                        durable: true,
+                        ..Default::default()
+                    },
+                    FieldTable::new(),
+                )
+                .map_err(connection_error)
+                .map(move |_| {
+                    declared.lock().unwrap().insert(topic);
+                }),
+        )
+    }
+}
+
+impl Publisher for RabbitMqClient {
+    fn publish(&self, topic: &str, body: Vec<u8>) -> MqFuture<()> {
+        let channel = self.channel.clone();
+        let topic = topic.to_string();
+
+        Box::new(self.declare_queue(&topic).and_then(move |_| {
+            channel
+                .basic_publish(
+                    "",
+                    &topic,
+                    body,
+                    BasicPublishOptions::default(),
+                    BasicProperties::default().with_delivery_mode(2),
+                )
+                .map(|_| ())
+                .map_err(connection_error)
+        }))
+    }
+}
+
+impl Consumer for RabbitMqClient {
+    fn consume(&self, topic: &str) -> MqStream<Delivery> {
+        let channel = self.channel.clone();
+        let topic = topic.to_string();
+
+        // Consuming requires the declared queue itself, so it is declared regardless of the cache
+        Box::new(
+            self.channel
+                .queue_declare(
+                    &topic,
+                    QueueDeclareOptions {
+                        durable: true,
+                        ..Default::default()
+                    },
+                    FieldTable::new(),
+                )
+                .and_then(move |queue| channel.basic_consume(&queue, "", BasicConsumeOptions::default(), FieldTable::new()))
+                .map(move |consumer| {
+                    consumer
+                        .map(move |message| Delivery {
+                            topic: topic.clone(),
+                            delivery_tag: message.delivery_tag,
+                            redelivered: message.redelivered,
+                            body: message.data,
+                        })
+                        .map_err(connection_error)
+                })
+                .map_err(connection_error)
+                .flatten_stream(),
+        )
+    }
+
+    fn ack(&self, delivery: &Delivery) -> MqFuture<()> {
+        Box::new(self.channel.basic_ack(delivery.delivery_tag, false).map_err(connection_error))
+    }
+
+    fn nack(&self, delivery: &Delivery, requeue: bool) -> MqFuture<()> {
+        Box::new(
+            self.channel
+                .basic_nack(delivery.delivery_tag, false, requeue)
+                .map_err(connection_error),
+        )
+    }
+}
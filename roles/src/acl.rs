@@ -0,0 +1,212 @@
+//! Standard repo ACL engines, so that services compose them instead of writing the same closures.
+//!
+//! ```ignore
+//! let acl = SuperuserOverride::new(login.clone(), OwnerOnly::new(login, |cart: &Cart| cart.user_id));
+//! CartRepoImpl::new(TABLE).with_afterop_acl_engine(acl)
+//! ```
+use futures::future;
+
+use stq_acl::*;
+use stq_db::repo::Action;
+use stq_types::*;
+
+use models::*;
+
+impl<T> RepoLogin<T>
+where
+    T: RoleModel,
+{
+    pub fn caller_id(&self) -> Option<UserId> {
+        match self {
+            RepoLogin::Anonymous => None,
+            RepoLogin::User { caller_id, .. } => Some(*caller_id),
+        }
+    }
+
+    pub fn is_su(&self) -> bool {
+        match self {
+            RepoLogin::Anonymous => false,
+            RepoLogin::User { caller_roles, .. } => caller_roles.iter().any(|entry| entry.role.is_su()),
+        }
+    }
+
+    /// Checks whether caller has a role managing the store
+    pub fn manages_store(&self, store_id: StoreId) -> bool {
+        match self {
+            RepoLogin::Anonymous => false,
+            RepoLogin::User { caller_roles, .. } => caller_roles.iter().any(|entry| entry.role.managed_store() == Some(store_id)),
+        }
+    }
+}
+
+/// Allows any action on entities owned by the caller. `user_id_column` extracts the owner of the entity.
+pub struct OwnerOnly<T, F> {
+    pub login: RepoLogin<T>,
+    pub user_id_column: F,
+}
+
+impl<T, F> OwnerOnly<T, F> {
+    pub fn new(login: RepoLogin<T>, user_id_column: F) -> Self {
+        Self { login, user_id_column }
+    }
+}
+
+impl<T, F, E, Error> AclEngine<(E, Action), Error> for OwnerOnly<T, F>
+where
+    T: RoleModel,
+    F: Fn(&E) -> UserId,
+    E: 'static,
+    Error: From<UnauthorizedError> + 'static,
+{
+    fn allows(&self, ctx: (E, Action)) -> Verdict<(E, Action), Error> {
+        let allowed = self.login.caller_id() == Some((self.user_id_column)(&ctx.0));
+        Box::new(future::ok((allowed, ctx)))
+    }
+}
+
+/// Allows any action on entities of stores managed by the caller, see `RoleModel::managed_store`.
+/// `store_id_extractor` extracts the store of the entity.
+pub struct StoreManagerOnly<T, F> {
+    pub login: RepoLogin<T>,
+    pub store_id_extractor: F,
+}
+
+impl<T, F> StoreManagerOnly<T, F> {
+    pub fn new(login: RepoLogin<T>, store_id_extractor: F) -> Self {
+        Self { login, store_id_extractor }
+    }
+}
+
+impl<T, F, E, Error> AclEngine<(E, Action), Error> for StoreManagerOnly<T, F>
+where
+    T: RoleModel,
+    F: Fn(&E) -> StoreId,
+    E: 'static,
+    Error: From<UnauthorizedError> + 'static,
+{
+    fn allows(&self, ctx: (E, Action)) -> Verdict<(E, Action), Error> {
+        let allowed = self.login.manages_store((self.store_id_extractor)(&ctx.0));
+        Box::new(future::ok((allowed, ctx)))
+    }
+}
+
+/// Allows superusers anything, otherwise asks the inner engine
+pub struct SuperuserOverride<T, E> {
+    pub login: RepoLogin<T>,
+    pub inner: E,
+}
+
+impl<T, E> SuperuserOverride<T, E> {
+    pub fn new(login: RepoLogin<T>, inner: E) -> Self {
+        Self { login, inner }
+    }
+}
+
+impl<T, E, Context, Error> AclEngine<Context, Error> for SuperuserOverride<T, E>
+where
+    T: RoleModel,
+    E: AclEngine<Context, Error>,
+    Context: 'static,
+    Error: From<UnauthorizedError> + 'static,
+{
+    fn allows(&self, ctx: Context) -> Verdict<Context, Error> {
+        if self.login.is_su() {
+            Box::new(future::ok((true, ctx)))
+        } else {
+            self.inner.allows(ctx)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::Future;
+    use serde_json::Value;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum TestRole {
+        Superuser,
+        StoreManager(StoreId),
+        User,
+    }
+
+    impl RoleModel for TestRole {
+        fn is_su(&self) -> bool {
+            *self == TestRole::Superuser
+        }
+
+        fn managed_store(&self) -> Option<StoreId> {
+            match self {
+                TestRole::StoreManager(store_id) => Some(*store_id),
+                _ => None,
+            }
+        }
+
+        fn from_db(_variant: &str, _data: Value) -> Result<Self, ::failure::Error> {
+            unreachable!()
+        }
+
+        fn into_db(self) -> (String, Value) {
+            unreachable!()
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct Product {
+        user_id: UserId,
+        store_id: StoreId,
+    }
+
+    fn login(caller_id: i32, role: TestRole) -> RepoLogin<TestRole> {
+        RepoLogin::User {
+            caller_id: UserId(caller_id),
+            caller_roles: vec![RoleEntry {
+                id: RoleEntryId::new(),
+                user_id: UserId(caller_id),
+                role,
+                expires_at: None,
+            }],
+        }
+    }
+
+    fn allows<E: AclEngine<(Product, Action), ::failure::Error>>(engine: &E) -> bool {
+        let product = Product {
+            user_id: UserId(1),
+            store_id: StoreId(10),
+        };
+        engine.allows((product, Action::Update)).wait().map(|(allowed, _)| allowed).unwrap()
+    }
+
+    fn owner_only(login: RepoLogin<TestRole>) -> OwnerOnly<TestRole, impl Fn(&Product) -> UserId> {
+        OwnerOnly::new(login, |product: &Product| product.user_id)
+    }
+
+    #[test]
+    fn test_owner_only() {
+        assert!(allows(&owner_only(login(1, TestRole::User))));
+        assert!(!allows(&owner_only(login(2, TestRole::User))));
+        assert!(!allows(&owner_only(login(2, TestRole::Superuser))));
+        assert!(!allows(&owner_only(RepoLogin::Anonymous)));
+    }
+
+    #[test]
+    fn test_store_manager_only() {
+        let engine = |login| StoreManagerOnly::new(login, |product: &Product| product.store_id);
+
+        assert!(allows(&engine(login(2, TestRole::StoreManager(StoreId(10))))));
+        assert!(!allows(&engine(login(2, TestRole::StoreManager(StoreId(11))))));
+        assert!(!allows(&engine(login(1, TestRole::User))));
+        assert!(!allows(&engine(RepoLogin::Anonymous)));
+    }
+
+    #[test]
+    fn test_superuser_override() {
+        let engine = |login: RepoLogin<TestRole>| SuperuserOverride::new(login.clone(), owner_only(login));
+
+        assert!(allows(&engine(login(2, TestRole::Superuser))));
+        assert!(allows(&engine(login(1, TestRole::User))));
+        assert!(!allows(&engine(login(2, TestRole::User))));
+    }
+}
@@ -0,0 +1,107 @@
+use models::*;
+
+use std::marker::PhantomData;
+use stq_cache::cache::Cache;
+use stq_types::*;
+
+/// Cache of user roles used to avoid hitting the database on every request.
+/// Errors of the underlying cache are logged and treated as cache misses.
+/// Use backend capabilities (e.g. `RedisCache::with_ttl`) to limit entry lifetime.
+pub trait RolesCache<T> {
+    fn get(&self, user_id: UserId) -> Option<Vec<RoleEntry<T>>>;
+    fn set(&self, user_id: UserId, roles: Vec<RoleEntry<T>>);
+    fn remove(&self, user_id: UserId);
+}
+
+pub struct RolesCacheImpl<C, T> {
+    backend: C,
+    phantom: PhantomData<T>,
+}
+
+impl<C, T> RolesCacheImpl<C, T>
+where
+    C: Cache<Vec<RoleEntry<T>>>,
+{
+    pub fn new(backend: C) -> Self {
+        Self {
+            backend,
+            phantom: PhantomData,
+        }
+    }
+}
+
+fn make_key(user_id: UserId) -> String {
+    format!("{}", user_id)
+}
+
+impl<C, T> RolesCache<T> for RolesCacheImpl<C, T>
+where
+    C: Cache<Vec<RoleEntry<T>>>,
+{
+    fn get(&self, user_id: UserId) -> Option<Vec<RoleEntry<T>>> {
+        match self.backend.get(&make_key(user_id)) {
+            Ok(roles) => roles,
+            Err(e) => {
+                error!("Failed to get roles for user {} from cache: {}", user_id, e);
+                None
+            }
+        }
+    }
+
+    fn set(&self, user_id: UserId, roles: Vec<RoleEntry<T>>) {
+        if let Err(e) = self.backend.set(&make_key(user_id), roles) {
+            error!("Failed to put roles for user {} into cache: {}", user_id, e);
+        }
+    }
+
+    fn remove(&self, user_id: UserId) {
+        if let Err(e) = self.backend.remove(&make_key(user_id)) {
+            error!("Failed to remove roles for user {} from cache: {}", user_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::Value;
+    use stq_cache::cache::InMemoryCache;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct TestRole;
+
+    impl RoleModel for TestRole {
+        fn is_su(&self) -> bool {
+            false
+        }
+
+        fn from_db(_variant: &str, _data: Value) -> Result<Self, ::failure::Error> {
+            Ok(TestRole)
+        }
+
+        fn into_db(self) -> (String, Value) {
+            ("test".to_string(), Value::Null)
+        }
+    }
+
+    #[test]
+    fn test_roles_cache() {
+        let cache = RolesCacheImpl::new(InMemoryCache::<Vec<RoleEntry<TestRole>>>::new());
+        let user_id = UserId(1);
+        let roles = vec![RoleEntry {
+            id: RoleEntryId::new(),
+            user_id,
+            role: TestRole,
+            expires_at: None,
+        }];
+
+        assert_eq!(cache.get(user_id), None);
+
+        cache.set(user_id, roles.clone());
+        assert_eq!(cache.get(user_id), Some(roles));
+
+        cache.remove(user_id);
+        assert_eq!(cache.get(user_id), None);
+    }
+}
@@ -0,0 +1,133 @@
+use models::*;
+
+/// Declares roles implying other roles, e.g. superuser implies moderator which implies user.
+/// Implications are transitive, so only direct ones need to be declared.
+#[derive(Clone, Debug)]
+pub struct RoleHierarchy<T> {
+    implications: Vec<(T, T)>,
+}
+
+impl<T> Default for RoleHierarchy<T> {
+    fn default() -> Self {
+        Self {
+            implications: Default::default(),
+        }
+    }
+}
+
+impl<T> RoleHierarchy<T>
+where
+    T: PartialEq + Clone,
+{
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Declares that having `role` grants `implied` as well
+    pub fn with_implication(mut self, role: T, implied: T) -> Self {
+        self.implications.push((role, implied));
+        self
+    }
+
+    /// Returns the role itself and all roles it implies
+    pub fn expand(&self, role: &T) -> Vec<T> {
+        let mut out = vec![role.clone()];
+        let mut i = 0;
+        while i < out.len() {
+            for (from, to) in &self.implications {
+                if *from == out[i] && !out.contains(to) {
+                    out.push(to.clone());
+                }
+            }
+            i += 1;
+        }
+        out
+    }
+
+    /// Checks whether `role` is `required` or implies it
+    pub fn implies(&self, role: &T, required: &T) -> bool {
+        self.expand(role).contains(required)
+    }
+}
+
+impl<T> RepoLogin<T>
+where
+    T: RoleModel + PartialEq,
+{
+    /// Checks whether caller has `required` role, either directly or via hierarchy. Superusers have every role.
+    pub fn has_role(&self, hierarchy: &RoleHierarchy<T>, required: &T) -> bool {
+        match self {
+            RepoLogin::Anonymous => false,
+            RepoLogin::User { caller_roles, .. } => caller_roles
+                .iter()
+                .any(|entry| entry.role.is_su() || hierarchy.implies(&entry.role, required)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::Value;
+    use stq_types::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum TestRole {
+        Superuser,
+        Moderator,
+        User,
+        Guest,
+    }
+
+    impl RoleModel for TestRole {
+        fn is_su(&self) -> bool {
+            *self == TestRole::Superuser
+        }
+
+        fn from_db(_variant: &str, _data: Value) -> Result<Self, ::failure::Error> {
+            unreachable!()
+        }
+
+        fn into_db(self) -> (String, Value) {
+            unreachable!()
+        }
+    }
+
+    fn make_hierarchy() -> RoleHierarchy<TestRole> {
+        RoleHierarchy::new()
+            .with_implication(TestRole::Superuser, TestRole::Moderator)
+            .with_implication(TestRole::Moderator, TestRole::User)
+    }
+
+    #[test]
+    fn test_role_hierarchy() {
+        let hierarchy = make_hierarchy();
+
+        assert_eq!(
+            hierarchy.expand(&TestRole::Superuser),
+            vec![TestRole::Superuser, TestRole::Moderator, TestRole::User]
+        );
+        assert!(hierarchy.implies(&TestRole::Moderator, &TestRole::User));
+        assert!(!hierarchy.implies(&TestRole::User, &TestRole::Moderator));
+        assert!(!hierarchy.implies(&TestRole::Moderator, &TestRole::Guest));
+    }
+
+    #[test]
+    fn test_repo_login_has_role() {
+        let hierarchy = make_hierarchy();
+        let login = RepoLogin::User {
+            caller_id: UserId(1),
+            caller_roles: vec![RoleEntry {
+                id: RoleEntryId::new(),
+                user_id: UserId(1),
+                role: TestRole::Moderator,
+                expires_at: None,
+            }],
+        };
+
+        assert!(login.has_role(&hierarchy, &TestRole::User));
+        assert!(!login.has_role(&hierarchy, &TestRole::Superuser));
+        assert!(!RepoLogin::Anonymous.has_role(&hierarchy, &TestRole::User));
+    }
+}
@@ -1,18 +1,27 @@
 extern crate failure;
 extern crate futures;
 extern crate hyper;
+#[macro_use]
+extern crate log;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
+extern crate serde_urlencoded;
 extern crate stq_acl;
+extern crate stq_cache;
 extern crate stq_db;
 extern crate stq_http;
 extern crate stq_router;
 extern crate stq_types;
 extern crate tokio_postgres;
 extern crate uuid;
+extern crate validator;
 
+pub mod acl;
+pub mod cache;
+pub mod hierarchy;
+pub mod middleware;
 pub mod models;
 pub mod repo;
 pub mod routing;
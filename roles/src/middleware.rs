@@ -0,0 +1,64 @@
+use cache::*;
+use models::*;
+use service::*;
+
+use futures::prelude::*;
+use hyper::header::Authorization;
+use hyper::Request;
+use std::rc::Rc;
+use stq_db::pool::Pool as DbPool;
+use stq_http::controller::{Controller, ControllerFuture};
+use stq_types::*;
+
+/// Reads caller id from `Authorization` header. Missing or malformed header means anonymous caller.
+pub fn get_user_id(req: &Request) -> Option<UserId> {
+    req.headers()
+        .get::<Authorization<String>>()
+        .and_then(|auth| auth.0.parse().ok())
+        .map(UserId)
+}
+
+/// Controller wrapper resolving `RepoLogin` of the caller before passing request to the handler
+pub struct RepoLoginMiddleware<T, F> {
+    pub db_pool: DbPool,
+    pub cache: Option<Rc<RolesCache<T>>>,
+    pub handler: Rc<F>,
+}
+
+impl<T, F> RepoLoginMiddleware<T, F>
+where
+    T: RoleModel,
+    F: Fn(Request, RepoLogin<T>) -> ControllerFuture + 'static,
+{
+    pub fn new(db_pool: DbPool, handler: F) -> Self {
+        Self {
+            db_pool,
+            cache: None,
+            handler: Rc::new(handler),
+        }
+    }
+
+    /// Reads caller roles through the cache instead of hitting the database on every request
+    pub fn with_cache(mut self, cache: Rc<RolesCache<T>>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    fn get_login(&self, caller_id: Option<UserId>) -> RepoLoginFuture<T> {
+        match self.cache {
+            Some(ref cache) => get_login_data_cached(&self.db_pool, cache.clone(), caller_id),
+            None => get_login_data(&self.db_pool, caller_id),
+        }
+    }
+}
+
+impl<T, F> Controller for RepoLoginMiddleware<T, F>
+where
+    T: RoleModel,
+    F: Fn(Request, RepoLogin<T>) -> ControllerFuture + 'static,
+{
+    fn call(&self, req: Request) -> ControllerFuture {
+        let handler = self.handler.clone();
+        Box::new(self.get_login(get_user_id(&req)).and_then(move |login| (handler)(req, login)))
+    }
+}
@@ -1,19 +1,32 @@
 use failure;
 use futures::prelude::*;
-use serde_json::Value;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{self, Value};
+use serde_urlencoded;
 use std::fmt::Debug;
 use std::rc::Rc;
+use std::time::SystemTime;
 use stq_db::statement::*;
 use stq_types::*;
 use tokio_postgres::rows::Row;
+use uuid::Uuid;
 
 pub const ID_COLUMN: &str = "id";
 pub const USER_ID_COLUMN: &str = "user_id";
 pub const ROLE_NAME_COLUMN: &str = "name";
 pub const ROLE_DATA_COLUMN: &str = "data";
+pub const EXPIRES_AT_COLUMN: &str = "expires_at";
+pub const ACTOR_ID_COLUMN: &str = "actor_id";
+pub const ACTION_COLUMN: &str = "action";
+pub const CREATED_AT_COLUMN: &str = "created_at";
+pub const CORRELATION_TOKEN_COLUMN: &str = "correlation_token";
 
 pub trait RoleModel: Clone + Debug + 'static {
     fn is_su(&self) -> bool;
+    /// Store the role grants management of, if any
+    fn managed_store(&self) -> Option<StoreId> {
+        None
+    }
     fn from_db(variant: &str, data: Value) -> Result<Self, failure::Error>;
     fn into_db(self) -> (String, Value);
 }
@@ -23,6 +36,18 @@ pub struct RoleEntry<T> {
     pub id: RoleEntryId,
     pub user_id: UserId,
     pub role: T,
+    #[serde(default)]
+    pub expires_at: Option<SystemTime>,
+}
+
+impl<T> RoleEntry<T> {
+    /// Role without expiration date never expires
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        match self.expires_at {
+            Some(expires_at) => expires_at <= now,
+            None => false,
+        }
+    }
 }
 
 impl<T> From<Row> for RoleEntry<T>
@@ -34,6 +59,7 @@ where
             id: RoleEntryId(row.get(ID_COLUMN)),
             user_id: UserId(row.get(USER_ID_COLUMN)),
             role: T::from_db(row.get(ROLE_NAME_COLUMN), row.get(ROLE_DATA_COLUMN)).unwrap(),
+            expires_at: row.get(EXPIRES_AT_COLUMN),
         }
     }
 }
@@ -42,13 +68,14 @@ impl<T> Inserter for RoleEntry<T>
 where
     T: RoleModel,
 {
-    fn into_insert_builder(self, table: &'static str) -> InsertBuilder {
+    fn into_insert_builder(self, table: TableName) -> InsertBuilder {
         let (role_name, role_data) = T::into_db(self.role);
         InsertBuilder::new(table)
             .with_arg(ID_COLUMN, self.id.0)
             .with_arg(USER_ID_COLUMN, self.user_id.0)
             .with_arg(ROLE_NAME_COLUMN, role_name)
             .with_arg(ROLE_DATA_COLUMN, role_data)
+            .with_arg(EXPIRES_AT_COLUMN, self.expires_at)
     }
 }
 
@@ -57,6 +84,14 @@ pub struct RoleFilter<T> {
     pub id: Option<RoleEntryId>,
     pub user_id: Option<UserId>,
     pub role: Option<T>,
+    pub expired_before: Option<SystemTime>,
+    /// Keep only entries not expired at this time
+    pub unexpired_at: Option<SystemTime>,
+    /// Keyset pagination: return entries of users with id greater than this one
+    pub user_id_after: Option<UserId>,
+    pub name: Option<String>,
+    /// Offset pagination
+    pub offset: Option<i64>,
 }
 
 impl<T> Default for RoleFilter<T> {
@@ -65,6 +100,11 @@ impl<T> Default for RoleFilter<T> {
             id: Default::default(),
             user_id: Default::default(),
             role: Default::default(),
+            expired_before: Default::default(),
+            unexpired_at: Default::default(),
+            user_id_after: Default::default(),
+            name: Default::default(),
+            offset: Default::default(),
         }
     }
 }
@@ -73,7 +113,7 @@ impl<T> Filter for RoleFilter<T>
 where
     T: RoleModel,
 {
-    fn into_filtered_operation_builder(self, table: &'static str) -> FilteredOperationBuilder {
+    fn into_filtered_operation_builder(self, table: TableName) -> FilteredOperationBuilder {
         let mut b = FilteredOperationBuilder::new(table);
 
         if let Some(id) = self.id {
@@ -89,7 +129,126 @@ where
             b = b.with_filter(ROLE_NAME_COLUMN, role_name).with_filter(ROLE_DATA_COLUMN, role_data);
         }
 
-        b
+        if let Some(expired_before) = self.expired_before {
+            b = b.with_filter::<SystemTime, _>(
+                EXPIRES_AT_COLUMN,
+                Range::To(RangeLimit {
+                    value: expired_before,
+                    inclusive: true,
+                }),
+            );
+        }
+
+        if let Some(unexpired_at) = self.unexpired_at {
+            b = b.with_nullable_filter::<SystemTime, _>(
+                EXPIRES_AT_COLUMN,
+                Range::From(RangeLimit {
+                    value: unexpired_at,
+                    inclusive: false,
+                }),
+            );
+        }
+
+        if let Some(name) = self.name {
+            b = b.with_filter(ROLE_NAME_COLUMN, name);
+        }
+
+        if self.offset.is_some() {
+            b = b.with_offset(self.offset);
+        }
+
+        if let Some(user_id_after) = self.user_id_after {
+            b = b.with_filter::<i32, _>(
+                USER_ID_COLUMN,
+                Range::From(RangeLimit {
+                    value: user_id_after.0,
+                    inclusive: false,
+                }),
+            );
+        }
+
+        // Both offset and keyset pagination need a stable order
+        b.with_order_by(&[USER_ID_COLUMN, ID_COLUMN])
+    }
+}
+
+/// Filtering and pagination of user roles listing, passed as query parameters
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RolesQuery {
+    pub name: Option<String>,
+    pub limit: Option<i32>,
+    pub offset: Option<i64>,
+}
+
+impl RolesQuery {
+    pub fn from_query(query: &str) -> Result<Self, failure::Error> {
+        Ok(serde_urlencoded::from_str(query)?)
+    }
+
+    pub fn to_query(&self) -> Result<String, failure::Error> {
+        Ok(serde_urlencoded::to_string(self)?)
+    }
+
+    pub fn into_filter<T>(self, user_id: UserId) -> RoleFilter<T> {
+        RoleFilter {
+            user_id: Some(user_id),
+            name: self.name,
+            unexpired_at: Some(SystemTime::now()),
+            offset: self.offset,
+            ..Default::default()
+        }
+    }
+}
+
+/// Replaces `data` payload of the role entry
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoleUpdater {
+    pub id: RoleEntryId,
+    pub data: Value,
+}
+
+impl Updater for RoleUpdater {
+    fn into_update_builder(self, table: TableName) -> UpdateBuilder {
+        UpdateBuilder::from(FilteredOperationBuilder::new(table).with_filter(ID_COLUMN, self.id.0)).with_value(ROLE_DATA_COLUMN, self.data)
+    }
+}
+
+/// Listing of users having the role, `from` and `count` paginate by user id
+#[derive(Clone, Debug, PartialEq)]
+pub struct UsersByRoleQuery<T> {
+    pub role: T,
+    pub from: Option<UserId>,
+    pub count: Option<i32>,
+}
+
+/// Query parameters of `UsersByRoleQuery`, the role is JSON encoded as it may carry data
+#[derive(Serialize, Deserialize)]
+struct UsersByRoleParams {
+    role: String,
+    from: Option<UserId>,
+    count: Option<i32>,
+}
+
+impl<T> UsersByRoleQuery<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    pub fn from_query(query: &str) -> Result<Self, failure::Error> {
+        let params = serde_urlencoded::from_str::<UsersByRoleParams>(query)?;
+        Ok(Self {
+            role: serde_json::from_str(&params.role)?,
+            from: params.from,
+            count: params.count,
+        })
+    }
+
+    pub fn to_query(&self) -> Result<String, failure::Error> {
+        let params = UsersByRoleParams {
+            role: serde_json::to_string(&self.role)?,
+            from: self.from,
+            count: self.count,
+        };
+        Ok(serde_urlencoded::to_string(params)?)
     }
 }
 
@@ -117,6 +276,111 @@ impl<T> From<RoleSearchTerms<T>> for RoleFilter<T> {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoleAuditAction {
+    Create,
+    Update,
+    Remove,
+}
+
+impl RoleAuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoleAuditAction::Create => "create",
+            RoleAuditAction::Update => "update",
+            RoleAuditAction::Remove => "remove",
+        }
+    }
+
+    pub fn from_db(s: &str) -> Result<Self, failure::Error> {
+        match s {
+            "create" => Ok(RoleAuditAction::Create),
+            "update" => Ok(RoleAuditAction::Update),
+            "remove" => Ok(RoleAuditAction::Remove),
+            other => Err(failure::err_msg(format!("Unknown role audit action: {}", other))),
+        }
+    }
+}
+
+/// Record of a single role change
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoleAuditEntry<T> {
+    pub id: Uuid,
+    /// User who made the change, `None` for changes made by the system itself
+    pub actor_id: Option<UserId>,
+    pub user_id: UserId,
+    pub action: RoleAuditAction,
+    pub role: T,
+    pub created_at: SystemTime,
+    pub correlation_token: Option<String>,
+}
+
+impl<T> RoleAuditEntry<T> {
+    pub fn new(action: RoleAuditAction, entry: RoleEntry<T>, actor_id: Option<UserId>, correlation_token: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            actor_id,
+            user_id: entry.user_id,
+            action,
+            role: entry.role,
+            created_at: SystemTime::now(),
+            correlation_token,
+        }
+    }
+}
+
+impl<T> From<Row> for RoleAuditEntry<T>
+where
+    T: RoleModel,
+{
+    fn from(row: Row) -> Self {
+        Self {
+            id: row.get(ID_COLUMN),
+            actor_id: row.get::<Option<i32>, _>(ACTOR_ID_COLUMN).map(UserId),
+            user_id: UserId(row.get(USER_ID_COLUMN)),
+            action: RoleAuditAction::from_db(row.get(ACTION_COLUMN)).unwrap(),
+            role: T::from_db(row.get(ROLE_NAME_COLUMN), row.get(ROLE_DATA_COLUMN)).unwrap(),
+            created_at: row.get(CREATED_AT_COLUMN),
+            correlation_token: row.get(CORRELATION_TOKEN_COLUMN),
+        }
+    }
+}
+
+impl<T> Inserter for RoleAuditEntry<T>
+where
+    T: RoleModel,
+{
+    fn into_insert_builder(self, table: TableName) -> InsertBuilder {
+        let (role_name, role_data) = T::into_db(self.role);
+        InsertBuilder::new(table)
+            .with_arg(ID_COLUMN, self.id)
+            .with_arg(ACTOR_ID_COLUMN, self.actor_id.map(|v| v.0))
+            .with_arg(USER_ID_COLUMN, self.user_id.0)
+            .with_arg(ACTION_COLUMN, self.action.as_str())
+            .with_arg(ROLE_NAME_COLUMN, role_name)
+            .with_arg(ROLE_DATA_COLUMN, role_data)
+            .with_arg(CREATED_AT_COLUMN, self.created_at)
+            .with_arg(CORRELATION_TOKEN_COLUMN, self.correlation_token)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RoleAuditFilter {
+    pub user_id: Option<UserId>,
+}
+
+impl Filter for RoleAuditFilter {
+    fn into_filtered_operation_builder(self, table: TableName) -> FilteredOperationBuilder {
+        let mut b = FilteredOperationBuilder::new(table);
+
+        if let Some(user_id) = self.user_id {
+            b = b.with_filter(USER_ID_COLUMN, user_id.0);
+        }
+
+        b.with_extra("ORDER BY created_at")
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum RepoLogin<T> {
     Anonymous,
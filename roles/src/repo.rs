@@ -2,25 +2,26 @@ use models::*;
 
 use stq_acl::*;
 use stq_db::repo::*;
-use stq_db::statement::{UpdateBuilder, Updater};
+use stq_db::statement::{TableName, UpdateBuilder, Updater};
 
 const TABLE: &str = "roles";
+const AUDIT_TABLE: &str = "role_audit";
 
 pub struct DummyRoleUpdater;
 
 impl Updater for DummyRoleUpdater {
-    fn into_update_builder(self, _table: &'static str) -> UpdateBuilder {
+    fn into_update_builder(self, _table: TableName) -> UpdateBuilder {
         unreachable!()
     }
 }
 
-pub trait RolesRepo<T>: DbRepo<RoleEntry<T>, RoleEntry<T>, RoleFilter<T>, DummyRoleUpdater, RepoError>
+pub trait RolesRepo<T>: DbRepo<RoleEntry<T>, RoleEntry<T>, RoleFilter<T>, RoleUpdater, RepoError>
 where
     T: RoleModel,
 {
 }
 
-pub type RolesRepoImpl<T> = DbRepoImpl<RoleEntry<T>, RoleEntry<T>, RoleFilter<T>, DummyRoleUpdater>;
+pub type RolesRepoImpl<T> = DbRepoImpl<RoleEntry<T>, RoleEntry<T>, RoleFilter<T>, RoleUpdater>;
 impl<T> RolesRepo<T> for RolesRepoImpl<T> where T: RoleModel {}
 
 pub fn make_su_repo<T>() -> RolesRepoImpl<T>
@@ -65,3 +66,47 @@ where
 {
     make_su_repo().with_afterop_acl_engine(InfallibleSyncACLFn(move |ctx: &mut AclContext<T>| check_acl(login.clone(), ctx)))
 }
+
+pub trait RoleAuditRepo<T>: DbRepo<RoleAuditEntry<T>, RoleAuditEntry<T>, RoleAuditFilter, DummyRoleUpdater, RepoError>
+where
+    T: RoleModel,
+{
+}
+
+pub type RoleAuditRepoImpl<T> = DbRepoImpl<RoleAuditEntry<T>, RoleAuditEntry<T>, RoleAuditFilter, DummyRoleUpdater>;
+impl<T> RoleAuditRepo<T> for RoleAuditRepoImpl<T> where T: RoleModel {}
+
+type AuditAclContext<T> = (RoleAuditEntry<T>, Action);
+
+fn check_audit_acl<T>(login: RepoLogin<T>, (entry, action): &mut AuditAclContext<T>) -> bool
+where
+    T: RoleModel,
+{
+    use self::RepoLogin::*;
+
+    match login {
+        Anonymous => false,
+        User { caller_id, caller_roles } => {
+            let is_su = caller_roles.iter().any(|user_role| user_role.role.is_su());
+
+            match action {
+                // Everyone records their own actions.
+                Action::Insert => is_su || entry.actor_id == Some(caller_id),
+                // Only superadmins can read the audit trail.
+                Action::Select => is_su,
+                // Audit trail is never changed.
+                Action::Update | Action::Delete => false,
+            }
+        }
+    }
+}
+
+/// Creates role audit repo. Audit trail is append-only and visible to superadmins only.
+pub fn make_audit_repo<T>(login: RepoLogin<T>) -> RoleAuditRepoImpl<T>
+where
+    T: RoleModel,
+{
+    RoleAuditRepoImpl::new(AUDIT_TABLE).with_afterop_acl_engine(InfallibleSyncACLFn(move |ctx: &mut AuditAclContext<T>| {
+        check_audit_acl(login.clone(), ctx)
+    }))
+}
@@ -1,9 +1,11 @@
 use models::*;
 use service::*;
 
+use failure;
 use futures::prelude::*;
-use hyper::{Body, Delete, Get, Method, Post};
+use hyper::{Body, Delete, Get, Method, Post, Put};
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
 use std::rc::Rc;
 use stq_http::controller::ControllerFuture;
 use stq_http::request_util::*;
@@ -13,8 +15,12 @@ use stq_types::*;
 #[derive(Clone, Debug)]
 pub enum Route {
     Roles,
+    RolesBulk,
+    RolesByRole,
     RoleById(RoleEntryId),
     RolesByUserId(UserId),
+    RolesPurgeExpired,
+    RolesAuditByUserId(UserId),
 }
 
 pub fn add_routes<R>(b: RouterBuilder<R>) -> RouterBuilder<R>
@@ -22,12 +28,21 @@ where
     R: From<Route>,
 {
     b.with_route(r"^/roles$", |_| Some(Route::Roles.into()))
+        .with_route(r"^/roles/bulk$", |_| Some(Route::RolesBulk.into()))
+        .with_route(r"^/roles/by-role$", |_| Some(Route::RolesByRole.into()))
+        .with_route(r"^/roles/purge-expired$", |_| Some(Route::RolesPurgeExpired.into()))
         .with_route(r"^/roles/by-user-id/(\d+)$", |params| {
             params
                 .get(0)
                 .and_then(|string_id| string_id.parse().ok())
                 .map(|v| Route::RolesByUserId(v).into())
         })
+        .with_route(r"^/roles/audit/by-user-id/(\d+)$", |params| {
+            params
+                .get(0)
+                .and_then(|string_id| string_id.parse().ok())
+                .map(|v| Route::RolesAuditByUserId(v).into())
+        })
         .with_route(r"^/roles/by-id/([a-zA-Z0-9-]+)$", |params| {
             params
                 .get(0)
@@ -45,26 +60,54 @@ where
     T: Serialize + DeserializeOwned + 'static,
 {
     pub fn call(&self, method: &Method, route: &Route, payload: Body) -> Option<ControllerFuture> {
+        self.call_with_query(method, route, "", payload)
+    }
+
+    /// Same as `call`, but also takes request query string used for filtering and pagination
+    pub fn call_with_query(&self, method: &Method, route: &Route, query: &str, payload: Body) -> Option<ControllerFuture> {
         let service = self.service.clone();
 
         match (method, route) {
             (Get, Route::RolesByUserId(user_id)) => Some({
                 let user_id = *user_id;
-                serialize_future({ service.get_roles_for_user(user_id) })
+                serialize_future({
+                    RolesQuery::from_query(query)
+                        .map_err(|e| failure::Error::from(e.context(ParseError::ConvertError)))
+                        .into_future()
+                        .and_then(move |query| service.search_roles_for_user(user_id, query))
+                })
             }),
+            (Get, Route::RolesByRole) => Some(serialize_future({
+                UsersByRoleQuery::<T>::from_query(query)
+                    .map_err(|e| failure::Error::from(e.context(ParseError::ConvertError)))
+                    .into_future()
+                    .and_then(move |UsersByRoleQuery { role, from, count }| service.get_users_by_role(role, from, count))
+            })),
             (Post, Route::Roles) => Some(serialize_future({
                 parse_body::<RoleEntry<T>>(payload).and_then(move |data| service.create_role(data))
             })),
+            (Post, Route::RolesBulk) => Some(serialize_future({
+                parse_body::<Vec<RoleEntry<T>>>(payload).and_then(move |data| service.create_roles(data))
+            })),
             (Delete, Route::RolesByUserId(user_id)) => Some({
                 let user_id = *user_id;
                 serialize_future({
                     parse_body::<Option<T>>(payload).and_then(move |role| service.remove_role(RoleSearchTerms::Meta((user_id, role))))
                 })
             }),
+            (Put, Route::RoleById(role_id)) => Some({
+                let role_id = *role_id;
+                serialize_future({ parse_body::<Value>(payload).and_then(move |data| service.update_role(role_id, data)) })
+            }),
             (Delete, Route::RoleById(role_id)) => Some({
                 let role_id = *role_id;
                 serialize_future({ service.remove_role(RoleSearchTerms::Id(role_id)) })
             }),
+            (Post, Route::RolesPurgeExpired) => Some(serialize_future({ service.purge_expired() })),
+            (Get, Route::RolesAuditByUserId(user_id)) => Some({
+                let user_id = *user_id;
+                serialize_future({ service.get_audit_for_user(user_id) })
+            }),
             (_, _) => None,
         }
     }
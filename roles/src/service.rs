@@ -1,13 +1,21 @@
+use cache::*;
 use models::*;
 use repo::*;
 
+use failure;
 use futures::future;
 use futures::prelude::*;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::rc::Rc;
+use std::time::SystemTime;
 use stq_db::pool::Pool as DbPool;
 use stq_db::repo::*;
+use stq_http::errors::ValidationFailed;
 use stq_types::*;
+use validator::{ValidationError, ValidationErrors};
 
 pub fn get_login_data<T>(db_pool: &DbPool, caller_id: Option<UserId>) -> RepoLoginFuture<T>
 where
@@ -19,21 +27,86 @@ where
             db_pool
                 .run(move |conn| make_su_repo().select(conn, RoleSearchTerms::Meta((caller_id, None)).into()))
                 .map_err(|e| e.context("Failed to fetch user roles").into())
-                .map(move |caller_roles| RepoLogin::User { caller_id, caller_roles }),
+                .map(move |caller_roles| RepoLogin::User {
+                    caller_id,
+                    caller_roles: filter_expired(caller_roles),
+                }),
         ),
     }
 }
 
+/// Same as `get_login_data`, but looks up roles in cache first and populates it on miss
+pub fn get_login_data_cached<T>(db_pool: &DbPool, cache: Rc<RolesCache<T>>, caller_id: Option<UserId>) -> RepoLoginFuture<T>
+where
+    T: RoleModel,
+{
+    match caller_id {
+        None => Box::new(future::ok(RepoLogin::Anonymous)),
+        Some(caller_id) => match cache.get(caller_id) {
+            Some(caller_roles) => Box::new(future::ok(RepoLogin::User {
+                caller_id,
+                caller_roles: filter_expired(caller_roles),
+            })),
+            None => Box::new(
+                db_pool
+                    .run(move |conn| make_su_repo().select(conn, RoleSearchTerms::Meta((caller_id, None)).into()))
+                    .map_err(|e| e.context("Failed to fetch user roles").into())
+                    .map(move |caller_roles: Vec<RoleEntry<T>>| {
+                        cache.set(caller_id, caller_roles.clone());
+                        RepoLogin::User {
+                            caller_id,
+                            caller_roles: filter_expired(caller_roles),
+                        }
+                    }),
+            ),
+        },
+    }
+}
+
+fn invalid_role_data(e: &failure::Error) -> failure::Error {
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        "data",
+        ValidationError {
+            code: Cow::from("role_data"),
+            message: Some(Cow::from(e.to_string())),
+            params: HashMap::new(),
+        },
+    );
+    ValidationFailed(errors).into()
+}
+
+fn filter_expired<T>(roles: Vec<RoleEntry<T>>) -> Vec<RoleEntry<T>> {
+    let now = SystemTime::now();
+    roles.into_iter().filter(|role| !role.is_expired(now)).collect()
+}
+
 pub trait RoleService<T> {
     fn get_roles_for_user(&self, user_id: UserId) -> ServiceFuture<Vec<RoleEntry<T>>>;
+    /// Same as `get_roles_for_user`, but filtered and paginated
+    fn search_roles_for_user(&self, user_id: UserId, query: RolesQuery) -> ServiceFuture<Vec<RoleEntry<T>>>;
+    /// Lists users having the role, `from` and `count` paginate by user id
+    fn get_users_by_role(&self, role: T, from: Option<UserId>, count: Option<i32>) -> ServiceFuture<Vec<UserId>>;
     fn create_role(&self, item: RoleEntry<T>) -> ServiceFuture<RoleEntry<T>>;
+    /// Creates all roles atomically
+    fn create_roles(&self, items: Vec<RoleEntry<T>>) -> ServiceFuture<Vec<RoleEntry<T>>>;
+    /// Replaces data payload of the role
+    fn update_role(&self, id: RoleEntryId, data: Value) -> ServiceFuture<RoleEntry<T>>;
     fn remove_role(&self, filter: RoleSearchTerms<T>) -> ServiceFuture<Option<RoleEntry<T>>>;
     fn remove_all_roles(&self, user_id: UserId) -> ServiceFuture<Vec<RoleEntry<T>>>;
+    /// Removes all roles with expiration date in the past
+    fn purge_expired(&self) -> ServiceFuture<Vec<RoleEntry<T>>>;
+    /// Lists role changes of the user, oldest first
+    fn get_audit_for_user(&self, user_id: UserId) -> ServiceFuture<Vec<RoleAuditEntry<T>>>;
 }
 
 pub struct RoleServiceImpl<T> {
     pub repo_factory: Rc<Fn() -> Box<RolesRepo<T>>>,
     pub db_pool: DbPool,
+    pub audit_repo_factory: Rc<Fn() -> Box<RoleAuditRepo<T>>>,
+    pub cache: Option<Rc<RolesCache<T>>>,
+    pub actor_id: Option<UserId>,
+    pub correlation_token: Option<String>,
 }
 
 impl<T> RoleServiceImpl<T>
@@ -41,9 +114,69 @@ where
     T: RoleModel + Clone,
 {
     pub fn new(db_pool: DbPool, login: RepoLogin<T>) -> Self {
+        let actor_id = match login {
+            RepoLogin::Anonymous => None,
+            RepoLogin::User { caller_id, .. } => Some(caller_id),
+        };
         Self {
             db_pool,
-            repo_factory: Rc::new(move || Box::new(make_repo(login.clone()))),
+            repo_factory: Rc::new({
+                let login = login.clone();
+                move || Box::new(make_repo(login.clone()))
+            }),
+            audit_repo_factory: Rc::new(move || Box::new(make_audit_repo(login.clone()))),
+            cache: None,
+            actor_id,
+            correlation_token: None,
+        }
+    }
+
+    /// Sets correlation token saved into audit trail along with role changes
+    pub fn with_correlation_token(mut self, correlation_token: String) -> Self {
+        self.correlation_token = Some(correlation_token);
+        self
+    }
+
+    /// Installs roles cache which is invalidated on every role change
+    pub fn with_cache(mut self, cache: Rc<RolesCache<T>>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    fn auditor(&self) -> Auditor<T> {
+        Auditor {
+            repo_factory: self.audit_repo_factory.clone(),
+            actor_id: self.actor_id,
+            correlation_token: self.correlation_token.clone(),
+        }
+    }
+}
+
+/// Records role changes into audit trail within the same transaction
+struct Auditor<T> {
+    repo_factory: Rc<Fn() -> Box<RoleAuditRepo<T>>>,
+    actor_id: Option<UserId>,
+    correlation_token: Option<String>,
+}
+
+impl<T> Auditor<T>
+where
+    T: RoleModel,
+{
+    fn record(self, conn: RepoConnection, action: RoleAuditAction, entries: &[RoleEntry<T>]) -> RepoConnectionFuture<()> {
+        let audit_entries = entries
+            .iter()
+            .cloned()
+            .map(|entry| RoleAuditEntry::new(action, entry, self.actor_id, self.correlation_token.clone()))
+            .collect();
+        Box::new((self.repo_factory)().insert_many(conn, audit_entries).map(|(_, conn)| ((), conn)))
+    }
+}
+
+fn invalidate_cache<T>(cache: &Option<Rc<RolesCache<T>>>, entries: &[RoleEntry<T>]) {
+    if let Some(cache) = cache {
+        for entry in entries {
+            cache.remove(entry.user_id);
         }
     }
 }
@@ -58,41 +191,196 @@ where
         Box::new(
             db_pool
                 .run(move |conn| (repo_factory)().select(conn, RoleSearchTerms::Meta((user_id, None)).into()))
+                .map(filter_expired)
                 .map_err(move |e| e.context(format!("Failed to get roles for user {}", user_id.0)).into()),
         )
     }
+    fn search_roles_for_user(&self, user_id: UserId, query: RolesQuery) -> ServiceFuture<Vec<RoleEntry<T>>> {
+        let repo_factory = self.repo_factory.clone();
+        let db_pool = self.db_pool.clone();
+        let limit = query.limit;
+        let filter = query.into_filter(user_id);
+        Box::new(
+            db_pool
+                .run(move |conn| (repo_factory)().select_full(conn, filter, limit, None))
+                .map_err(move |e| e.context(format!("Failed to search roles for user {}", user_id.0)).into()),
+        )
+    }
+    fn get_users_by_role(&self, role: T, from: Option<UserId>, count: Option<i32>) -> ServiceFuture<Vec<UserId>> {
+        let repo_factory = self.repo_factory.clone();
+        let db_pool = self.db_pool.clone();
+        let filter = RoleFilter {
+            role: Some(role.clone()),
+            unexpired_at: Some(SystemTime::now()),
+            user_id_after: from,
+            ..Default::default()
+        };
+        Box::new(
+            db_pool
+                .run(move |conn| (repo_factory)().select_full(conn, filter, count, None))
+                .map(|roles| roles.into_iter().map(|entry| entry.user_id).collect())
+                .map_err(move |e| e.context(format!("Failed to get users with role {:?}", role)).into()),
+        )
+    }
     fn create_role(&self, item: RoleEntry<T>) -> ServiceFuture<RoleEntry<T>> {
         let repo_factory = self.repo_factory.clone();
         let db_pool = self.db_pool.clone();
+        let cache = self.cache.clone();
+        let auditor = self.auditor();
         Box::new(
             db_pool
                 .run({
                     let item = item.clone();
-                    move |conn| (repo_factory)().insert_exactly_one(conn, item)
+                    move |conn| {
+                        (repo_factory)().insert_exactly_one(conn, item).and_then(move |(entry, conn)| {
+                            auditor
+                                .record(conn, RoleAuditAction::Create, &[entry.clone()])
+                                .map(move |(_, conn)| (entry, conn))
+                        })
+                    }
+                })
+                .map(move |entry| {
+                    invalidate_cache(&cache, &[entry.clone()]);
+                    entry
                 })
                 .map_err(move |e| e.context(format!("Failed to create role: {:?}", item)).into()),
         )
     }
+    fn create_roles(&self, items: Vec<RoleEntry<T>>) -> ServiceFuture<Vec<RoleEntry<T>>> {
+        let repo_factory = self.repo_factory.clone();
+        let db_pool = self.db_pool.clone();
+        let cache = self.cache.clone();
+        let auditor = self.auditor();
+        Box::new(
+            db_pool
+                .run({
+                    let items = items.clone();
+                    move |conn| {
+                        (repo_factory)().insert_many(conn, items).and_then(move |(entries, conn)| {
+                            auditor
+                                .record(conn, RoleAuditAction::Create, &entries)
+                                .map(move |(_, conn)| (entries, conn))
+                        })
+                    }
+                })
+                .map(move |entries| {
+                    invalidate_cache(&cache, &entries);
+                    entries
+                })
+                .map_err(move |e| e.context(format!("Failed to create roles: {:?}", items)).into()),
+        )
+    }
+    fn update_role(&self, id: RoleEntryId, data: Value) -> ServiceFuture<RoleEntry<T>> {
+        let repo_factory = self.repo_factory.clone();
+        let db_pool = self.db_pool.clone();
+        let cache = self.cache.clone();
+        let auditor = self.auditor();
+        Box::new(
+            db_pool
+                .run(move |conn| {
+                    // Data is checked against the role before it's stored, as entries which don't decode can't be read back
+                    (repo_factory)()
+                        .select_exactly_one(conn, RoleSearchTerms::Id(id).into())
+                        .and_then(move |(entry, conn)| {
+                            let (role_name, _) = T::into_db(entry.role);
+                            match T::from_db(&role_name, data.clone()) {
+                                Ok(_) => (repo_factory)().update_exactly_one(conn, RoleUpdater { id, data }),
+                                Err(e) => Box::new(future::err((invalid_role_data(&e), conn))) as RepoConnectionFuture<RoleEntry<T>>,
+                            }
+                        })
+                        .and_then(move |(entry, conn)| {
+                            auditor
+                                .record(conn, RoleAuditAction::Update, &[entry.clone()])
+                                .map(move |(_, conn)| (entry, conn))
+                        })
+                })
+                .map(move |entry| {
+                    invalidate_cache(&cache, &[entry.clone()]);
+                    entry
+                })
+                .map_err(move |e| e.context(format!("Failed to update role {}", id)).into()),
+        )
+    }
     fn remove_role(&self, filter: RoleSearchTerms<T>) -> ServiceFuture<Option<RoleEntry<T>>> {
         let repo_factory = self.repo_factory.clone();
         let db_pool = self.db_pool.clone();
+        let cache = self.cache.clone();
+        let auditor = self.auditor();
         Box::new(
             db_pool
                 .run({
                     let filter = filter.clone();
-                    move |conn| (repo_factory)().delete(conn, filter.into())
+                    move |conn| {
+                        (repo_factory)().delete(conn, filter.into()).and_then(move |(entries, conn)| {
+                            auditor
+                                .record(conn, RoleAuditAction::Remove, &entries)
+                                .map(move |(_, conn)| (entries, conn))
+                        })
+                    }
+                })
+                .map(move |mut v| {
+                    invalidate_cache(&cache, &v);
+                    v.pop()
                 })
-                .map(|mut v| v.pop())
                 .map_err(move |e| e.context(format!("Failed to remove role: {:?}", filter)).into()),
         )
     }
     fn remove_all_roles(&self, user_id: UserId) -> ServiceFuture<Vec<RoleEntry<T>>> {
         let repo_factory = self.repo_factory.clone();
         let db_pool = self.db_pool.clone();
+        let cache = self.cache.clone();
+        let auditor = self.auditor();
         Box::new(
             db_pool
-                .run(move |conn| (repo_factory)().delete(conn, RoleSearchTerms::Meta((user_id, None)).into()))
+                .run(move |conn| {
+                    (repo_factory)()
+                        .delete(conn, RoleSearchTerms::Meta((user_id, None)).into())
+                        .and_then(move |(entries, conn)| {
+                            auditor
+                                .record(conn, RoleAuditAction::Remove, &entries)
+                                .map(move |(_, conn)| (entries, conn))
+                        })
+                })
+                .map(move |entries| {
+                    invalidate_cache(&cache, &entries);
+                    entries
+                })
                 .map_err(move |e| e.context(format!("Failed to remove all roles for user {}", user_id.0)).into()),
         )
     }
+    fn purge_expired(&self) -> ServiceFuture<Vec<RoleEntry<T>>> {
+        let repo_factory = self.repo_factory.clone();
+        let db_pool = self.db_pool.clone();
+        let cache = self.cache.clone();
+        let filter = RoleFilter {
+            expired_before: Some(SystemTime::now()),
+            ..Default::default()
+        };
+        let auditor = self.auditor();
+        Box::new(
+            db_pool
+                .run(move |conn| {
+                    (repo_factory)().delete(conn, filter).and_then(move |(entries, conn)| {
+                        auditor
+                            .record(conn, RoleAuditAction::Remove, &entries)
+                            .map(move |(_, conn)| (entries, conn))
+                    })
+                })
+                .map(move |entries| {
+                    invalidate_cache(&cache, &entries);
+                    entries
+                })
+                .map_err(|e| e.context("Failed to purge expired roles").into()),
+        )
+    }
+    fn get_audit_for_user(&self, user_id: UserId) -> ServiceFuture<Vec<RoleAuditEntry<T>>> {
+        let audit_repo_factory = self.audit_repo_factory.clone();
+        let db_pool = self.db_pool.clone();
+        let filter = RoleAuditFilter { user_id: Some(user_id) };
+        Box::new(
+            db_pool
+                .run(move |conn| (audit_repo_factory)().select(conn, filter))
+                .map_err(move |e| e.context(format!("Failed to get role audit for user {}", user_id.0)).into()),
+        )
+    }
 }
@@ -1,14 +1,51 @@
 extern crate regex;
 
 use regex::Regex;
+use std::ops::{Bound, RangeBounds};
 use std::sync::Arc;
 
 pub type ParamsConverter<T> = Arc<Fn(Vec<&str>) -> Option<T> + Send + Sync>;
 
+/// API versions a route is served for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VersionRange {
+    start: Bound<u32>,
+    end: Bound<u32>,
+}
+
+impl VersionRange {
+    pub fn new<R: RangeBounds<u32>>(range: R) -> Self {
+        let cloned = |bound: Bound<&u32>| match bound {
+            Bound::Included(v) => Bound::Included(*v),
+            Bound::Excluded(v) => Bound::Excluded(*v),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        Self {
+            start: cloned(range.start_bound()),
+            end: cloned(range.end_bound()),
+        }
+    }
+
+    pub fn contains(&self, version: u32) -> bool {
+        let after_start = match self.start {
+            Bound::Included(start) => version >= start,
+            Bound::Excluded(start) => version > start,
+            Bound::Unbounded => true,
+        };
+        let before_end = match self.end {
+            Bound::Included(end) => version <= end,
+            Bound::Excluded(end) => version < end,
+            Bound::Unbounded => true,
+        };
+        after_start && before_end
+    }
+}
+
 /// `Router` class maps regex to type-safe list of routes, defined by `enum Route`
 #[derive(Clone)]
 pub struct Router<T> {
-    regex_and_converters: Vec<(Regex, ParamsConverter<T>)>,
+    regex_and_converters: Vec<(Regex, ParamsConverter<T>, Option<VersionRange>)>,
 }
 
 /// The builder for `Router`
@@ -51,7 +88,40 @@ impl<T> Builder<T> {
         F: Fn(Vec<&str>) -> Option<T> + Send + Sync + 'static,
     {
         let regex = Regex::new(regex_pattern).unwrap();
-        self.0.regex_and_converters.push((regex, Arc::new(converter)));
+        self.0.regex_and_converters.push((regex, Arc::new(converter), None));
+        self
+    }
+
+    /// Adds mapping served only for API versions in `versions`, so that payload of a route can change
+    /// in a new version while clients of the old one keep working
+    ///
+    /// #Examples
+    ///
+    /// ```
+    /// use stq_router::Builder as RouterBuilder;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// pub enum Route {
+    ///     Users,
+    ///     UsersV2,
+    /// }
+    ///
+    /// let router = RouterBuilder::default()
+    ///     .with_versioned_route(..2, r"^/users$", |_| Some(Route::Users))
+    ///     .with_versioned_route(2.., r"^/users$", |_| Some(Route::UsersV2))
+    ///     .build();
+    /// assert_eq!(router.test_versioned("/users", 1), Some(Route::Users));
+    /// assert_eq!(router.test_versioned("/users", 3), Some(Route::UsersV2));
+    /// ```
+    pub fn with_versioned_route<R, F>(mut self, versions: R, regex_pattern: &str, converter: F) -> Self
+    where
+        R: RangeBounds<u32>,
+        F: Fn(Vec<&str>) -> Option<T> + Send + Sync + 'static,
+    {
+        let regex = Regex::new(regex_pattern).unwrap();
+        self.0
+            .regex_and_converters
+            .push((regex, Arc::new(converter), Some(VersionRange::new(versions))));
         self
     }
 
@@ -77,8 +147,9 @@ impl<T> Router<T> {
     /// let route = router.test("/users").unwrap();
     /// assert_eq!(route, Route::Users);
     /// ```
+    /// Version constraints of routes are ignored, see `test_versioned`.
     pub fn test(&self, route: &str) -> Option<T> {
-        for (pattern, test_func) in &self.regex_and_converters {
+        for (pattern, test_func, _) in &self.regex_and_converters {
             if let Some(v) = Self::get_matches(&pattern, route) {
                 return test_func(v);
             }
@@ -86,6 +157,18 @@ impl<T> Router<T> {
         None
     }
 
+    /// Tests string router for matches among routes served for API `version`
+    pub fn test_versioned(&self, route: &str, version: u32) -> Option<T> {
+        for (pattern, test_func, versions) in &self.regex_and_converters {
+            if versions.map(|versions| versions.contains(version)).unwrap_or(true) {
+                if let Some(v) = Self::get_matches(&pattern, route) {
+                    return test_func(v);
+                }
+            }
+        }
+        None
+    }
+
     fn get_matches<'a>(regex: &Regex, string: &'a str) -> Option<Vec<&'a str>> {
         regex.captures(string).and_then(|captures| {
             captures
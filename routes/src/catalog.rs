@@ -0,0 +1,136 @@
+//! Base URLs of microservices and building of full URLs to their endpoints.
+use service::Service;
+
+/// Base URLs of microservices, e.g. `http://users:8000`, usually a section of service config
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceUrls {
+    pub users: String,
+    pub stores: String,
+    pub orders: String,
+    pub billing: String,
+    pub delivery: String,
+    pub notifications: String,
+    pub warehouses: String,
+    pub saga: String,
+}
+
+impl ServiceUrls {
+    pub fn base_url(&self, service: Service) -> &str {
+        match service {
+            Service::Users => &self.users,
+            Service::Stores => &self.stores,
+            Service::Orders => &self.orders,
+            Service::Billing => &self.billing,
+            Service::Delivery => &self.delivery,
+            Service::Notifications => &self.notifications,
+            Service::Warehouses => &self.warehouses,
+            Service::Saga => &self.saga,
+        }
+    }
+
+    /// Full URL of the route, e.g. `http://users:8000/users/1`
+    pub fn resolve(&self, route: &ServiceRoute) -> String {
+        format!(
+            "{}/{}",
+            self.base_url(route.service()).trim_end_matches('/'),
+            route.path().trim_start_matches('/')
+        )
+    }
+}
+
+/// Path of an endpoint of a microservice, relative to its base URL
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ServiceRoute {
+    Users(String),
+    Stores(String),
+    Orders(String),
+    Billing(String),
+    Delivery(String),
+    Notifications(String),
+    Warehouses(String),
+    Saga(String),
+}
+
+impl ServiceRoute {
+    pub fn new<S: Into<String>>(service: Service, path: S) -> Self {
+        let path = path.into();
+        match service {
+            Service::Users => ServiceRoute::Users(path),
+            Service::Stores => ServiceRoute::Stores(path),
+            Service::Orders => ServiceRoute::Orders(path),
+            Service::Billing => ServiceRoute::Billing(path),
+            Service::Delivery => ServiceRoute::Delivery(path),
+            Service::Notifications => ServiceRoute::Notifications(path),
+            Service::Warehouses => ServiceRoute::Warehouses(path),
+            Service::Saga => ServiceRoute::Saga(path),
+        }
+    }
+
+    pub fn service(&self) -> Service {
+        match self {
+            ServiceRoute::Users(_) => Service::Users,
+            ServiceRoute::Stores(_) => Service::Stores,
+            ServiceRoute::Orders(_) => Service::Orders,
+            ServiceRoute::Billing(_) => Service::Billing,
+            ServiceRoute::Delivery(_) => Service::Delivery,
+            ServiceRoute::Notifications(_) => Service::Notifications,
+            ServiceRoute::Warehouses(_) => Service::Warehouses,
+            ServiceRoute::Saga(_) => Service::Saga,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            ServiceRoute::Users(path)
+            | ServiceRoute::Stores(path)
+            | ServiceRoute::Orders(path)
+            | ServiceRoute::Billing(path)
+            | ServiceRoute::Delivery(path)
+            | ServiceRoute::Notifications(path)
+            | ServiceRoute::Warehouses(path)
+            | ServiceRoute::Saga(path) => path,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls() -> ServiceUrls {
+        ServiceUrls {
+            users: "http://users:8000".to_string(),
+            stores: "http://stores:8000/".to_string(),
+            orders: "http://orders:8000".to_string(),
+            billing: "http://billing:8000".to_string(),
+            delivery: "http://delivery:8000".to_string(),
+            notifications: "http://notifications:8000".to_string(),
+            warehouses: "http://warehouses:8000".to_string(),
+            saga: "http://saga:8000".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_joins_with_single_slash() {
+        let urls = urls();
+        assert_eq!(
+            urls.resolve(&ServiceRoute::Users("users/1".to_string())),
+            "http://users:8000/users/1"
+        );
+        assert_eq!(
+            urls.resolve(&ServiceRoute::Stores("/stores/1".to_string())),
+            "http://stores:8000/stores/1"
+        );
+    }
+
+    #[test]
+    fn test_routes_resolve_against_their_service() {
+        let urls = urls();
+        for service in Service::enum_iter() {
+            let route = ServiceRoute::new(service, "healthcheck");
+            assert_eq!(route.service(), service);
+            assert_eq!(route.path(), "healthcheck");
+            assert_eq!(urls.resolve(&route), format!("http://{}:8000/healthcheck", service));
+        }
+    }
+}
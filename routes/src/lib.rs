@@ -8,6 +8,7 @@ extern crate serde;
 extern crate serde_derive;
 extern crate uuid;
 
+pub mod catalog;
 pub mod model;
 pub mod role;
 pub mod service;
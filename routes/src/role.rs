@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use uuid::Uuid;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -21,3 +23,86 @@ pub struct UserRole {
     pub user_id: i32,
     pub role: Role,
 }
+
+pub type RouteMatcher<T> = Arc<Fn(&T) -> bool + Send + Sync>;
+
+/// Which roles may access which routes, `T` is usually a service `Route` enum.
+/// Rules are checked in the order they were added, the first matching rule decides.
+/// Routes not matched by any rule are not allowed.
+#[derive(Clone)]
+pub struct RouteAccess<T> {
+    rules: Vec<(RouteMatcher<T>, Option<Vec<Role>>)>,
+}
+
+/// The builder for `RouteAccess`
+#[derive(Clone)]
+pub struct RouteAccessBuilder<T>(RouteAccess<T>);
+
+impl<T> Default for RouteAccessBuilder<T> {
+    fn default() -> Self {
+        RouteAccessBuilder(RouteAccess { rules: Default::default() })
+    }
+}
+
+impl<T> RouteAccessBuilder<T> {
+    /// Allows routes matched by `matcher` to users having any of `roles`
+    ///
+    /// #Examples
+    ///
+    /// ```
+    /// use stq_routes::role::{Role, RouteAccessBuilder};
+    ///
+    /// pub enum Route {
+    ///     Users,
+    ///     User(i32),
+    /// }
+    ///
+    /// let access = RouteAccessBuilder::<Route>::default()
+    ///     .allow(|route| match route {
+    ///         Route::Users => true,
+    ///         _ => false,
+    ///     }, &[Role::Superuser])
+    ///     .allow_any(|route| match route {
+    ///         Route::User(_) => true,
+    ///         _ => false,
+    ///     })
+    ///     .build();
+    ///
+    /// assert!(access.is_allowed(&Route::Users, &[Role::Superuser]));
+    /// assert!(!access.is_allowed(&Route::Users, &[Role::User]));
+    /// assert!(access.is_allowed(&Route::User(1), &[]));
+    /// ```
+    pub fn allow<F>(mut self, matcher: F, roles: &[Role]) -> Self
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.0.rules.push((Arc::new(matcher), Some(roles.to_vec())));
+        self
+    }
+
+    /// Allows routes matched by `matcher` to everyone, including users without roles
+    pub fn allow_any<F>(mut self, matcher: F) -> Self
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.0.rules.push((Arc::new(matcher), None));
+        self
+    }
+
+    pub fn build(self) -> RouteAccess<T> {
+        self.0
+    }
+}
+
+impl<T> RouteAccess<T> {
+    pub fn is_allowed(&self, route: &T, roles: &[Role]) -> bool {
+        self.rules
+            .iter()
+            .find(|(matcher, _)| matcher(route))
+            .map(|(_, allowed_roles)| match allowed_roles {
+                Some(allowed_roles) => roles.iter().any(|role| allowed_roles.contains(role)),
+                None => true,
+            })
+            .unwrap_or(false)
+    }
+}
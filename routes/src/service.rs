@@ -12,6 +12,7 @@ pub enum Service {
     Notifications,
     Billing,
     Delivery,
+    Saga,
 }
 
 impl fmt::Display for Service {
@@ -27,6 +28,7 @@ impl fmt::Display for Service {
                 Service::Notifications => "notifications",
                 Service::Billing => "billing",
                 Service::Delivery => "delivery",
+                Service::Saga => "saga",
             }
         )
     }
@@ -44,6 +46,7 @@ impl FromStr for Service {
             "notifications" => Ok(Service::Notifications),
             "billing" => Ok(Service::Billing),
             "delivery" => Ok(Service::Delivery),
+            "saga" => Ok(Service::Saga),
             _ => Err(FieldError::new(
                 "Unknown service",
                 graphql_value!({ "code": 300, "details": {
@@ -1,4 +1,4 @@
-#[derive(GraphQLEnum, Deserialize, Serialize, Debug, Clone, PartialEq, DieselTypes)]
+#[derive(GraphQLEnum, Deserialize, Serialize, Debug, Clone, PartialEq, DieselTypes, EnumIterator)]
 #[graphql(name = "AttributeType", description = "Attribute Type")]
 pub enum AttributeType {
     #[graphql(description = "String type. Can represent enums, bool, int and strings.")]
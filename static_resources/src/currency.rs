@@ -14,7 +14,7 @@ use diesel::types::{FromSqlRow, IsNull, ToSql};
 use diesel::Queryable;
 use juniper::FieldError;
 
-use super::CurrencyType;
+use super::{CurrencyType, Language};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, EnumIterator, GraphQLEnum)]
 pub enum Currency {
@@ -24,6 +24,12 @@ pub enum Currency {
     BTC,
     ETH,
     STQ,
+    GBP,
+    JPY,
+    CNY,
+    UAH,
+    USDT,
+    USDC,
 }
 
 impl Currency {
@@ -35,17 +41,43 @@ impl Currency {
             Currency::BTC => "BTC",
             Currency::ETH => "ETH",
             Currency::STQ => "STQ",
+            Currency::GBP => "GBP",
+            Currency::JPY => "JPY",
+            Currency::CNY => "CNY",
+            Currency::UAH => "UAH",
+            Currency::USDT => "USDT",
+            Currency::USDC => "USDC",
         }
     }
 
+    /// Parses currency code, "USDT" is read as `Currency::USD` for the EUR/USD exchange pair.
+    /// Use `from_str` to get `Currency::USDT`.
     pub fn from_code(s: &str) -> Option<Self> {
+        Self::from_exchange_code(s)
+    }
+
+    /// Parses code of an exchange pair currency, treats USDT as USD, as exchanges quote EUR/USD pair as EUR/USDT
+    pub fn from_exchange_code(s: &str) -> Option<Self> {
+        match Self::from_exact_code(s) {
+            Some(Currency::USDT) => Some(Currency::USD),
+            other => other,
+        }
+    }
+
+    fn from_exact_code(s: &str) -> Option<Self> {
         Some(match s.to_ascii_uppercase().as_str() {
             "RUB" => Currency::RUB,
             "EUR" => Currency::EUR,
-            "USD" | "USDT" => Currency::USD, // USDT - for EUR/USD exchange pair
+            "USD" => Currency::USD,
             "BTC" => Currency::BTC,
             "ETH" => Currency::ETH,
             "STQ" => Currency::STQ,
+            "GBP" => Currency::GBP,
+            "JPY" => Currency::JPY,
+            "CNY" => Currency::CNY,
+            "UAH" => Currency::UAH,
+            "USDT" => Currency::USDT,
+            "USDC" => Currency::USDC,
             _ => {
                 return None;
             }
@@ -54,8 +86,85 @@ impl Currency {
 
     pub fn currency_type(&self) -> CurrencyType {
         match self {
-            Currency::RUB | Currency::EUR | Currency::USD => CurrencyType::Fiat,
-            Currency::BTC | Currency::ETH | Currency::STQ => CurrencyType::Crypto,
+            Currency::RUB | Currency::EUR | Currency::USD | Currency::GBP | Currency::JPY | Currency::CNY | Currency::UAH => {
+                CurrencyType::Fiat
+            }
+            Currency::BTC | Currency::ETH | Currency::STQ | Currency::USDT | Currency::USDC => CurrencyType::Crypto,
+        }
+    }
+
+    /// Number of decimal digits of the minor unit. Crypto currencies are limited to 8 digits,
+    /// so that amounts in minor units still fit into `i64`.
+    pub fn exponent(&self) -> u32 {
+        match self {
+            Currency::JPY => 0,
+            Currency::RUB | Currency::EUR | Currency::USD | Currency::GBP | Currency::CNY | Currency::UAH => 2,
+            Currency::USDT | Currency::USDC => 6,
+            Currency::BTC | Currency::ETH | Currency::STQ => 8,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::RUB => "₽",
+            Currency::EUR => "€",
+            Currency::USD => "$",
+            Currency::GBP => "£",
+            Currency::JPY | Currency::CNY => "¥",
+            Currency::UAH => "₴",
+            Currency::BTC => "₿",
+            Currency::ETH => "Ξ",
+            Currency::USDT => "₮",
+            Currency::STQ | Currency::USDC => self.code(),
+        }
+    }
+
+    /// Stablecoins are crypto currencies pegged to a fiat currency
+    pub fn is_stablecoin(&self) -> bool {
+        match self {
+            Currency::USDT | Currency::USDC => true,
+            _ => false,
+        }
+    }
+
+    /// Currencies of the type, e.g. for UI pickers
+    pub fn all_of_type(currency_type: CurrencyType) -> Vec<Currency> {
+        Currency::enum_iter()
+            .filter(|currency| currency.currency_type() == currency_type)
+            .collect()
+    }
+
+    /// Formats amount given in minor units for the language, e.g. `$1,234.50` for English and `1 234,50 ₽` for Russian
+    pub fn format_amount(&self, amount_minor: i64, language: &Language) -> String {
+        let exponent = self.exponent();
+        let units = 10u64.pow(exponent);
+        let abs = i128::from(amount_minor).abs() as u64;
+        let integer = (abs / units).to_string();
+        let fraction = format!("{:0width$}", abs % units, width = exponent as usize);
+
+        let (group_separator, decimal_separator, symbol_first) = match language {
+            Language::En | Language::Ch | Language::Ko | Language::Ja => (",", ".", true),
+            Language::De | Language::Es | Language::Po => (".", ",", false),
+            Language::Ru | Language::Fr => ("\u{a0}", ",", false),
+        };
+
+        let mut grouped = String::new();
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                grouped.push_str(group_separator);
+            }
+            grouped.push(digit);
+        }
+        if exponent > 0 {
+            grouped.push_str(decimal_separator);
+            grouped.push_str(&fraction);
+        }
+
+        let sign = if amount_minor < 0 { "-" } else { "" };
+        if symbol_first {
+            format!("{}{}{}", sign, self.symbol(), grouped)
+        } else {
+            format!("{}{}\u{a0}{}", sign, grouped, self.symbol())
         }
     }
 }
@@ -66,11 +175,13 @@ impl Display for Currency {
     }
 }
 
+/// Parses exact currency code, so "USDT" is `Currency::USDT`. It used to be parsed as `Currency::USD`
+/// before USDT was supported, exchange pair codes still are with `from_code` and `from_exchange_code`.
 impl FromStr for Currency {
     type Err = FieldError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Self::from_code(s).ok_or_else(|| {
+        Self::from_exact_code(s).ok_or_else(|| {
             FieldError::new(
                 "Unknown Currency",
                 graphql_value!({ "code": 300, "details": {
@@ -112,9 +223,52 @@ impl FromSqlRow<VarChar, Pg> for Currency {
         match row.take() {
             Some(v) => {
                 let s = str::from_utf8(v).unwrap_or("unreadable value");
-                Self::from_code(s).ok_or_else(|| format!("Unrecognized enum variant: {:?}", s).into())
+                Self::from_exact_code(s).ok_or_else(|| format!("Unrecognized enum variant: {:?}", s).into())
             }
             None => Err("Unexpected null for non-null column".into()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usdt_code() {
+        assert_eq!(Currency::from_code("USDT"), Some(Currency::USD));
+        assert_eq!(Currency::from_code("usdt"), Some(Currency::USD));
+        assert_eq!(Currency::from_exchange_code("USDT"), Some(Currency::USD));
+        assert_eq!("USDT".parse::<Currency>().ok(), Some(Currency::USDT));
+        assert_eq!(Currency::from_code("USDC"), Some(Currency::USDC));
+        assert_eq!(Currency::from_code("XXX"), None);
+    }
+
+    #[test]
+    fn test_from_str_parses_usdt_as_itself() {
+        assert_eq!("USDT".parse::<Currency>().ok(), Some(Currency::USDT));
+        assert_eq!("usdt".parse::<Currency>().ok(), Some(Currency::USDT));
+        assert_eq!("USD".parse::<Currency>().ok(), Some(Currency::USD));
+        assert!("XXX".parse::<Currency>().is_err());
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(Currency::USD.format_amount(123_450, &Language::En), "$1,234.50");
+        assert_eq!(Currency::USD.format_amount(5, &Language::En), "$0.05");
+        assert_eq!(Currency::RUB.format_amount(123_450, &Language::Ru), "1\u{a0}234,50\u{a0}₽");
+        assert_eq!(Currency::EUR.format_amount(-123_450, &Language::De), "-1.234,50\u{a0}€");
+        assert_eq!(Currency::USDC.format_amount(100_000_000, &Language::De), "100,000000\u{a0}USDC");
+    }
+
+    #[test]
+    fn test_format_amount_edge_cases() {
+        // No fraction for currencies without minor units
+        assert_eq!(Currency::JPY.format_amount(1_234_567, &Language::En), "¥1,234,567");
+        assert_eq!(Currency::BTC.format_amount(1, &Language::En), "₿0.00000001");
+        assert_eq!(
+            Currency::USD.format_amount(i64::min_value(), &Language::En),
+            "-$92,233,720,368,547,758.08"
+        );
+    }
+}
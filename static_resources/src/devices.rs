@@ -1,12 +1,16 @@
 use std::fmt;
 use std::str::FromStr;
 
+use project::Project;
+
 #[derive(GraphQLEnum, Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, DieselTypes, EnumIterator)]
 #[graphql(name = "Device", description = "Device type")]
 pub enum Device {
     IOS,
     WEB,
     Android,
+    #[graphql(description = "Partner integration using the API directly")]
+    PartnerApi,
 }
 
 impl FromStr for Device {
@@ -16,6 +20,7 @@ impl FromStr for Device {
             "ios" => Ok(Device::IOS),
             "web" => Ok(Device::WEB),
             "android" => Ok(Device::Android),
+            "partner_api" => Ok(Device::PartnerApi),
             _ => Err(()),
         }
     }
@@ -27,6 +32,7 @@ impl fmt::Display for Device {
             Device::IOS => write!(f, "ios"),
             Device::WEB => write!(f, "web"),
             Device::Android => write!(f, "android"),
+            Device::PartnerApi => write!(f, "partner_api"),
         }
     }
 }
@@ -36,3 +42,45 @@ impl Device {
         Device::enum_iter().collect()
     }
 }
+
+/// Client of the request, as detected from `User-Agent` header
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct UserAgentInfo {
+    pub device: Device,
+    pub project: Project,
+}
+
+/// Detects device and project from `User-Agent` header value.
+///
+/// Browsers, including mobile ones, are recognized by `Mozilla/` prefix; mobile apps by platform names
+/// and HTTP libraries they use, e.g. `Storiqa/1.4 (iPhone; iOS 12.1)` or `StoriqaWallet/2.0 okhttp/3.10`;
+/// partner integrations by `Storiqa-Partner` product or common HTTP client libraries.
+/// Project is `Wallet` if user agent mentions wallet, `MarketPlace` otherwise.
+pub fn parse_user_agent(user_agent: &str) -> Option<UserAgentInfo> {
+    const IOS: &[&str] = &["iphone", "ipad", "ios", "cfnetwork", "darwin", "alamofire"];
+    const ANDROID: &[&str] = &["android", "okhttp", "dalvik"];
+    const PARTNER_API: &[&str] = &["storiqa-partner", "curl/", "python-requests", "go-http-client", "java/", "postman"];
+
+    let user_agent = user_agent.to_lowercase();
+    let mentions = |words: &[&str]| words.iter().any(|word| user_agent.contains(word));
+
+    let device = if mentions(PARTNER_API) {
+        Device::PartnerApi
+    } else if user_agent.starts_with("mozilla/") || user_agent.starts_with("opera/") {
+        // Mobile browsers mention platform too, but they are web clients
+        Device::WEB
+    } else if mentions(ANDROID) {
+        Device::Android
+    } else if mentions(IOS) {
+        Device::IOS
+    } else {
+        return None;
+    };
+    let project = if user_agent.contains("wallet") {
+        Project::Wallet
+    } else {
+        Project::MarketPlace
+    };
+
+    Some(UserAgentInfo { device, project })
+}
@@ -0,0 +1,270 @@
+//! Rendering of emails from Handlebars templates.
+//!
+//! Every email has a subject and an HTML body template per `TemplateVariant` and `Language`,
+//! plain text body is generated from the HTML one.
+//! Email structs serve as template contexts, so templates refer to their fields, e.g. `{{user.first_name}}`.
+//! Values are inserted as is, without HTML escaping, the same way hard-coded emails did.
+//! Templates may be loaded from a directory with files named `<variant>.<language>.subject.hbs`
+//! and `<variant>.<language>.text.hbs`, e.g. `order_create_for_user.ru.text.hbs`.
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use handlebars::{self, Handlebars};
+use serde::Serialize;
+use serde_json::{self, Value};
+
+use emails::{EmailMessage, TemplateVariant};
+use language::Language;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EmailTemplateError {
+    /// Template has invalid syntax
+    Template(String),
+    /// Template refers to a missing field or fails to render otherwise
+    Render(String),
+    /// Neither template to the language nor English one is registered
+    NotFound(TemplateVariant, Language),
+    Io(String),
+}
+
+impl fmt::Display for EmailTemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmailTemplateError::Template(e) => write!(f, "Invalid email template: {}", e),
+            EmailTemplateError::Render(e) => write!(f, "Failed to render email template: {}", e),
+            EmailTemplateError::NotFound(variant, lang) => write!(f, "Email template {} not found for language {}", variant, lang),
+            EmailTemplateError::Io(e) => write!(f, "Failed to read email templates: {}", e),
+        }
+    }
+}
+
+impl Error for EmailTemplateError {}
+
+/// Email struct used as a template context
+pub trait EmailContext: Serialize {
+    const TEMPLATE: TemplateVariant;
+
+    fn recipient(&self) -> &str;
+
+    /// Values templates refer to, the serialized email by default
+    fn template_data(&self) -> Result<Value, EmailTemplateError> {
+        serde_json::to_value(self).map_err(|e| EmailTemplateError::Render(e.to_string()))
+    }
+}
+
+pub trait TemplateRenderer {
+    fn render<C: EmailContext>(&self, context: &C, lang: Language) -> Result<EmailMessage, EmailTemplateError>;
+}
+
+/// Templates keyed by `TemplateVariant` and `Language`. Templates missing for the language fall back to English ones.
+/// Rendering fails if template refers to a field missing in the context.
+pub struct HandlebarsRenderer {
+    subjects: Handlebars,
+    texts: Handlebars,
+}
+
+fn template_name(variant: TemplateVariant, lang: Language) -> String {
+    format!("{}.{}", variant, lang)
+}
+
+impl HandlebarsRenderer {
+    pub fn new() -> Self {
+        let mut subjects = Handlebars::new();
+        subjects.set_strict_mode(true);
+        subjects.register_escape_fn(handlebars::no_escape);
+
+        let mut texts = Handlebars::new();
+        texts.set_strict_mode(true);
+        texts.register_escape_fn(handlebars::no_escape);
+
+        Self { subjects, texts }
+    }
+
+    /// Renderer with English templates for all emails defined in this crate
+    pub fn with_defaults() -> Self {
+        let mut renderer = Self::new();
+        for (variant, subject, text) in DEFAULT_TEMPLATES.iter() {
+            renderer
+                .register(*variant, Language::En, subject, text)
+                .expect("Default email templates must be valid");
+        }
+        renderer
+    }
+
+    /// Registers templates, replacing previously registered ones
+    pub fn register(&mut self, variant: TemplateVariant, lang: Language, subject: &str, text: &str) -> Result<(), EmailTemplateError> {
+        let name = template_name(variant, lang);
+        self.subjects
+            .register_template_string(&name, subject)
+            .map_err(|e| EmailTemplateError::Template(format!("{} subject: {}", name, e)))?;
+        self.texts
+            .register_template_string(&name, text)
+            .map_err(|e| EmailTemplateError::Template(format!("{} text: {}", name, e)))
+    }
+
+    /// Registers templates from `.hbs` files of the directory, see module docs for naming.
+    /// Files with other names are ignored.
+    pub fn load_directory<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), EmailTemplateError> {
+        let io_error = |e: ::std::io::Error| EmailTemplateError::Io(e.to_string());
+
+        for entry in fs::read_dir(dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            let file_name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(file_name) if file_name.ends_with(".hbs") => file_name.trim_end_matches(".hbs").to_string(),
+                _ => continue,
+            };
+            let parts = file_name.split('.').collect::<Vec<_>>();
+            let (variant, lang, part) = match parts.as_slice() {
+                [variant, lang, part] => match (variant.parse::<TemplateVariant>(), lang.parse::<Language>()) {
+                    (Ok(variant), Ok(lang)) => (variant, lang, *part),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+
+            let template = fs::read_to_string(&path).map_err(io_error)?;
+            let name = template_name(variant, lang);
+            let registry = match part {
+                "subject" => &mut self.subjects,
+                "text" => &mut self.texts,
+                _ => continue,
+            };
+            registry
+                .register_template_string(&name, template)
+                .map_err(|e| EmailTemplateError::Template(format!("{}: {}", file_name, e)))?;
+        }
+        Ok(())
+    }
+
+    fn resolve_name(&self, variant: TemplateVariant, lang: Language) -> Result<String, EmailTemplateError> {
+        [lang, Language::En]
+            .iter()
+            .map(|lang| template_name(variant, *lang))
+            .find(|name| self.subjects.get_template(name).is_some() && self.texts.get_template(name).is_some())
+            .ok_or(EmailTemplateError::NotFound(variant, lang))
+    }
+}
+
+impl Default for HandlebarsRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TemplateRenderer for HandlebarsRenderer {
+    fn render<C: EmailContext>(&self, context: &C, lang: Language) -> Result<EmailMessage, EmailTemplateError> {
+        let name = self.resolve_name(C::TEMPLATE, lang)?;
+        let render_error = |e: handlebars::RenderError| EmailTemplateError::Render(format!("{}: {}", name, e));
+
+        let data = context.template_data()?;
+        let subject = self.subjects.render(&name, &data).map_err(render_error)?;
+        let html = self.texts.render(&name, &data).map_err(render_error)?;
+        Ok(EmailMessage::new(context.recipient().to_string(), subject, html))
+    }
+}
+
+lazy_static! {
+    /// Renderer with default templates, used by `Email::into_email_message` and as a fallback of `Email::into_email_message_with`
+    pub static ref DEFAULT_RENDERER: HandlebarsRenderer = HandlebarsRenderer::with_defaults();
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const DEFAULT_TEMPLATES: [(TemplateVariant, &str, &str); 19] = [
+    (
+        TemplateVariant::OrderUpdateStateForUser,
+        "The order {{order_slug}} status",
+        "Orders' {{order_slug}} state is '{{order_state}}' now. You can view current info about your order on <a href=\"{{cluster_url}}/profile/orders/{{order_slug}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::OrderUpdateStateForStore,
+        "The order {{order_slug}} status",
+        "Orders' {{order_slug}} state is '{{order_state}}' now. You can view current order info on <a href=\"{{cluster_url}}/manage/store/{{store_id}}/orders/{{order_slug}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::OrderCreateForUser,
+        "New order {{order_slug}}.",
+        "Order {{order_slug}} was created. You can view current info about your order on <a href=\"{{cluster_url}}/profile/orders/{{order_slug}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::OrderCreateForStore,
+        "New order {{order_slug}}.",
+        "Order {{order_slug}} was created. You can view current order info on <a href=\"{{cluster_url}}/manage/store/{{store_id}}/orders/{{order_slug}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::EmailVerificationForUser,
+        "Verify your account on Storiqa",
+        "{{verify_email_path}}/{{token}}",
+    ),
+    (
+        TemplateVariant::PasswordResetForUser,
+        "Password reset",
+        "{{reset_password_path}}/{{token}}",
+    ),
+    (
+        TemplateVariant::ApplyPasswordResetForUser,
+        "Successful password reset",
+        "Password for linked account has been successfully reset.",
+    ),
+    (
+        TemplateVariant::ApplyEmailVerificationForUser,
+        "Successful registration",
+        "Email for linked account has been verified.",
+    ),
+    (
+        TemplateVariant::StoreModerationStatusForUser,
+        "The moderation status of the store has changed. New status {{status}}",
+        "Store {{store_id}} status has been changed. <br> New status {{status}}. <br> You can view current store info on <a href=\"{{cluster_url}}/store/{{store_id}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::BaseProductModerationStatusForUser,
+        "The moderation status of the product has changed. New status {{status}}.",
+        "Product {{base_product_id}} status has been changed. <br> New status {{status}}. <br> You can view current product info on <a href=\"{{cluster_url}}/store/{{store_id}}/products/{{base_product_id}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::StoreModerationStatusForModerator,
+        "The moderation status of the store has changed. New status {{status}}.",
+        "Store {{store_id}} status has been changed. <br> New status {{status}}. <br> You can view current store info on <a href=\"{{cluster_url}}/store/{{store_id}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::BaseProductModerationStatusForModerator,
+        "The moderation status of the product has changed. New status {{status}}.",
+        "Product {{base_product_id}} status has been changed. <br> New status {{status}}. <br> You can view current product info on <a href=\"{{cluster_url}}/store/{{store_id}}/products/{{base_product_id}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::DisputeOpenedForUser,
+        "A dispute on the order {{order_slug}} is opened",
+        "A dispute on the order {{order_slug}} is opened. You can follow it on <a href=\"{{cluster_url}}/profile/orders/{{order_slug}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::DisputeOpenedForStore,
+        "A dispute on the order {{order_slug}} is opened",
+        "Customer opened a dispute on the order {{order_slug}}. You can view the order and respond on <a href=\"{{cluster_url}}/manage/store/{{store_id}}/orders/{{order_slug}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::DisputeResolvedForUser,
+        "The dispute on the order {{order_slug}} is resolved",
+        "The dispute on the order {{order_slug}} is resolved: {{resolution}}. You can view current info about your order on <a href=\"{{cluster_url}}/profile/orders/{{order_slug}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::DisputeResolvedForStore,
+        "The dispute on the order {{order_slug}} is resolved",
+        "The dispute on the order {{order_slug}} is resolved: {{resolution}}. You can view current order info on <a href=\"{{cluster_url}}/manage/store/{{store_id}}/orders/{{order_slug}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::RefundIssuedForUser,
+        "Refund for the order {{order_slug}}",
+        "{{amount}} has been refunded for the order {{order_slug}}. You can view current info about your order on <a href=\"{{cluster_url}}/profile/orders/{{order_slug}}\">this page</a>.",
+    ),
+    (
+        TemplateVariant::PayoutSentForStore,
+        "Payout of {{amount}} is sent",
+        "Payout of {{amount}} has been sent to the store {{store_id}}. You can view the store payouts on <a href=\"{{cluster_url}}/manage/store/{{store_id}}/finances\">this page</a>.",
+    ),
+    (
+        TemplateVariant::PreOrderReminderForUser,
+        "{{product_name}} will be available soon",
+        "{{product_name}} you pre-ordered in the order {{order_slug}} will be available on {{available_at}}. You can view current info about your order on <a href=\"{{cluster_url}}/profile/orders/{{order_slug}}\">this page</a>.",
+    ),
+];
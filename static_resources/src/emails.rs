@@ -1,8 +1,12 @@
+use serde_json::{self, Value};
 use std::fmt;
 use std::str::FromStr;
 
+use email_templates::{EmailContext, EmailTemplateError, TemplateRenderer, DEFAULT_RENDERER};
+use language::Language;
 use moderation_status::ModerationStatus;
 
+/// Single part email, `text` is actually HTML. Prefer `EmailMessage` for new code.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SimpleMail {
     pub to: String,
@@ -10,6 +14,178 @@ pub struct SimpleMail {
     pub text: String,
 }
 
+/// File attached to an email, content is downloaded by the sender from `url`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EmailAttachment {
+    pub file_name: String,
+    pub content_type: String,
+    pub url: String,
+    pub size: Option<u64>,
+}
+
+/// Email with both HTML and plain text bodies, sent as `multipart/alternative`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub bcc: Vec<String>,
+    #[serde(default)]
+    pub attachments: Vec<EmailAttachment>,
+}
+
+impl EmailMessage {
+    /// Message with plain text body generated from `html`
+    pub fn new(to: String, subject: String, html: String) -> Self {
+        let text = html_to_text(&html);
+        Self {
+            to,
+            subject,
+            html,
+            text,
+            reply_to: None,
+            cc: vec![],
+            bcc: vec![],
+            attachments: vec![],
+        }
+    }
+
+    pub fn with_text(mut self, text: String) -> Self {
+        self.text = text;
+        self
+    }
+
+    pub fn with_reply_to(mut self, reply_to: String) -> Self {
+        self.reply_to = Some(reply_to);
+        self
+    }
+
+    pub fn with_cc(mut self, cc: String) -> Self {
+        self.cc.push(cc);
+        self
+    }
+
+    pub fn with_bcc(mut self, bcc: String) -> Self {
+        self.bcc.push(bcc);
+        self
+    }
+
+    pub fn with_attachment(mut self, attachment: EmailAttachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+}
+
+/// Drops plain text body, copies, reply-to and attachments
+impl From<EmailMessage> for SimpleMail {
+    fn from(v: EmailMessage) -> Self {
+        Self {
+            to: v.to,
+            subject: v.subject,
+            text: v.html,
+        }
+    }
+}
+
+impl From<SimpleMail> for EmailMessage {
+    fn from(v: SimpleMail) -> Self {
+        EmailMessage::new(v.to, v.subject, v.text)
+    }
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if entity.starts_with('#') {
+        let code = &entity[1..];
+        let code = if code.starts_with('x') || code.starts_with('X') {
+            u32::from_str_radix(&code[1..], 16).ok()
+        } else {
+            code.parse::<u32>().ok()
+        };
+        return code.and_then(::std::char::from_u32);
+    }
+    Some(match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        _ => return None,
+    })
+}
+
+fn tag_attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = tag[start..].find('"')? + start;
+    Some(tag[start..end].to_string())
+}
+
+/// Converts HTML email body to plain text: drops tags, turns line breaks and paragraphs into new lines,
+/// appends link targets to their labels and decodes basic named and numeric entities
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut href = None;
+    let mut rest = html;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '<' => {
+                let end = match rest.find('>') {
+                    Some(end) => end,
+                    None => {
+                        text.push_str(rest);
+                        break;
+                    }
+                };
+                let tag = &rest[1..end];
+                let name = tag
+                    .trim_start_matches('/')
+                    .split(|c: char| c.is_whitespace() || c == '/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_lowercase();
+                let closing = tag.starts_with('/');
+                match name.as_str() {
+                    "br" | "p" | "div" | "tr" | "li" if name == "br" || closing => text.push('\n'),
+                    "a" if closing => {
+                        if let Some(href) = href.take() {
+                            text.push_str(&format!(" ({})", href));
+                        }
+                    }
+                    "a" => href = tag_attribute(tag, "href"),
+                    _ => {}
+                }
+                rest = &rest[end + 1..];
+            }
+            '&' => {
+                let decoded = rest.find(';').and_then(|end| decode_entity(&rest[1..end]).map(|c| (c, end)));
+                match decoded {
+                    Some((c, end)) => {
+                        text.push(c);
+                        rest = &rest[end + 1..];
+                    }
+                    None => {
+                        text.push('&');
+                        rest = &rest[1..];
+                    }
+                }
+            }
+            c => {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    text.lines().map(str::trim).collect::<Vec<_>>().join("\n").trim().to_string()
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EmailUser {
     pub email: String,
@@ -19,6 +195,78 @@ pub struct EmailUser {
 
 pub trait Email {
     fn into_send_mail(self) -> SimpleMail;
+
+    /// Same as `into_send_mail`, but returns an error instead of panicking if the email fails to render
+    fn try_into_send_mail(self) -> Result<SimpleMail, EmailTemplateError>
+    where
+        Self: Sized,
+    {
+        Ok(self.into_send_mail())
+    }
+
+    /// Plain text body is generated from the HTML one unless overridden
+    fn into_email_message(self) -> Result<EmailMessage, EmailTemplateError>
+    where
+        Self: Sized,
+    {
+        self.try_into_send_mail().map(EmailMessage::from)
+    }
+
+    /// Renders the email with `renderer` in `lang`, e.g. with templates loaded from a directory.
+    /// Emails not rendered from templates ignore the renderer.
+    fn into_email_message_with<R>(self, _renderer: &R, _lang: Language) -> Result<EmailMessage, EmailTemplateError>
+    where
+        R: TemplateRenderer,
+        Self: Sized,
+    {
+        self.into_email_message()
+    }
+
+    fn into_send_mail_with<R>(self, renderer: &R, lang: Language) -> Result<SimpleMail, EmailTemplateError>
+    where
+        R: TemplateRenderer,
+        Self: Sized,
+    {
+        self.into_email_message_with(renderer, lang).map(SimpleMail::from)
+    }
+}
+
+/// Renders the email with default templates
+impl<T> Email for T
+where
+    T: EmailContext,
+{
+    /// Panics if the default template fails to render, which means the template and the email struct diverged
+    fn into_send_mail(self) -> SimpleMail {
+        self.try_into_send_mail()
+            .unwrap_or_else(|e| panic!("Default email template failed to render: {}", e))
+    }
+
+    fn try_into_send_mail(self) -> Result<SimpleMail, EmailTemplateError> {
+        self.into_email_message().map(SimpleMail::from)
+    }
+
+    fn into_email_message(self) -> Result<EmailMessage, EmailTemplateError> {
+        DEFAULT_RENDERER.render(&self, Language::En)
+    }
+
+    /// Falls back to the default English templates if `renderer` has no templates of the email
+    fn into_email_message_with<R>(self, renderer: &R, lang: Language) -> Result<EmailMessage, EmailTemplateError>
+    where
+        R: TemplateRenderer,
+    {
+        match renderer.render(&self, lang) {
+            Err(EmailTemplateError::NotFound(_, _)) => self.into_email_message(),
+            res => res,
+        }
+    }
+}
+
+/// Template data with moderation status displayed as in the rest of the emails, e.g. `published`
+fn moderation_template_data<T: EmailContext>(email: &T, status: ModerationStatus) -> Result<Value, EmailTemplateError> {
+    let mut data = serde_json::to_value(email).map_err(|e| EmailTemplateError::Render(e.to_string()))?;
+    data["status"] = Value::String(status.to_string());
+    Ok(data)
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -29,16 +277,11 @@ pub struct OrderUpdateStateForUser {
     pub cluster_url: String,
 }
 
-impl Email for OrderUpdateStateForUser {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to : self.user.email,
-            subject : format!("The order {} status", self.order_slug),
-            text : format!(
-                "Orders' {} state is '{}' now. You can view current info about your order on <a href=\"{}/profile/orders/{}\">this page</a>.",
-                self.order_slug, self.order_state, self.cluster_url, self.order_slug
-            ),
-        }
+impl EmailContext for OrderUpdateStateForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::OrderUpdateStateForUser;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
     }
 }
 
@@ -51,16 +294,11 @@ pub struct OrderUpdateStateForStore {
     pub store_id: String,
 }
 
-impl Email for OrderUpdateStateForStore {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to: self.store_email,
-            subject: format!("The order {} status", self.order_slug),
-            text: format!(
-                "Orders' {} state is '{}' now. You can view current order info on <a href=\"{}/manage/store/{}/orders/{}\">this page</a>.",
-                self.order_slug, self.order_state, self.cluster_url, self.store_id, self.order_slug
-            ),
-        }
+impl EmailContext for OrderUpdateStateForStore {
+    const TEMPLATE: TemplateVariant = TemplateVariant::OrderUpdateStateForStore;
+
+    fn recipient(&self) -> &str {
+        &self.store_email
     }
 }
 
@@ -71,16 +309,11 @@ pub struct OrderCreateForUser {
     pub cluster_url: String,
 }
 
-impl Email for OrderCreateForUser {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to: self.user.email,
-            subject: format!("New order {}.", self.order_slug),
-            text: format!(
-                "Order {} was created. You can view current info about your order on <a href=\"{}/profile/orders/{}\">this page</a>.",
-                self.order_slug, self.cluster_url, self.order_slug
-            ),
-        }
+impl EmailContext for OrderCreateForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::OrderCreateForUser;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
     }
 }
 
@@ -92,16 +325,11 @@ pub struct OrderCreateForStore {
     pub store_id: String,
 }
 
-impl Email for OrderCreateForStore {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to: self.store_email,
-            subject: format!("New order {}.", self.order_slug),
-            text: format!(
-                "Order {} was created. You can view current order info on <a href=\"{}/manage/store/{}/orders/{}\">this page</a>.",
-                self.order_slug, self.cluster_url, self.store_id, self.order_slug
-            ),
-        }
+impl EmailContext for OrderCreateForStore {
+    const TEMPLATE: TemplateVariant = TemplateVariant::OrderCreateForStore;
+
+    fn recipient(&self) -> &str {
+        &self.store_email
     }
 }
 
@@ -112,13 +340,11 @@ pub struct EmailVerificationForUser {
     pub token: String,
 }
 
-impl Email for EmailVerificationForUser {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to: self.user.email,
-            subject: "Verify your account on Storiqa".to_string(),
-            text: format!("{}/{}", self.verify_email_path, self.token),
-        }
+impl EmailContext for EmailVerificationForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::EmailVerificationForUser;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
     }
 }
 
@@ -129,13 +355,11 @@ pub struct PasswordResetForUser {
     pub token: String,
 }
 
-impl Email for PasswordResetForUser {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to: self.user.email,
-            subject: "Password reset".to_string(),
-            text: format!("{}/{}", self.reset_password_path, self.token),
-        }
+impl EmailContext for PasswordResetForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::PasswordResetForUser;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
     }
 }
 
@@ -145,13 +369,11 @@ pub struct ApplyPasswordResetForUser {
     pub cluster_url: String,
 }
 
-impl Email for ApplyPasswordResetForUser {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to: self.user.email,
-            subject: "Successful password reset".to_string(),
-            text: "Password for linked account has been successfully reset.".to_string(),
-        }
+impl EmailContext for ApplyPasswordResetForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::ApplyPasswordResetForUser;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
     }
 }
 
@@ -163,16 +385,15 @@ pub struct StoreModerationStatusForUser {
     pub status: ModerationStatus,
 }
 
-impl Email for StoreModerationStatusForUser {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to: self.store_email,
-            subject: format!("The moderation status of the store has changed. New status {}", self.status),
-            text: format!(
-                "Store {} status has been changed. <br> New status {}. <br> You can view current store info on <a href=\"{}/store/{}\">this page</a>.",
-                self.store_id, self.status, self.cluster_url, self.store_id
-            ),
-        }
+impl EmailContext for StoreModerationStatusForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::StoreModerationStatusForUser;
+
+    fn recipient(&self) -> &str {
+        &self.store_email
+    }
+
+    fn template_data(&self) -> Result<Value, EmailTemplateError> {
+        moderation_template_data(self, self.status)
     }
 }
 
@@ -185,16 +406,15 @@ pub struct BaseProductModerationStatusForUser {
     pub status: ModerationStatus,
 }
 
-impl Email for BaseProductModerationStatusForUser {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to: self.store_email,
-            subject: format!("The moderation status of the product has changed. New status {}.", self.status),
-            text: format!(
-                "Product {} status has been changed. <br> New status {}. <br> You can view current product info on <a href=\"{}/store/{}/products/{}\">this page</a>.",
-                self.base_product_id, self.status, self.cluster_url, self.store_id, self.base_product_id
-            ),
-        }
+impl EmailContext for BaseProductModerationStatusForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::BaseProductModerationStatusForUser;
+
+    fn recipient(&self) -> &str {
+        &self.store_email
+    }
+
+    fn template_data(&self) -> Result<Value, EmailTemplateError> {
+        moderation_template_data(self, self.status)
     }
 }
 
@@ -206,16 +426,15 @@ pub struct StoreModerationStatusForModerator {
     pub status: ModerationStatus,
 }
 
-impl Email for StoreModerationStatusForModerator {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to: self.user.email,
-            subject: format!("The moderation status of the store has changed. New status {}.", self.status),
-            text: format!(
-                "Store {} status has been changed. <br> New status {}. <br> You can view current store info on <a href=\"{}/store/{}\">this page</a>.",
-                self.store_id, self.status, self.cluster_url, self.store_id
-            ),
-        }
+impl EmailContext for StoreModerationStatusForModerator {
+    const TEMPLATE: TemplateVariant = TemplateVariant::StoreModerationStatusForModerator;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
+    }
+
+    fn template_data(&self) -> Result<Value, EmailTemplateError> {
+        moderation_template_data(self, self.status)
     }
 }
 
@@ -228,16 +447,15 @@ pub struct BaseProductModerationStatusForModerator {
     pub status: ModerationStatus,
 }
 
-impl Email for BaseProductModerationStatusForModerator {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to: self.user.email,
-            subject: format!("The moderation status of the product has changed. New status {}.", self.status),
-            text: format!(
-                "Product {} status has been changed. <br> New status {}. <br> You can view current product info on <a href=\"{}/store/{}/products/{}\">this page</a>.",
-                self.base_product_id, self.status, self.cluster_url, self.store_id, self.base_product_id
-            ),
-        }
+impl EmailContext for BaseProductModerationStatusForModerator {
+    const TEMPLATE: TemplateVariant = TemplateVariant::BaseProductModerationStatusForModerator;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
+    }
+
+    fn template_data(&self) -> Result<Value, EmailTemplateError> {
+        moderation_template_data(self, self.status)
     }
 }
 
@@ -246,17 +464,133 @@ pub struct ApplyEmailVerificationForUser {
     pub user: EmailUser,
 }
 
-impl Email for ApplyEmailVerificationForUser {
-    fn into_send_mail(self) -> SimpleMail {
-        SimpleMail {
-            to: self.user.email,
-            subject: "Successful registration".to_string(),
-            text: "Email for linked account has been verified.".to_string(),
-        }
+impl EmailContext for ApplyEmailVerificationForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::ApplyEmailVerificationForUser;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DisputeOpenedForUser {
+    pub user: EmailUser,
+    pub order_slug: String,
+    pub cluster_url: String,
+}
+
+impl EmailContext for DisputeOpenedForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::DisputeOpenedForUser;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
     }
 }
 
-#[derive(GraphQLEnum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, DieselTypes)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DisputeOpenedForStore {
+    pub store_email: String,
+    pub order_slug: String,
+    pub cluster_url: String,
+    pub store_id: String,
+}
+
+impl EmailContext for DisputeOpenedForStore {
+    const TEMPLATE: TemplateVariant = TemplateVariant::DisputeOpenedForStore;
+
+    fn recipient(&self) -> &str {
+        &self.store_email
+    }
+}
+
+/// `resolution` is a human readable outcome of the dispute, e.g. "Refunded"
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DisputeResolvedForUser {
+    pub user: EmailUser,
+    pub order_slug: String,
+    pub resolution: String,
+    pub cluster_url: String,
+}
+
+impl EmailContext for DisputeResolvedForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::DisputeResolvedForUser;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
+    }
+}
+
+/// `resolution` is a human readable outcome of the dispute, e.g. "Refunded"
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DisputeResolvedForStore {
+    pub store_email: String,
+    pub order_slug: String,
+    pub resolution: String,
+    pub cluster_url: String,
+    pub store_id: String,
+}
+
+impl EmailContext for DisputeResolvedForStore {
+    const TEMPLATE: TemplateVariant = TemplateVariant::DisputeResolvedForStore;
+
+    fn recipient(&self) -> &str {
+        &self.store_email
+    }
+}
+
+/// `amount` is formatted with currency, e.g. with `Currency::format_amount`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RefundIssuedForUser {
+    pub user: EmailUser,
+    pub order_slug: String,
+    pub amount: String,
+    pub cluster_url: String,
+}
+
+impl EmailContext for RefundIssuedForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::RefundIssuedForUser;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
+    }
+}
+
+/// `amount` is formatted with currency, e.g. with `Currency::format_amount`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PayoutSentForStore {
+    pub store_email: String,
+    pub store_id: String,
+    pub amount: String,
+    pub cluster_url: String,
+}
+
+impl EmailContext for PayoutSentForStore {
+    const TEMPLATE: TemplateVariant = TemplateVariant::PayoutSentForStore;
+
+    fn recipient(&self) -> &str {
+        &self.store_email
+    }
+}
+
+/// Reminds that pre-ordered product becomes available, `available_at` is a formatted date
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PreOrderReminderForUser {
+    pub user: EmailUser,
+    pub order_slug: String,
+    pub product_name: String,
+    pub available_at: String,
+    pub cluster_url: String,
+}
+
+impl EmailContext for PreOrderReminderForUser {
+    const TEMPLATE: TemplateVariant = TemplateVariant::PreOrderReminderForUser;
+
+    fn recipient(&self) -> &str {
+        &self.user.email
+    }
+}
+
+#[derive(GraphQLEnum, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, DieselTypes, EnumIterator)]
 #[graphql(name = "TemplateVariant", description = "Template variant")]
 pub enum TemplateVariant {
     #[graphql(description = "order create for user.")]
@@ -291,6 +625,20 @@ pub enum TemplateVariant {
     WalletApplyPasswordResetForUser,
     #[graphql(description = "apply email verification.")]
     WalletApplyEmailVerificationForUser,
+    #[graphql(description = "dispute opened for user.")]
+    DisputeOpenedForUser,
+    #[graphql(description = "dispute opened for store.")]
+    DisputeOpenedForStore,
+    #[graphql(description = "dispute resolved for user.")]
+    DisputeResolvedForUser,
+    #[graphql(description = "dispute resolved for store.")]
+    DisputeResolvedForStore,
+    #[graphql(description = "refund issued for user.")]
+    RefundIssuedForUser,
+    #[graphql(description = "payout sent for store.")]
+    PayoutSentForStore,
+    #[graphql(description = "pre-order reminder for user.")]
+    PreOrderReminderForUser,
 }
 
 impl FromStr for TemplateVariant {
@@ -313,6 +661,13 @@ impl FromStr for TemplateVariant {
             "wallet_password_reset_for_user" => Ok(TemplateVariant::WalletPasswordResetForUser),
             "wallet_apply_password_reset_for_user" => Ok(TemplateVariant::WalletApplyPasswordResetForUser),
             "wallet_apply_email_verification_for_user" => Ok(TemplateVariant::WalletApplyEmailVerificationForUser),
+            "dispute_opened_for_user" => Ok(TemplateVariant::DisputeOpenedForUser),
+            "dispute_opened_for_store" => Ok(TemplateVariant::DisputeOpenedForStore),
+            "dispute_resolved_for_user" => Ok(TemplateVariant::DisputeResolvedForUser),
+            "dispute_resolved_for_store" => Ok(TemplateVariant::DisputeResolvedForStore),
+            "refund_issued_for_user" => Ok(TemplateVariant::RefundIssuedForUser),
+            "payout_sent_for_store" => Ok(TemplateVariant::PayoutSentForStore),
+            "pre_order_reminder_for_user" => Ok(TemplateVariant::PreOrderReminderForUser),
             _ => Err(()),
         }
     }
@@ -337,6 +692,414 @@ impl fmt::Display for TemplateVariant {
             TemplateVariant::WalletPasswordResetForUser => write!(f, "wallet_password_reset_for_user"),
             TemplateVariant::WalletApplyPasswordResetForUser => write!(f, "wallet_apply_password_reset_for_user"),
             TemplateVariant::WalletApplyEmailVerificationForUser => write!(f, "wallet_apply_email_verification_for_user"),
+            TemplateVariant::DisputeOpenedForUser => write!(f, "dispute_opened_for_user"),
+            TemplateVariant::DisputeOpenedForStore => write!(f, "dispute_opened_for_store"),
+            TemplateVariant::DisputeResolvedForUser => write!(f, "dispute_resolved_for_user"),
+            TemplateVariant::DisputeResolvedForStore => write!(f, "dispute_resolved_for_store"),
+            TemplateVariant::RefundIssuedForUser => write!(f, "refund_issued_for_user"),
+            TemplateVariant::PayoutSentForStore => write!(f, "payout_sent_for_store"),
+            TemplateVariant::PreOrderReminderForUser => write!(f, "pre_order_reminder_for_user"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use email_templates::HandlebarsRenderer;
+
+    const CLUSTER_URL: &str = "https://storiqa.com";
+
+    fn user() -> EmailUser {
+        EmailUser {
+            email: "user@storiqa.com".to_string(),
+            first_name: "John".to_string(),
+            last_name: "O'Neil".to_string(),
+        }
+    }
+
+    fn render<E: Email>(email: E) -> EmailMessage {
+        email.into_email_message().unwrap()
+    }
+
+    #[test]
+    fn html_to_text_drops_tags_and_keeps_links() {
+        assert_eq!(
+            html_to_text("<p>Store status has been changed.</p><p>New status <b>published</b>.</p> View it on <a href=\"https://storiqa.com/store/5\">this page</a>.<br>"),
+            "Store status has been changed.\nNew status published.\nView it on this page (https://storiqa.com/store/5)."
+        );
+    }
+
+    #[test]
+    fn html_to_text_decodes_entities() {
+        assert_eq!(
+            html_to_text("Tom &amp; Jerry&#x27;s &lt;mug&gt; &quot;big&quot;"),
+            "Tom & Jerry's <mug> \"big\""
+        );
+        assert_eq!(html_to_text("a&#x3D;b&#61;c &#39;d&#39; &apos;e&apos;"), "a=b=c 'd' 'e'");
+        assert_eq!(html_to_text("R&amp;D &unknown; &#xZZ; & 5"), "R&D &unknown; &#xZZ; & 5");
+    }
+
+    #[test]
+    fn send_mail_keeps_html_body() {
+        let mail = ApplyEmailVerificationForUser { user: user() }.into_send_mail();
+        assert_eq!(mail.to, "user@storiqa.com");
+        assert_eq!(mail.text, "Email for linked account has been verified.");
+
+        let mail = ApplyEmailVerificationForUser { user: user() }.try_into_send_mail().unwrap();
+        assert_eq!(mail.text, "Email for linked account has been verified.");
+    }
+
+    #[test]
+    fn email_message_defaults_to_send_mail() {
+        struct Custom;
+
+        impl Email for Custom {
+            fn into_send_mail(self) -> SimpleMail {
+                SimpleMail {
+                    to: "user@storiqa.com".to_string(),
+                    subject: "Hello".to_string(),
+                    text: "Visit <a href=\"https://storiqa.com\">Storiqa</a>".to_string(),
+                }
+            }
+        }
+
+        let message = Custom.into_email_message().unwrap();
+        assert_eq!(message.subject, "Hello");
+        assert_eq!(message.html, "Visit <a href=\"https://storiqa.com\">Storiqa</a>");
+        assert_eq!(message.text, "Visit Storiqa (https://storiqa.com)");
+    }
+
+    #[test]
+    fn renders_with_given_renderer_and_language() {
+        let email = || ApplyEmailVerificationForUser { user: user() };
+
+        let mut renderer = HandlebarsRenderer::with_defaults();
+        renderer
+            .register(
+                TemplateVariant::ApplyEmailVerificationForUser,
+                Language::Ru,
+                "Регистрация завершена",
+                "{{user.first_name}}, почта подтверждена.",
+            )
+            .unwrap();
+        let mail = email().into_send_mail_with(&renderer, Language::Ru).unwrap();
+        assert_eq!(mail.subject, "Регистрация завершена");
+        assert_eq!(mail.text, "John, почта подтверждена.");
+
+        // Templates missing for the language fall back to the English ones of the renderer
+        renderer
+            .register(
+                TemplateVariant::ApplyEmailVerificationForUser,
+                Language::En,
+                "Welcome",
+                "Welcome, {{user.first_name}}",
+            )
+            .unwrap();
+        let message = email().into_email_message_with(&renderer, Language::De).unwrap();
+        assert_eq!(message.subject, "Welcome");
+        assert_eq!(message.text, "Welcome, John");
+
+        // Renderer without templates of the email falls back to the default ones
+        let message = email().into_email_message_with(&HandlebarsRenderer::new(), Language::Ru).unwrap();
+        assert_eq!(message.subject, "Successful registration");
+    }
+
+    #[test]
+    fn renders_order_update_state_for_user() {
+        let message = render(OrderUpdateStateForUser {
+            user: user(),
+            order_slug: "1234".to_string(),
+            order_state: "In processing".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+        });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(message.subject, "The order 1234 status");
+        assert_eq!(
+            message.html,
+            "Orders' 1234 state is 'In processing' now. You can view current info about your order on <a href=\"https://storiqa.com/profile/orders/1234\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_order_update_state_for_store() {
+        let message = render(OrderUpdateStateForStore {
+            store_email: "store@storiqa.com".to_string(),
+            order_slug: "1234".to_string(),
+            order_state: "Paid".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+            store_id: "5".to_string(),
+        });
+        assert_eq!(message.to, "store@storiqa.com");
+        assert_eq!(message.subject, "The order 1234 status");
+        assert_eq!(
+            message.html,
+            "Orders' 1234 state is 'Paid' now. You can view current order info on <a href=\"https://storiqa.com/manage/store/5/orders/1234\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_order_create_for_user() {
+        let message = render(OrderCreateForUser {
+            user: user(),
+            order_slug: "1234".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+        });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(message.subject, "New order 1234.");
+        assert_eq!(
+            message.html,
+            "Order 1234 was created. You can view current info about your order on <a href=\"https://storiqa.com/profile/orders/1234\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_order_create_for_store() {
+        let message = render(OrderCreateForStore {
+            store_email: "store@storiqa.com".to_string(),
+            order_slug: "1234".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+            store_id: "5".to_string(),
+        });
+        assert_eq!(message.to, "store@storiqa.com");
+        assert_eq!(message.subject, "New order 1234.");
+        assert_eq!(
+            message.html,
+            "Order 1234 was created. You can view current order info on <a href=\"https://storiqa.com/manage/store/5/orders/1234\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_email_verification_for_user() {
+        let message = render(EmailVerificationForUser {
+            user: user(),
+            verify_email_path: "https://storiqa.com/verify?a=1&b=2".to_string(),
+            token: "token".to_string(),
+        });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(message.subject, "Verify your account on Storiqa");
+        assert_eq!(message.html, "https://storiqa.com/verify?a=1&b=2/token");
+    }
+
+    #[test]
+    fn renders_password_reset_for_user() {
+        let message = render(PasswordResetForUser {
+            user: user(),
+            reset_password_path: "https://storiqa.com/reset".to_string(),
+            token: "token".to_string(),
+        });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(message.subject, "Password reset");
+        assert_eq!(message.html, "https://storiqa.com/reset/token");
+    }
+
+    #[test]
+    fn renders_apply_password_reset_for_user() {
+        let message = render(ApplyPasswordResetForUser {
+            user: user(),
+            cluster_url: CLUSTER_URL.to_string(),
+        });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(message.subject, "Successful password reset");
+        assert_eq!(message.html, "Password for linked account has been successfully reset.");
+    }
+
+    #[test]
+    fn renders_apply_email_verification_for_user() {
+        let message = render(ApplyEmailVerificationForUser { user: user() });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(message.subject, "Successful registration");
+        assert_eq!(message.html, "Email for linked account has been verified.");
+    }
+
+    #[test]
+    fn renders_store_moderation_status_for_user() {
+        let message = render(StoreModerationStatusForUser {
+            store_email: "store@storiqa.com".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+            store_id: "5".to_string(),
+            status: ModerationStatus::Published,
+        });
+        assert_eq!(message.to, "store@storiqa.com");
+        assert_eq!(
+            message.subject,
+            "The moderation status of the store has changed. New status published"
+        );
+        assert_eq!(
+            message.html,
+            "Store 5 status has been changed. <br> New status published. <br> You can view current store info on <a href=\"https://storiqa.com/store/5\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_base_product_moderation_status_for_user() {
+        let message = render(BaseProductModerationStatusForUser {
+            store_email: "store@storiqa.com".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+            base_product_id: "7".to_string(),
+            store_id: "5".to_string(),
+            status: ModerationStatus::Decline,
+        });
+        assert_eq!(message.to, "store@storiqa.com");
+        assert_eq!(
+            message.subject,
+            "The moderation status of the product has changed. New status decline."
+        );
+        assert_eq!(
+            message.html,
+            "Product 7 status has been changed. <br> New status decline. <br> You can view current product info on <a href=\"https://storiqa.com/store/5/products/7\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_store_moderation_status_for_moderator() {
+        let message = render(StoreModerationStatusForModerator {
+            user: user(),
+            cluster_url: CLUSTER_URL.to_string(),
+            store_id: "5".to_string(),
+            status: ModerationStatus::Moderation,
+        });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(
+            message.subject,
+            "The moderation status of the store has changed. New status moderation."
+        );
+        assert_eq!(
+            message.html,
+            "Store 5 status has been changed. <br> New status moderation. <br> You can view current store info on <a href=\"https://storiqa.com/store/5\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_base_product_moderation_status_for_moderator() {
+        let message = render(BaseProductModerationStatusForModerator {
+            user: user(),
+            cluster_url: CLUSTER_URL.to_string(),
+            store_id: "5".to_string(),
+            base_product_id: "7".to_string(),
+            status: ModerationStatus::Blocked,
+        });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(
+            message.subject,
+            "The moderation status of the product has changed. New status blocked."
+        );
+        assert_eq!(
+            message.html,
+            "Product 7 status has been changed. <br> New status blocked. <br> You can view current product info on <a href=\"https://storiqa.com/store/5/products/7\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_dispute_opened_for_user() {
+        let message = render(DisputeOpenedForUser {
+            user: user(),
+            order_slug: "1234".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+        });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(message.subject, "A dispute on the order 1234 is opened");
+        assert_eq!(
+            message.html,
+            "A dispute on the order 1234 is opened. You can follow it on <a href=\"https://storiqa.com/profile/orders/1234\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_dispute_opened_for_store() {
+        let message = render(DisputeOpenedForStore {
+            store_email: "store@storiqa.com".to_string(),
+            order_slug: "1234".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+            store_id: "5".to_string(),
+        });
+        assert_eq!(message.to, "store@storiqa.com");
+        assert_eq!(message.subject, "A dispute on the order 1234 is opened");
+        assert_eq!(
+            message.html,
+            "Customer opened a dispute on the order 1234. You can view the order and respond on <a href=\"https://storiqa.com/manage/store/5/orders/1234\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_dispute_resolved_for_user() {
+        let message = render(DisputeResolvedForUser {
+            user: user(),
+            order_slug: "1234".to_string(),
+            resolution: "Refunded".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+        });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(message.subject, "The dispute on the order 1234 is resolved");
+        assert_eq!(
+            message.html,
+            "The dispute on the order 1234 is resolved: Refunded. You can view current info about your order on <a href=\"https://storiqa.com/profile/orders/1234\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_dispute_resolved_for_store() {
+        let message = render(DisputeResolvedForStore {
+            store_email: "store@storiqa.com".to_string(),
+            order_slug: "1234".to_string(),
+            resolution: "Refunded".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+            store_id: "5".to_string(),
+        });
+        assert_eq!(message.to, "store@storiqa.com");
+        assert_eq!(message.subject, "The dispute on the order 1234 is resolved");
+        assert_eq!(
+            message.html,
+            "The dispute on the order 1234 is resolved: Refunded. You can view current order info on <a href=\"https://storiqa.com/manage/store/5/orders/1234\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_refund_issued_for_user() {
+        let message = render(RefundIssuedForUser {
+            user: user(),
+            order_slug: "1234".to_string(),
+            amount: "10.50 $".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+        });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(message.subject, "Refund for the order 1234");
+        assert_eq!(
+            message.html,
+            "10.50 $ has been refunded for the order 1234. You can view current info about your order on <a href=\"https://storiqa.com/profile/orders/1234\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_payout_sent_for_store() {
+        let message = render(PayoutSentForStore {
+            store_email: "store@storiqa.com".to_string(),
+            store_id: "5".to_string(),
+            amount: "100 STQ".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+        });
+        assert_eq!(message.to, "store@storiqa.com");
+        assert_eq!(message.subject, "Payout of 100 STQ is sent");
+        assert_eq!(
+            message.html,
+            "Payout of 100 STQ has been sent to the store 5. You can view the store payouts on <a href=\"https://storiqa.com/manage/store/5/finances\">this page</a>."
+        );
+    }
+
+    #[test]
+    fn renders_pre_order_reminder_for_user() {
+        let message = render(PreOrderReminderForUser {
+            user: user(),
+            order_slug: "1234".to_string(),
+            product_name: "Tom & Jerry's mug".to_string(),
+            available_at: "2018-12-01".to_string(),
+            cluster_url: CLUSTER_URL.to_string(),
+        });
+        assert_eq!(message.to, "user@storiqa.com");
+        assert_eq!(message.subject, "Tom & Jerry's mug will be available soon");
+        assert_eq!(
+            message.html,
+            "Tom & Jerry's mug you pre-ordered in the order 1234 will be available on 2018-12-01. You can view current info about your order on <a href=\"https://storiqa.com/profile/orders/1234\">this page</a>."
+        );
+    }
+}
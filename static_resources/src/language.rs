@@ -1,53 +1,264 @@
 //! Module containing structs to work with languages and translations.
 //! To work correctly GraphQL wants to InputObject and OutputObjects to be separate,
 //! so TranslationInput and Translation were created.
+use std::cmp::Ordering;
+use std::error::Error;
 use std::fmt;
+use std::slice;
+use std::str::FromStr;
 
-#[derive(GraphQLEnum, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, EnumIterator)]
+use isolang;
+use juniper::meta::MetaType;
+use juniper::{Executor, GraphQLType, Registry, Selection, Value};
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+/// Languages the user interface is translated to, see `IsoLanguage` for any ISO 639 language.
+/// Serialized as ISO 639-1 codes, except Chinese and Portuguese, which keep the former codes "ch" and "po"
+/// stored by existing clients. Both the former and the ISO codes "zh" and "pt" are accepted on input.
+#[derive(GraphQLEnum, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIterator)]
 #[graphql(name = "Language", description = "Applicable Languages")]
-#[serde(rename_all = "lowercase")]
 pub enum Language {
     #[graphql(description = "English")]
+    #[serde(rename = "en")]
     En,
     #[graphql(description = "Chinese")]
+    #[serde(rename = "ch", alias = "zh")]
     Ch,
     #[graphql(description = "German")]
+    #[serde(rename = "de")]
     De,
     #[graphql(description = "Russian")]
+    #[serde(rename = "ru")]
     Ru,
     #[graphql(description = "Spanish")]
+    #[serde(rename = "es")]
     Es,
     #[graphql(description = "French")]
+    #[serde(rename = "fr")]
     Fr,
     #[graphql(description = "Korean")]
+    #[serde(rename = "ko")]
     Ko,
     #[graphql(description = "Portuguese")]
+    #[serde(rename = "po", alias = "pt")]
     Po,
     #[graphql(description = "Japanese")]
+    #[serde(rename = "ja")]
     Ja,
 }
 
-impl fmt::Display for Language {
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseLanguageError(String);
+
+impl fmt::Display for ParseLanguageError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let lang = match *self {
-            Language::En => "en",
-            Language::Ch => "ch",
-            Language::De => "de",
-            Language::Ru => "ru",
-            Language::Es => "es",
-            Language::Fr => "fr",
-            Language::Ko => "ko",
-            Language::Po => "po",
-            Language::Ja => "ja",
-        };
-        write!(f, "{}", lang)
+        write!(f, "Unknown language code: {}", self.0)
     }
 }
 
+impl Error for ParseLanguageError {}
+
 impl Language {
+    /// All languages, in the order of declaration
+    pub fn supported() -> &'static [Language] {
+        const SUPPORTED: [Language; 9] = [
+            Language::En,
+            Language::Ch,
+            Language::De,
+            Language::Ru,
+            Language::Es,
+            Language::Fr,
+            Language::Ko,
+            Language::Po,
+            Language::Ja,
+        ];
+        &SUPPORTED
+    }
+
     pub fn as_vec() -> Vec<LanguageGraphQl> {
         Language::enum_iter().map(|value| LanguageGraphQl::new(value.to_string())).collect()
     }
+
+    pub fn iso(self) -> isolang::Language {
+        match self {
+            Language::En => isolang::Language::Eng,
+            Language::Ch => isolang::Language::Zho,
+            Language::De => isolang::Language::Deu,
+            Language::Ru => isolang::Language::Rus,
+            Language::Es => isolang::Language::Spa,
+            Language::Fr => isolang::Language::Fra,
+            Language::Ko => isolang::Language::Kor,
+            Language::Po => isolang::Language::Por,
+            Language::Ja => isolang::Language::Jpn,
+        }
+    }
+
+    /// `None` if the language is not one of the user interface
+    pub fn from_iso(iso: isolang::Language) -> Option<Self> {
+        Language::enum_iter().find(|language| language.iso() == iso)
+    }
+
+    /// Parses ISO 639-1 or ISO 639-3 code, case insensitive
+    pub fn from_code(code: &str) -> Option<Self> {
+        parse_iso_code(code).and_then(Language::from_iso)
+    }
+
+    /// ISO 639-1 code, "zh" and "pt" for `Ch` and `Po`, unlike `Display` and serialization
+    pub fn code(self) -> &'static str {
+        IsoLanguage(self.iso()).code()
+    }
+
+    /// Code the language is serialized with
+    fn legacy_code(self) -> &'static str {
+        match self {
+            Language::Ch => "ch",
+            Language::Po => "po",
+            _ => self.code(),
+        }
+    }
+
+    /// English name of the language
+    pub fn name(self) -> &'static str {
+        self.iso().to_name()
+    }
+}
+
+/// Parses ISO 639-1 or ISO 639-3 code of any language, case insensitive
+fn parse_iso_code(code: &str) -> Option<isolang::Language> {
+    let code = code.to_ascii_lowercase();
+    match code.len() {
+        2 => isolang::Language::from_639_1(&code),
+        3 => isolang::Language::from_639_3(&code),
+        _ => None,
+    }
+}
+
+/// Same code as in serialization, see `Language::code` for ISO 639-1 code
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.legacy_code())
+    }
+}
+
+/// Same as `Language::from_code`, but also accepts the former codes "ch" and "po"
+impl FromStr for Language {
+    type Err = ParseLanguageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ch" => Ok(Language::Ch),
+            "po" => Ok(Language::Po),
+            code => Language::from_code(code).ok_or_else(|| ParseLanguageError(s.to_string())),
+        }
+    }
+}
+
+/// Any of ISO 639 languages, serialized as ISO 639-1 code, or ISO 639-3 code for languages having no two-letter one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IsoLanguage(pub isolang::Language);
+
+impl IsoLanguage {
+    pub fn code(self) -> &'static str {
+        self.0.to_639_1().unwrap_or_else(|| self.0.to_639_3())
+    }
+
+    /// English name of the language
+    pub fn name(self) -> &'static str {
+        self.0.to_name()
+    }
+
+    /// `None` if the user interface is not translated to the language
+    pub fn ui_language(self) -> Option<Language> {
+        Language::from_iso(self.0)
+    }
+}
+
+impl From<Language> for IsoLanguage {
+    fn from(v: Language) -> Self {
+        IsoLanguage(v.iso())
+    }
+}
+
+impl fmt::Display for IsoLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Parses ISO 639-1 or ISO 639-3 code, case insensitive
+impl FromStr for IsoLanguage {
+    type Err = ParseLanguageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_iso_code(s).map(IsoLanguage).ok_or_else(|| ParseLanguageError(s.to_string()))
+    }
+}
+
+impl Serialize for IsoLanguage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for IsoLanguage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Single entry of `Accept-Language` header, `language` is `None` for the `*` wildcard.
+/// Any ISO 639 language is recognized, not only the languages of the user interface.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LanguageRange {
+    pub language: Option<isolang::Language>,
+    pub quality: f32,
+}
+
+/// Parses `Accept-Language` header value, e.g. `ru-RU, en;q=0.8, *;q=0.1`, ordering ranges by quality.
+/// Region subtags are ignored, entries with unknown languages or malformed quality are skipped.
+pub fn parse_accept_language(header: &str) -> Vec<LanguageRange> {
+    let mut ranges = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let tag = parts.next().unwrap_or_default();
+            let mut quality = 1.0;
+            for param in parts {
+                if param.starts_with("q=") {
+                    quality = param[2..].parse::<f32>().ok().filter(|q| *q >= 0.0 && *q <= 1.0)?;
+                }
+            }
+
+            let language = if tag == "*" {
+                None
+            } else {
+                Some(parse_iso_code(tag.split('-').next().unwrap_or_default())?)
+            };
+            Some(LanguageRange { language, quality })
+        })
+        .collect::<Vec<_>>();
+    ranges.sort_by(|a, b| b.quality.partial_cmp(&a.quality).unwrap_or(Ordering::Equal));
+    ranges
+}
+
+/// Chooses the most preferred of `supported` languages according to `Accept-Language` header.
+/// Wildcard matches the first of `supported` languages not explicitly rejected with `q=0`.
+pub fn negotiate(supported: &[Language], header: &str) -> Option<Language> {
+    let ranges = parse_accept_language(header);
+    let rejected = |language: &Language| {
+        ranges
+            .iter()
+            .any(|range| range.quality == 0.0 && range.language == Some(language.iso()))
+    };
+
+    ranges
+        .iter()
+        .filter(|range| range.quality > 0.0)
+        .find_map(|range| match range.language {
+            Some(language) => supported.iter().find(|l| l.iso() == language).cloned(),
+            None => supported.iter().find(|l| !rejected(l)).cloned(),
+        })
 }
 
 #[derive(GraphQLInputObject, Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -74,6 +285,144 @@ impl Translation {
     }
 }
 
+impl From<TranslationInput> for Translation {
+    fn from(v: TranslationInput) -> Self {
+        Self {
+            lang: v.lang,
+            text: v.text,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranslationError {
+    /// More than one translation to the language
+    Duplicate(Language),
+}
+
+impl fmt::Display for TranslationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TranslationError::Duplicate(lang) => write!(f, "Duplicate translation to language: {}", lang),
+        }
+    }
+}
+
+impl Error for TranslationError {}
+
+/// Text translated to several languages, at most one translation per language
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Translations(Vec<Translation>);
+
+impl Translations {
+    /// Validates input translations, rejecting more than one translation to the same language
+    pub fn new(translations: Vec<Translation>) -> Result<Self, TranslationError> {
+        for (i, translation) in translations.iter().enumerate() {
+            if translations[..i].iter().any(|t| t.lang == translation.lang) {
+                return Err(TranslationError::Duplicate(translation.lang));
+            }
+        }
+        Ok(Translations(translations))
+    }
+
+    pub fn from_inputs(inputs: Vec<TranslationInput>) -> Result<Self, TranslationError> {
+        Self::new(inputs.into_iter().map(Translation::from).collect())
+    }
+
+    /// Keeps the last translation to every language, e.g. for texts already stored with duplicates
+    pub fn deduplicated(translations: Vec<Translation>) -> Self {
+        let mut out = Translations::default();
+        for translation in translations {
+            out.set(translation.lang, translation.text);
+        }
+        out
+    }
+
+    /// Text in the language if it is present, in English otherwise
+    pub fn get(&self, lang: Language) -> Option<&str> {
+        self.get_with_fallback(lang, &[Language::En])
+    }
+
+    /// Text in the language if it is present, in the first present language of `fallback` otherwise
+    pub fn get_with_fallback(&self, lang: Language, fallback: &[Language]) -> Option<&str> {
+        ::std::iter::once(&lang).chain(fallback).find_map(|lang| self.get_exact(*lang))
+    }
+
+    /// Text in the language, without fallback
+    pub fn get_exact(&self, lang: Language) -> Option<&str> {
+        self.0.iter().find(|t| t.lang == lang).map(|t| t.text.as_str())
+    }
+
+    /// Adds translation or replaces the existing one to the same language
+    pub fn set(&mut self, lang: Language, text: String) {
+        match self.0.iter_mut().find(|t| t.lang == lang) {
+            Some(translation) => translation.text = text,
+            None => self.0.push(Translation::new(lang, text)),
+        }
+    }
+
+    pub fn languages(&self) -> Vec<Language> {
+        self.0.iter().map(|t| t.lang).collect()
+    }
+
+    pub fn iter(&self) -> slice::Iter<Translation> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn into_inner(self) -> Vec<Translation> {
+        self.0
+    }
+}
+
+impl From<Translations> for Vec<Translation> {
+    fn from(v: Translations) -> Self {
+        v.0
+    }
+}
+
+impl<'a> IntoIterator for &'a Translations {
+    type Item = &'a Translation;
+    type IntoIter = slice::Iter<'a, Translation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl Serialize for Translations {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Translations {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Translation>::deserialize(deserializer).map(Translations::deduplicated)
+    }
+}
+
+/// Resolved as a list of `Translation`
+impl GraphQLType for Translations {
+    type Context = ();
+    type TypeInfo = ();
+
+    fn name(_: &()) -> Option<&str> {
+        None
+    }
+
+    fn meta<'r>(info: &(), registry: &mut Registry<'r>) -> MetaType<'r> {
+        Vec::<Translation>::meta(info, registry)
+    }
+
+    fn resolve(&self, info: &(), selection_set: Option<&[Selection]>, executor: &Executor<()>) -> Value {
+        self.0.resolve(info, selection_set, executor)
+    }
+}
+
 #[derive(GraphQLObject, Serialize, Deserialize, Debug)]
 pub struct LanguageGraphQl {
     #[graphql(description = "ISO 639-1 code")]
@@ -85,3 +434,124 @@ impl LanguageGraphQl {
         Self { iso_code }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn language_keeps_former_codes() {
+        assert_eq!(serde_json::from_str::<Language>("\"ch\"").unwrap(), Language::Ch);
+        assert_eq!(serde_json::from_str::<Language>("\"zh\"").unwrap(), Language::Ch);
+        assert_eq!(serde_json::from_str::<Language>("\"po\"").unwrap(), Language::Po);
+        assert_eq!(serde_json::from_str::<Language>("\"pt\"").unwrap(), Language::Po);
+        assert_eq!(serde_json::to_string(&Language::Po).unwrap(), "\"po\"");
+        assert_eq!(serde_json::to_string(&Language::Ch).unwrap(), "\"ch\"");
+        assert_eq!(serde_json::to_string(&Language::Ru).unwrap(), "\"ru\"");
+        assert!(serde_json::from_str::<Language>("\"it\"").is_err());
+
+        assert_eq!("PO".parse::<Language>(), Ok(Language::Po));
+        assert_eq!("por".parse::<Language>(), Ok(Language::Po));
+        assert_eq!(Language::Ch.to_string(), "ch");
+        assert_eq!(Language::Ch.code(), "zh");
+        assert_eq!(Language::Po.code(), "pt");
+    }
+
+    #[test]
+    fn iso_language_covers_all_of_iso_639() {
+        let italian = "it".parse::<IsoLanguage>().unwrap();
+        assert_eq!(italian, IsoLanguage(isolang::Language::Ita));
+        assert_eq!(italian.ui_language(), None);
+        assert_eq!(serde_json::to_string(&italian).unwrap(), "\"it\"");
+        assert_eq!(serde_json::from_str::<IsoLanguage>("\"ITA\"").unwrap(), italian);
+
+        // Languages without ISO 639-1 code use ISO 639-3 one
+        let hawaiian = "haw".parse::<IsoLanguage>().unwrap();
+        assert_eq!(hawaiian.to_string(), "haw");
+
+        assert_eq!(IsoLanguage::from(Language::Po).to_string(), "pt");
+        assert_eq!("pt".parse::<IsoLanguage>().unwrap().ui_language(), Some(Language::Po));
+        assert!("xx".parse::<IsoLanguage>().is_err());
+    }
+
+    #[test]
+    fn parse_accept_language_orders_by_quality() {
+        let ranges = parse_accept_language("en;q=0.8, ru-RU, *;q=0.1, de;q=0.9");
+        assert_eq!(
+            ranges,
+            vec![
+                LanguageRange {
+                    language: Some(isolang::Language::Rus),
+                    quality: 1.0,
+                },
+                LanguageRange {
+                    language: Some(isolang::Language::Deu),
+                    quality: 0.9,
+                },
+                LanguageRange {
+                    language: Some(isolang::Language::Eng),
+                    quality: 0.8,
+                },
+                LanguageRange {
+                    language: None,
+                    quality: 0.1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_accept_language_skips_invalid_entries() {
+        let ranges = parse_accept_language("xx, en;q=2, fr;q=abc, , es ; q=0.5");
+        assert_eq!(
+            ranges,
+            vec![LanguageRange {
+                language: Some(isolang::Language::Spa),
+                quality: 0.5,
+            }]
+        );
+        assert!(parse_accept_language("").is_empty());
+    }
+
+    #[test]
+    fn negotiate_chooses_most_preferred_supported() {
+        let supported = [Language::En, Language::Ru, Language::Ch];
+        assert_eq!(negotiate(&supported, "de, ru;q=0.5, en;q=0.4"), Some(Language::Ru));
+        assert_eq!(negotiate(&supported, "zh-CN, en;q=0.4"), Some(Language::Ch));
+        assert_eq!(negotiate(&supported, "de, fr"), None);
+        assert_eq!(negotiate(&supported, ""), None);
+    }
+
+    #[test]
+    fn negotiate_wildcard_skips_rejected() {
+        let supported = [Language::En, Language::Ru];
+        assert_eq!(negotiate(&supported, "de, *;q=0.5"), Some(Language::En));
+        assert_eq!(negotiate(&supported, "en;q=0, *"), Some(Language::Ru));
+        assert_eq!(negotiate(&supported, "en;q=0, ru;q=0, *"), None);
+    }
+
+    #[test]
+    fn translations_deserialize_deduplicates() {
+        let translations: Translations =
+            serde_json::from_str(r#"[{"lang":"en","text":"Old"},{"lang":"ru","text":"Привет"},{"lang":"en","text":"Hello"}]"#).unwrap();
+        assert_eq!(translations.languages(), vec![Language::En, Language::Ru]);
+        assert_eq!(translations.get_exact(Language::En), Some("Hello"));
+    }
+
+    #[test]
+    fn translations_validation_rejects_duplicates() {
+        let translations = vec![
+            Translation::new(Language::En, "Old".to_string()),
+            Translation::new(Language::En, "Hello".to_string()),
+        ];
+        assert_eq!(
+            Translations::new(translations.clone()),
+            Err(TranslationError::Duplicate(Language::En))
+        );
+        assert_eq!(
+            Translations::deduplicated(translations).into_inner(),
+            vec![Translation::new(Language::En, "Hello".to_string())]
+        );
+    }
+}
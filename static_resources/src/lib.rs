@@ -4,7 +4,11 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate diesel;
+extern crate handlebars;
 extern crate isolang;
+extern crate serde_json;
+#[macro_use]
+extern crate lazy_static;
 #[macro_use]
 extern crate stq_diesel_macro_derive;
 #[macro_use]
@@ -18,13 +22,16 @@ pub mod committer_role;
 pub mod currency;
 pub mod currency_type;
 pub mod devices;
+pub mod email_templates;
 pub mod emails;
 pub mod gender;
 pub mod language;
 pub mod moderation_status;
+pub mod order_state_machine;
 pub mod order_status;
 pub mod project;
 pub mod provider;
+pub mod schema_export;
 pub mod token_type;
 
 pub use attribute_type::*;
@@ -32,11 +39,14 @@ pub use committer_role::*;
 pub use currency::Currency;
 pub use currency_type::*;
 pub use devices::*;
+pub use email_templates::*;
 pub use emails::*;
 pub use gender::*;
 pub use language::*;
 pub use moderation_status::*;
+pub use order_state_machine::*;
 pub use order_status::*;
 pub use project::*;
 pub use provider::*;
+pub use schema_export::*;
 pub use token_type::*;
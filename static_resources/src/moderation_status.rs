@@ -37,8 +37,103 @@ impl fmt::Display for ModerationStatus {
     }
 }
 
+/// Who changes moderation status of a store or a product
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationActor {
+    /// Store manager
+    Owner,
+    Moderator,
+}
+
 impl ModerationStatus {
     pub fn as_vec() -> Vec<ModerationStatus> {
         ModerationStatus::enum_iter().collect()
     }
+
+    /// Statuses the store or product can be moved to from this status by `actor`
+    pub fn allowed_transitions(self, actor: ModerationActor) -> &'static [ModerationStatus] {
+        use self::ModerationActor::*;
+        use self::ModerationStatus::*;
+
+        match (actor, self) {
+            (Owner, Draft) => &[Moderation],
+            (Owner, Decline) => &[Draft, Moderation],
+            (Owner, Published) => &[Draft],
+
+            (Moderator, Draft) => &[Blocked],
+            (Moderator, Moderation) => &[Published, Decline, Blocked],
+            (Moderator, Decline) => &[Blocked],
+            (Moderator, Blocked) => &[Draft, Published],
+            (Moderator, Published) => &[Blocked],
+
+            _ => &[],
+        }
+    }
+
+    pub fn can_transition(from: ModerationStatus, to: ModerationStatus, actor: ModerationActor) -> bool {
+        from.allowed_transitions(actor).contains(&to)
+    }
+
+    /// Store or product is shown to customers
+    pub fn is_publicly_visible(self) -> bool {
+        self == ModerationStatus::Published
+    }
+
+    /// Store or product may be edited by its owner without moderator involvement
+    pub fn is_editable_by_owner(self) -> bool {
+        match self {
+            ModerationStatus::Draft | ModerationStatus::Decline => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ModerationActor::*;
+    use super::ModerationStatus::*;
+    use super::*;
+
+    #[test]
+    fn test_owner_sends_to_moderation_and_moderator_decides() {
+        assert!(ModerationStatus::can_transition(Draft, Moderation, Owner));
+        assert!(!ModerationStatus::can_transition(Moderation, Published, Owner));
+        assert!(ModerationStatus::can_transition(Moderation, Published, Moderator));
+        assert!(ModerationStatus::can_transition(Moderation, Decline, Moderator));
+        assert!(!ModerationStatus::can_transition(Draft, Published, Moderator));
+        assert!(Moderation.allowed_transitions(Owner).is_empty());
+        assert!(Blocked.allowed_transitions(Owner).is_empty());
+    }
+
+    #[test]
+    fn test_transitions_change_status() {
+        for status in ModerationStatus::as_vec() {
+            for actor in &[Owner, Moderator] {
+                assert!(!ModerationStatus::can_transition(status, status, *actor));
+            }
+        }
+    }
+
+    #[test]
+    fn test_visibility_and_editability() {
+        let visible = ModerationStatus::as_vec()
+            .into_iter()
+            .filter(|status| status.is_publicly_visible())
+            .collect::<Vec<_>>();
+        assert_eq!(visible, vec![Published]);
+
+        let editable = ModerationStatus::as_vec()
+            .into_iter()
+            .filter(|status| status.is_editable_by_owner())
+            .collect::<Vec<_>>();
+        assert_eq!(editable, vec![Draft, Decline]);
+    }
+
+    #[test]
+    fn test_string_roundtrip() {
+        for status in ModerationStatus::as_vec() {
+            assert_eq!(status.to_string().parse::<ModerationStatus>(), Ok(status));
+        }
+    }
 }
@@ -0,0 +1,133 @@
+use committer_role::CommitterRole;
+use order_status::OrderState;
+
+impl OrderState {
+    /// States the order can be moved to from this state by `role`
+    pub fn allowed_transitions(self, role: CommitterRole) -> &'static [OrderState] {
+        use self::CommitterRole::*;
+        use self::OrderState::*;
+
+        match (role, self) {
+            (System, New) => &[PaymentAwaited, Cancelled],
+            (System, PaymentAwaited) => &[TransactionPending, AmountExpired, Paid, Cancelled],
+            (System, TransactionPending) => &[Paid, AmountExpired, Cancelled],
+            (System, AmountExpired) => &[PaymentAwaited, Cancelled],
+            (System, Paid) => &[InProcessing, Cancelled],
+            (System, Sent) => &[Delivered],
+            (System, Delivered) => &[Received, Complete],
+            (System, Received) => &[Complete],
+            (System, Dispute) => &[Cancelled, Complete],
+
+            (Seller, Paid) => &[InProcessing, Cancelled],
+            (Seller, InProcessing) => &[Sent, Cancelled],
+            (Seller, Sent) => &[Delivered],
+
+            (Customer, New) | (Customer, PaymentAwaited) | (Customer, AmountExpired) => &[Cancelled],
+            (Customer, Sent) | (Customer, Delivered) => &[Received, Dispute],
+            (Customer, Received) => &[Complete, Dispute],
+
+            _ => &[],
+        }
+    }
+
+    pub fn can_transition(from: OrderState, to: OrderState, role: CommitterRole) -> bool {
+        from.allowed_transitions(role).contains(&to)
+    }
+
+    /// No transitions are possible from the state
+    pub fn is_final(self) -> bool {
+        match self {
+            OrderState::Cancelled | OrderState::Complete => true,
+            _ => false,
+        }
+    }
+}
+
+/// Legal transitions between order states depending on who commits the change
+pub struct OrderStateMachine;
+
+impl OrderStateMachine {
+    /// States the order can be moved to from `from` by `role`
+    pub fn allowed_transitions(from: OrderState, role: CommitterRole) -> Vec<OrderState> {
+        from.allowed_transitions(role).to_vec()
+    }
+
+    pub fn can_transition(from: OrderState, to: OrderState, role: CommitterRole) -> bool {
+        OrderState::can_transition(from, to, role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use committer_role::CommitterRole::*;
+    use order_status::OrderState::*;
+
+    const ALLOWED: &[(CommitterRole, OrderState, OrderState)] = &[
+        (System, New, PaymentAwaited),
+        (System, New, Cancelled),
+        (System, PaymentAwaited, TransactionPending),
+        (System, PaymentAwaited, AmountExpired),
+        (System, PaymentAwaited, Paid),
+        (System, PaymentAwaited, Cancelled),
+        (System, TransactionPending, Paid),
+        (System, TransactionPending, AmountExpired),
+        (System, TransactionPending, Cancelled),
+        (System, AmountExpired, PaymentAwaited),
+        (System, AmountExpired, Cancelled),
+        (System, Paid, InProcessing),
+        (System, Paid, Cancelled),
+        (System, Sent, Delivered),
+        (System, Delivered, Received),
+        (System, Delivered, Complete),
+        (System, Received, Complete),
+        (System, Dispute, Cancelled),
+        (System, Dispute, Complete),
+        (Seller, Paid, InProcessing),
+        (Seller, Paid, Cancelled),
+        (Seller, InProcessing, Sent),
+        (Seller, InProcessing, Cancelled),
+        (Seller, Sent, Delivered),
+        (Customer, New, Cancelled),
+        (Customer, PaymentAwaited, Cancelled),
+        (Customer, AmountExpired, Cancelled),
+        (Customer, Sent, Received),
+        (Customer, Sent, Dispute),
+        (Customer, Delivered, Received),
+        (Customer, Delivered, Dispute),
+        (Customer, Received, Complete),
+        (Customer, Received, Dispute),
+    ];
+
+    #[test]
+    fn transitions_match_matrix() {
+        for role in CommitterRole::enum_iter() {
+            for from in OrderState::enum_iter() {
+                for to in OrderState::enum_iter() {
+                    let expected = ALLOWED.contains(&(role, from, to));
+                    assert_eq!(
+                        OrderState::can_transition(from, to, role),
+                        expected,
+                        "{:?} moving order from {:?} to {:?}",
+                        role,
+                        from,
+                        to
+                    );
+                    assert_eq!(OrderStateMachine::can_transition(from, to, role), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn final_states_have_no_transitions() {
+        for role in CommitterRole::enum_iter() {
+            for state in OrderState::enum_iter() {
+                if state.is_final() {
+                    assert!(state.allowed_transitions(role).is_empty(), "{:?} from {:?}", role, state);
+                }
+                assert!(!OrderState::can_transition(state, state, role), "{:?} from {:?}", role, state);
+            }
+        }
+    }
+}
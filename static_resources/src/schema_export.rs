@@ -0,0 +1,148 @@
+//! Export of valid values of enums as JSON schema, e.g. for frontend pickers.
+//! Values are taken in their serialized form, as they appear in JSON APIs.
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::{self, Map, Value};
+
+use super::*;
+
+/// Enum exported to JSON schema
+pub trait SchemaEnum: Serialize + Sized {
+    const NAME: &'static str;
+
+    fn all_values() -> Vec<Self>;
+}
+
+macro_rules! schema_enum {
+    ($($x:ident),*) => {
+        $(
+            impl SchemaEnum for $x {
+                const NAME: &'static str = stringify!($x);
+
+                fn all_values() -> Vec<Self> {
+                    $x::enum_iter().collect()
+                }
+            }
+        )*
+    };
+}
+
+schema_enum!(
+    AttributeType,
+    CommitterRole,
+    Currency,
+    CurrencyType,
+    Device,
+    Gender,
+    ModerationStatus,
+    OrderState,
+    Project,
+    Provider,
+    TemplateVariant,
+    TokenType
+);
+
+/// Only languages of the user interface are exported
+impl SchemaEnum for Language {
+    const NAME: &'static str = "Language";
+
+    fn all_values() -> Vec<Self> {
+        Language::supported().to_vec()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EnumSchema {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl EnumSchema {
+    pub fn of<T: SchemaEnum>() -> Self {
+        let values = T::all_values()
+            .iter()
+            .filter_map(|value| match serde_json::to_value(value) {
+                Ok(Value::String(s)) => Some(s),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            name: T::NAME.to_string(),
+            values,
+        }
+    }
+
+    /// JSON schema of a string restricted to the values
+    pub fn to_json_schema(&self) -> Value {
+        let mut schema = Map::new();
+        schema.insert("title".to_string(), Value::String(self.name.clone()));
+        schema.insert("type".to_string(), Value::String("string".to_string()));
+        schema.insert(
+            "enum".to_string(),
+            Value::Array(self.values.iter().cloned().map(Value::String).collect()),
+        );
+        Value::Object(schema)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SchemaExport {
+    enums: Vec<EnumSchema>,
+}
+
+impl SchemaExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All enums of this crate
+    pub fn all() -> Self {
+        Self::new()
+            .with::<AttributeType>()
+            .with::<CommitterRole>()
+            .with::<Currency>()
+            .with::<CurrencyType>()
+            .with::<Device>()
+            .with::<Gender>()
+            .with::<Language>()
+            .with::<ModerationStatus>()
+            .with::<OrderState>()
+            .with::<Project>()
+            .with::<Provider>()
+            .with::<TemplateVariant>()
+            .with::<TokenType>()
+    }
+
+    pub fn with<T: SchemaEnum>(mut self) -> Self {
+        self.enums.push(EnumSchema::of::<T>());
+        self
+    }
+
+    pub fn enums(&self) -> &[EnumSchema] {
+        &self.enums
+    }
+
+    /// Valid values by enum name
+    pub fn valid_values(&self) -> BTreeMap<String, Vec<String>> {
+        self.enums.iter().map(|e| (e.name.clone(), e.values.clone())).collect()
+    }
+
+    /// JSON schema document with the enums in `definitions`
+    pub fn to_json_schema(&self) -> Value {
+        let definitions = self
+            .enums
+            .iter()
+            .map(|e| (e.name.clone(), e.to_json_schema()))
+            .collect::<Map<_, _>>();
+
+        let mut schema = Map::new();
+        schema.insert(
+            "$schema".to_string(),
+            Value::String("http://json-schema.org/draft-07/schema#".to_string()),
+        );
+        schema.insert("definitions".to_string(), Value::Object(definitions));
+        Value::Object(schema)
+    }
+}
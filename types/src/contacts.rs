@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use validator::{self, ValidationError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ContactError {
+    InvalidEmail(String),
+    InvalidPhone(String),
+}
+
+impl fmt::Display for ContactError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContactError::InvalidEmail(email) => write!(f, "Incorrect email format: {}", email),
+            ContactError::InvalidPhone(phone) => write!(f, "Incorrect phone format: {}", phone),
+        }
+    }
+}
+
+impl Error for ContactError {}
+
+/// Email address, validated on construction. Domain part is lowercased.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EmailAddress(String);
+
+impl EmailAddress {
+    pub fn parse(s: &str) -> Result<Self, ContactError> {
+        let s = s.trim();
+        if !validator::validate_email(s) {
+            return Err(ContactError::InvalidEmail(s.to_string()));
+        }
+        let at = s.rfind('@').unwrap_or_default();
+        let (local, domain) = s.split_at(at);
+        Ok(EmailAddress(format!("{}{}", local, domain.to_lowercase())))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+/// Phone number, validated on construction and normalized to E.164 format: `+` followed by 7 to 15 digits,
+/// e.g. `+79991234567`. Spaces, dashes, dots and parentheses are removed, international `00` prefix is replaced with `+`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PhoneNumber(String);
+
+impl PhoneNumber {
+    pub fn parse(s: &str) -> Result<Self, ContactError> {
+        lazy_static! {
+            static ref PHONE_VALIDATION_RE: Regex = Regex::new(r"^(?:\+|00)?(\d{7,15})$").unwrap();
+        }
+
+        let stripped = s
+            .chars()
+            .filter(|c| !c.is_whitespace() && !['-', '.', '(', ')'].contains(c))
+            .collect::<String>();
+        PHONE_VALIDATION_RE
+            .captures(&stripped)
+            .map(|captures| PhoneNumber(format!("+{}", &captures[1])))
+            .ok_or_else(|| ContactError::InvalidPhone(s.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+macro_rules! contact_impls {
+    ($x:ident) => {
+        impl fmt::Display for $x {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl FromStr for $x {
+            type Err = ContactError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $x::parse(s)
+            }
+        }
+
+        impl AsRef<str> for $x {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<$x> for String {
+            fn from(v: $x) -> Self {
+                v.0
+            }
+        }
+
+        impl Serialize for $x {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $x {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                $x::parse(&s).map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+contact_impls!(EmailAddress);
+contact_impls!(PhoneNumber);
+
+fn validation_error(code: &'static str, message: &'static str) -> ValidationError {
+    ValidationError {
+        code: code.into(),
+        message: Some(message.into()),
+        params: HashMap::new(),
+    }
+}
+
+/// Validator for string fields, use as `#[validate(custom = "validate_phone")]`.
+/// Accepts digits with optional leading `+` only, punctuation is rejected.
+pub fn validate_phone(phone: &str) -> Result<(), ValidationError> {
+    lazy_static! {
+        static ref PHONE_VALIDATION_RE: Regex = Regex::new(r"^\+?\d{7,15}$").unwrap();
+    }
+
+    if PHONE_VALIDATION_RE.is_match(phone) {
+        Ok(())
+    } else {
+        Err(validation_error("phone", "Incorrect phone format"))
+    }
+}
+
+/// Validator for string fields, use as `#[validate(custom = "validate_email")]`
+pub fn validate_email(email: &str) -> Result<(), ValidationError> {
+    EmailAddress::parse(email)
+        .map(|_| ())
+        .map_err(|_| validation_error("email", "Incorrect email format"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn test_phone_parse() {
+        assert_eq!(PhoneNumber::parse("+79991234567").unwrap().as_str(), "+79991234567");
+        assert_eq!(PhoneNumber::parse("1234567").unwrap().as_str(), "+1234567");
+        assert!(PhoneNumber::parse("123456").is_err());
+        assert!(PhoneNumber::parse("+1234567890123456").is_err());
+        assert!(PhoneNumber::parse("+7999123456a").is_err());
+        assert!(PhoneNumber::parse("++79991234567").is_err());
+        assert!(PhoneNumber::parse("").is_err());
+    }
+
+    #[test]
+    fn test_phone_normalize() {
+        for s in &["+7 (999) 123-45-67", "7.999.123.45.67", " 79991234567 ", "0079991234567"] {
+            assert_eq!(PhoneNumber::parse(s).unwrap().as_str(), "+79991234567");
+        }
+    }
+
+    #[test]
+    fn test_email_parse() {
+        assert_eq!(EmailAddress::parse("User@Example.COM").unwrap().as_str(), "User@example.com");
+        assert_eq!(EmailAddress::parse(" user@example.com ").unwrap().as_str(), "user@example.com");
+        assert!(EmailAddress::parse("user").is_err());
+        assert!(EmailAddress::parse("user@").is_err());
+        assert!(EmailAddress::parse("").is_err());
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let phone: PhoneNumber = serde_json::from_str(r#""+7 999 123-45-67""#).unwrap();
+        assert_eq!(serde_json::to_string(&phone).unwrap(), r#""+79991234567""#);
+        assert_eq!(
+            serde_json::from_str::<PhoneNumber>(&serde_json::to_string(&phone).unwrap()).unwrap(),
+            phone
+        );
+        assert!(serde_json::from_str::<PhoneNumber>(r#""12345""#).is_err());
+
+        let email: EmailAddress = serde_json::from_str(r#""User@Example.com""#).unwrap();
+        assert_eq!(serde_json::to_string(&email).unwrap(), r#""User@example.com""#);
+        assert_eq!(
+            serde_json::from_str::<EmailAddress>(&serde_json::to_string(&email).unwrap()).unwrap(),
+            email
+        );
+        assert!(serde_json::from_str::<EmailAddress>(r#""not an email""#).is_err());
+    }
+
+    #[test]
+    fn test_validators() {
+        assert!(validate_phone("+79991234567").is_ok());
+        assert!(validate_phone("79991234567").is_ok());
+        assert!(validate_phone("+7 999 123-45-67").is_err());
+        assert!(validate_phone("123").is_err());
+        assert!(validate_email("user@example.com").is_ok());
+        assert!(validate_email("user").is_err());
+    }
+}
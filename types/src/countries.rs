@@ -0,0 +1,416 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer};
+
+use super::{Alpha2, Alpha3};
+
+/// Country of ISO 3166-1
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CountryInfo {
+    pub alpha2: &'static str,
+    pub alpha3: &'static str,
+    pub name: &'static str,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CountryCodeError {
+    UnknownAlpha2(String),
+    UnknownAlpha3(String),
+}
+
+impl fmt::Display for CountryCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CountryCodeError::UnknownAlpha2(code) => write!(f, "Unknown ISO 3166-1 alpha-2 country code: {}", code),
+            CountryCodeError::UnknownAlpha3(code) => write!(f, "Unknown ISO 3166-1 alpha-3 country code: {}", code),
+        }
+    }
+}
+
+impl Error for CountryCodeError {}
+
+impl CountryInfo {
+    /// Finds country by alpha-2 code, case insensitive
+    pub fn by_alpha2(code: &str) -> Option<&'static CountryInfo> {
+        COUNTRIES.iter().find(|country| country.alpha2.eq_ignore_ascii_case(code))
+    }
+
+    /// Finds country by alpha-3 code, case insensitive
+    pub fn by_alpha3(code: &str) -> Option<&'static CountryInfo> {
+        COUNTRIES.iter().find(|country| country.alpha3.eq_ignore_ascii_case(code))
+    }
+}
+
+impl Alpha2 {
+    /// Validates the code against ISO 3166-1, normalizing it to upper case
+    pub fn parse(code: &str) -> Result<Self, CountryCodeError> {
+        CountryInfo::by_alpha2(code)
+            .map(|country| Alpha2(country.alpha2.to_string()))
+            .ok_or_else(|| CountryCodeError::UnknownAlpha2(code.to_string()))
+    }
+
+    pub fn country(&self) -> Option<&'static CountryInfo> {
+        CountryInfo::by_alpha2(&self.0)
+    }
+
+    pub fn to_alpha3(&self) -> Option<Alpha3> {
+        self.country().map(|country| Alpha3(country.alpha3.to_string()))
+    }
+
+    pub fn country_name(&self) -> Option<&'static str> {
+        self.country().map(|country| country.name)
+    }
+}
+
+impl Alpha3 {
+    /// Validates the code against ISO 3166-1, normalizing it to upper case
+    pub fn parse(code: &str) -> Result<Self, CountryCodeError> {
+        CountryInfo::by_alpha3(code)
+            .map(|country| Alpha3(country.alpha3.to_string()))
+            .ok_or_else(|| CountryCodeError::UnknownAlpha3(code.to_string()))
+    }
+
+    pub fn country(&self) -> Option<&'static CountryInfo> {
+        CountryInfo::by_alpha3(&self.0)
+    }
+
+    pub fn to_alpha2(&self) -> Option<Alpha2> {
+        self.country().map(|country| Alpha2(country.alpha2.to_string()))
+    }
+
+    pub fn country_name(&self) -> Option<&'static str> {
+        self.country().map(|country| country.name)
+    }
+}
+
+impl FromStr for Alpha2 {
+    type Err = CountryCodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Alpha2::parse(s)
+    }
+}
+
+impl FromStr for Alpha3 {
+    type Err = CountryCodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Alpha3::parse(s)
+    }
+}
+
+impl TryFrom<String> for Alpha2 {
+    type Error = CountryCodeError;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        Alpha2::parse(&code)
+    }
+}
+
+impl TryFrom<String> for Alpha3 {
+    type Error = CountryCodeError;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        Alpha3::parse(&code)
+    }
+}
+
+impl<'de> Deserialize<'de> for Alpha2 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Alpha2::parse(&code).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Alpha3 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Alpha3::parse(&code).map_err(de::Error::custom)
+    }
+}
+
+/// ISO 3166-1 countries, sorted by alpha-3 code
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub static COUNTRIES: &[CountryInfo] = &[
+    CountryInfo { alpha2: "AW", alpha3: "ABW", name: "Aruba" },
+    CountryInfo { alpha2: "AF", alpha3: "AFG", name: "Afghanistan" },
+    CountryInfo { alpha2: "AO", alpha3: "AGO", name: "Angola" },
+    CountryInfo { alpha2: "AI", alpha3: "AIA", name: "Anguilla" },
+    CountryInfo { alpha2: "AX", alpha3: "ALA", name: "Åland Islands" },
+    CountryInfo { alpha2: "AL", alpha3: "ALB", name: "Albania" },
+    CountryInfo { alpha2: "AD", alpha3: "AND", name: "Andorra" },
+    CountryInfo { alpha2: "AE", alpha3: "ARE", name: "United Arab Emirates" },
+    CountryInfo { alpha2: "AR", alpha3: "ARG", name: "Argentina" },
+    CountryInfo { alpha2: "AM", alpha3: "ARM", name: "Armenia" },
+    CountryInfo { alpha2: "AS", alpha3: "ASM", name: "American Samoa" },
+    CountryInfo { alpha2: "AQ", alpha3: "ATA", name: "Antarctica" },
+    CountryInfo { alpha2: "TF", alpha3: "ATF", name: "French Southern Territories" },
+    CountryInfo { alpha2: "AG", alpha3: "ATG", name: "Antigua and Barbuda" },
+    CountryInfo { alpha2: "AU", alpha3: "AUS", name: "Australia" },
+    CountryInfo { alpha2: "AT", alpha3: "AUT", name: "Austria" },
+    CountryInfo { alpha2: "AZ", alpha3: "AZE", name: "Azerbaijan" },
+    CountryInfo { alpha2: "BI", alpha3: "BDI", name: "Burundi" },
+    CountryInfo { alpha2: "BE", alpha3: "BEL", name: "Belgium" },
+    CountryInfo { alpha2: "BJ", alpha3: "BEN", name: "Benin" },
+    CountryInfo { alpha2: "BQ", alpha3: "BES", name: "Bonaire, Sint Eustatius and Saba" },
+    CountryInfo { alpha2: "BF", alpha3: "BFA", name: "Burkina Faso" },
+    CountryInfo { alpha2: "BD", alpha3: "BGD", name: "Bangladesh" },
+    CountryInfo { alpha2: "BG", alpha3: "BGR", name: "Bulgaria" },
+    CountryInfo { alpha2: "BH", alpha3: "BHR", name: "Bahrain" },
+    CountryInfo { alpha2: "BS", alpha3: "BHS", name: "Bahamas" },
+    CountryInfo { alpha2: "BA", alpha3: "BIH", name: "Bosnia and Herzegovina" },
+    CountryInfo { alpha2: "BL", alpha3: "BLM", name: "Saint Barthélemy" },
+    CountryInfo { alpha2: "BY", alpha3: "BLR", name: "Belarus" },
+    CountryInfo { alpha2: "BZ", alpha3: "BLZ", name: "Belize" },
+    CountryInfo { alpha2: "BM", alpha3: "BMU", name: "Bermuda" },
+    CountryInfo { alpha2: "BO", alpha3: "BOL", name: "Bolivia, Plurinational State of" },
+    CountryInfo { alpha2: "BR", alpha3: "BRA", name: "Brazil" },
+    CountryInfo { alpha2: "BB", alpha3: "BRB", name: "Barbados" },
+    CountryInfo { alpha2: "BN", alpha3: "BRN", name: "Brunei Darussalam" },
+    CountryInfo { alpha2: "BT", alpha3: "BTN", name: "Bhutan" },
+    CountryInfo { alpha2: "BV", alpha3: "BVT", name: "Bouvet Island" },
+    CountryInfo { alpha2: "BW", alpha3: "BWA", name: "Botswana" },
+    CountryInfo { alpha2: "CF", alpha3: "CAF", name: "Central African Republic" },
+    CountryInfo { alpha2: "CA", alpha3: "CAN", name: "Canada" },
+    CountryInfo { alpha2: "CC", alpha3: "CCK", name: "Cocos (Keeling) Islands" },
+    CountryInfo { alpha2: "CH", alpha3: "CHE", name: "Switzerland" },
+    CountryInfo { alpha2: "CL", alpha3: "CHL", name: "Chile" },
+    CountryInfo { alpha2: "CN", alpha3: "CHN", name: "China" },
+    CountryInfo { alpha2: "CI", alpha3: "CIV", name: "Côte d'Ivoire" },
+    CountryInfo { alpha2: "CM", alpha3: "CMR", name: "Cameroon" },
+    CountryInfo { alpha2: "CD", alpha3: "COD", name: "Congo, The Democratic Republic of the" },
+    CountryInfo { alpha2: "CG", alpha3: "COG", name: "Congo" },
+    CountryInfo { alpha2: "CK", alpha3: "COK", name: "Cook Islands" },
+    CountryInfo { alpha2: "CO", alpha3: "COL", name: "Colombia" },
+    CountryInfo { alpha2: "KM", alpha3: "COM", name: "Comoros" },
+    CountryInfo { alpha2: "CV", alpha3: "CPV", name: "Cabo Verde" },
+    CountryInfo { alpha2: "CR", alpha3: "CRI", name: "Costa Rica" },
+    CountryInfo { alpha2: "CU", alpha3: "CUB", name: "Cuba" },
+    CountryInfo { alpha2: "CW", alpha3: "CUW", name: "Curaçao" },
+    CountryInfo { alpha2: "CX", alpha3: "CXR", name: "Christmas Island" },
+    CountryInfo { alpha2: "KY", alpha3: "CYM", name: "Cayman Islands" },
+    CountryInfo { alpha2: "CY", alpha3: "CYP", name: "Cyprus" },
+    CountryInfo { alpha2: "CZ", alpha3: "CZE", name: "Czechia" },
+    CountryInfo { alpha2: "DE", alpha3: "DEU", name: "Germany" },
+    CountryInfo { alpha2: "DJ", alpha3: "DJI", name: "Djibouti" },
+    CountryInfo { alpha2: "DM", alpha3: "DMA", name: "Dominica" },
+    CountryInfo { alpha2: "DK", alpha3: "DNK", name: "Denmark" },
+    CountryInfo { alpha2: "DO", alpha3: "DOM", name: "Dominican Republic" },
+    CountryInfo { alpha2: "DZ", alpha3: "DZA", name: "Algeria" },
+    CountryInfo { alpha2: "EC", alpha3: "ECU", name: "Ecuador" },
+    CountryInfo { alpha2: "EG", alpha3: "EGY", name: "Egypt" },
+    CountryInfo { alpha2: "ER", alpha3: "ERI", name: "Eritrea" },
+    CountryInfo { alpha2: "EH", alpha3: "ESH", name: "Western Sahara" },
+    CountryInfo { alpha2: "ES", alpha3: "ESP", name: "Spain" },
+    CountryInfo { alpha2: "EE", alpha3: "EST", name: "Estonia" },
+    CountryInfo { alpha2: "ET", alpha3: "ETH", name: "Ethiopia" },
+    CountryInfo { alpha2: "FI", alpha3: "FIN", name: "Finland" },
+    CountryInfo { alpha2: "FJ", alpha3: "FJI", name: "Fiji" },
+    CountryInfo { alpha2: "FK", alpha3: "FLK", name: "Falkland Islands (Malvinas)" },
+    CountryInfo { alpha2: "FR", alpha3: "FRA", name: "France" },
+    CountryInfo { alpha2: "FO", alpha3: "FRO", name: "Faroe Islands" },
+    CountryInfo { alpha2: "FM", alpha3: "FSM", name: "Micronesia, Federated States of" },
+    CountryInfo { alpha2: "GA", alpha3: "GAB", name: "Gabon" },
+    CountryInfo { alpha2: "GB", alpha3: "GBR", name: "United Kingdom" },
+    CountryInfo { alpha2: "GE", alpha3: "GEO", name: "Georgia" },
+    CountryInfo { alpha2: "GG", alpha3: "GGY", name: "Guernsey" },
+    CountryInfo { alpha2: "GH", alpha3: "GHA", name: "Ghana" },
+    CountryInfo { alpha2: "GI", alpha3: "GIB", name: "Gibraltar" },
+    CountryInfo { alpha2: "GN", alpha3: "GIN", name: "Guinea" },
+    CountryInfo { alpha2: "GP", alpha3: "GLP", name: "Guadeloupe" },
+    CountryInfo { alpha2: "GM", alpha3: "GMB", name: "Gambia" },
+    CountryInfo { alpha2: "GW", alpha3: "GNB", name: "Guinea-Bissau" },
+    CountryInfo { alpha2: "GQ", alpha3: "GNQ", name: "Equatorial Guinea" },
+    CountryInfo { alpha2: "GR", alpha3: "GRC", name: "Greece" },
+    CountryInfo { alpha2: "GD", alpha3: "GRD", name: "Grenada" },
+    CountryInfo { alpha2: "GL", alpha3: "GRL", name: "Greenland" },
+    CountryInfo { alpha2: "GT", alpha3: "GTM", name: "Guatemala" },
+    CountryInfo { alpha2: "GF", alpha3: "GUF", name: "French Guiana" },
+    CountryInfo { alpha2: "GU", alpha3: "GUM", name: "Guam" },
+    CountryInfo { alpha2: "GY", alpha3: "GUY", name: "Guyana" },
+    CountryInfo { alpha2: "HK", alpha3: "HKG", name: "Hong Kong" },
+    CountryInfo { alpha2: "HM", alpha3: "HMD", name: "Heard Island and McDonald Islands" },
+    CountryInfo { alpha2: "HN", alpha3: "HND", name: "Honduras" },
+    CountryInfo { alpha2: "HR", alpha3: "HRV", name: "Croatia" },
+    CountryInfo { alpha2: "HT", alpha3: "HTI", name: "Haiti" },
+    CountryInfo { alpha2: "HU", alpha3: "HUN", name: "Hungary" },
+    CountryInfo { alpha2: "ID", alpha3: "IDN", name: "Indonesia" },
+    CountryInfo { alpha2: "IM", alpha3: "IMN", name: "Isle of Man" },
+    CountryInfo { alpha2: "IN", alpha3: "IND", name: "India" },
+    CountryInfo { alpha2: "IO", alpha3: "IOT", name: "British Indian Ocean Territory" },
+    CountryInfo { alpha2: "IE", alpha3: "IRL", name: "Ireland" },
+    CountryInfo { alpha2: "IR", alpha3: "IRN", name: "Iran, Islamic Republic of" },
+    CountryInfo { alpha2: "IQ", alpha3: "IRQ", name: "Iraq" },
+    CountryInfo { alpha2: "IS", alpha3: "ISL", name: "Iceland" },
+    CountryInfo { alpha2: "IL", alpha3: "ISR", name: "Israel" },
+    CountryInfo { alpha2: "IT", alpha3: "ITA", name: "Italy" },
+    CountryInfo { alpha2: "JM", alpha3: "JAM", name: "Jamaica" },
+    CountryInfo { alpha2: "JE", alpha3: "JEY", name: "Jersey" },
+    CountryInfo { alpha2: "JO", alpha3: "JOR", name: "Jordan" },
+    CountryInfo { alpha2: "JP", alpha3: "JPN", name: "Japan" },
+    CountryInfo { alpha2: "KZ", alpha3: "KAZ", name: "Kazakhstan" },
+    CountryInfo { alpha2: "KE", alpha3: "KEN", name: "Kenya" },
+    CountryInfo { alpha2: "KG", alpha3: "KGZ", name: "Kyrgyzstan" },
+    CountryInfo { alpha2: "KH", alpha3: "KHM", name: "Cambodia" },
+    CountryInfo { alpha2: "KI", alpha3: "KIR", name: "Kiribati" },
+    CountryInfo { alpha2: "KN", alpha3: "KNA", name: "Saint Kitts and Nevis" },
+    CountryInfo { alpha2: "KR", alpha3: "KOR", name: "Korea, Republic of" },
+    CountryInfo { alpha2: "KW", alpha3: "KWT", name: "Kuwait" },
+    CountryInfo { alpha2: "LA", alpha3: "LAO", name: "Lao People's Democratic Republic" },
+    CountryInfo { alpha2: "LB", alpha3: "LBN", name: "Lebanon" },
+    CountryInfo { alpha2: "LR", alpha3: "LBR", name: "Liberia" },
+    CountryInfo { alpha2: "LY", alpha3: "LBY", name: "Libya" },
+    CountryInfo { alpha2: "LC", alpha3: "LCA", name: "Saint Lucia" },
+    CountryInfo { alpha2: "LI", alpha3: "LIE", name: "Liechtenstein" },
+    CountryInfo { alpha2: "LK", alpha3: "LKA", name: "Sri Lanka" },
+    CountryInfo { alpha2: "LS", alpha3: "LSO", name: "Lesotho" },
+    CountryInfo { alpha2: "LT", alpha3: "LTU", name: "Lithuania" },
+    CountryInfo { alpha2: "LU", alpha3: "LUX", name: "Luxembourg" },
+    CountryInfo { alpha2: "LV", alpha3: "LVA", name: "Latvia" },
+    CountryInfo { alpha2: "MO", alpha3: "MAC", name: "Macao" },
+    CountryInfo { alpha2: "MF", alpha3: "MAF", name: "Saint Martin (French part)" },
+    CountryInfo { alpha2: "MA", alpha3: "MAR", name: "Morocco" },
+    CountryInfo { alpha2: "MC", alpha3: "MCO", name: "Monaco" },
+    CountryInfo { alpha2: "MD", alpha3: "MDA", name: "Moldova, Republic of" },
+    CountryInfo { alpha2: "MG", alpha3: "MDG", name: "Madagascar" },
+    CountryInfo { alpha2: "MV", alpha3: "MDV", name: "Maldives" },
+    CountryInfo { alpha2: "MX", alpha3: "MEX", name: "Mexico" },
+    CountryInfo { alpha2: "MH", alpha3: "MHL", name: "Marshall Islands" },
+    CountryInfo { alpha2: "MK", alpha3: "MKD", name: "North Macedonia" },
+    CountryInfo { alpha2: "ML", alpha3: "MLI", name: "Mali" },
+    CountryInfo { alpha2: "MT", alpha3: "MLT", name: "Malta" },
+    CountryInfo { alpha2: "MM", alpha3: "MMR", name: "Myanmar" },
+    CountryInfo { alpha2: "ME", alpha3: "MNE", name: "Montenegro" },
+    CountryInfo { alpha2: "MN", alpha3: "MNG", name: "Mongolia" },
+    CountryInfo { alpha2: "MP", alpha3: "MNP", name: "Northern Mariana Islands" },
+    CountryInfo { alpha2: "MZ", alpha3: "MOZ", name: "Mozambique" },
+    CountryInfo { alpha2: "MR", alpha3: "MRT", name: "Mauritania" },
+    CountryInfo { alpha2: "MS", alpha3: "MSR", name: "Montserrat" },
+    CountryInfo { alpha2: "MQ", alpha3: "MTQ", name: "Martinique" },
+    CountryInfo { alpha2: "MU", alpha3: "MUS", name: "Mauritius" },
+    CountryInfo { alpha2: "MW", alpha3: "MWI", name: "Malawi" },
+    CountryInfo { alpha2: "MY", alpha3: "MYS", name: "Malaysia" },
+    CountryInfo { alpha2: "YT", alpha3: "MYT", name: "Mayotte" },
+    CountryInfo { alpha2: "NA", alpha3: "NAM", name: "Namibia" },
+    CountryInfo { alpha2: "NC", alpha3: "NCL", name: "New Caledonia" },
+    CountryInfo { alpha2: "NE", alpha3: "NER", name: "Niger" },
+    CountryInfo { alpha2: "NF", alpha3: "NFK", name: "Norfolk Island" },
+    CountryInfo { alpha2: "NG", alpha3: "NGA", name: "Nigeria" },
+    CountryInfo { alpha2: "NI", alpha3: "NIC", name: "Nicaragua" },
+    CountryInfo { alpha2: "NU", alpha3: "NIU", name: "Niue" },
+    CountryInfo { alpha2: "NL", alpha3: "NLD", name: "Netherlands" },
+    CountryInfo { alpha2: "NO", alpha3: "NOR", name: "Norway" },
+    CountryInfo { alpha2: "NP", alpha3: "NPL", name: "Nepal" },
+    CountryInfo { alpha2: "NR", alpha3: "NRU", name: "Nauru" },
+    CountryInfo { alpha2: "NZ", alpha3: "NZL", name: "New Zealand" },
+    CountryInfo { alpha2: "OM", alpha3: "OMN", name: "Oman" },
+    CountryInfo { alpha2: "PK", alpha3: "PAK", name: "Pakistan" },
+    CountryInfo { alpha2: "PA", alpha3: "PAN", name: "Panama" },
+    CountryInfo { alpha2: "PN", alpha3: "PCN", name: "Pitcairn" },
+    CountryInfo { alpha2: "PE", alpha3: "PER", name: "Peru" },
+    CountryInfo { alpha2: "PH", alpha3: "PHL", name: "Philippines" },
+    CountryInfo { alpha2: "PW", alpha3: "PLW", name: "Palau" },
+    CountryInfo { alpha2: "PG", alpha3: "PNG", name: "Papua New Guinea" },
+    CountryInfo { alpha2: "PL", alpha3: "POL", name: "Poland" },
+    CountryInfo { alpha2: "PR", alpha3: "PRI", name: "Puerto Rico" },
+    CountryInfo { alpha2: "KP", alpha3: "PRK", name: "Korea, Democratic People's Republic of" },
+    CountryInfo { alpha2: "PT", alpha3: "PRT", name: "Portugal" },
+    CountryInfo { alpha2: "PY", alpha3: "PRY", name: "Paraguay" },
+    CountryInfo { alpha2: "PS", alpha3: "PSE", name: "Palestine, State of" },
+    CountryInfo { alpha2: "PF", alpha3: "PYF", name: "French Polynesia" },
+    CountryInfo { alpha2: "QA", alpha3: "QAT", name: "Qatar" },
+    CountryInfo { alpha2: "RE", alpha3: "REU", name: "Réunion" },
+    CountryInfo { alpha2: "RO", alpha3: "ROU", name: "Romania" },
+    CountryInfo { alpha2: "RU", alpha3: "RUS", name: "Russian Federation" },
+    CountryInfo { alpha2: "RW", alpha3: "RWA", name: "Rwanda" },
+    CountryInfo { alpha2: "SA", alpha3: "SAU", name: "Saudi Arabia" },
+    CountryInfo { alpha2: "SD", alpha3: "SDN", name: "Sudan" },
+    CountryInfo { alpha2: "SN", alpha3: "SEN", name: "Senegal" },
+    CountryInfo { alpha2: "SG", alpha3: "SGP", name: "Singapore" },
+    CountryInfo { alpha2: "GS", alpha3: "SGS", name: "South Georgia and the South Sandwich Islands" },
+    CountryInfo { alpha2: "SH", alpha3: "SHN", name: "Saint Helena, Ascension and Tristan da Cunha" },
+    CountryInfo { alpha2: "SJ", alpha3: "SJM", name: "Svalbard and Jan Mayen" },
+    CountryInfo { alpha2: "SB", alpha3: "SLB", name: "Solomon Islands" },
+    CountryInfo { alpha2: "SL", alpha3: "SLE", name: "Sierra Leone" },
+    CountryInfo { alpha2: "SV", alpha3: "SLV", name: "El Salvador" },
+    CountryInfo { alpha2: "SM", alpha3: "SMR", name: "San Marino" },
+    CountryInfo { alpha2: "SO", alpha3: "SOM", name: "Somalia" },
+    CountryInfo { alpha2: "PM", alpha3: "SPM", name: "Saint Pierre and Miquelon" },
+    CountryInfo { alpha2: "RS", alpha3: "SRB", name: "Serbia" },
+    CountryInfo { alpha2: "SS", alpha3: "SSD", name: "South Sudan" },
+    CountryInfo { alpha2: "ST", alpha3: "STP", name: "Sao Tome and Principe" },
+    CountryInfo { alpha2: "SR", alpha3: "SUR", name: "Suriname" },
+    CountryInfo { alpha2: "SK", alpha3: "SVK", name: "Slovakia" },
+    CountryInfo { alpha2: "SI", alpha3: "SVN", name: "Slovenia" },
+    CountryInfo { alpha2: "SE", alpha3: "SWE", name: "Sweden" },
+    CountryInfo { alpha2: "SZ", alpha3: "SWZ", name: "Eswatini" },
+    CountryInfo { alpha2: "SX", alpha3: "SXM", name: "Sint Maarten (Dutch part)" },
+    CountryInfo { alpha2: "SC", alpha3: "SYC", name: "Seychelles" },
+    CountryInfo { alpha2: "SY", alpha3: "SYR", name: "Syrian Arab Republic" },
+    CountryInfo { alpha2: "TC", alpha3: "TCA", name: "Turks and Caicos Islands" },
+    CountryInfo { alpha2: "TD", alpha3: "TCD", name: "Chad" },
+    CountryInfo { alpha2: "TG", alpha3: "TGO", name: "Togo" },
+    CountryInfo { alpha2: "TH", alpha3: "THA", name: "Thailand" },
+    CountryInfo { alpha2: "TJ", alpha3: "TJK", name: "Tajikistan" },
+    CountryInfo { alpha2: "TK", alpha3: "TKL", name: "Tokelau" },
+    CountryInfo { alpha2: "TM", alpha3: "TKM", name: "Turkmenistan" },
+    CountryInfo { alpha2: "TL", alpha3: "TLS", name: "Timor-Leste" },
+    CountryInfo { alpha2: "TO", alpha3: "TON", name: "Tonga" },
+    CountryInfo { alpha2: "TT", alpha3: "TTO", name: "Trinidad and Tobago" },
+    CountryInfo { alpha2: "TN", alpha3: "TUN", name: "Tunisia" },
+    CountryInfo { alpha2: "TR", alpha3: "TUR", name: "Türkiye" },
+    CountryInfo { alpha2: "TV", alpha3: "TUV", name: "Tuvalu" },
+    CountryInfo { alpha2: "TW", alpha3: "TWN", name: "Taiwan, Province of China" },
+    CountryInfo { alpha2: "TZ", alpha3: "TZA", name: "Tanzania, United Republic of" },
+    CountryInfo { alpha2: "UG", alpha3: "UGA", name: "Uganda" },
+    CountryInfo { alpha2: "UA", alpha3: "UKR", name: "Ukraine" },
+    CountryInfo { alpha2: "UM", alpha3: "UMI", name: "United States Minor Outlying Islands" },
+    CountryInfo { alpha2: "UY", alpha3: "URY", name: "Uruguay" },
+    CountryInfo { alpha2: "US", alpha3: "USA", name: "United States" },
+    CountryInfo { alpha2: "UZ", alpha3: "UZB", name: "Uzbekistan" },
+    CountryInfo { alpha2: "VA", alpha3: "VAT", name: "Holy See (Vatican City State)" },
+    CountryInfo { alpha2: "VC", alpha3: "VCT", name: "Saint Vincent and the Grenadines" },
+    CountryInfo { alpha2: "VE", alpha3: "VEN", name: "Venezuela, Bolivarian Republic of" },
+    CountryInfo { alpha2: "VG", alpha3: "VGB", name: "Virgin Islands, British" },
+    CountryInfo { alpha2: "VI", alpha3: "VIR", name: "Virgin Islands, U.S." },
+    CountryInfo { alpha2: "VN", alpha3: "VNM", name: "Viet Nam" },
+    CountryInfo { alpha2: "VU", alpha3: "VUT", name: "Vanuatu" },
+    CountryInfo { alpha2: "WF", alpha3: "WLF", name: "Wallis and Futuna" },
+    CountryInfo { alpha2: "WS", alpha3: "WSM", name: "Samoa" },
+    CountryInfo { alpha2: "YE", alpha3: "YEM", name: "Yemen" },
+    CountryInfo { alpha2: "ZA", alpha3: "ZAF", name: "South Africa" },
+    CountryInfo { alpha2: "ZM", alpha3: "ZMB", name: "Zambia" },
+    CountryInfo { alpha2: "ZW", alpha3: "ZWE", name: "Zimbabwe" },
+];
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    #[test]
+    fn test_try_from_string() {
+        assert_eq!(Alpha2::try_from("ru".to_string()), Ok(Alpha2("RU".to_string())));
+        assert_eq!(Alpha3::try_from("rus".to_string()), Ok(Alpha3("RUS".to_string())));
+        assert_eq!(
+            Alpha2::try_from("XX".to_string()),
+            Err(CountryCodeError::UnknownAlpha2("XX".to_string()))
+        );
+        assert_eq!(
+            Alpha3::try_from("RU".to_string()),
+            Err(CountryCodeError::UnknownAlpha3("RU".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_conversion() {
+        let code: Alpha3 = "usa".parse().unwrap();
+        assert_eq!(code.to_alpha2(), Some(Alpha2("US".to_string())));
+        assert_eq!(code.country_name(), Some("United States"));
+        assert_eq!(String::from(code), "USA".to_string());
+    }
+}
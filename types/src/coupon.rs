@@ -0,0 +1,280 @@
+use std::error::Error;
+use std::fmt;
+use std::time::SystemTime;
+
+use super::{CartItem, CouponId, Money, MoneyError, ProductId, RoundingMode, StoreId};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CouponError {
+    Expired,
+    UsageLimitReached,
+    /// Order amount is less than the minimum required by the coupon
+    OrderAmountTooLow(Money),
+    StoreNotApplicable(StoreId),
+    ProductNotApplicable(ProductId),
+    /// Amounts of the coupon and the item are in different currencies, or the discount overflows
+    Money(MoneyError),
+}
+
+impl fmt::Display for CouponError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CouponError::Expired => write!(f, "Coupon has expired"),
+            CouponError::UsageLimitReached => write!(f, "Coupon usage limit is reached"),
+            CouponError::OrderAmountTooLow(min) => write!(f, "Coupon requires order amount of at least {}", min),
+            CouponError::StoreNotApplicable(store_id) => write!(f, "Coupon is not applicable to store {}", store_id),
+            CouponError::ProductNotApplicable(product_id) => write!(f, "Coupon is not applicable to product {}", product_id),
+            CouponError::Money(e) => write!(f, "Coupon discount cannot be evaluated: {}", e),
+        }
+    }
+}
+
+impl Error for CouponError {}
+
+impl From<MoneyError> for CouponError {
+    fn from(e: MoneyError) -> Self {
+        CouponError::Money(e)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CouponValue {
+    /// Percent of the item price, from 0 to 100
+    Percent(i32),
+    /// Amount taken off the item total, must be in the currency of the item price
+    Fixed(Money),
+}
+
+/// Coupon along with the rules of its use
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CouponPolicy {
+    pub id: CouponId,
+    pub value: CouponValue,
+    /// Must be in the currency of the order amount
+    pub min_order_amount: Option<Money>,
+    pub max_uses: Option<i32>,
+    /// Number of times the coupon is already used
+    pub uses: i32,
+    pub expires_at: Option<SystemTime>,
+    /// Store the coupon is issued by, `None` if it is applicable to all stores
+    pub store_id: Option<StoreId>,
+    /// Products the coupon is applicable to, `None` if it is applicable to all products
+    pub product_ids: Option<Vec<ProductId>>,
+}
+
+/// Amount taken off a cart item by the coupon
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Discount {
+    pub coupon_id: CouponId,
+    pub amount: Money,
+}
+
+impl CouponPolicy {
+    /// Percent coupon without restrictions
+    pub fn percent(id: CouponId, percent: i32) -> Self {
+        Self {
+            id,
+            value: CouponValue::Percent(percent),
+            min_order_amount: None,
+            max_uses: None,
+            uses: 0,
+            expires_at: None,
+            store_id: None,
+            product_ids: None,
+        }
+    }
+
+    /// Checks the rules of the coupon and evaluates its discount of the item with unit `price`.
+    /// `order_amount` is the total the minimum order amount is checked against. Discount never exceeds the item total,
+    /// percent discount is rounded to minor units half away from zero.
+    pub fn apply(&self, item: &CartItem, price: Money, order_amount: Money, now: SystemTime) -> Result<Discount, CouponError> {
+        if self.expires_at.map(|expires_at| now >= expires_at).unwrap_or(false) {
+            return Err(CouponError::Expired);
+        }
+        if self.max_uses.map(|max_uses| self.uses >= max_uses).unwrap_or(false) {
+            return Err(CouponError::UsageLimitReached);
+        }
+        if let Some(min_order_amount) = self.min_order_amount {
+            if order_amount.checked_sub(min_order_amount)?.amount_minor < 0 {
+                return Err(CouponError::OrderAmountTooLow(min_order_amount));
+            }
+        }
+        if self.store_id.map(|store_id| store_id != item.store_id).unwrap_or(false) {
+            return Err(CouponError::StoreNotApplicable(item.store_id));
+        }
+        if let Some(ref product_ids) = self.product_ids {
+            if !product_ids.contains(&item.product_id) {
+                return Err(CouponError::ProductNotApplicable(item.product_id));
+            }
+        }
+
+        let item_total = price.checked_mul(i64::from(item.quantity.0))?;
+        let amount = match self.value {
+            CouponValue::Percent(percent) => {
+                item_total.checked_mul_ratio(i64::from(percent.max(0).min(100)), 100, RoundingMode::HalfAwayFromZero)?
+            }
+            CouponValue::Fixed(amount) => {
+                if amount.currency != item_total.currency {
+                    return Err(MoneyError::CurrencyMismatch(item_total.currency, amount.currency).into());
+                }
+                Money::new(amount.amount_minor.max(0).min(item_total.amount_minor), item_total.currency)
+            }
+        };
+        Ok(Discount {
+            coupon_id: self.id,
+            amount,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use stq_static_resources::{Currency, CurrencyType};
+
+    use super::super::{CartCustomer, CartItemId, Quantity, UserId};
+    use super::*;
+
+    fn item(product_id: i32, store_id: i32, quantity: i32) -> CartItem {
+        CartItem {
+            id: CartItemId::default(),
+            customer: CartCustomer::User(UserId(1)),
+            product_id: ProductId(product_id),
+            quantity: Quantity(quantity),
+            selected: true,
+            comment: String::new(),
+            store_id: StoreId(store_id),
+            pre_order: false,
+            pre_order_days: 0,
+            coupon_id: Some(CouponId(1)),
+            delivery_method_id: None,
+            currency_type: CurrencyType::Crypto,
+            user_country_code: None,
+        }
+    }
+
+    fn usd(amount_minor: i64) -> Money {
+        Money::new(amount_minor, Currency::USD)
+    }
+
+    fn apply(policy: &CouponPolicy, item: &CartItem, price: Money) -> Result<Money, CouponError> {
+        policy.apply(item, price, price, SystemTime::now()).map(|discount| discount.amount)
+    }
+
+    #[test]
+    fn test_expiry() {
+        let now = SystemTime::now();
+        let policy = CouponPolicy {
+            expires_at: Some(now),
+            ..CouponPolicy::percent(CouponId(1), 10)
+        };
+        let item = item(1, 1, 1);
+
+        assert_eq!(policy.apply(&item, usd(10_000), usd(10_000), now), Err(CouponError::Expired));
+        assert_eq!(
+            policy
+                .apply(&item, usd(10_000), usd(10_000), now - Duration::from_secs(60))
+                .map(|discount| discount.amount),
+            Ok(usd(1_000))
+        );
+    }
+
+    #[test]
+    fn test_usage_limit() {
+        let policy = CouponPolicy {
+            max_uses: Some(3),
+            uses: 3,
+            ..CouponPolicy::percent(CouponId(1), 10)
+        };
+        assert_eq!(apply(&policy, &item(1, 1, 1), usd(10_000)), Err(CouponError::UsageLimitReached));
+
+        let policy = CouponPolicy { uses: 2, ..policy };
+        assert_eq!(apply(&policy, &item(1, 1, 1), usd(10_000)), Ok(usd(1_000)));
+    }
+
+    #[test]
+    fn test_min_order_amount() {
+        let policy = CouponPolicy {
+            min_order_amount: Some(usd(10_000)),
+            ..CouponPolicy::percent(CouponId(1), 10)
+        };
+        let item = item(1, 1, 1);
+
+        assert_eq!(
+            policy.apply(&item, usd(5_000), usd(9_999), SystemTime::now()),
+            Err(CouponError::OrderAmountTooLow(usd(10_000)))
+        );
+        // The minimum is checked against the order amount, not the item price
+        assert_eq!(
+            policy
+                .apply(&item, usd(5_000), usd(10_000), SystemTime::now())
+                .map(|discount| discount.amount),
+            Ok(usd(500))
+        );
+        assert_eq!(
+            policy.apply(&item, usd(5_000), Money::new(10_000, Currency::EUR), SystemTime::now()),
+            Err(CouponError::Money(MoneyError::CurrencyMismatch(Currency::EUR, Currency::USD)))
+        );
+    }
+
+    #[test]
+    fn test_store_and_product_scope() {
+        let policy = CouponPolicy {
+            store_id: Some(StoreId(2)),
+            ..CouponPolicy::percent(CouponId(1), 10)
+        };
+        assert_eq!(
+            apply(&policy, &item(1, 1, 1), usd(10_000)),
+            Err(CouponError::StoreNotApplicable(StoreId(1)))
+        );
+        assert_eq!(apply(&policy, &item(1, 2, 1), usd(10_000)), Ok(usd(1_000)));
+
+        let policy = CouponPolicy {
+            product_ids: Some(vec![ProductId(2), ProductId(3)]),
+            ..CouponPolicy::percent(CouponId(1), 10)
+        };
+        assert_eq!(
+            apply(&policy, &item(1, 1, 1), usd(10_000)),
+            Err(CouponError::ProductNotApplicable(ProductId(1)))
+        );
+        assert_eq!(apply(&policy, &item(3, 1, 1), usd(10_000)), Ok(usd(1_000)));
+    }
+
+    #[test]
+    fn test_percent_is_clamped() {
+        let item = item(1, 1, 2);
+
+        assert_eq!(apply(&CouponPolicy::percent(CouponId(1), 25), &item, usd(1_000)), Ok(usd(500)));
+        assert_eq!(apply(&CouponPolicy::percent(CouponId(1), 150), &item, usd(1_000)), Ok(usd(2_000)));
+        assert_eq!(apply(&CouponPolicy::percent(CouponId(1), -10), &item, usd(1_000)), Ok(usd(0)));
+    }
+
+    #[test]
+    fn test_percent_is_rounded() {
+        // 15% of 3 * 0.33 = 0.1485, rounded to 0.15
+        assert_eq!(apply(&CouponPolicy::percent(CouponId(1), 15), &item(1, 1, 3), usd(33)), Ok(usd(15)));
+        // 10% of 0.05 = 0.005, rounded half away from zero to 0.01
+        assert_eq!(apply(&CouponPolicy::percent(CouponId(1), 10), &item(1, 1, 1), usd(5)), Ok(usd(1)));
+        assert_eq!(
+            apply(&CouponPolicy::percent(CouponId(1), 10), &item(1, 1, 2), usd(i64::max_value())),
+            Err(CouponError::Money(MoneyError::Overflow))
+        );
+    }
+
+    #[test]
+    fn test_fixed_discount_is_capped_at_item_total() {
+        let fixed = |amount: Money| CouponPolicy {
+            value: CouponValue::Fixed(amount),
+            ..CouponPolicy::percent(CouponId(1), 0)
+        };
+        let item = item(1, 1, 3);
+
+        assert_eq!(apply(&fixed(usd(1_000)), &item, usd(1_000)), Ok(usd(1_000)));
+        assert_eq!(apply(&fixed(usd(5_000)), &item, usd(1_000)), Ok(usd(3_000)));
+        assert_eq!(apply(&fixed(usd(-500)), &item, usd(1_000)), Ok(usd(0)));
+        assert_eq!(
+            apply(&fixed(Money::new(1_000, Currency::EUR)), &item, usd(1_000)),
+            Err(CouponError::Money(MoneyError::CurrencyMismatch(Currency::USD, Currency::EUR)))
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use super::{CartItem, CompanyPackageId, DeliveryMethodId, ShippingId, StoreId};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryMethodError {
+    /// Deprecated `Package` method, for which there is no shipping of the package
+    UnmappedPackage(CompanyPackageId),
+    /// Some products of the store are picked up while others are shipped
+    MixedMethods(StoreId),
+}
+
+impl fmt::Display for DeliveryMethodError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeliveryMethodError::UnmappedPackage(id) => write!(f, "No shipping found for company package {}", id),
+            DeliveryMethodError::MixedMethods(store_id) => {
+                write!(f, "Products of store {} can't be both picked up and shipped", store_id)
+            }
+        }
+    }
+}
+
+impl Error for DeliveryMethodError {}
+
+impl DeliveryMethodId {
+    pub fn is_deprecated(&self) -> bool {
+        match self {
+            DeliveryMethodId::Package { .. } => true,
+            DeliveryMethodId::Pickup { .. } | DeliveryMethodId::ShippingPackage { .. } => false,
+        }
+    }
+
+    pub fn is_pickup(&self) -> bool {
+        match self {
+            DeliveryMethodId::Pickup { .. } => true,
+            DeliveryMethodId::Package { .. } | DeliveryMethodId::ShippingPackage { .. } => false,
+        }
+    }
+
+    /// Replaces deprecated `Package` with `ShippingPackage` of the shipping found by `shipping_for`,
+    /// other methods are returned as is
+    pub fn upgrade<F>(self, shipping_for: F) -> Result<DeliveryMethodId, DeliveryMethodError>
+    where
+        F: FnOnce(CompanyPackageId) -> Option<ShippingId>,
+    {
+        match self {
+            DeliveryMethodId::Package { id } => shipping_for(id)
+                .map(|id| DeliveryMethodId::ShippingPackage { id })
+                .ok_or(DeliveryMethodError::UnmappedPackage(id)),
+            method => Ok(method),
+        }
+    }
+}
+
+/// Checks that products of each store are either all picked up or all shipped.
+/// Items without delivery method are skipped, pass selected items only to check the cart being converted to orders.
+pub fn check_delivery_methods<'a, I>(items: I) -> Result<(), DeliveryMethodError>
+where
+    I: IntoIterator<Item = &'a CartItem>,
+{
+    let mut pickup_by_store = HashMap::new();
+    for item in items {
+        if let Some(delivery_method_id) = item.delivery_method_id {
+            let is_pickup = delivery_method_id.is_pickup();
+            if *pickup_by_store.entry(item.store_id).or_insert(is_pickup) != is_pickup {
+                return Err(DeliveryMethodError::MixedMethods(item.store_id));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use stq_static_resources::CurrencyType;
+
+    use super::super::{CartCustomer, CartItemId, PickupId, ProductId, Quantity, UserId};
+    use super::*;
+
+    fn item(store_id: i32, delivery_method_id: Option<DeliveryMethodId>) -> CartItem {
+        CartItem {
+            id: CartItemId::default(),
+            customer: CartCustomer::User(UserId(1)),
+            product_id: ProductId(1),
+            quantity: Quantity(1),
+            selected: true,
+            comment: String::new(),
+            store_id: StoreId(store_id),
+            pre_order: false,
+            pre_order_days: 0,
+            coupon_id: None,
+            delivery_method_id,
+            currency_type: CurrencyType::Crypto,
+            user_country_code: None,
+        }
+    }
+
+    fn pickup(id: i32) -> Option<DeliveryMethodId> {
+        Some(DeliveryMethodId::Pickup { id: PickupId(id) })
+    }
+
+    fn shipping(id: i32) -> Option<DeliveryMethodId> {
+        Some(DeliveryMethodId::ShippingPackage { id: ShippingId(id) })
+    }
+
+    #[test]
+    fn test_upgrade_package() {
+        let package = DeliveryMethodId::Package { id: CompanyPackageId(1) };
+        assert!(package.is_deprecated());
+
+        let upgraded = package.upgrade(|id| if id == CompanyPackageId(1) { Some(ShippingId(10)) } else { None });
+        assert_eq!(upgraded, Ok(DeliveryMethodId::ShippingPackage { id: ShippingId(10) }));
+        assert!(!upgraded.unwrap().is_deprecated());
+
+        let pickup = DeliveryMethodId::Pickup { id: PickupId(1) };
+        assert_eq!(pickup.upgrade(|_| Some(ShippingId(10))), Ok(pickup));
+    }
+
+    #[test]
+    fn test_upgrade_unmapped_package() {
+        let package = DeliveryMethodId::Package { id: CompanyPackageId(2) };
+        assert_eq!(
+            package.upgrade(|_| None),
+            Err(DeliveryMethodError::UnmappedPackage(CompanyPackageId(2)))
+        );
+    }
+
+    #[test]
+    fn test_mixed_methods_in_store() {
+        let items = vec![item(1, shipping(1)), item(1, None), item(1, pickup(1))];
+        assert_eq!(check_delivery_methods(&items), Err(DeliveryMethodError::MixedMethods(StoreId(1))));
+
+        let items = vec![item(1, shipping(1)), item(1, None), item(1, shipping(2))];
+        assert_eq!(check_delivery_methods(&items), Ok(()));
+    }
+
+    #[test]
+    fn test_different_methods_in_different_stores() {
+        let items = vec![item(1, pickup(1)), item(2, shipping(1)), item(1, pickup(2)), item(2, shipping(2))];
+        assert_eq!(check_delivery_methods(&items), Ok(()));
+    }
+}
@@ -3,6 +3,8 @@ use super::*;
 use std::fmt;
 use std::str::FromStr;
 
+use uuid::{self, Uuid};
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, DieselTypes)]
 pub enum StoresRole {
     Superuser,
@@ -202,7 +204,7 @@ pub enum WarehouseIdentifier {
 }
 
 /// Anything that can uniquely identify an Order
-#[derive(Clone, Copy, Debug, Eq, From, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Eq, From, PartialEq, Hash, Serialize, Deserialize)]
 pub enum OrderIdentifier {
     Id(OrderId),
     Slug(OrderSlug),
@@ -222,16 +224,58 @@ pub enum StoreIdentifier {
     Slug(StoreSlug),
 }
 
+/// Anonymous session, legacy sessions are identified by integers and new ones by UUIDs.
+/// Serialized as a number or a string respectively.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, From, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SessionIdentifier {
+    Int(SessionId),
+    Uuid(Uuid),
+}
+
+impl SessionIdentifier {
+    pub fn new_uuid() -> Self {
+        SessionIdentifier::Uuid(Uuid::new_v4())
+    }
+}
+
+impl fmt::Display for SessionIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionIdentifier::Int(id) => write!(f, "{}", id),
+            SessionIdentifier::Uuid(id) => write!(f, "{}", id.hyphenated()),
+        }
+    }
+}
+
+/// Integers are parsed as legacy sessions, anything else must be a UUID
+impl FromStr for SessionIdentifier {
+    type Err = uuid::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<i32>() {
+            Ok(id) => Ok(SessionIdentifier::Int(SessionId(id))),
+            Err(_) => Uuid::parse_str(s).map(SessionIdentifier::Uuid),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, From, Hash, Serialize, Deserialize)]
 pub enum CartCustomer {
     User(UserId),
-    Anonymous(SessionId),
+    Anonymous(SessionIdentifier),
+}
+
+impl From<SessionId> for CartCustomer {
+    fn from(v: SessionId) -> Self {
+        CartCustomer::Anonymous(v.into())
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, From, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum DeliveryMethodId {
-    Package { id: CompanyPackageId }, // deprecated
+    Package { id: CompanyPackageId }, // deprecated, see `DeliveryMethodId::upgrade`
     Pickup { id: PickupId },
     ShippingPackage { id: ShippingId },
 }
@@ -250,3 +294,37 @@ impl fmt::Display for CartCustomer {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+
+    #[test]
+    fn test_session_identifier_serialization() {
+        let uuid = Uuid::parse_str("8b7a3c5e-0d4f-4e6a-9b1c-2d3e4f5a6b7c").unwrap();
+        let legacy = SessionIdentifier::Int(SessionId(42));
+        let new = SessionIdentifier::Uuid(uuid);
+
+        assert_eq!(serde_json::to_string(&legacy).unwrap(), "42");
+        assert_eq!(serde_json::to_string(&new).unwrap(), "\"8b7a3c5e-0d4f-4e6a-9b1c-2d3e4f5a6b7c\"");
+        assert_eq!(serde_json::from_str::<SessionIdentifier>("42").unwrap(), legacy);
+        assert_eq!(
+            serde_json::from_str::<SessionIdentifier>("\"8b7a3c5e-0d4f-4e6a-9b1c-2d3e4f5a6b7c\"").unwrap(),
+            new
+        );
+    }
+
+    #[test]
+    fn test_session_identifier_string_roundtrip() {
+        let new = SessionIdentifier::new_uuid();
+        assert_eq!(new.to_string().parse::<SessionIdentifier>(), Ok(new));
+        assert_eq!("42".parse::<SessionIdentifier>(), Ok(SessionIdentifier::Int(SessionId(42))));
+        assert!("session".parse::<SessionIdentifier>().is_err());
+        assert_eq!(
+            CartCustomer::from(SessionId(42)),
+            CartCustomer::Anonymous(SessionIdentifier::Int(SessionId(42)))
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use diesel::deserialize::FromSql;
+use diesel::expression::bound::Bound;
+use diesel::expression::AsExpression;
+use diesel::pg::Pg;
+use diesel::row::Row;
+use diesel::serialize::Output;
+use diesel::sql_types::{Double, NotNull, Nullable, SingleValue};
+use diesel::types::{FromSqlRow, IsNull, ToSql};
+use diesel::Queryable;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use stq_static_resources::Currency;
+
+use super::{Money, MoneyError};
+
+/// Number of decimal digits stored in `ExchangeRate`
+pub const EXCHANGE_RATE_SCALE: u32 = 12;
+
+fn pow10(exponent: u32) -> i128 {
+    10i128.pow(exponent)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RoundingMode {
+    /// Ties are rounded away from zero, 2.5 -> 3, -2.5 -> -3
+    HalfAwayFromZero,
+    /// Banker's rounding, ties are rounded to the nearest even number, 2.5 -> 2, 3.5 -> 4
+    HalfEven,
+}
+
+impl Default for RoundingMode {
+    fn default() -> Self {
+        RoundingMode::HalfAwayFromZero
+    }
+}
+
+impl RoundingMode {
+    /// Divides `numerator` by positive `denominator` rounding the result
+    pub(crate) fn div(self, numerator: i128, denominator: i128) -> i128 {
+        let quotient = numerator / denominator;
+        let remainder = (numerator % denominator).abs();
+        let step = if numerator < 0 { -1 } else { 1 };
+
+        match (remainder * 2).cmp(&denominator) {
+            ::std::cmp::Ordering::Less => quotient,
+            ::std::cmp::Ordering::Greater => quotient + step,
+            ::std::cmp::Ordering::Equal => match self {
+                RoundingMode::HalfAwayFromZero => quotient + step,
+                RoundingMode::HalfEven => {
+                    if quotient % 2 == 0 {
+                        quotient
+                    } else {
+                        quotient + step
+                    }
+                }
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseExchangeRateError(String);
+
+impl fmt::Display for ParseExchangeRateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid exchange rate: {}", self.0)
+    }
+}
+
+impl Error for ParseExchangeRateError {}
+
+/// Fixed point exchange rate: amount of the target currency for one unit of the source currency,
+/// stored with `EXCHANGE_RATE_SCALE` decimal digits.
+///
+/// Serialized as a floating point number and stored as `double precision` to stay compatible with the former `f64` representation,
+/// use `From<f64>` and `as_f64` where the floating point value is needed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExchangeRate(i128);
+
+impl ExchangeRate {
+    /// Creates rate from its value multiplied by `10^EXCHANGE_RATE_SCALE`
+    pub fn from_scaled(scaled: i128) -> Self {
+        ExchangeRate(scaled)
+    }
+
+    pub fn scaled(&self) -> i128 {
+        self.0
+    }
+
+    /// Rounds the value to `EXCHANGE_RATE_SCALE` digits, returns `None` for non-finite values and values out of range
+    pub fn from_f64(value: f64) -> Option<Self> {
+        let scaled = (value * pow10(EXCHANGE_RATE_SCALE) as f64).round();
+        if !scaled.is_finite() || scaled.abs() >= i128::max_value() as f64 {
+            return None;
+        }
+        Some(Self::from_scaled(scaled as i128))
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.0 as f64 / pow10(EXCHANGE_RATE_SCALE) as f64
+    }
+
+    /// Rate of the reverse conversion, `None` for zero rate
+    pub fn inverse(&self, rounding: RoundingMode) -> Option<Self> {
+        if self.0 == 0 {
+            return None;
+        }
+        let (numerator, denominator) = if self.0 < 0 {
+            (-pow10(2 * EXCHANGE_RATE_SCALE), -self.0)
+        } else {
+            (pow10(2 * EXCHANGE_RATE_SCALE), self.0)
+        };
+        Some(Self::from_scaled(rounding.div(numerator, denominator)))
+    }
+
+    /// Converts `money` to `currency`, rounding the result to minor units of `currency`
+    pub fn convert(&self, money: Money, currency: Currency, rounding: RoundingMode) -> Result<Money, MoneyError> {
+        let from_exponent = money.currency.exponent();
+        let to_exponent = currency.exponent();
+
+        let mut numerator = i128::from(money.amount_minor).checked_mul(self.0).ok_or(MoneyError::Overflow)?;
+        let mut denominator = pow10(EXCHANGE_RATE_SCALE);
+        if to_exponent >= from_exponent {
+            numerator = numerator
+                .checked_mul(pow10(to_exponent - from_exponent))
+                .ok_or(MoneyError::Overflow)?;
+        } else {
+            denominator *= pow10(from_exponent - to_exponent);
+        }
+
+        let amount_minor = rounding.div(numerator, denominator);
+        if amount_minor > i128::from(i64::max_value()) || amount_minor < i128::from(i64::min_value()) {
+            return Err(MoneyError::Overflow);
+        }
+        Ok(Money::new(amount_minor as i64, currency))
+    }
+}
+
+/// Kept for compatibility with the former `f64` newtype, non-finite values and values out of range become zero rate
+impl From<f64> for ExchangeRate {
+    fn from(value: f64) -> Self {
+        Self::from_f64(value).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ExchangeRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let units = pow10(EXCHANGE_RATE_SCALE);
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let integer = (self.0 / units).abs();
+        let fraction = format!("{:0width$}", (self.0 % units).abs(), width = EXCHANGE_RATE_SCALE as usize);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            write!(f, "{}{}", sign, integer)
+        } else {
+            write!(f, "{}{}.{}", sign, integer, fraction)
+        }
+    }
+}
+
+/// Parses decimal string exactly, digits beyond `EXCHANGE_RATE_SCALE` are not allowed
+impl FromStr for ExchangeRate {
+    type Err = ParseExchangeRateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseExchangeRateError(s.to_string());
+
+        let (negative, unsigned) = if s.starts_with('-') { (true, &s[1..]) } else { (false, s) };
+        let mut parts = unsigned.splitn(2, '.');
+        let integer = parts.next().unwrap_or_default();
+        let fraction = parts.next().unwrap_or_default();
+        let is_digits = |v: &str| v.chars().all(|c| c.is_ascii_digit());
+        if integer.is_empty() || !is_digits(integer) || !is_digits(fraction) || fraction.len() > EXCHANGE_RATE_SCALE as usize {
+            return Err(error());
+        }
+
+        let integer = integer.parse::<i128>().map_err(|_| error())?;
+        let fraction = format!("{:0<width$}", fraction, width = EXCHANGE_RATE_SCALE as usize)
+            .parse::<i128>()
+            .map_err(|_| error())?;
+        let scaled = integer
+            .checked_mul(pow10(EXCHANGE_RATE_SCALE))
+            .and_then(|v| v.checked_add(fraction))
+            .ok_or_else(error)?;
+
+        Ok(Self::from_scaled(if negative { -scaled } else { scaled }))
+    }
+}
+
+impl Serialize for ExchangeRate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.as_f64())
+    }
+}
+
+impl<'de> Deserialize<'de> for ExchangeRate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = f64::deserialize(deserializer)?;
+        Self::from_f64(value).ok_or_else(|| de::Error::custom(format!("Invalid exchange rate: {}", value)))
+    }
+}
+
+impl NotNull for ExchangeRate {}
+impl SingleValue for ExchangeRate {}
+impl Queryable<Double, Pg> for ExchangeRate {
+    type Row = ExchangeRate;
+    fn build(row: Self::Row) -> Self {
+        row
+    }
+}
+impl AsExpression<Double> for ExchangeRate {
+    type Expression = Bound<Double, ExchangeRate>;
+    fn as_expression(self) -> Self::Expression {
+        Bound::new(self)
+    }
+}
+impl<'a> AsExpression<Double> for &'a ExchangeRate {
+    type Expression = Bound<Double, &'a ExchangeRate>;
+    fn as_expression(self) -> Self::Expression {
+        Bound::new(self)
+    }
+}
+impl ToSql<Double, Pg> for ExchangeRate {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> Result<IsNull, Box<Error + Send + Sync>> {
+        <f64 as ToSql<Double, Pg>>::to_sql(&self.as_f64(), out)
+    }
+}
+// Nullable impls are kept for compatibility with the former `f64` newtype
+impl AsExpression<Nullable<Double>> for ExchangeRate {
+    type Expression = Bound<Nullable<Double>, ExchangeRate>;
+    fn as_expression(self) -> Self::Expression {
+        Bound::new(self)
+    }
+}
+impl<'a> AsExpression<Nullable<Double>> for &'a ExchangeRate {
+    type Expression = Bound<Nullable<Double>, &'a ExchangeRate>;
+    fn as_expression(self) -> Self::Expression {
+        Bound::new(self)
+    }
+}
+impl ToSql<Nullable<Double>, Pg> for ExchangeRate {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> Result<IsNull, Box<Error + Send + Sync>> {
+        <f64 as ToSql<Double, Pg>>::to_sql(&self.as_f64(), out)
+    }
+}
+impl FromSqlRow<Double, Pg> for ExchangeRate {
+    fn build_from_row<R: Row<Pg>>(row: &mut R) -> Result<Self, Box<Error + Send + Sync>> {
+        let value = <f64 as FromSql<Double, Pg>>::from_sql(row.take())?;
+        Self::from_f64(value).ok_or_else(|| format!("Invalid exchange rate: {}", value).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(s: &str) -> ExchangeRate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_string_round_trip() {
+        for s in &["0", "1", "-2.5", "0.000000000001", "64.123456789012", "123456789.5"] {
+            assert_eq!(rate(s).to_string(), *s);
+        }
+        assert_eq!(rate("1.50").to_string(), "1.5");
+        assert!("0.0000000000001".parse::<ExchangeRate>().is_err());
+        assert!("1e3".parse::<ExchangeRate>().is_err());
+        assert!(".5".parse::<ExchangeRate>().is_err());
+    }
+
+    #[test]
+    fn test_f64_round_trip() {
+        for value in &[0.0, 1.0, 0.1, 64.25, 0.000_012_345, 12_345.678_9] {
+            assert_eq!(ExchangeRate::from_f64(*value).unwrap().as_f64(), *value);
+        }
+        assert_eq!(ExchangeRate::from_f64(0.1), Some(rate("0.1")));
+        assert_eq!(ExchangeRate::from_f64(::std::f64::INFINITY), None);
+        assert_eq!(ExchangeRate::from(::std::f64::NAN), ExchangeRate::default());
+    }
+
+    #[test]
+    fn test_f64_compatibility() {
+        let value = ExchangeRate::from(0.25);
+        assert_eq!(value.as_f64(), 0.25);
+        assert_eq!(value, rate("0.25"));
+        assert_eq!(::serde_json::to_string(&value).unwrap(), "0.25");
+        assert_eq!(::serde_json::from_str::<ExchangeRate>("0.25").unwrap(), value);
+    }
+
+    #[test]
+    fn test_convert_precision() {
+        let usd = Money::new(1_000_000_000_000, Currency::USD);
+        assert_eq!(
+            rate("0.000000000001").convert(usd, Currency::EUR, RoundingMode::HalfAwayFromZero),
+            Ok(Money::new(1, Currency::EUR))
+        );
+
+        let btc = Money::new(100_000_000, Currency::BTC);
+        assert_eq!(
+            rate("6543.215").convert(btc, Currency::USD, RoundingMode::HalfAwayFromZero),
+            Ok(Money::new(654_322, Currency::USD))
+        );
+        assert_eq!(
+            rate("6543.215").convert(btc, Currency::USD, RoundingMode::HalfEven),
+            Ok(Money::new(654_322, Currency::USD))
+        );
+        assert_eq!(
+            rate("6543.225").convert(btc, Currency::USD, RoundingMode::HalfEven),
+            Ok(Money::new(654_322, Currency::USD))
+        );
+
+        let usd = Money::new(100, Currency::USD);
+        assert_eq!(
+            rate("0.00001").convert(usd, Currency::BTC, RoundingMode::HalfAwayFromZero),
+            Ok(Money::new(1_000, Currency::BTC))
+        );
+        assert_eq!(
+            rate("1000000").convert(Money::new(i64::max_value(), Currency::USD), Currency::EUR, RoundingMode::HalfEven),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_inverse() {
+        assert_eq!(rate("4").inverse(RoundingMode::HalfEven), Some(rate("0.25")));
+        assert_eq!(rate("3").inverse(RoundingMode::HalfAwayFromZero), Some(rate("0.333333333333")));
+        assert_eq!(rate("-8").inverse(RoundingMode::HalfEven), Some(rate("-0.125")));
+        assert_eq!(rate("0").inverse(RoundingMode::HalfEven), None);
+    }
+}
@@ -0,0 +1,147 @@
+//! GraphQL scalars for newtypes, enabled with `graphql` feature.
+//! Integer identifiers are represented as `Int`, floating point values as `Float` and the rest as `String`.
+//! String input is validated the same way as in `FromStr` implementations.
+//! Page info objects are exposed by GraphQL types of pages, with counts as `Int`.
+use juniper::{InputValue, Value};
+
+use super::*;
+
+macro_rules! int_scalars {
+    ($($x:ident),*) => {
+        $(
+            graphql_scalar!($x {
+                resolve(&self) -> Value {
+                    Value::int(self.0)
+                }
+
+                from_input_value(v: &InputValue) -> Option<$x> {
+                    v.as_int_value().map($x)
+                }
+            });
+        )*
+    };
+}
+
+macro_rules! float_scalars {
+    ($($x:ident),*) => {
+        $(
+            graphql_scalar!($x {
+                resolve(&self) -> Value {
+                    Value::float(self.0)
+                }
+
+                from_input_value(v: &InputValue) -> Option<$x> {
+                    v.as_float_value().or_else(|| v.as_int_value().map(f64::from)).map($x)
+                }
+            });
+        )*
+    };
+}
+
+macro_rules! string_scalars {
+    ($($x:ident),*) => {
+        $(
+            graphql_scalar!($x {
+                resolve(&self) -> Value {
+                    Value::string(&self.to_string())
+                }
+
+                from_input_value(v: &InputValue) -> Option<$x> {
+                    v.as_string_value().and_then(|s| s.parse().ok())
+                }
+            });
+        )*
+    };
+}
+
+int_scalars!(
+    UserId,
+    SessionId,
+    ProductId,
+    BaseProductId,
+    Quantity,
+    StoreId,
+    OrderSlug,
+    CompanyPackageId,
+    CompanyId,
+    PackageId,
+    CustomAttributeId,
+    AttributeId,
+    CategoryId,
+    CouponId,
+    PickupId,
+    ShippingId,
+    ShippingRatesId,
+    ProdAttrId,
+    AttributeValueId,
+    EmarsysId,
+    StoreBillingTypeId,
+    InternationalBillingId,
+    RussiaBillingId,
+    ProxyCompanyBillingInfoId,
+    StoreSubscriptionId,
+    SubscriptionId,
+    SubscriptionPaymentId
+);
+
+float_scalars!(ProductPrice, CashbackPercent);
+
+string_scalars!(
+    WarehouseSlug,
+    CountryLabel,
+    PageSlug,
+    Alpha2,
+    Alpha3,
+    AttributeValueCode,
+    CouponCode,
+    BaseProductSlug,
+    StoreSlug,
+    CategorySlug,
+    SwiftId,
+    RoleEntryId,
+    RoleId,
+    StockId,
+    InvoiceId,
+    SagaId,
+    MerchantId,
+    CartItemId,
+    OrderId,
+    OrderDiffId,
+    OrderInfoId,
+    CallbackId,
+    ConversionId,
+    WarehouseId,
+    CurrencyExchangeId,
+    PageId,
+    PayoutId,
+    EmailAddress,
+    PhoneNumber
+);
+
+graphql_object!(PageInfo: () as "PageInfo" |&self| {
+    description: "Position of a page in the listing"
+
+    field total() -> i32 as "Number of items in the whole listing" {
+        self.total as i32
+    }
+
+    field offset() -> i32 {
+        self.offset as i32
+    }
+
+    field limit() -> Option<i32> {
+        self.limit.map(|limit| limit as i32)
+    }
+
+    field has_next() -> bool as "Whether there are items after the page" {
+        self.has_next
+    }
+});
+
+graphql_object!(CursorPageInfo: () as "CursorPageInfo" |&self| {
+    description: "Position of a page in the listing paged through by cursor"
+
+    field next_cursor() -> Option<&str> as "Cursor to request the next page with, null if this is the last one" {
+        self.next_cursor.as_ref().map(|cursor| cursor.as_str())
+    }
+});
@@ -4,15 +4,44 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate diesel;
+#[cfg(test)]
+extern crate serde_json;
+#[cfg(feature = "graphql")]
+#[macro_use]
+extern crate juniper;
+#[macro_use]
+extern crate lazy_static;
+extern crate regex;
 extern crate uuid;
+extern crate validator;
 #[macro_use]
 extern crate stq_diesel_macro_derive;
 extern crate stq_static_resources;
 
+pub mod contacts;
+pub mod countries;
+pub mod coupon;
+pub mod delivery_method;
 pub mod enums;
+pub mod exchange_rate;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod money;
 pub mod newtypes;
+pub mod page;
+pub mod quantity;
+pub mod slug;
 pub mod structs;
 
+pub use self::contacts::*;
+pub use self::countries::*;
+pub use self::coupon::*;
+pub use self::delivery_method::*;
 pub use self::enums::*;
+pub use self::exchange_rate::*;
+pub use self::money::*;
 pub use self::newtypes::*;
+pub use self::page::*;
+pub use self::quantity::*;
+pub use self::slug::*;
 pub use self::structs::*;
@@ -0,0 +1,275 @@
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+
+use diesel::deserialize::{FromSql, FromSqlRow};
+use diesel::expression::bound::Bound;
+use diesel::expression::AsExpression;
+use diesel::pg::Pg;
+use diesel::row::Row;
+use diesel::serialize::{IsNull, Output, ToSql, WriteTuple};
+use diesel::sql_types::{BigInt, Record, VarChar};
+use diesel::Queryable;
+
+use stq_static_resources::Currency;
+
+use super::RoundingMode;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MoneyError {
+    CurrencyMismatch(Currency, Currency),
+    Overflow,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoneyError::CurrencyMismatch(left, right) => write!(f, "Currency mismatch: {} and {}", left, right),
+            MoneyError::Overflow => write!(f, "Money amount overflow"),
+        }
+    }
+}
+
+impl Error for MoneyError {}
+
+/// Amount of money stored as an integer number of minor units of the currency,
+/// e.g. cents for USD, see `Currency::exponent`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    pub amount_minor: i64,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount_minor: i64, currency: Currency) -> Self {
+        Self { amount_minor, currency }
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Number of minor units in one major unit of the currency
+    pub fn minor_units_per_major(currency: Currency) -> i64 {
+        10i64.pow(currency.exponent())
+    }
+
+    /// Converts amount in major units, rounding it half away from zero to the currency exponent.
+    /// Returns `None` for non-finite amounts and amounts out of range.
+    pub fn from_major(amount: f64, currency: Currency) -> Option<Self> {
+        let minor = (amount * Self::minor_units_per_major(currency) as f64).round();
+        if !minor.is_finite() || minor < i64::min_value() as f64 || minor >= i64::max_value() as f64 {
+            return None;
+        }
+        Some(Self::new(minor as i64, currency))
+    }
+
+    /// Amount in major units, may lose precision, use it for display only
+    pub fn to_major(&self) -> f64 {
+        self.amount_minor as f64 / Self::minor_units_per_major(self.currency) as f64
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount_minor == 0
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Money, MoneyError> {
+        self.check_currency(other)?;
+        self.amount_minor
+            .checked_add(other.amount_minor)
+            .map(|amount_minor| Self::new(amount_minor, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Result<Money, MoneyError> {
+        self.check_currency(other)?;
+        self.amount_minor
+            .checked_sub(other.amount_minor)
+            .map(|amount_minor| Self::new(amount_minor, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// Multiplies by quantity, e.g. to get total of a cart item
+    pub fn checked_mul(self, quantity: i64) -> Result<Money, MoneyError> {
+        self.amount_minor
+            .checked_mul(quantity)
+            .map(|amount_minor| Self::new(amount_minor, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// Multiplies by `numerator / denominator`, rounding the result to minor units, e.g. to take a percent of the amount.
+    /// `denominator` must be positive.
+    pub fn checked_mul_ratio(self, numerator: i64, denominator: i64, rounding: RoundingMode) -> Result<Money, MoneyError> {
+        let amount_minor = rounding.div(i128::from(self.amount_minor) * i128::from(numerator), i128::from(denominator));
+        if amount_minor > i128::from(i64::max_value()) || amount_minor < i128::from(i64::min_value()) {
+            return Err(MoneyError::Overflow);
+        }
+        Ok(Self::new(amount_minor as i64, self.currency))
+    }
+
+    /// Sums amounts, all of them must be in `currency`
+    pub fn checked_sum<I>(currency: Currency, amounts: I) -> Result<Money, MoneyError>
+    where
+        I: IntoIterator<Item = Money>,
+    {
+        amounts
+            .into_iter()
+            .fold(Ok(Self::zero(currency)), |acc, amount| acc.and_then(|acc| acc.checked_add(amount)))
+    }
+
+    fn check_currency(&self, other: Money) -> Result<(), MoneyError> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch(self.currency, other.currency))
+        }
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let exponent = self.currency.exponent() as usize;
+        let units = Self::minor_units_per_major(self.currency) as u64;
+        let sign = if self.amount_minor < 0 { "-" } else { "" };
+        let amount = i128::from(self.amount_minor).abs() as u64;
+        if exponent == 0 {
+            write!(f, "{}{} {}", sign, amount, self.currency)
+        } else {
+            write!(
+                f,
+                "{}{}.{:0width$} {}",
+                sign,
+                amount / units,
+                amount % units,
+                self.currency,
+                width = exponent
+            )
+        }
+    }
+}
+
+/// Allows loading `(amount_minor, currency)` column pair as `Money`
+impl Queryable<(BigInt, VarChar), Pg> for Money {
+    type Row = (i64, Currency);
+
+    fn build((amount_minor, currency): Self::Row) -> Self {
+        Self { amount_minor, currency }
+    }
+}
+
+/// SQL type of `Money` stored in a single column of composite type `(amount_minor bigint, currency varchar)`
+pub type MoneyRecord = Record<(BigInt, VarChar)>;
+
+impl Queryable<MoneyRecord, Pg> for Money {
+    type Row = Money;
+    fn build(row: Self::Row) -> Self {
+        row
+    }
+}
+impl AsExpression<MoneyRecord> for Money {
+    type Expression = Bound<MoneyRecord, Money>;
+    fn as_expression(self) -> Self::Expression {
+        Bound::new(self)
+    }
+}
+impl<'a> AsExpression<MoneyRecord> for &'a Money {
+    type Expression = Bound<MoneyRecord, &'a Money>;
+    fn as_expression(self) -> Self::Expression {
+        Bound::new(self)
+    }
+}
+impl ToSql<MoneyRecord, Pg> for Money {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> Result<IsNull, Box<Error + Send + Sync>> {
+        WriteTuple::<(BigInt, VarChar)>::write_tuple(&(self.amount_minor, self.currency), out)
+    }
+}
+impl FromSql<MoneyRecord, Pg> for Money {
+    fn from_sql(bytes: Option<&[u8]>) -> Result<Self, Box<Error + Send + Sync>> {
+        let (amount_minor, code) = <(i64, String) as FromSql<MoneyRecord, Pg>>::from_sql(bytes)?;
+        let currency = code.parse::<Currency>().map_err(|_| format!("Unrecognized currency: {:?}", code))?;
+        Ok(Self { amount_minor, currency })
+    }
+}
+impl FromSqlRow<MoneyRecord, Pg> for Money {
+    fn build_from_row<R: Row<Pg>>(row: &mut R) -> Result<Self, Box<Error + Send + Sync>> {
+        Self::from_sql(row.take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_bytes(amount_minor: i64, code: &str) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&2i32.to_be_bytes());
+        bytes.extend_from_slice(&20u32.to_be_bytes());
+        bytes.extend_from_slice(&8i32.to_be_bytes());
+        bytes.extend_from_slice(&amount_minor.to_be_bytes());
+        bytes.extend_from_slice(&1043u32.to_be_bytes());
+        bytes.extend_from_slice(&(code.len() as i32).to_be_bytes());
+        bytes.extend_from_slice(code.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Money::new(123_450, Currency::USD).to_string(), "1234.50 USD");
+        assert_eq!(Money::new(-5, Currency::EUR).to_string(), "-0.05 EUR");
+        assert_eq!(Money::new(1_500, Currency::JPY).to_string(), "1500 JPY");
+        assert_eq!(Money::new(1, Currency::BTC).to_string(), "0.00000001 BTC");
+    }
+
+    #[test]
+    fn test_from_major() {
+        assert_eq!(Money::from_major(12.345, Currency::USD), Some(Money::new(1_235, Currency::USD)));
+        assert_eq!(Money::from_major(-0.005, Currency::EUR), Some(Money::new(-1, Currency::EUR)));
+        assert_eq!(Money::from_major(1.5, Currency::JPY), Some(Money::new(2, Currency::JPY)));
+        assert_eq!(Money::from_major(::std::f64::NAN, Currency::USD), None);
+        assert_eq!(Money::from_major(1e30, Currency::USD), None);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let usd = |amount_minor| Money::new(amount_minor, Currency::USD);
+
+        assert_eq!(usd(150).checked_add(usd(50)), Ok(usd(200)));
+        assert_eq!(usd(150).checked_sub(usd(200)), Ok(usd(-50)));
+        assert_eq!(usd(150).checked_mul(3), Ok(usd(450)));
+        assert_eq!(Money::checked_sum(Currency::USD, vec![usd(1), usd(2), usd(3)]), Ok(usd(6)));
+        assert_eq!(
+            usd(1).checked_add(Money::new(1, Currency::EUR)),
+            Err(MoneyError::CurrencyMismatch(Currency::USD, Currency::EUR))
+        );
+        assert_eq!(usd(i64::max_value()).checked_add(usd(1)), Err(MoneyError::Overflow));
+        assert_eq!(usd(i64::max_value()).checked_mul(2), Err(MoneyError::Overflow));
+    }
+
+    #[test]
+    fn test_mul_ratio() {
+        let usd = |amount_minor| Money::new(amount_minor, Currency::USD);
+
+        assert_eq!(usd(1_000).checked_mul_ratio(15, 100, RoundingMode::HalfEven), Ok(usd(150)));
+        assert_eq!(usd(5).checked_mul_ratio(1, 2, RoundingMode::HalfAwayFromZero), Ok(usd(3)));
+        assert_eq!(usd(5).checked_mul_ratio(1, 2, RoundingMode::HalfEven), Ok(usd(2)));
+        assert_eq!(usd(-5).checked_mul_ratio(1, 2, RoundingMode::HalfAwayFromZero), Ok(usd(-3)));
+        assert_eq!(usd(333).checked_mul_ratio(1, 3, RoundingMode::HalfEven), Ok(usd(111)));
+        assert_eq!(
+            usd(i64::max_value()).checked_mul_ratio(3, 2, RoundingMode::HalfEven),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_from_sql() {
+        let bytes = record_bytes(-12_345, "EUR");
+        assert_eq!(
+            <Money as FromSql<MoneyRecord, Pg>>::from_sql(Some(&bytes)).unwrap(),
+            Money::new(-12_345, Currency::EUR)
+        );
+
+        let bytes = record_bytes(1, "XXX");
+        assert!(<Money as FromSql<MoneyRecord, Pg>>::from_sql(Some(&bytes)).is_err());
+        assert!(<Money as FromSql<MoneyRecord, Pg>>::from_sql(None).is_err());
+    }
+}
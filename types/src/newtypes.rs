@@ -1,141 +1,190 @@
 use uuid::Uuid;
 
-macro_rules! f64_newtype {
-    ($x:ident) => {
-        #[derive(Clone, Copy, Debug, Display, Default, PartialEq, PartialOrd, From, FromStr, Into, Serialize, Deserialize, DieselTypes)]
-        pub struct $x(pub f64);
-    };
-}
-macro_rules! i32_newtype {
-    ($x:ident) => {
-        #[derive(
-            Clone,
-            Copy,
-            Debug,
-            Display,
-            Default,
-            PartialEq,
-            Eq,
-            PartialOrd,
-            Ord,
-            From,
-            FromStr,
-            Into,
-            Hash,
-            Serialize,
-            Deserialize,
-            DieselTypes,
-        )]
-        pub struct $x(pub i32);
-    };
-}
-macro_rules! string_newtype {
-    ($x:ident) => {
-        #[derive(
-            Clone, Debug, Display, Default, PartialEq, Eq, PartialOrd, Ord, From, FromStr, Into, Hash, Serialize, Deserialize, DieselTypes,
-        )]
-        pub struct $x(pub String);
-
-        impl std::convert::AsRef<str> for $x {
-            fn as_ref(&self) -> &str {
-                &self.0
-            }
-        }
-    };
-}
-macro_rules! uuid_newtype {
-    ($x:ident) => {
-        #[derive(
-            Clone,
-            Copy,
-            Debug,
-            Default,
-            Display,
-            PartialEq,
-            Eq,
-            PartialOrd,
-            Ord,
-            From,
-            FromStr,
-            Into,
-            Hash,
-            Serialize,
-            Deserialize,
-            DieselTypes,
-        )]
-        pub struct $x(pub Uuid);
-
-        impl $x {
-            pub fn new() -> Self {
-                $x(Uuid::new_v4())
-            }
-        }
-    };
-}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct UserId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct SessionId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct ProductId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct BaseProductId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct Quantity(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct StoreId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct OrderSlug(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct CompanyPackageId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct CompanyId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct PackageId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct CustomAttributeId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct AttributeId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct CategoryId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct CouponId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct PickupId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct ShippingId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct ShippingRatesId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct ProdAttrId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct AttributeValueId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct EmarsysId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct StoreBillingTypeId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct InternationalBillingId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct RussiaBillingId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct ProxyCompanyBillingInfoId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct StoreSubscriptionId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct SubscriptionId(pub i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct SubscriptionPaymentId(pub i32);
 
-i32_newtype!(UserId);
-i32_newtype!(SessionId);
-i32_newtype!(ProductId);
-i32_newtype!(BaseProductId);
-i32_newtype!(Quantity);
-i32_newtype!(StoreId);
-i32_newtype!(OrderSlug);
-i32_newtype!(CompanyPackageId);
-i32_newtype!(CompanyId);
-i32_newtype!(PackageId);
-i32_newtype!(CustomAttributeId);
-i32_newtype!(AttributeId);
-i32_newtype!(CategoryId);
-i32_newtype!(CouponId);
-i32_newtype!(PickupId);
-i32_newtype!(ShippingId);
-i32_newtype!(ShippingRatesId);
-i32_newtype!(ProdAttrId);
-i32_newtype!(AttributeValueId);
-i32_newtype!(EmarsysId);
-i32_newtype!(StoreBillingTypeId);
-i32_newtype!(InternationalBillingId);
-i32_newtype!(RussiaBillingId);
-i32_newtype!(ProxyCompanyBillingInfoId);
-i32_newtype!(StoreSubscriptionId);
-i32_newtype!(SubscriptionId);
-i32_newtype!(SubscriptionPaymentId);
-
-string_newtype!(WarehouseSlug);
-string_newtype!(CountryLabel);
-string_newtype!(PageSlug);
-string_newtype!(Alpha2);
-string_newtype!(Alpha3);
-string_newtype!(AttributeValueCode);
-string_newtype!(CouponCode);
-string_newtype!(BaseProductSlug);
-string_newtype!(StoreSlug);
-string_newtype!(CategorySlug);
-string_newtype!(SwiftId);
+/// Lowercased on parsing and deserialization, see `slug`
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, StqNewtype)]
+#[stq_newtype(skip_from_str)]
+pub struct WarehouseSlug(pub String);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct CountryLabel(pub String);
+
+/// Lowercased on parsing and deserialization, see `slug`
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, StqNewtype)]
+#[stq_newtype(skip_from_str)]
+pub struct PageSlug(pub String);
+
+/// Validated on parsing and deserialization, see `countries`
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, StqNewtype)]
+#[stq_newtype(skip_from_str, skip_from_inner)]
+pub struct Alpha2(pub String);
+
+/// Validated on parsing and deserialization, see `countries`
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, StqNewtype)]
+#[stq_newtype(skip_from_str, skip_from_inner)]
+pub struct Alpha3(pub String);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct AttributeValueCode(pub String);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct CouponCode(pub String);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct BaseProductSlug(pub String);
+
+/// Lowercased on parsing and deserialization, see `slug`
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, StqNewtype)]
+#[stq_newtype(skip_from_str)]
+pub struct StoreSlug(pub String);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct CategorySlug(pub String);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct SwiftId(pub String);
 
 pub mod stripe {
-    string_newtype!(SourceId);
-    string_newtype!(PaymentIntentId);
-    string_newtype!(ChargeId);
+    #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+    pub struct SourceId(pub String);
+
+    #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+    pub struct PaymentIntentId(pub String);
+
+    #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+    pub struct ChargeId(pub String);
 }
 
-uuid_newtype!(RoleEntryId);
-uuid_newtype!(RoleId);
-uuid_newtype!(StockId);
-uuid_newtype!(InvoiceId);
-uuid_newtype!(SagaId);
-uuid_newtype!(MerchantId);
-uuid_newtype!(CartItemId);
-uuid_newtype!(OrderId);
-uuid_newtype!(OrderDiffId);
-uuid_newtype!(OrderInfoId);
-uuid_newtype!(CallbackId);
-uuid_newtype!(ConversionId);
-uuid_newtype!(WarehouseId);
-uuid_newtype!(CurrencyExchangeId);
-uuid_newtype!(PageId);
-uuid_newtype!(PayoutId);
-
-f64_newtype!(ProductPrice);
-f64_newtype!(ExchangeRate);
-f64_newtype!(CashbackPercent);
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct RoleEntryId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct RoleId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct StockId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct InvoiceId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct SagaId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct MerchantId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct CartItemId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct OrderId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct OrderDiffId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct OrderInfoId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct CallbackId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct ConversionId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct WarehouseId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct CurrencyExchangeId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct PageId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, StqNewtype)]
+pub struct PayoutId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize, StqNewtype)]
+pub struct ProductPrice(pub f64);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize, StqNewtype)]
+pub struct CashbackPercent(pub f64);
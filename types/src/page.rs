@@ -0,0 +1,121 @@
+//! Pagination envelopes, so that listings are responded in the same shape by all services
+
+/// Page of a listing requested by offset
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Number of items in the whole listing. `total_count` is accepted from services responding with the older shape.
+    #[serde(alias = "total_count")]
+    pub total: i64,
+    #[serde(default)]
+    pub offset: i64,
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: i64, offset: i64, limit: Option<i64>) -> Self {
+        Self {
+            items,
+            total,
+            offset,
+            limit,
+        }
+    }
+
+    /// Offset of the next page, `None` if this is the last one
+    pub fn next_offset(&self) -> Option<i64> {
+        let next_offset = self.offset + self.items.len() as i64;
+        if self.items.is_empty() || next_offset >= self.total {
+            None
+        } else {
+            Some(next_offset)
+        }
+    }
+
+    pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+
+    pub fn page_info(&self) -> PageInfo {
+        PageInfo {
+            total: self.total,
+            offset: self.offset,
+            limit: self.limit,
+            has_next: self.next_offset().is_some(),
+        }
+    }
+}
+
+/// Page of a listing requested by cursor, for listings changing while they are paged through
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    /// Cursor to request the next page with, `None` if this is the last one
+    pub next_cursor: Option<String>,
+}
+
+impl<T> CursorPage<T> {
+    pub fn new(items: Vec<T>, next_cursor: Option<String>) -> Self {
+        Self { items, next_cursor }
+    }
+
+    pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> CursorPage<U> {
+        CursorPage {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+
+    pub fn page_info(&self) -> CursorPageInfo {
+        CursorPageInfo {
+            next_cursor: self.next_cursor.clone(),
+        }
+    }
+}
+
+/// Position of a page in the listing. GraphQL types of pages expose it along with the items,
+/// as GraphQL types can't be generic.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageInfo {
+    pub total: i64,
+    pub offset: i64,
+    pub limit: Option<i64>,
+    pub has_next: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CursorPageInfo {
+    pub next_cursor: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+
+    #[test]
+    fn test_next_offset() {
+        assert_eq!(Page::new(vec![1, 2], 5, 0, Some(2)).next_offset(), Some(2));
+        assert_eq!(Page::new(vec![3, 4], 5, 2, Some(2)).next_offset(), Some(4));
+        assert_eq!(Page::new(vec![5], 5, 4, Some(2)).next_offset(), None);
+        // Listing shrunk while paged through
+        assert_eq!(Page::new(vec![1, 2], 3, 2, Some(2)).next_offset(), None);
+        assert_eq!(Page::<i32>::new(vec![], 5, 0, Some(2)).next_offset(), None);
+
+        assert!(Page::new(vec![1, 2], 5, 0, Some(2)).page_info().has_next);
+        assert!(!Page::new(vec![5], 5, 4, Some(2)).page_info().has_next);
+    }
+
+    #[test]
+    fn test_page_accepts_total_count() {
+        let page: Page<i32> = serde_json::from_str(r#"{"items": [1, 2], "total_count": 5}"#).unwrap();
+        assert_eq!(page, Page::new(vec![1, 2], 5, 0, None));
+    }
+}
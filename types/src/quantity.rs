@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use super::Quantity;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuantityError {
+    /// Operation would result in the negative quantity
+    Negative(i32),
+    Overflow,
+    Zero,
+}
+
+impl fmt::Display for QuantityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QuantityError::Negative(value) => write!(f, "Quantity can't be negative: {}", value),
+            QuantityError::Overflow => write!(f, "Quantity overflow"),
+            QuantityError::Zero => write!(f, "Quantity can't be zero"),
+        }
+    }
+}
+
+impl Error for QuantityError {}
+
+fn non_negative(value: i32) -> Result<Quantity, QuantityError> {
+    if value < 0 {
+        Err(QuantityError::Negative(value))
+    } else {
+        Ok(Quantity(value))
+    }
+}
+
+impl Quantity {
+    /// Adds quantities, `other` may be a negative delta, but the result may not
+    pub fn checked_add(self, other: Quantity) -> Result<Quantity, QuantityError> {
+        self.0.checked_add(other.0).ok_or(QuantityError::Overflow).and_then(non_negative)
+    }
+
+    /// Subtracts quantities, fails instead of going below zero
+    pub fn checked_sub(self, other: Quantity) -> Result<Quantity, QuantityError> {
+        self.0.checked_sub(other.0).ok_or(QuantityError::Overflow).and_then(non_negative)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+}
+
+/// Positive quantity, e.g. of a product added to a cart
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NonZeroQuantity(i32);
+
+impl NonZeroQuantity {
+    pub fn new(value: i32) -> Result<Self, QuantityError> {
+        match value {
+            0 => Err(QuantityError::Zero),
+            v if v < 0 => Err(QuantityError::Negative(v)),
+            v => Ok(NonZeroQuantity(v)),
+        }
+    }
+
+    pub fn from_quantity(quantity: Quantity) -> Result<Self, QuantityError> {
+        Self::new(quantity.0)
+    }
+
+    pub fn get(&self) -> i32 {
+        self.0
+    }
+
+    pub fn checked_add(self, other: NonZeroQuantity) -> Result<NonZeroQuantity, QuantityError> {
+        self.0.checked_add(other.0).ok_or(QuantityError::Overflow).and_then(Self::new)
+    }
+
+    /// Fails if the result is not positive
+    pub fn checked_sub(self, other: Quantity) -> Result<NonZeroQuantity, QuantityError> {
+        self.0.checked_sub(other.0).ok_or(QuantityError::Overflow).and_then(Self::new)
+    }
+}
+
+impl From<NonZeroQuantity> for Quantity {
+    fn from(v: NonZeroQuantity) -> Self {
+        Quantity(v.0)
+    }
+}
+
+impl fmt::Display for NonZeroQuantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Serialize for NonZeroQuantity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i32(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for NonZeroQuantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = i32::deserialize(deserializer)?;
+        NonZeroQuantity::new(value).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::i32;
+
+    use serde_json;
+
+    use super::*;
+
+    #[test]
+    fn test_quantity_checked_ops() {
+        assert_eq!(Quantity(2).checked_add(Quantity(3)), Ok(Quantity(5)));
+        assert_eq!(Quantity(5).checked_add(Quantity(-2)), Ok(Quantity(3)));
+        assert_eq!(Quantity(2).checked_add(Quantity(-3)), Err(QuantityError::Negative(-1)));
+        assert_eq!(Quantity(i32::MAX).checked_add(Quantity(1)), Err(QuantityError::Overflow));
+
+        assert_eq!(Quantity(5).checked_sub(Quantity(5)), Ok(Quantity(0)));
+        assert_eq!(Quantity(2).checked_sub(Quantity(3)), Err(QuantityError::Negative(-1)));
+        assert_eq!(Quantity(i32::MIN).checked_sub(Quantity(1)), Err(QuantityError::Overflow));
+    }
+
+    #[test]
+    fn test_non_zero_quantity() {
+        assert_eq!(NonZeroQuantity::new(0), Err(QuantityError::Zero));
+        assert_eq!(NonZeroQuantity::new(-1), Err(QuantityError::Negative(-1)));
+        assert_eq!(NonZeroQuantity::from_quantity(Quantity(3)).map(Quantity::from), Ok(Quantity(3)));
+
+        let two = NonZeroQuantity::new(2).unwrap();
+        assert_eq!(two.checked_add(two).map(|q| q.get()), Ok(4));
+        assert_eq!(
+            two.checked_add(NonZeroQuantity::new(i32::MAX).unwrap()),
+            Err(QuantityError::Overflow)
+        );
+        assert_eq!(two.checked_sub(Quantity(1)).map(|q| q.get()), Ok(1));
+        assert_eq!(two.checked_sub(Quantity(2)), Err(QuantityError::Zero));
+    }
+
+    #[test]
+    fn test_non_zero_quantity_is_validated_when_deserialized() {
+        assert_eq!(serde_json::from_str::<NonZeroQuantity>("3").unwrap().get(), 3);
+        assert!(serde_json::from_str::<NonZeroQuantity>("0").is_err());
+        assert!(serde_json::from_str::<NonZeroQuantity>("-1").is_err());
+        assert_eq!(serde_json::to_string(&NonZeroQuantity::new(3).unwrap()).unwrap(), "3");
+    }
+}
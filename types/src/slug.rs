@@ -0,0 +1,218 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer};
+
+use super::{PageSlug, StoreSlug, WarehouseSlug};
+
+/// Maximum length of a generated or validated slug
+pub const SLUG_MAX_LENGTH: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlugError {
+    Empty,
+    TooLong(String),
+    InvalidCharacters(String),
+}
+
+impl fmt::Display for SlugError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SlugError::Empty => write!(f, "Slug is empty"),
+            SlugError::TooLong(slug) => write!(f, "Slug is longer than {} characters: {}", SLUG_MAX_LENGTH, slug),
+            SlugError::InvalidCharacters(slug) => {
+                write!(f, "Slug can contain only latin letters, digits and dashes between them: {}", slug)
+            }
+        }
+    }
+}
+
+impl Error for SlugError {}
+
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'д' => "d",
+        'е' | 'ё' | 'э' => "e",
+        'ж' => "zh",
+        'з' => "z",
+        'и' => "i",
+        'й' | 'ы' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ю' => "yu",
+        'я' => "ya",
+        _ => return None,
+    })
+}
+
+/// Cuts the slug to `max_length` bytes on a dash if possible, so that words are not split
+fn truncate(slug: &str, max_length: usize) -> &str {
+    if slug.len() <= max_length {
+        return slug;
+    }
+    let end = (0..=max_length).rev().find(|i| slug.is_char_boundary(*i)).unwrap_or_default();
+    let cut = &slug[..end];
+    match cut.rfind('-') {
+        Some(i) if i > 0 => &cut[..i],
+        _ => cut,
+    }
+}
+
+/// Generates URL-safe slug from an arbitrary name: lowercases it, transliterates cyrillic letters,
+/// replaces other characters with dashes and limits its length to `SLUG_MAX_LENGTH`.
+/// Result is empty if the name has no latin or cyrillic letters and digits.
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if let Some(latin) = transliterate(c) {
+            slug.push_str(latin);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    truncate(slug.trim_end_matches('-'), SLUG_MAX_LENGTH).to_string()
+}
+
+/// Appends numeric suffix to make the slug unique, e.g. `shop-2`, keeping the result within `SLUG_MAX_LENGTH`
+pub fn slug_with_suffix(slug: &str, suffix: u32) -> String {
+    let suffix = format!("-{}", suffix);
+    let base = truncate(slug, SLUG_MAX_LENGTH.saturating_sub(suffix.len()));
+    format!("{}{}", base.trim_end_matches('-'), suffix)
+}
+
+/// Checks that the slug consists of lowercase latin letters and digits, optionally separated with single dashes
+pub fn validate_slug(slug: &str) -> Result<(), SlugError> {
+    if slug.is_empty() {
+        return Err(SlugError::Empty);
+    }
+    if slug.len() > SLUG_MAX_LENGTH {
+        return Err(SlugError::TooLong(slug.to_string()));
+    }
+    let valid_chars = slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid_chars || slug.starts_with('-') || slug.ends_with('-') || slug.contains("--") {
+        return Err(SlugError::InvalidCharacters(slug.to_string()));
+    }
+    Ok(())
+}
+
+macro_rules! slug_impls {
+    ($x:ident) => {
+        impl $x {
+            /// Validates the slug, converting it to lower case
+            pub fn parse(s: &str) -> Result<Self, SlugError> {
+                let slug = s.to_lowercase();
+                validate_slug(&slug)?;
+                Ok($x(slug))
+            }
+
+            /// Generates a valid slug from an arbitrary name
+            pub fn from_name(name: &str) -> Result<Self, SlugError> {
+                $x::parse(&slugify(name))
+            }
+        }
+
+        /// Accepts slugs created before validation was introduced, only converting them to lower case,
+        /// so that existing entities can still be looked up. Use `parse` to validate new slugs.
+        impl FromStr for $x {
+            type Err = SlugError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                if s.is_empty() {
+                    return Err(SlugError::Empty);
+                }
+                Ok($x(s.to_lowercase()))
+            }
+        }
+
+        /// Same as `FromStr`
+        impl<'de> Deserialize<'de> for $x {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+slug_impls!(WarehouseSlug);
+slug_impls!(StoreSlug);
+slug_impls!(PageSlug);
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Мой Магазин №1!"), "moy-magazin-1");
+        assert_eq!(slugify("  Coffee & Tea  "), "coffee-tea");
+        assert_eq!(slugify("!!!"), "");
+
+        let long = slugify(&"word ".repeat(20));
+        assert!(long.len() <= SLUG_MAX_LENGTH);
+        assert!(!long.ends_with('-'));
+        assert_eq!(validate_slug(&long), Ok(()));
+    }
+
+    #[test]
+    fn test_slug_with_suffix() {
+        assert_eq!(slug_with_suffix("shop", 2), "shop-2");
+
+        let long = slug_with_suffix(&"a".repeat(SLUG_MAX_LENGTH), 12);
+        assert_eq!(long.len(), SLUG_MAX_LENGTH);
+        assert!(long.ends_with("a-12"));
+    }
+
+    #[test]
+    fn test_validate_slug() {
+        assert_eq!(validate_slug("my-shop-1"), Ok(()));
+        assert_eq!(validate_slug(""), Err(SlugError::Empty));
+        assert_eq!(
+            validate_slug(&"a".repeat(SLUG_MAX_LENGTH + 1)),
+            Err(SlugError::TooLong("a".repeat(SLUG_MAX_LENGTH + 1)))
+        );
+        for invalid in &["-shop", "shop-", "my--shop", "my_shop", "Shop"] {
+            assert_eq!(validate_slug(invalid), Err(SlugError::InvalidCharacters(invalid.to_string())));
+        }
+    }
+
+    #[test]
+    fn test_parse_validates_and_from_str_accepts_legacy_slugs() {
+        assert_eq!(StoreSlug::parse("My-Shop"), Ok(StoreSlug("my-shop".to_string())));
+        assert!(StoreSlug::parse("my_shop").is_err());
+        assert_eq!(StoreSlug::from_name("My Shop"), Ok(StoreSlug("my-shop".to_string())));
+
+        assert_eq!("My_Shop".parse::<StoreSlug>(), Ok(StoreSlug("my_shop".to_string())));
+        assert_eq!("".parse::<StoreSlug>(), Err(SlugError::Empty));
+        assert_eq!(
+            serde_json::from_str::<StoreSlug>("\"My_Shop\"").unwrap(),
+            StoreSlug("my_shop".to_string())
+        );
+        assert!(serde_json::from_str::<StoreSlug>("\"\"").is_err());
+    }
+}
@@ -2,11 +2,24 @@ use super::*;
 
 use std::collections::HashSet;
 use std::fmt::{self, Debug, Display};
+use std::ops::Range;
 use std::str::FromStr;
 
-use stq_static_resources::{Currency, CurrencyType};
+use stq_static_resources::{Currency, CurrencyType, ModerationStatus};
 use uuid::{self, Uuid};
 
+/// Wrapper for a new value of a field in partial updates, `None` in place of it means "leave as is"
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValueContainer<T> {
+    pub value: T,
+}
+
+impl<T> From<T> for ValueContainer<T> {
+    fn from(value: T) -> Self {
+        Self { value }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ProductSellerPrice {
     pub price: ProductPrice,
@@ -33,7 +46,13 @@ pub struct CartItem {
 
 pub type Cart = HashSet<CartItem>;
 
-#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+/// Number of trailing bytes of `TransactionId` used as a counter by `TransactionId::derive_for`
+const TRANSACTION_ID_COUNTER_BYTES: usize = 4;
+
+/// Transaction ids of an order are derived from the order id: its last 4 bytes, read as a big-endian number,
+/// are incremented by the transaction index. Ids of the same order differ only in these bytes,
+/// so they don't collide for the first 2^32 transactions.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TransactionId(Uuid);
 
 impl TransactionId {
@@ -49,6 +68,34 @@ impl TransactionId {
         TransactionId(Uuid::new_v4())
     }
 
+    /// Id of the transaction number `index` of the order
+    pub fn derive_for(order_id: OrderId, index: u32) -> Self {
+        let mut bytes = *order_id.0.as_bytes();
+        let counter_start = bytes.len() - TRANSACTION_ID_COUNTER_BYTES;
+        let counter = read_counter(&bytes[counter_start..]).wrapping_add(index);
+        for (i, byte) in bytes[counter_start..].iter_mut().enumerate() {
+            *byte = (counter >> (8 * (TRANSACTION_ID_COUNTER_BYTES - 1 - i))) as u8;
+        }
+        TransactionId(Uuid::from_bytes(&bytes).unwrap())
+    }
+
+    /// Index of the transaction if it was derived from the order id, see `derive_for`
+    pub fn index_for(&self, order_id: OrderId) -> Option<u32> {
+        let bytes = self.0.as_bytes();
+        let base = order_id.0.as_bytes();
+        let counter_start = bytes.len() - TRANSACTION_ID_COUNTER_BYTES;
+        if bytes[..counter_start] != base[..counter_start] {
+            return None;
+        }
+        Some(read_counter(&bytes[counter_start..]).wrapping_sub(read_counter(&base[counter_start..])))
+    }
+
+    /// Ids of transactions with indices in `range` of the order
+    pub fn range_for(order_id: OrderId, range: Range<u32>) -> TransactionIdRange {
+        TransactionIdRange { order_id, indices: range }
+    }
+
+    /// Increments the last byte, wrapping it, so ids collide after 256 steps. Use `derive_for` for new ids.
     pub fn next(&self) -> Self {
         let mut bytes = self.0.as_bytes().to_vec();
         let last = bytes.len() - 1;
@@ -57,6 +104,7 @@ impl TransactionId {
         TransactionId(uuid)
     }
 
+    /// Decrements the last byte, wrapping it, so ids collide after 256 steps. Use `derive_for` for new ids.
     pub fn prev(&self) -> Self {
         let mut bytes = self.0.as_bytes().to_vec();
         let last = bytes.len() - 1;
@@ -72,6 +120,37 @@ impl TransactionId {
     }
 }
 
+fn read_counter(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |acc, byte| (acc << 8) | u32::from(*byte))
+}
+
+/// Iterator over derived transaction ids of an order, see `TransactionId::range_for`
+#[derive(Clone, Debug)]
+pub struct TransactionIdRange {
+    order_id: OrderId,
+    indices: Range<u32>,
+}
+
+impl Iterator for TransactionIdRange {
+    type Item = TransactionId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.indices.next().map(|index| TransactionId::derive_for(self.order_id, index))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for TransactionIdRange {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.indices
+            .next_back()
+            .map(|index| TransactionId::derive_for(self.order_id, index))
+    }
+}
+
 impl FromStr for TransactionId {
     type Err = uuid::ParseError;
 
@@ -98,3 +177,73 @@ impl Display for TransactionId {
         f.write_str(&format!("{}", self.0.hyphenated()))
     }
 }
+
+/// Moderator's decision on a store or a product, kept for audit
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModerationDecision {
+    pub status: ModerationStatus,
+    pub reason: Option<String>,
+    pub moderator_id: UserId,
+}
+
+impl ModerationDecision {
+    pub fn new(status: ModerationStatus, moderator_id: UserId) -> Self {
+        Self {
+            status,
+            reason: None,
+            moderator_id,
+        }
+    }
+
+    pub fn with_reason<S: Into<String>>(mut self, reason: S) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order_id(last_bytes: [u8; 4]) -> OrderId {
+        let mut bytes = [7u8; 16];
+        bytes[12..].copy_from_slice(&last_bytes);
+        OrderId(Uuid::from_bytes(&bytes).unwrap())
+    }
+
+    #[test]
+    fn test_transaction_id_is_derived_from_order_id() {
+        let order = order_id([0, 0, 0, 1]);
+        assert_eq!(*TransactionId::derive_for(order, 0).inner(), order.0);
+
+        let id = TransactionId::derive_for(order, 300);
+        assert_eq!(&id.inner().as_bytes()[..12], &order.0.as_bytes()[..12]);
+        assert_eq!(&id.inner().as_bytes()[12..], &[0, 0, 1, 45]);
+        assert_eq!(id.index_for(order), Some(300));
+        assert_eq!(id.index_for(OrderId(Uuid::nil())), None);
+    }
+
+    #[test]
+    fn test_transaction_id_counter_wraps() {
+        let order = order_id([255, 255, 255, 255]);
+        let id = TransactionId::derive_for(order, 2);
+        assert_eq!(&id.inner().as_bytes()[12..], &[0, 0, 0, 1]);
+        assert_eq!(id.index_for(order), Some(2));
+    }
+
+    #[test]
+    fn test_transaction_id_range() {
+        let order = order_id([0, 0, 0, 255]);
+        let range = TransactionId::range_for(order, 1..4);
+        assert_eq!(range.size_hint(), (3, Some(3)));
+
+        let ids = range.clone().collect::<Vec<_>>();
+        let expected = (1..4).map(|index| TransactionId::derive_for(order, index)).collect::<Vec<_>>();
+        assert_eq!(ids, expected);
+        assert_eq!(
+            ids.iter().map(|id| id.index_for(order)).collect::<Vec<_>>(),
+            vec![Some(1), Some(2), Some(3)]
+        );
+        assert_eq!(range.rev().collect::<Vec<_>>(), expected.into_iter().rev().collect::<Vec<_>>());
+    }
+}